@@ -1,8 +1,112 @@
-use clap::Parser;
+use crate::hardware::binary::parse_address;
+use crate::hardware::machine::Reset;
+use crate::hardware::trace::TraceFilter;
+use crate::int;
+use crate::watch::Watcher;
+use clap::{Parser, Subcommand};
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
     #[arg(long)]
     pub k7: Option<String>,
+    /// Record the sound output to a WAV file
+    #[arg(long, value_name = "FILE")]
+    pub record_audio: Option<String>,
+    /// Capture the video to an animated GIF, or to a directory of PNG files if the name does not end with .gif
+    #[arg(long, value_name = "FILE")]
+    pub record_video: Option<String>,
+    /// Save a PNG screenshot at the end of the headless run
+    #[arg(long, value_name = "FILE")]
+    pub screenshot: Option<String>,
+    /// Scale factor of the screenshots and captured videos
+    #[arg(long, default_value_t = 1)]
+    pub scale: usize,
+    /// Run without window for the given number of emulated seconds, then exit
+    #[arg(long, value_name = "SECONDS")]
+    pub headless: Option<u32>,
+    /// Write every instruction run by the CPU with the registers to a text file
+    #[arg(long, value_name = "FILE")]
+    pub trace: Option<String>,
+    /// Only trace the instructions at these hexadecimal addresses, like F000-F0FF
+    #[arg(long, value_name = "RANGE", value_parser = TraceFilter::parse_pc, requires = "trace")]
+    pub trace_pc: Option<RangeInclusive<int>>,
+    /// Only trace during these frames, like 50-60
+    #[arg(long, value_name = "RANGE", value_parser = TraceFilter::parse_frames, requires = "trace")]
+    pub trace_frames: Option<RangeInclusive<u64>>,
+    /// Start the trace when the CPU reaches this hexadecimal address
+    #[arg(long, value_name = "ADDRESS", value_parser = parse_address, requires = "trace")]
+    pub trace_after: Option<int>,
+    /// Restart the machine with this Thomson BIN file, raw binary or tape each time it changes
+    #[arg(long, value_name = "FILE")]
+    pub watch: Option<String>,
+    /// The hexadecimal load address of a watched raw binary, it is also its start address
+    #[arg(long, value_name = "ADDRESS", value_parser = parse_address, requires = "watch")]
+    pub watch_address: Option<int>,
+    /// How the machine is reset when the watched file changes, soft or hard
+    #[arg(long, value_parser = Reset::parse, default_value = "soft", requires = "watch")]
+    pub watch_reset: Reset,
+    /// Keys typed once BASIC started after a reload, like 'LOAD\n', \n being Return
+    #[arg(long, value_name = "KEYS", requires = "watch")]
+    pub watch_keys: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Print the listing of a BASIC program saved on a tape
+    ListBasic {
+        /// The tape file
+        k7: String,
+        /// The name of the program, like GAME.BAS, the first one by default
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// Write a BASIC listing to a new tape as a tokenized program
+    BasicToK7 {
+        /// The text file of the listing
+        bas: String,
+        /// The tape file to create
+        k7: String,
+        /// The name of the program on the tape, up to 8 characters, the name of the listing by default
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// Write a Thomson BIN file, or a raw binary, to a new tape as a binary file for LOADM
+    BinToK7 {
+        /// The binary file
+        bin: String,
+        /// The tape file to create
+        k7: String,
+        /// The hexadecimal load address of a raw binary, it is also its start address
+        #[arg(long, value_parser = parse_address)]
+        address: Option<int>,
+        /// The name of the program on the tape, up to 8 characters, the name of the binary by default
+        #[arg(long)]
+        name: Option<String>,
+    },
+}
+
+impl Args {
+    pub fn trace_filter(&self) -> TraceFilter {
+        TraceFilter {
+            pc: self.trace_pc.clone(),
+            frames: self.trace_frames.clone(),
+            after: self.trace_after,
+        }
+    }
+
+    pub fn watcher(&self) -> Option<Watcher> {
+        self.watch.as_ref().map(|file| {
+            Watcher::new(
+                PathBuf::from(file),
+                self.watch_address.map(|address| address as u16),
+                self.watch_reset,
+                self.watch_keys.as_deref().unwrap_or_default(),
+            )
+        })
+    }
 }
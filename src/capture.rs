@@ -0,0 +1,223 @@
+use crate::hardware::screen::color::COLOR_DEPTH;
+use crate::raw_image::RawImage;
+use image::ImageEncoder;
+use image::codecs::png::PngEncoder;
+use log::info;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::Path;
+
+/// Delay between two frames in hundredths of second (50Hz)
+const GIF_FRAME_DELAY: u16 = 2;
+
+/// Encodes the image to PNG, `scale` times the native MO5 resolution
+pub fn encode_png(image: &RawImage, scale: usize) -> Result<Vec<u8>, String> {
+    let (width, height) = scaled_size(image, scale);
+    encode_rgb_png(&image.resize(width, height), width, height)
+}
+
+fn encode_rgb_png(pixels: &[u8], width: usize, height: usize) -> Result<Vec<u8>, String> {
+    let mut png = Vec::new();
+    PngEncoder::new(&mut png)
+        .write_image(
+            pixels,
+            width as u32,
+            height as u32,
+            image::ExtendedColorType::Rgb8,
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(png)
+}
+
+/// Saves the image to a PNG file, `scale` times the native MO5 resolution
+pub fn save_png(image: &RawImage, scale: usize, name: &str) -> Result<(), String> {
+    let png = encode_png(image, scale)?;
+    fs::write(name, png).map_err(|e| e.to_string())?;
+    info!("Screenshot saved to {name}");
+    Ok(())
+}
+
+const fn scaled_size(image: &RawImage, scale: usize) -> (usize, usize) {
+    let scale = if scale == 0 { 1 } else { scale };
+    (image.width() * scale, image.height() * scale)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoFormat {
+    /// An animated GIF file
+    Gif,
+    /// A directory containing one PNG file per frame
+    PngSequence,
+}
+
+impl VideoFormat {
+    /// Guess the format from the name, files ending with .gif are animated GIFs, anything else is
+    /// a directory of PNG files
+    pub fn from_name(name: &str) -> Self {
+        if name.to_lowercase().ends_with(".gif") {
+            Self::Gif
+        } else {
+            Self::PngSequence
+        }
+    }
+}
+
+/// Captures the emulated frames to an animated GIF or to a sequence of PNG files
+pub struct VideoRecorder {
+    name: String,
+    format: VideoFormat,
+    scale: usize,
+    frames: u32,
+    // size of the frames, given by the first frame
+    size: Option<(usize, usize)>,
+    gif: Option<gif::Encoder<BufWriter<File>>>,
+}
+
+impl VideoRecorder {
+    pub fn new(name: &str, format: VideoFormat, scale: usize) -> Result<Self, String> {
+        match format {
+            VideoFormat::Gif => File::create(name).map(|_| ()),
+            VideoFormat::PngSequence => fs::create_dir_all(name),
+        }
+        .map_err(|e| e.to_string())?;
+        info!("Capturing video to {name}");
+        Ok(Self {
+            name: name.to_string(),
+            format,
+            scale,
+            frames: 0,
+            size: None,
+            gif: None,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub const fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// The name of the WAV file recorded along the video
+    pub fn audio_name(&self) -> String {
+        match self.format {
+            VideoFormat::Gif => Path::new(&self.name)
+                .with_extension("wav")
+                .to_string_lossy()
+                .to_string(),
+            VideoFormat::PngSequence => Path::new(&self.name)
+                .join("audio.wav")
+                .to_string_lossy()
+                .to_string(),
+        }
+    }
+
+    pub fn push(&mut self, image: &RawImage) -> Result<(), String> {
+        // if the size of the picture changes while capturing, the frames are resized to the first one
+        let (width, height) = *self.size.get_or_insert(scaled_size(image, self.scale));
+        match self.format {
+            VideoFormat::Gif => {
+                let encoder = match &mut self.gif {
+                    Some(encoder) => encoder,
+                    None => self.gif.insert(create_gif(&self.name, width, height)?),
+                };
+                let (palette, indexes) = index_colors(&image.resize(width, height));
+                let mut frame = gif::Frame::from_palette_pixels(
+                    width as u16,
+                    height as u16,
+                    indexes,
+                    palette,
+                    None,
+                );
+                frame.delay = GIF_FRAME_DELAY;
+                encoder.write_frame(&frame).map_err(|e| e.to_string())?;
+            }
+            VideoFormat::PngSequence => {
+                let name = Path::new(&self.name).join(format!("frame_{:05}.png", self.frames));
+                let png = encode_rgb_png(&image.resize(width, height), width, height)?;
+                fs::write(name, png).map_err(|e| e.to_string())?;
+            }
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<String, String> {
+        if let Some(encoder) = self.gif {
+            encoder.into_inner().map_err(|e| e.to_string())?;
+        }
+        info!("Captured {} frames to {}", self.frames, self.name);
+        Ok(self.name)
+    }
+}
+
+fn create_gif(
+    name: &str,
+    width: usize,
+    height: usize,
+) -> Result<gif::Encoder<BufWriter<File>>, String> {
+    let file = File::create(name).map_err(|e| e.to_string())?;
+    let mut encoder = gif::Encoder::new(BufWriter::new(file), width as u16, height as u16, &[])
+        .map_err(|e| e.to_string())?;
+    encoder
+        .set_repeat(gif::Repeat::Infinite)
+        .map_err(|e| e.to_string())?;
+    Ok(encoder)
+}
+
+/// Converts RGB pixels to a GIF palette and the palette index of each pixel.
+/// The MO5 only displays 16 colors so the palette never overflows, but colors beyond 256 are
+/// mapped to the last one just in case.
+fn index_colors(pixels: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let mut colors: Vec<&[u8]> = Vec::with_capacity(16);
+    let mut indexes = Vec::with_capacity(pixels.len() / COLOR_DEPTH);
+    for pixel in pixels.chunks_exact(COLOR_DEPTH) {
+        let index = match colors.iter().position(|color| *color == pixel) {
+            Some(index) => index,
+            None if colors.len() < 256 => {
+                colors.push(pixel);
+                colors.len() - 1
+            }
+            None => colors.len() - 1,
+        };
+        indexes.push(index as u8);
+    }
+    (colors.concat(), indexes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hardware::screen::{HEIGHT, WIDTH};
+
+    #[test]
+    fn test_encode_png() {
+        let pixels = vec![0x80; WIDTH * HEIGHT * COLOR_DEPTH];
+        let image = RawImage::new_with_data(WIDTH, HEIGHT, &pixels);
+        let png = encode_png(&image, 2).unwrap();
+        assert_eq!(b"\x89PNG", &png[0..4]);
+        // IHDR chunk contains the width and the height
+        assert_eq!(
+            (2 * WIDTH) as u32,
+            u32::from_be_bytes(png[16..20].try_into().unwrap())
+        );
+        assert_eq!(
+            (2 * HEIGHT) as u32,
+            u32::from_be_bytes(png[20..24].try_into().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_index_colors() {
+        let (palette, indexes) = index_colors(&[0, 0, 0, 255, 0, 0, 0, 0, 0, 255, 0, 0]);
+        assert_eq!(vec![0, 0, 0, 255, 0, 0], palette);
+        assert_eq!(vec![0, 1, 0, 1], indexes);
+    }
+
+    #[test]
+    fn test_video_format_from_name() {
+        assert_eq!(VideoFormat::Gif, VideoFormat::from_name("capture.GIF"));
+        assert_eq!(VideoFormat::PngSequence, VideoFormat::from_name("capture"));
+    }
+}
@@ -0,0 +1,125 @@
+use crate::hardware::binary::{Binary, Entry};
+use crate::hardware::k7;
+use crate::hardware::machine::Machine;
+use egui::Ui;
+use log::{info, warn};
+use std::path::Path;
+
+enum FileAction {
+    Load,
+    SaveK7,
+}
+
+/// Loads a machine code program in memory without the tape, from a Thomson BIN file or from a
+/// raw binary, and saves it as a tape for the real hardware
+#[derive(Default)]
+pub(super) struct BinaryLoader {
+    // empty for a BIN file
+    address: String,
+    pc: String,
+    s: String,
+    run: bool,
+    // the last program loaded with its tape name
+    loaded: Option<(String, Binary)>,
+    file_dialog: Option<(egui_file_dialog::FileDialog, FileAction)>,
+    message: Option<String>,
+}
+
+impl BinaryLoader {
+    pub(super) fn ui(&mut self, ui: &mut Ui, machine: &mut Machine) {
+        egui::Grid::new("binary_grid").show(ui, |ui| {
+            ui.label("Load address");
+            ui.add(egui::TextEdit::singleline(&mut self.address).desired_width(56.0))
+                .on_hover_text("Where a raw binary is loaded, empty for a Thomson BIN file");
+            ui.end_row();
+            ui.label("PC");
+            ui.add(egui::TextEdit::singleline(&mut self.pc).desired_width(56.0))
+                .on_hover_text("Empty to start at the start address of the program");
+            ui.end_row();
+            ui.label("S");
+            ui.add(egui::TextEdit::singleline(&mut self.s).desired_width(56.0))
+                .on_hover_text("Empty to keep the stack");
+            ui.end_row();
+        });
+        ui.checkbox(&mut self.run, "Run after loading");
+        ui.horizontal(|ui| {
+            if ui.button("Load...").clicked() {
+                let mut fd = egui_file_dialog::FileDialog::new();
+                fd.pick_file();
+                self.file_dialog = Some((fd, FileAction::Load));
+            }
+            if let Some((name, _)) = &self.loaded
+                && ui.button("Save as K7...").clicked()
+            {
+                let mut fd =
+                    egui_file_dialog::FileDialog::new().default_file_name(&format!("{name}.k7"));
+                fd.save_file();
+                self.file_dialog = Some((fd, FileAction::SaveK7));
+            }
+        });
+        if let Some(message) = &self.message {
+            ui.label(message);
+        }
+        self.handle_file_dialog(ui, machine);
+    }
+
+    fn handle_file_dialog(&mut self, ui: &mut Ui, machine: &mut Machine) {
+        let Some((fd, action)) = &mut self.file_dialog else {
+            return;
+        };
+        fd.update(ui.ctx());
+        let Some(path) = fd.take_picked() else {
+            return;
+        };
+        let result = match action {
+            FileAction::Load => self.load(&path, machine),
+            FileAction::SaveK7 => self.save_k7(&path),
+        };
+        self.message = Some(match result {
+            Ok(message) => {
+                info!("{message}");
+                message
+            }
+            Err(e) => {
+                warn!("Binary error {e}");
+                format!("Error: {e}")
+            }
+        });
+        self.file_dialog = None;
+    }
+
+    fn load(&mut self, path: &Path, machine: &mut Machine) -> Result<String, String> {
+        let data = std::fs::read(path).map_err(|e| e.to_string())?;
+        let binary = match optional_hex(&self.address)? {
+            Some(address) => Binary::raw(data, address),
+            None => Binary::parse(&data)?,
+        };
+        let entry = Entry {
+            pc: optional_hex(&self.pc)?,
+            s: optional_hex(&self.s)?,
+        };
+        machine.load_binary(&binary, self.run.then_some(entry))?;
+        self.loaded = Some((k7::tape_name(path), binary));
+        Ok(format!("{} loaded", path.display()))
+    }
+
+    fn save_k7(&self, path: &Path) -> Result<String, String> {
+        let Some((name, binary)) = &self.loaded else {
+            return Err("No binary loaded".to_string());
+        };
+        binary
+            .tape(name)?
+            .save(&path.to_string_lossy())
+            .map(|_| format!("Tape saved to {}", path.display()))
+    }
+}
+
+fn optional_hex(text: &str) -> Result<Option<u16>, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    u16::from_str_radix(text.trim_start_matches('$'), 16)
+        .map(Some)
+        .map_err(|_| format!("Invalid address {text}"))
+}
@@ -0,0 +1,323 @@
+use std::sync::{Arc, Mutex};
+
+use eframe::egui_glow;
+use eframe::glow::{self, HasContext};
+use egui::{PaintCallback, Rect, TextureId};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+/// Strength of each effect of the CRT simulation, from 0 (off) to 1
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CrtSettings {
+    pub scanlines: f32,
+    pub mask: f32,
+    pub bloom: f32,
+    pub curvature: f32,
+    pub pal_blur: f32,
+}
+
+impl CrtSettings {
+    /// The picture is displayed as is, without shader
+    pub const PIXEL_EXACT: Self = Self {
+        scanlines: 0.0,
+        mask: 0.0,
+        bloom: 0.0,
+        curvature: 0.0,
+        pal_blur: 0.0,
+    };
+
+    pub fn is_pixel_exact(&self) -> bool {
+        *self == Self::PIXEL_EXACT
+    }
+}
+
+impl Default for CrtSettings {
+    fn default() -> Self {
+        Self::PIXEL_EXACT
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrtPreset {
+    PixelExact,
+    /// A RGB monitor plugged to the PERITEL adapter
+    Peritel,
+    /// A PAL TV through the SCART plug
+    PalTv,
+    /// An old curved TV with a blurry picture
+    WornTv,
+}
+
+impl CrtPreset {
+    pub const ALL: [Self; 4] = [Self::PixelExact, Self::Peritel, Self::PalTv, Self::WornTv];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::PixelExact => "Pixel exact",
+            Self::Peritel => "PERITEL monitor",
+            Self::PalTv => "PAL TV",
+            Self::WornTv => "Worn TV",
+        }
+    }
+
+    pub const fn settings(self) -> CrtSettings {
+        match self {
+            Self::PixelExact => CrtSettings::PIXEL_EXACT,
+            Self::Peritel => CrtSettings {
+                scanlines: 0.4,
+                mask: 0.2,
+                bloom: 0.1,
+                curvature: 0.0,
+                pal_blur: 0.0,
+            },
+            Self::PalTv => CrtSettings {
+                scanlines: 0.5,
+                mask: 0.4,
+                bloom: 0.3,
+                curvature: 0.3,
+                pal_blur: 0.6,
+            },
+            Self::WornTv => CrtSettings {
+                scanlines: 0.7,
+                mask: 0.6,
+                bloom: 0.5,
+                curvature: 0.7,
+                pal_blur: 1.0,
+            },
+        }
+    }
+
+    /// Returns the preset having these settings, if any
+    pub fn matching(settings: &CrtSettings) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|preset| preset.settings() == *settings)
+    }
+}
+
+/// Draws the picture through the CRT shader. The OpenGL objects are created when the first
+/// frame is painted, since the context is only available in the paint callback.
+#[derive(Default, Clone)]
+pub(crate) struct CrtDisplay {
+    renderer: Arc<Mutex<Option<Result<CrtRenderer, String>>>>,
+}
+
+impl CrtDisplay {
+    pub(crate) fn paint_callback(
+        &self,
+        rect: Rect,
+        texture: TextureId,
+        [width, height]: [usize; 2],
+        settings: CrtSettings,
+    ) -> PaintCallback {
+        let renderer = self.renderer.clone();
+        let callback = egui_glow::CallbackFn::new(move |_, painter| {
+            let mut renderer = renderer.lock().unwrap();
+            let renderer = renderer.get_or_insert_with(|| {
+                CrtRenderer::new(painter.gl()).inspect_err(|e| warn!("CRT shader disabled: {e}"))
+            });
+            if let (Ok(renderer), Some(texture)) = (renderer, painter.texture(texture)) {
+                renderer.paint(painter.gl(), texture, width, height, &settings);
+            }
+        });
+        PaintCallback {
+            rect,
+            callback: Arc::new(callback),
+        }
+    }
+
+    pub(crate) fn destroy(&self, gl: &glow::Context) {
+        if let Some(Ok(renderer)) = self.renderer.lock().unwrap().take() {
+            renderer.destroy(gl);
+        }
+    }
+}
+
+const VERTEX_SHADER: &str = r#"
+out vec2 v_uv;
+
+void main() {
+    // a quad covering the whole viewport, drawn as a triangle strip
+    vec2 corner = vec2(float(gl_VertexID & 1), float(gl_VertexID >> 1));
+    v_uv = vec2(corner.x, 1.0 - corner.y);
+    gl_Position = vec4(corner * 2.0 - 1.0, 0.0, 1.0);
+}
+"#;
+
+const FRAGMENT_SHADER: &str = r#"
+uniform sampler2D u_texture;
+uniform vec2 u_source_size;
+uniform float u_scanlines;
+uniform float u_mask;
+uniform float u_bloom;
+uniform float u_curvature;
+uniform float u_pal_blur;
+
+in vec2 v_uv;
+out vec4 f_color;
+
+const mat3 RGB_TO_YUV = mat3(0.299, -0.147, 0.615, 0.587, -0.289, -0.515, 0.114, 0.436, -0.100);
+const mat3 YUV_TO_RGB = mat3(1.0, 1.0, 1.0, 0.0, -0.395, 2.032, 1.140, -0.581, 0.0);
+
+vec3 fetch(vec2 uv) {
+    return texture(u_texture, uv).rgb;
+}
+
+// barrel distortion of the tube
+vec2 curve(vec2 uv) {
+    uv = uv * 2.0 - 1.0;
+    vec2 offset = uv.yx * uv.yx * u_curvature * 0.2;
+    uv += uv * offset;
+    return uv * 0.5 + 0.5;
+}
+
+void main() {
+    vec2 uv = curve(v_uv);
+    if (uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0) {
+        f_color = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+    vec2 texel = 1.0 / u_source_size;
+
+    // PAL carries the colors with less bandwidth than the luminance
+    vec3 yuv = RGB_TO_YUV * fetch(uv);
+    vec2 chroma = vec2(0.0);
+    for (int i = -2; i <= 2; i++) {
+        float weight = float(3 - abs(i)) / 9.0;
+        chroma += (RGB_TO_YUV * fetch(uv + vec2(float(i) * texel.x, 0.0))).yz * weight;
+    }
+    yuv.yz = mix(yuv.yz, chroma, u_pal_blur);
+    vec3 color = YUV_TO_RGB * yuv;
+
+    // the bright pixels glow on their neighbours
+    vec3 glow = vec3(0.0);
+    for (int x = -1; x <= 1; x++) {
+        for (int y = -1; y <= 1; y++) {
+            glow += fetch(uv + vec2(float(x), float(y)) * texel * 1.5);
+        }
+    }
+    glow /= 9.0;
+    color += glow * glow * u_bloom;
+
+    // the beam is thinner at the edges of each line
+    float beam = sin(3.14159265 * fract(uv.y * u_source_size.y));
+    color *= 1.0 - u_scanlines * (1.0 - beam);
+
+    // aperture grille, one phosphor per screen pixel
+    int phosphor = int(mod(gl_FragCoord.x, 3.0));
+    vec3 mask = vec3(1.0 - 0.6 * u_mask);
+    mask[phosphor] = 1.0;
+    color *= mask * (1.0 + 0.3 * u_mask);
+
+    f_color = vec4(clamp(color, 0.0, 1.0), 1.0);
+}
+"#;
+
+struct CrtRenderer {
+    program: glow::Program,
+    vertex_array: glow::VertexArray,
+}
+
+impl CrtRenderer {
+    fn new(gl: &glow::Context) -> Result<Self, String> {
+        let shader_version = egui_glow::ShaderVersion::get(gl);
+        if !shader_version.is_new_shader_interface() {
+            return Err(format!("{shader_version:?} shaders are not supported"));
+        }
+        let precision = if shader_version.is_embedded() {
+            "precision highp float;\n"
+        } else {
+            ""
+        };
+        unsafe {
+            let program = gl.create_program()?;
+            let mut shaders = Vec::with_capacity(2);
+            for (kind, source) in [
+                (glow::VERTEX_SHADER, VERTEX_SHADER),
+                (glow::FRAGMENT_SHADER, FRAGMENT_SHADER),
+            ] {
+                let shader = gl.create_shader(kind)?;
+                gl.shader_source(
+                    shader,
+                    &format!(
+                        "{}{precision}{source}",
+                        shader_version.version_declaration()
+                    ),
+                );
+                gl.compile_shader(shader);
+                if !gl.get_shader_compile_status(shader) {
+                    return Err(gl.get_shader_info_log(shader));
+                }
+                gl.attach_shader(program, shader);
+                shaders.push(shader);
+            }
+            gl.link_program(program);
+            for shader in shaders {
+                gl.detach_shader(program, shader);
+                gl.delete_shader(shader);
+            }
+            if !gl.get_program_link_status(program) {
+                return Err(gl.get_program_info_log(program));
+            }
+            let vertex_array = gl.create_vertex_array()?;
+            Ok(Self {
+                program,
+                vertex_array,
+            })
+        }
+    }
+
+    fn paint(
+        &self,
+        gl: &glow::Context,
+        texture: glow::Texture,
+        width: usize,
+        height: usize,
+        settings: &CrtSettings,
+    ) {
+        unsafe {
+            gl.use_program(Some(self.program));
+            let uniform = |name| gl.get_uniform_location(self.program, name);
+            gl.uniform_1_i32(uniform("u_texture").as_ref(), 0);
+            gl.uniform_2_f32(
+                uniform("u_source_size").as_ref(),
+                width as f32,
+                height as f32,
+            );
+            gl.uniform_1_f32(uniform("u_scanlines").as_ref(), settings.scanlines);
+            gl.uniform_1_f32(uniform("u_mask").as_ref(), settings.mask);
+            gl.uniform_1_f32(uniform("u_bloom").as_ref(), settings.bloom);
+            gl.uniform_1_f32(uniform("u_curvature").as_ref(), settings.curvature);
+            gl.uniform_1_f32(uniform("u_pal_blur").as_ref(), settings.pal_blur);
+            gl.active_texture(glow::TEXTURE0);
+            gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+            gl.bind_vertex_array(Some(self.vertex_array));
+            gl.draw_arrays(glow::TRIANGLE_STRIP, 0, 4);
+        }
+    }
+
+    fn destroy(self, gl: &glow::Context) {
+        unsafe {
+            gl.delete_program(self.program);
+            gl.delete_vertex_array(self.vertex_array);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preset_matching() {
+        for preset in CrtPreset::ALL {
+            assert_eq!(Some(preset), CrtPreset::matching(&preset.settings()));
+        }
+        let custom = CrtSettings {
+            scanlines: 0.1,
+            ..CrtSettings::PIXEL_EXACT
+        };
+        assert_eq!(None, CrtPreset::matching(&custom));
+        assert!(CrtSettings::default().is_pixel_exact());
+    }
+}
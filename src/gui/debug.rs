@@ -1,25 +1,511 @@
+use crate::hardware::M6809::Registers;
+use crate::hardware::debugger::{Access, Stop, Watchpoint};
 use crate::hardware::machine::Machine;
-use egui::{Response, Ui, Widget};
+use crate::hardware::memory::Bank;
+use crate::hardware::symbols::Symbols;
+#[cfg(not(target_family = "wasm"))]
+use crate::hardware::trace::TraceFilter;
+use crate::int;
+use egui::{Color32, RichText, Ui};
+#[cfg(not(target_family = "wasm"))]
+use log::{info, warn};
 
-pub(super) struct Debug<'a> {
-    machine: &'a mut Machine,
+const DISASSEMBLY_LINES: usize = 20;
+
+const CC_FLAGS: [(u8, &str); 8] = [
+    (0x80, "E"),
+    (0x40, "F"),
+    (0x20, "H"),
+    (0x10, "I"),
+    (0x08, "N"),
+    (0x04, "Z"),
+    (0x02, "V"),
+    (0x01, "C"),
+];
+
+/// Controls the execution of the CPU: breakpoints, stepping and registers
+#[derive(Default)]
+pub(super) struct DebugPanel {
+    breakpoint: String,
+    watchpoint: WatchpointForm,
+    listing: Listing,
+    #[cfg(not(target_family = "wasm"))]
+    trace: TraceForm,
+}
+
+/// The filters of the next trace, the fields left empty do not filter
+#[cfg(not(target_family = "wasm"))]
+#[derive(Default)]
+struct TraceForm {
+    pc: String,
+    frames: String,
+    after: String,
+    message: Option<String>,
+}
+
+/// The watchpoint being typed, the addresses and the value are hexadecimal
+struct WatchpointForm {
+    start: String,
+    end: String,
+    access: Access,
+    value: String,
+    bank: Option<Bank>,
+}
+
+impl Default for WatchpointForm {
+    fn default() -> Self {
+        Self {
+            start: String::new(),
+            end: String::new(),
+            access: Access::Write,
+            value: String::new(),
+            bank: None,
+        }
+    }
+}
+
+impl WatchpointForm {
+    fn watchpoint(&self) -> Option<Watchpoint> {
+        let start = parse_hex(&self.start)?;
+        let end = if self.end.trim().is_empty() {
+            start
+        } else {
+            parse_hex(&self.end)?
+        };
+        let value = if self.value.trim().is_empty() {
+            None
+        } else {
+            Some(parse_hex(&self.value)?)
+        };
+        Some(Watchpoint {
+            start: start.min(end),
+            end: start.max(end),
+            access: self.access,
+            value,
+            bank: self.bank,
+        })
+    }
+}
+
+fn parse_hex(text: &str) -> Option<int> {
+    int::from_str_radix(text.trim(), 16)
+        .ok()
+        .filter(|value| (0..=0xFFFF).contains(value))
 }
 
-impl<'a> Debug<'a> {
-    pub(super) fn new(machine: &'a mut Machine) -> Self {
-        Self { machine }
+fn describe(watchpoint: &Watchpoint) -> String {
+    let mut text = format!("{} {:04X}", watchpoint.access.name(), watchpoint.start);
+    if watchpoint.end != watchpoint.start {
+        text.push_str(&format!("-{:04X}", watchpoint.end));
     }
+    if let Some(value) = watchpoint.value {
+        text.push_str(&format!(" = {value:02X}"));
+    }
+    if let Some(bank) = watchpoint.bank {
+        text.push_str(&format!(" in {}", bank.name()));
+    }
+    text
 }
 
-impl Widget for Debug<'_> {
-    fn ui(self, ui: &mut Ui) -> Response {
-        let dbg = self.machine.dump_registers();
-        let unassemble = self.machine.unassemble_from_pc(10, &self.machine.mem);
+impl DebugPanel {
+    pub(super) fn ui(&mut self, ui: &mut Ui, machine: &mut Machine) {
+        self.controls_ui(ui, machine);
+        ui.separator();
+        let running = machine.is_running();
+        ui.add_enabled_ui(!running, |ui| registers_ui(ui, machine));
+        ui.separator();
+        self.breakpoints_ui(ui, machine);
+        self.watchpoints_ui(ui, machine);
+        #[cfg(not(target_family = "wasm"))]
+        {
+            ui.separator();
+            self.trace_ui(ui, machine);
+        }
+        ui.separator();
+        self.listing.ui(ui, machine);
+    }
+
+    fn controls_ui(&mut self, ui: &mut Ui, machine: &mut Machine) {
+        let running = machine.is_running();
+        ui.horizontal(|ui| {
+            if running {
+                if ui.button("Pause").clicked() {
+                    machine.pause();
+                }
+            } else if ui.button("Continue").clicked() {
+                machine.start();
+            }
+            ui.add_enabled_ui(!running, |ui| {
+                if ui.button("Step into").clicked() {
+                    machine.step_into();
+                }
+                if ui.button("Step over").clicked() {
+                    machine.step_over();
+                }
+                if ui.button("Step out").clicked() {
+                    machine.step_out();
+                }
+            });
+            let pc = machine.registers().pc;
+            ui.label(if running {
+                "Running".to_string()
+            } else {
+                match machine.debugger().last_stop() {
+                    Some(Stop::Breakpoint(_)) => format!("Breakpoint at {pc:04X}"),
+                    Some(Stop::Watchpoint(hit)) => format!(
+                        "{} {:02X} at {:04X}, paused at {pc:04X}",
+                        if hit.write { "Wrote" } else { "Read" },
+                        hit.value,
+                        hit.address
+                    ),
+                    Some(Stop::IllegalOpcode(illegal)) => {
+                        format!("Illegal opcode {:02X} at {pc:04X}", illegal.opcode)
+                    }
+                    _ => format!("Paused at {pc:04X}"),
+                }
+            });
+        });
+    }
+
+    fn breakpoints_ui(&mut self, ui: &mut Ui, machine: &mut Machine) {
+        ui.horizontal(|ui| {
+            ui.label("Breakpoint");
+            let edit = ui.add(egui::TextEdit::singleline(&mut self.breakpoint).desired_width(40.0));
+            let submitted = edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if (ui.button("Add").clicked() || submitted)
+                && let Some(address) = parse_address(&self.breakpoint, machine)
+                && !machine.debugger().has_breakpoint(address)
+            {
+                machine.toggle_breakpoint(address);
+                self.breakpoint.clear();
+            }
+        });
+        let breakpoints: Vec<int> = machine.debugger().breakpoints().collect();
+        ui.horizontal_wrapped(|ui| {
+            for address in breakpoints {
+                if ui
+                    .button(format!("{address:04X} ✖"))
+                    .on_hover_text("Remove the breakpoint")
+                    .clicked()
+                {
+                    machine.toggle_breakpoint(address);
+                }
+            }
+        });
+    }
 
-        ui.vertical(|ui| {
-            ui.label(dbg);
-            ui.label(unassemble);
+    fn watchpoints_ui(&mut self, ui: &mut Ui, machine: &mut Machine) {
+        let form = &mut self.watchpoint;
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("watchpoint_access")
+                .width(60.0)
+                .selected_text(form.access.name())
+                .show_ui(ui, |ui| {
+                    for access in Access::ALL {
+                        ui.selectable_value(&mut form.access, access, access.name());
+                    }
+                });
+            ui.add(egui::TextEdit::singleline(&mut form.start).desired_width(40.0))
+                .on_hover_text("First address");
+            ui.label("-");
+            ui.add(egui::TextEdit::singleline(&mut form.end).desired_width(40.0))
+                .on_hover_text("Last address, empty to watch a single address");
+            ui.label("=");
+            ui.add(egui::TextEdit::singleline(&mut form.value).desired_width(24.0))
+                .on_hover_text("Value read or written, empty for any value");
+            egui::ComboBox::from_id_salt("watchpoint_bank")
+                .width(60.0)
+                .selected_text(form.bank.map_or("Any bank", Bank::name))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut form.bank, None, "Any bank");
+                    for bank in Bank::ALL {
+                        ui.selectable_value(&mut form.bank, Some(bank), bank.name());
+                    }
+                });
+            let watchpoint = form.watchpoint();
+            if ui
+                .add_enabled(watchpoint.is_some(), egui::Button::new("Watch"))
+                .clicked()
+                && let Some(watchpoint) = watchpoint
+            {
+                machine.add_watchpoint(watchpoint);
+                *form = WatchpointForm::default();
+            }
+        });
+        let mut removed = None;
+        ui.horizontal_wrapped(|ui| {
+            for (index, watchpoint) in machine.watchpoints().iter().enumerate() {
+                if ui
+                    .button(format!("{} ✖", describe(watchpoint)))
+                    .on_hover_text("Remove the watchpoint")
+                    .clicked()
+                {
+                    removed = Some(index);
+                }
+            }
+        });
+        if let Some(index) = removed {
+            machine.remove_watchpoint(index);
+        }
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl DebugPanel {
+    fn trace_ui(&mut self, ui: &mut Ui, machine: &mut Machine) {
+        let form = &mut self.trace;
+        ui.horizontal(|ui| {
+            let tracing = machine.is_tracing();
+            ui.add_enabled_ui(!tracing, |ui| {
+                ui.label("Trace PC");
+                ui.add(egui::TextEdit::singleline(&mut form.pc).desired_width(72.0))
+                    .on_hover_text("Addresses like F000-F0FF, empty for all of them");
+                ui.label("Frames");
+                ui.add(egui::TextEdit::singleline(&mut form.frames).desired_width(56.0))
+                    .on_hover_text("Frames like 10-20 counted from the start of the trace");
+                ui.label("After");
+                ui.add(egui::TextEdit::singleline(&mut form.after).desired_width(56.0))
+                    .on_hover_text("Starts when the CPU reaches this address or label");
+            });
+            if tracing {
+                if ui.button("Stop trace").clicked() {
+                    form.message = Some(match machine.stop_trace() {
+                        Ok(name) => format!("Trace saved to {}", name.unwrap_or_default()),
+                        Err(e) => format!("Failed to save trace: {e}"),
+                    });
+                }
+            } else if ui.button("Start trace").clicked() {
+                form.message = Some(match form.filter(machine) {
+                    Ok(filter) => {
+                        let name = chrono::Local::now()
+                            .format("%Y-%m-%d-%H_%M_%S.trace")
+                            .to_string();
+                        match machine.start_trace(&name, filter) {
+                            Ok(()) => format!("Tracing to {name}"),
+                            Err(e) => format!("Failed to trace: {e}"),
+                        }
+                    }
+                    Err(e) => e,
+                });
+            }
+        });
+        if let Some(message) = &form.message {
+            ui.label(message);
+        }
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl TraceForm {
+    fn filter(&self, machine: &Machine) -> Result<TraceFilter, String> {
+        Ok(TraceFilter {
+            pc: optional(&self.pc).map(TraceFilter::parse_pc).transpose()?,
+            frames: optional(&self.frames)
+                .map(TraceFilter::parse_frames)
+                .transpose()?,
+            after: optional(&self.after)
+                .map(|text| {
+                    parse_address(text, machine).ok_or_else(|| format!("Unknown address {text}"))
+                })
+                .transpose()?,
         })
-        .response
     }
 }
+
+#[cfg(not(target_family = "wasm"))]
+fn optional(text: &str) -> Option<&str> {
+    Some(text.trim()).filter(|text| !text.is_empty())
+}
+
+fn registers_ui(ui: &mut Ui, machine: &mut Machine) {
+    let mut registers = machine.registers();
+    egui::Grid::new("registers_grid").show(ui, |ui| {
+        let Registers {
+            a,
+            b,
+            dp,
+            cc,
+            x,
+            y,
+            u,
+            s,
+            pc,
+        } = &mut registers;
+        for (name, value) in [("A", a), ("B", b), ("DP", dp)] {
+            ui.label(name);
+            ui.add(hex_value(value, 2));
+        }
+        ui.label("CC");
+        ui.add(hex_value(cc, 2));
+        ui.end_row();
+        for (name, value) in [("X", x), ("Y", y), ("U", u), ("S", s)] {
+            ui.label(name);
+            ui.add(hex_value(value, 4));
+        }
+        ui.end_row();
+        ui.label("PC");
+        ui.add(hex_value(pc, 4));
+    });
+    ui.horizontal(|ui| {
+        for (mask, name) in CC_FLAGS {
+            let mut set = registers.cc & mask != 0;
+            if ui.checkbox(&mut set, name).changed() {
+                registers.cc ^= mask;
+            }
+        }
+    });
+    if registers != machine.registers() {
+        machine.set_registers(&registers);
+    }
+}
+
+fn hex_value<T: egui::emath::Numeric>(value: &mut T, digits: usize) -> egui::DragValue<'_> {
+    egui::DragValue::new(value)
+        .hexadecimal(digits, false, true)
+        .speed(0.0)
+}
+
+/// Where the disassembly starts
+#[derive(Default)]
+struct Listing {
+    // the address of the followed branch, the listing follows PC when None
+    address: Option<int>,
+    // the previous addresses, to come back from a branch
+    history: Vec<Option<int>>,
+    goto: String,
+    #[cfg(not(target_family = "wasm"))]
+    file_dialog: Option<egui_file_dialog::FileDialog>,
+    message: Option<String>,
+}
+
+impl Listing {
+    fn follow(&mut self, address: Option<int>) {
+        self.history.push(self.address);
+        self.address = address;
+    }
+
+    fn ui(&mut self, ui: &mut Ui, machine: &mut Machine) {
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(!self.history.is_empty(), egui::Button::new("Back"))
+                .clicked()
+            {
+                self.address = self.history.pop().flatten();
+            }
+            if ui
+                .add_enabled(self.address.is_some(), egui::Button::new("PC"))
+                .on_hover_text("Follow the program counter")
+                .clicked()
+            {
+                self.follow(None);
+            }
+            ui.label("Go to");
+            let edit = ui
+                .add(egui::TextEdit::singleline(&mut self.goto).desired_width(80.0))
+                .on_hover_text("Hexadecimal address or label");
+            if edit.lost_focus()
+                && ui.input(|i| i.key_pressed(egui::Key::Enter))
+                && let Some(address) = parse_address(&self.goto, machine)
+            {
+                self.follow(Some(address));
+            }
+            #[cfg(not(target_family = "wasm"))]
+            self.symbols_ui(ui, machine);
+        });
+        if let Some(message) = &self.message {
+            ui.label(message);
+        }
+
+        let pc = machine.registers().pc as int;
+        let start = self.address.unwrap_or(pc);
+        for instruction in machine.disassemble(start, DISASSEMBLY_LINES) {
+            let address = instruction.address;
+            if let Some(label) = machine.symbols().get(address) {
+                ui.label(RichText::new(format!("{label}:")).monospace().weak());
+            }
+            ui.horizontal(|ui| {
+                let breakpoint = machine.debugger().has_breakpoint(address);
+                let marker =
+                    RichText::new(if breakpoint { "●" } else { "○" }).color(if breakpoint {
+                        Color32::RED
+                    } else {
+                        Color32::GRAY
+                    });
+                if ui
+                    .add(egui::Label::new(marker).sense(egui::Sense::click()))
+                    .on_hover_text("Toggle breakpoint")
+                    .clicked()
+                {
+                    machine.toggle_breakpoint(address);
+                }
+                let mut text =
+                    RichText::new(instruction.to_string_with(machine.symbols())).monospace();
+                if address == pc {
+                    text = text
+                        .strong()
+                        .background_color(ui.visuals().selection.bg_fill);
+                }
+                ui.add(egui::Label::new(text).sense(egui::Sense::click()))
+                    .on_hover_text(format!("{} cycles", instruction.cycles))
+                    .context_menu(|ui| {
+                        if ui.button("Run to cursor").clicked() {
+                            machine.run_to(address);
+                            ui.close();
+                        }
+                        if ui.button("Toggle breakpoint").clicked() {
+                            machine.toggle_breakpoint(address);
+                            ui.close();
+                        }
+                    });
+                if let Some(target) = instruction.target
+                    && ui
+                        .small_button("→")
+                        .on_hover_text(format!("Follow to {target:04X}"))
+                        .clicked()
+                {
+                    self.follow(Some(target));
+                }
+            });
+        }
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    fn symbols_ui(&mut self, ui: &mut Ui, machine: &mut Machine) {
+        if ui.button("Load symbols").clicked() {
+            let mut fd = egui_file_dialog::FileDialog::new();
+            fd.pick_file();
+            self.file_dialog = Some(fd);
+        }
+        let Some(fd) = &mut self.file_dialog else {
+            return;
+        };
+        fd.update(ui.ctx());
+        let Some(path) = fd.take_picked() else {
+            return;
+        };
+        self.message = Some(
+            match std::fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|text| Symbols::parse(&text))
+            {
+                Ok(symbols) => {
+                    let message =
+                        format!("{} symbols loaded from {}", symbols.len(), path.display());
+                    info!("{message}");
+                    machine.add_symbols(symbols);
+                    message
+                }
+                Err(e) => {
+                    warn!("Symbols error {e}");
+                    format!("Error: {e}")
+                }
+            },
+        );
+        self.file_dialog = None;
+    }
+}
+
+/// Reads a hexadecimal address or the name of a symbol
+fn parse_address(text: &str, machine: &Machine) -> Option<int> {
+    parse_hex(text).or_else(|| machine.symbols().address_of(text.trim()))
+}
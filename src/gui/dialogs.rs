@@ -1,13 +1,30 @@
 use crate::gui::about::About;
 #[cfg(not(target_family = "wasm"))]
-use crate::gui::debug::Debug;
+use crate::gui::binary::BinaryLoader;
+#[cfg(not(target_family = "wasm"))]
+use crate::gui::debug::DebugPanel;
+use crate::gui::memory::MemoryViewer;
+use crate::gui::palette::PaletteEditor;
+use crate::gui::profiler::ProfilerView;
 use crate::hardware::machine::Machine;
 use egui::{Context, Widget};
 
 #[derive(Default)]
 pub(crate) struct Dialogs {
     debug: bool,
+    #[cfg(not(target_family = "wasm"))]
+    debug_panel: DebugPanel,
     about: bool,
+    palette: bool,
+    palette_editor: PaletteEditor,
+    memory: bool,
+    memory_viewer: MemoryViewer,
+    profiler: bool,
+    profiler_view: ProfilerView,
+    #[cfg(not(target_family = "wasm"))]
+    binary: bool,
+    #[cfg(not(target_family = "wasm"))]
+    binary_loader: BinaryLoader,
 }
 
 impl Dialogs {
@@ -18,12 +35,54 @@ impl Dialogs {
         if self.about {
             self.show_about(ctx);
         }
+        if self.palette {
+            egui::Window::new("Palette")
+                .open(&mut self.palette)
+                .resizable(false)
+                .show(ctx, |ui| self.palette_editor.ui(ui, machine));
+        }
+        if self.memory {
+            egui::Window::new("Memory")
+                .open(&mut self.memory)
+                .default_size([640.0, 480.0])
+                .show(ctx, |ui| self.memory_viewer.ui(ui, machine));
+        }
+        if self.profiler {
+            egui::Window::new("Profiler")
+                .open(&mut self.profiler)
+                .default_size([520.0, 480.0])
+                .show(ctx, |ui| self.profiler_view.ui(ui, machine));
+        }
+        #[cfg(not(target_family = "wasm"))]
+        if self.binary {
+            egui::Window::new("Load binary")
+                .open(&mut self.binary)
+                .resizable(false)
+                .show(ctx, |ui| self.binary_loader.ui(ui, machine));
+        }
     }
 
     pub(crate) const fn set_show_about(&mut self) {
         self.about = true;
     }
 
+    pub(crate) const fn set_show_palette(&mut self) {
+        self.palette = true;
+    }
+
+    pub(crate) const fn set_show_memory(&mut self) {
+        self.memory = true;
+    }
+
+    pub(crate) const fn set_show_profiler(&mut self) {
+        self.profiler = true;
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub(crate) const fn set_show_binary_loader(&mut self) {
+        self.binary = true;
+    }
+
     pub(crate) const fn set_show_debug(&mut self) {
         self.debug = true;
     }
@@ -37,15 +96,15 @@ impl Dialogs {
         //         .max_size([600.0, 600.0])
         //         .open(&mut self.debug)
         //         .resizable(true)
-        //         .show(ctx, |ui| Debug::new(machine).ui(ui));
+        //         .show(ctx, |ui| self.debug_panel.ui(ui, machine));
         // }
 
         #[cfg(not(target_family = "wasm"))]
         ctx.show_viewport_immediate(
-            egui::ViewportId::from_hash_of("about_viewport"),
+            egui::ViewportId::from_hash_of("debug_viewport"),
             egui::ViewportBuilder::default()
                 .with_title("Debug Maurice")
-                .with_inner_size([400.0, 200.0]),
+                .with_inner_size([560.0, 640.0]),
             move |ctx, class| {
                 assert!(
                     class == egui::ViewportClass::Immediate,
@@ -53,7 +112,7 @@ impl Dialogs {
                 );
 
                 egui::CentralPanel::default().show(ctx, |ui| {
-                    Debug::new(machine).ui(ui);
+                    self.debug_panel.ui(ui, machine);
                 });
                 if ctx.input(|i| i.viewport().close_requested()) {
                     // Tell parent to close us.
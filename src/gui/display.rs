@@ -0,0 +1,106 @@
+use egui::{Rect, TextureOptions, Vec2, vec2};
+use serde::{Deserialize, Serialize};
+
+/// On a TV the 320x200 picture fills a 4:3 screen, so the MO5 pixels are taller than wide
+const PIXEL_ASPECT: f32 = 1.2;
+
+/// How the GPU scales the picture to the window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Filter {
+    /// Nearest pixel, sharp but the pixels may not all have the same size
+    #[default]
+    Nearest,
+    /// Largest integer scale of the pixel width that fits in the window
+    Integer,
+    /// Bilinear filtering
+    Smooth,
+}
+
+impl Filter {
+    pub const ALL: [Self; 3] = [Self::Nearest, Self::Integer, Self::Smooth];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Nearest => "Nearest",
+            Self::Integer => "Integer scaling",
+            Self::Smooth => "Smooth",
+        }
+    }
+
+    pub(crate) const fn texture_options(self) -> TextureOptions {
+        match self {
+            Self::Smooth => TextureOptions::LINEAR,
+            Self::Nearest | Self::Integer => TextureOptions::NEAREST,
+        }
+    }
+}
+
+/// Returns the size of a window displaying the image `zoom` times with a 4:3 aspect ratio
+pub fn window_size(width: usize, height: usize, zoom: usize) -> Vec2 {
+    vec2((width * zoom) as f32, (height * zoom) as f32 * PIXEL_ASPECT)
+}
+
+/// Returns where the image is displayed in the available area. The image is centered and
+/// letterboxed to keep the 4:3 aspect ratio of a TV, or stretched to the whole area if
+/// `keep_aspect` is false. The integer scaling gives every pixel the same whole width, the
+/// height following the aspect ratio, or square pixels if `keep_aspect` is false. The image
+/// only shrinks below its size in a smaller area.
+pub(crate) fn image_rect(
+    available: Rect,
+    [width, height]: [usize; 2],
+    filter: Filter,
+    keep_aspect: bool,
+) -> Rect {
+    let width = width as f32;
+    let height = height as f32 * if keep_aspect { PIXEL_ASPECT } else { 1.0 };
+    let scale = (available.width() / width).min(available.height() / height);
+    let size = match filter {
+        // 1.2 is not exact in floating point, a 3x picture would floor to 2x
+        Filter::Integer if scale >= 1.0 => vec2(width, height) * (scale + 1e-4).floor(),
+        _ if keep_aspect || filter == Filter::Integer => vec2(width, height) * scale,
+        _ => available.size(),
+    };
+    Rect::from_center_size(available.center(), size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::pos2;
+
+    const AVAILABLE: Rect = Rect {
+        min: pos2(0.0, 20.0),
+        max: pos2(1000.0, 740.0),
+    };
+
+    #[test]
+    fn test_image_rect_letterbox() {
+        let rect = image_rect(AVAILABLE, [320, 200], Filter::Nearest, true);
+        assert_eq!(vec2(960.0, 720.0), rect.size().round());
+        assert_eq!(pos2(20.0, 20.0), rect.min.round());
+    }
+
+    #[test]
+    fn test_image_rect_stretch() {
+        let rect = image_rect(AVAILABLE, [320, 200], Filter::Smooth, false);
+        assert_eq!(AVAILABLE, rect);
+    }
+
+    #[test]
+    fn test_image_rect_integer() {
+        let rect = image_rect(AVAILABLE, [320, 200], Filter::Integer, true);
+        assert_eq!(vec2(960.0, 720.0), rect.size().round());
+        assert_eq!(pos2(20.0, 20.0), rect.min.round());
+        let rect = image_rect(AVAILABLE, [320, 200], Filter::Integer, false);
+        assert_eq!(vec2(960.0, 600.0), rect.size());
+        assert_eq!(pos2(20.0, 80.0), rect.min);
+    }
+
+    #[test]
+    fn test_image_rect_integer_small() {
+        let available = Rect::from_min_size(pos2(0.0, 0.0), vec2(200.0, 100.0));
+        let rect = image_rect(available, [320, 200], Filter::Integer, false);
+        assert_eq!(vec2(160.0, 100.0), rect.size());
+        assert!(available.contains_rect(rect));
+    }
+}
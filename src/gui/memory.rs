@@ -0,0 +1,356 @@
+use crate::hardware::machine::Machine;
+use crate::hardware::memory::{Bank, Space};
+use egui::{Color32, RichText, Ui};
+#[cfg(not(target_family = "wasm"))]
+use log::{info, warn};
+
+const BYTES_PER_ROW: usize = 16;
+
+#[cfg(not(target_family = "wasm"))]
+enum FileAction {
+    Import,
+    Export,
+}
+
+/// Shows the memory as seen by the CPU or one of the banks, the bytes changed during the last
+/// frame are highlighted
+pub(super) struct MemoryViewer {
+    space: Space,
+    // the content of the space before and after the last frame
+    previous: Vec<u8>,
+    current: Vec<u8>,
+    frame: u64,
+    selected: Option<usize>,
+    value: String,
+    goto: String,
+    search: String,
+    scroll_to: Option<usize>,
+    #[cfg(not(target_family = "wasm"))]
+    range_start: String,
+    #[cfg(not(target_family = "wasm"))]
+    range_end: String,
+    #[cfg(not(target_family = "wasm"))]
+    file_dialog: Option<(egui_file_dialog::FileDialog, FileAction)>,
+    message: Option<String>,
+}
+
+impl Default for MemoryViewer {
+    fn default() -> Self {
+        Self {
+            space: Space::Cpu,
+            previous: Vec::new(),
+            current: Vec::new(),
+            frame: u64::MAX,
+            selected: None,
+            value: String::new(),
+            goto: String::new(),
+            search: String::new(),
+            scroll_to: None,
+            #[cfg(not(target_family = "wasm"))]
+            range_start: String::new(),
+            #[cfg(not(target_family = "wasm"))]
+            range_end: String::new(),
+            #[cfg(not(target_family = "wasm"))]
+            file_dialog: None,
+            message: None,
+        }
+    }
+}
+
+impl MemoryViewer {
+    pub(super) fn ui(&mut self, ui: &mut Ui, machine: &mut Machine) {
+        self.update(machine, false);
+        self.space_ui(ui, machine);
+        self.navigation_ui(ui);
+        self.edit_ui(ui, machine);
+        #[cfg(not(target_family = "wasm"))]
+        self.import_export_ui(ui, machine);
+        if let Some(message) = &self.message {
+            ui.label(message);
+        }
+        ui.separator();
+        self.bytes_ui(ui);
+    }
+
+    /// Takes a snapshot of the space when the machine has run since the last one
+    fn update(&mut self, machine: &Machine, force: bool) {
+        if force || machine.frame() != self.frame {
+            self.frame = machine.frame();
+            let current = machine.mem.read_space(self.space, 0, self.space.size());
+            self.previous = std::mem::replace(&mut self.current, current);
+            if self.previous.len() != self.current.len() {
+                self.previous.clone_from(&self.current);
+            }
+        }
+    }
+
+    fn space_ui(&mut self, ui: &mut Ui, machine: &Machine) {
+        let mut space = self.space;
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut space, Space::Cpu, Space::Cpu.name());
+            for bank in Bank::ALL {
+                ui.selectable_value(&mut space, Space::Bank(bank), bank.name());
+            }
+        });
+        if space != self.space {
+            self.space = space;
+            self.selected = None;
+            self.current.clear();
+            self.update(machine, true);
+        }
+    }
+
+    fn navigation_ui(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Go to");
+            let edit = ui.add(egui::TextEdit::singleline(&mut self.goto).desired_width(40.0));
+            if edit.lost_focus()
+                && ui.input(|i| i.key_pressed(egui::Key::Enter))
+                && let Some(offset) = parse_offset(&self.goto, self.space)
+            {
+                self.select(offset);
+            }
+            ui.label("Search");
+            let edit = ui
+                .add(egui::TextEdit::singleline(&mut self.search).desired_width(120.0))
+                .on_hover_text("Hexadecimal bytes like A7 C0, or a \"string\"");
+            let submitted = edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui.button("Next").clicked() || submitted {
+                self.find_next();
+            }
+        });
+    }
+
+    fn find_next(&mut self) {
+        self.message = match parse_pattern(&self.search) {
+            Ok(pattern) => {
+                let from = self.selected.map_or(0, |offset| offset + 1);
+                match find(&self.current, &pattern, from) {
+                    Some(offset) => {
+                        self.select(offset);
+                        None
+                    }
+                    None => Some("Not found".to_string()),
+                }
+            }
+            Err(e) => Some(e),
+        };
+    }
+
+    fn select(&mut self, offset: usize) {
+        self.selected = Some(offset);
+        self.scroll_to = Some(offset / BYTES_PER_ROW);
+        self.value = self
+            .current
+            .get(offset)
+            .map_or(String::new(), |value| format!("{value:02X}"));
+    }
+
+    fn edit_ui(&mut self, ui: &mut Ui, machine: &mut Machine) {
+        let Some(offset) = self.selected else {
+            ui.label("Click a byte to edit it");
+            return;
+        };
+        ui.horizontal(|ui| {
+            ui.label(format!("{offset:04X}"));
+            let edit = ui.add(egui::TextEdit::singleline(&mut self.value).desired_width(24.0));
+            if edit.lost_focus()
+                && ui.input(|i| i.key_pressed(egui::Key::Enter))
+                && let Ok(value) = u8::from_str_radix(self.value.trim(), 16)
+            {
+                machine.mem.poke_space(self.space, offset, value);
+                self.update(machine, true);
+                // typing goes on with the next byte
+                if offset + 1 < self.space.size() {
+                    self.select(offset + 1);
+                }
+                edit.request_focus();
+            }
+        });
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    fn import_export_ui(&mut self, ui: &mut Ui, machine: &mut Machine) {
+        ui.horizontal(|ui| {
+            ui.label("Range");
+            ui.add(egui::TextEdit::singleline(&mut self.range_start).desired_width(40.0));
+            ui.label("-");
+            ui.add(egui::TextEdit::singleline(&mut self.range_end).desired_width(40.0));
+            if ui
+                .button("Import")
+                .on_hover_text("Loads a binary file at the start of the range")
+                .clicked()
+            {
+                let mut fd = egui_file_dialog::FileDialog::new();
+                fd.pick_file();
+                self.file_dialog = Some((fd, FileAction::Import));
+            }
+            if ui.button("Export").clicked() {
+                let mut fd = egui_file_dialog::FileDialog::new().default_file_name("memory.bin");
+                fd.save_file();
+                self.file_dialog = Some((fd, FileAction::Export));
+            }
+        });
+        let Some((fd, action)) = &mut self.file_dialog else {
+            return;
+        };
+        fd.update(ui.ctx());
+        let Some(path) = fd.take_picked() else {
+            return;
+        };
+        let start = parse_offset(&self.range_start, self.space).unwrap_or(0);
+        let result = match action {
+            FileAction::Import => std::fs::read(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|data| machine.mem.write_space(self.space, start, &data))
+                .map(|_| format!("{} imported at {start:04X}", path.display())),
+            FileAction::Export => {
+                let end =
+                    parse_offset(&self.range_end, self.space).unwrap_or(self.space.size() - 1);
+                let data = machine
+                    .mem
+                    .read_space(self.space, start, end.saturating_sub(start) + 1);
+                std::fs::write(&path, &data)
+                    .map_err(|e| e.to_string())
+                    .map(|_| format!("{} bytes exported to {}", data.len(), path.display()))
+            }
+        };
+        self.message = Some(match result {
+            Ok(message) => {
+                info!("{message}");
+                message
+            }
+            Err(e) => {
+                warn!("Memory error {e}");
+                format!("Error: {e}")
+            }
+        });
+        self.file_dialog = None;
+        self.update(machine, true);
+    }
+
+    fn bytes_ui(&mut self, ui: &mut Ui) {
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace) + 2.0;
+        let rows = self.current.len() / BYTES_PER_ROW;
+        let mut scroll = egui::ScrollArea::vertical().auto_shrink(false);
+        if let Some(row) = self.scroll_to.take() {
+            scroll = scroll.vertical_scroll_offset(row as f32 * row_height);
+        }
+        let mut clicked = None;
+        scroll.show_rows(ui, row_height, rows, |ui, rows| {
+            for row in rows {
+                let start = row * BYTES_PER_ROW;
+                ui.horizontal(|ui| {
+                    ui.spacing_mut().item_spacing.x = 4.0;
+                    ui.label(RichText::new(format!("{start:04X}")).monospace().weak());
+                    for offset in start..start + BYTES_PER_ROW {
+                        if ui
+                            .add(
+                                egui::Label::new(self.byte_text(ui, offset))
+                                    .sense(egui::Sense::click()),
+                            )
+                            .clicked()
+                        {
+                            clicked = Some(offset);
+                        }
+                    }
+                    let ascii: String = self.current[start..start + BYTES_PER_ROW]
+                        .iter()
+                        .map(|&byte| {
+                            if (0x20..0x7F).contains(&byte) {
+                                byte as char
+                            } else {
+                                '.'
+                            }
+                        })
+                        .collect();
+                    ui.label(RichText::new(ascii).monospace());
+                });
+            }
+        });
+        if let Some(offset) = clicked {
+            self.selected = Some(offset);
+            self.value = format!("{:02X}", self.current[offset]);
+        }
+    }
+
+    fn byte_text(&self, ui: &Ui, offset: usize) -> RichText {
+        let value = self.current[offset];
+        let mut text = RichText::new(format!("{value:02X}")).monospace();
+        if self.previous.get(offset).is_some_and(|&old| old != value) {
+            text = text.color(Color32::from_rgb(0xFF, 0x60, 0x40));
+        }
+        if self.selected == Some(offset) {
+            text = text.background_color(ui.visuals().selection.bg_fill);
+        }
+        text
+    }
+}
+
+fn parse_offset(text: &str, space: Space) -> Option<usize> {
+    usize::from_str_radix(text.trim(), 16)
+        .ok()
+        .filter(|&offset| offset < space.size())
+}
+
+/// Reads a search pattern: a string between double quotes, or hexadecimal bytes optionally
+/// separated by spaces
+fn parse_pattern(text: &str) -> Result<Vec<u8>, String> {
+    let text = text.trim();
+    if let Some(string) = text
+        .strip_prefix('"')
+        .map(|string| string.strip_suffix('"').unwrap_or(string))
+    {
+        return if string.is_empty() {
+            Err("Empty pattern".to_string())
+        } else {
+            Ok(string.bytes().collect())
+        };
+    }
+    let digits: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if digits.is_empty() || !digits.len().is_multiple_of(2) {
+        return Err(format!("Invalid pattern {text}"));
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| {
+            digits
+                .get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(|| format!("Invalid pattern {text}"))
+        })
+        .collect()
+}
+
+/// Finds the pattern from the given offset, the search goes on from the start
+fn find(data: &[u8], pattern: &[u8], from: usize) -> Option<usize> {
+    let matches = |offset: &usize| data[*offset..].starts_with(pattern);
+    (from.min(data.len())..data.len())
+        .find(matches)
+        .or_else(|| (0..from.min(data.len())).find(matches))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pattern() {
+        assert_eq!(Ok(vec![0xA7, 0xC0]), parse_pattern("A7 C0"));
+        assert_eq!(Ok(vec![0xA7, 0xC0]), parse_pattern("a7c0"));
+        assert_eq!(Ok(b"RUN".to_vec()), parse_pattern("\"RUN\""));
+        assert!(parse_pattern("A7C").is_err());
+        assert!(parse_pattern("ZZ").is_err());
+        assert!(parse_pattern("\"\"").is_err());
+    }
+
+    #[test]
+    fn test_find() {
+        let data = [1, 2, 3, 1, 2, 3];
+        assert_eq!(Some(0), find(&data, &[1, 2], 0));
+        assert_eq!(Some(3), find(&data, &[1, 2], 1));
+        // the search wraps around
+        assert_eq!(Some(0), find(&data, &[1, 2], 4));
+        assert_eq!(None, find(&data, &[4], 0));
+    }
+}
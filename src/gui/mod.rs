@@ -1,44 +1,106 @@
 mod about;
+#[cfg(not(target_family = "wasm"))]
+mod binary;
+pub mod crt;
 mod debug;
 mod dialogs;
+pub mod display;
+mod memory;
 mod message;
+mod palette;
+mod profiler;
+mod settings;
 
 use crate::hardware::keyboard::vkey::MO5VirtualKeyCode;
 use egui::{DroppedFile, FontFamily, FontId, HoveredFile, Pos2, RichText};
 use {
     eframe::{App, Frame, epaint::TextureHandle},
-    egui::{Color32, Context, Event, Key, Rect, TextureOptions, Ui, ViewportCommand, pos2},
+    egui::{Color32, Context, Event, Key, Rect, Ui, ViewportCommand, pos2},
 };
 
+#[cfg(not(target_family = "wasm"))]
+use crate::capture::VideoFormat;
+use crate::gui::crt::{CrtDisplay, CrtPreset, CrtSettings};
 use crate::gui::dialogs::Dialogs;
+use crate::gui::display::{Filter, image_rect};
 use crate::gui::message::Message;
+use crate::gui::settings::Settings;
+use crate::hardware::M6809::{IllegalOpcode, IllegalOpcodePolicy};
 use crate::hardware::k7::K7;
 use crate::hardware::machine::Machine;
-use crate::hardware::screen::{HEIGHT, WIDTH};
+use crate::hardware::screen::BORDER_SIZES;
+use crate::hardware::trace::TraceFilter;
+#[cfg(not(target_family = "wasm"))]
+use crate::watch::Watcher;
 use log::{info, warn};
 
-#[derive(Default)]
+/// What is done with the file picked in the file dialog
+#[cfg(not(target_family = "wasm"))]
+#[derive(Clone, Copy)]
+enum FileAction {
+    OpenK7,
+    ImportBasic,
+}
+
 pub struct Gui {
     machine: Machine,
     image: Option<TextureHandle>,
     dialogs: Dialogs,
     #[cfg(not(target_family = "wasm"))]
-    file_dialog: Option<egui_file_dialog::FileDialog>,
+    file_dialog: Option<(egui_file_dialog::FileDialog, FileAction)>,
     message: Option<Message>,
+    #[cfg(not(target_family = "wasm"))]
+    capture_scale: usize,
+    #[cfg(not(target_family = "wasm"))]
+    watcher: Option<Watcher>,
+    filter: Filter,
+    keep_aspect: bool,
+    crt: CrtSettings,
+    crt_display: CrtDisplay,
+}
+
+impl Default for Gui {
+    fn default() -> Self {
+        Self {
+            machine: Machine::default(),
+            image: None,
+            dialogs: Dialogs::default(),
+            #[cfg(not(target_family = "wasm"))]
+            file_dialog: None,
+            message: None,
+            #[cfg(not(target_family = "wasm"))]
+            capture_scale: 1,
+            #[cfg(not(target_family = "wasm"))]
+            watcher: None,
+            filter: Filter::default(),
+            keep_aspect: true,
+            crt: CrtSettings::default(),
+            crt_display: CrtDisplay::default(),
+        }
+    }
 }
 
 impl Gui {
     fn handle_input(&mut self, ctx: &Context) -> bool {
-        ctx.input(|input_state| {
+        let toggle_fullscreen = ctx.input(|input_state| {
             let modifiers = input_state.modifiers;
             self.machine.keyboard.modifiers = modifiers.into();
             input_state.events.iter().for_each(|event| {
                 self.handle_key_event(event);
             });
+            input_state.key_pressed(Key::F11)
         });
+        if toggle_fullscreen {
+            Self::toggle_fullscreen(ctx);
+        }
         self.handle_drag_drop(ctx)
     }
 
+    fn toggle_fullscreen(ctx: &Context) {
+        let fullscreen = ctx.input(|input_state| input_state.viewport().fullscreen);
+        ctx.send_viewport_cmd(ViewportCommand::Fullscreen(!fullscreen.unwrap_or(false)));
+    }
+
     fn handle_key_event(&mut self, event: &Event) {
         let Event::Key {
             key,
@@ -54,6 +116,12 @@ impl Gui {
         match key {
             Key::F7 => self.machine.reset_soft(),
             Key::F8 => self.machine.reset_hard(),
+            #[cfg(not(target_family = "wasm"))]
+            Key::F12 => {
+                if *pressed {
+                    self.take_screenshot();
+                }
+            }
             _ => {
                 if let Ok(vk) = MO5VirtualKeyCode::try_from(*key) {
                     if *pressed {
@@ -75,10 +143,151 @@ impl Gui {
         })
     }
 
+    /// Restores the settings saved when the emulator was last closed
+    pub fn load_settings(&mut self, storage: Option<&dyn eframe::Storage>) {
+        let Some(settings) =
+            storage.and_then(|storage| eframe::get_value::<Settings>(storage, eframe::APP_KEY))
+        else {
+            return;
+        };
+        info!("Settings loaded");
+        self.machine.set_palette(settings.palette);
+        self.machine.set_border(settings.border);
+        self.filter = settings.filter;
+        self.keep_aspect = settings.keep_aspect;
+        self.crt = settings.crt;
+        self.machine.set_illegal_opcodes(settings.illegal_opcodes);
+    }
+
+    fn settings(&self) -> Settings {
+        Settings {
+            palette: *self.machine.palette(),
+            border: self.machine.border(),
+            filter: self.filter,
+            keep_aspect: self.keep_aspect,
+            crt: self.crt,
+            illegal_opcodes: self.machine.illegal_opcodes(),
+        }
+    }
+
     pub fn set_k7(&mut self, k7: K7) {
         self.machine.set_k7(k7);
     }
 
+    pub fn start_audio_recording(&mut self, name: &str) -> Result<(), String> {
+        self.machine.start_audio_recording(name)
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    fn take_screenshot(&mut self) {
+        let name = chrono::Local::now()
+            .format("%Y-%m-%d-%H_%M_%S.png")
+            .to_string();
+        let image = self.machine.screenshot();
+        let message = match crate::capture::save_png(&image, self.capture_scale, &name) {
+            Ok(()) => format!("Screenshot saved to {name}"),
+            Err(e) => format!("Failed to save screenshot: {e}"),
+        };
+        info!("{message}");
+        self.message = Some(Message::new(message));
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    fn export_basic_listing(&mut self) {
+        let name = chrono::Local::now()
+            .format("%Y-%m-%d-%H_%M_%S.bas")
+            .to_string();
+        let message = match self
+            .machine
+            .basic_listing()
+            .and_then(|listing| std::fs::write(&name, listing).map_err(|e| e.to_string()))
+        {
+            Ok(()) => format!("BASIC listing saved to {name}"),
+            Err(e) => format!("Failed to export the BASIC listing: {e}"),
+        };
+        info!("{message}");
+        self.message = Some(Message::new(message));
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn start_video_capture(
+        &mut self,
+        name: &str,
+        format: VideoFormat,
+        scale: usize,
+    ) -> Result<(), String> {
+        self.machine.start_video_capture(name, format, scale)
+    }
+
+    /// Restarts the machine with the watched file each time it changes
+    #[cfg(not(target_family = "wasm"))]
+    pub fn watch(&mut self, watcher: Watcher) {
+        self.watcher = Some(watcher);
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    fn poll_watcher(&mut self) {
+        if let Some(watcher) = &mut self.watcher
+            && let Some(message) = watcher.poll(&mut self.machine)
+        {
+            info!("{message}");
+            self.message = Some(Message::new(message));
+        }
+    }
+
+    pub fn start_trace(&mut self, name: &str, filter: TraceFilter) -> Result<(), String> {
+        self.machine.start_trace(name, filter)
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    fn start_new_video_capture(&mut self, format: VideoFormat) {
+        let mut name = chrono::Local::now().format("%Y-%m-%d-%H_%M_%S").to_string();
+        if format == VideoFormat::Gif {
+            name.push_str(".gif");
+        }
+        let message = match self
+            .machine
+            .start_video_capture(&name, format, self.capture_scale)
+        {
+            Ok(()) => format!("Capturing video to {name}"),
+            Err(e) => format!("Failed to capture video: {e}"),
+        };
+        info!("{message}");
+        self.message = Some(Message::new(message));
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    fn stop_video_capture(&mut self) {
+        let message = match self.machine.stop_video_capture() {
+            Ok(Some(name)) => format!("Video saved to {name}"),
+            Ok(None) => return,
+            Err(e) => format!("Failed to save video: {e}"),
+        };
+        info!("{message}");
+        self.message = Some(Message::new(message));
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    fn toggle_audio_recording(&mut self) {
+        let message = if self.machine.is_recording_audio() {
+            match self.machine.stop_audio_recording() {
+                Ok(Some(name)) => format!("Audio saved to {name}"),
+                Ok(None) => return,
+                Err(e) => format!("Failed to save audio: {e}"),
+            }
+        } else {
+            let name = chrono::Local::now()
+                .format("%Y-%m-%d-%H_%M_%S.wav")
+                .to_string();
+            match self.machine.start_audio_recording(&name) {
+                Ok(()) => format!("Recording audio to {name}"),
+                Err(e) => format!("Failed to record audio: {e}"),
+            }
+        };
+        info!("{message}");
+        self.message = Some(Message::new(message));
+    }
+
     const fn handle_hovered_files(&mut self, hovered_files: &[HoveredFile]) -> bool {
         if hovered_files.is_empty() {
             return false;
@@ -113,14 +322,27 @@ impl Gui {
 
         if let Some(buf) = pixels {
             let image = egui::ColorImage::from_rgb([buf.width, buf.height], buf.data);
+            let options = self.filter.texture_options();
             match &mut self.image {
-                None => {
-                    self.image =
-                        Some(ctx.load_texture("my_texture", image, TextureOptions::default()))
-                }
-                Some(texture) => texture.set(image, TextureOptions::default()),
+                None => self.image = Some(ctx.load_texture("my_texture", image, options)),
+                Some(texture) => texture.set(image, options),
             }
         }
+        if let Some(illegal) = self.machine.take_illegal_opcode() {
+            self.show_illegal_opcode(illegal);
+        }
+    }
+
+    fn show_illegal_opcode(&mut self, illegal: IllegalOpcode) {
+        let instruction = &self.machine.disassemble(illegal.address, 1)[0];
+        self.message = Some(Message::new(format!(
+            "Illegal opcode {:02X}: {}",
+            illegal.opcode,
+            instruction.to_string_with(self.machine.symbols())
+        )));
+        if self.machine.illegal_opcodes() == IllegalOpcodePolicy::Break {
+            self.dialogs.set_show_debug();
+        }
     }
 
     fn build_menu_panel(&mut self, ctx: &Context) {
@@ -144,14 +366,35 @@ impl Gui {
         ui.menu_button("File", |ui| {
             #[cfg(not(target_family = "wasm"))]
             if ui.button("Select K7").clicked() {
-                let mut fd = egui_file_dialog::FileDialog::new();
-                fd.pick_file();
-                self.file_dialog = Some(fd);
+                self.pick_file(FileAction::OpenK7);
             }
             if ui.button("Rewind Tape").clicked() {
                 self.machine.rewind_k7();
             }
             #[cfg(not(target_family = "wasm"))]
+            {
+                let label = if self.machine.is_recording_audio() {
+                    "Stop audio recording"
+                } else {
+                    "Start audio recording"
+                };
+                if ui.button(label).clicked() {
+                    self.toggle_audio_recording();
+                }
+            }
+            #[cfg(not(target_family = "wasm"))]
+            if ui.button("Load binary...").clicked() {
+                self.dialogs.set_show_binary_loader();
+            }
+            #[cfg(not(target_family = "wasm"))]
+            if ui.button("Import BASIC listing").clicked() {
+                self.pick_file(FileAction::ImportBasic);
+            }
+            #[cfg(not(target_family = "wasm"))]
+            if ui.button("Export BASIC listing").clicked() {
+                self.export_basic_listing();
+            }
+            #[cfg(not(target_family = "wasm"))]
             if ui.button("Exit").clicked() {
                 info!("Exit");
                 ui.ctx().send_viewport_cmd(egui::ViewportCommand::Close);
@@ -184,24 +427,86 @@ impl Gui {
     #[cfg(not(target_family = "wasm"))]
     fn image_menu(&mut self, ui: &mut Ui, ctx: &Context) {
         ui.menu_button("Image", |ui| {
-            if ui.button("Zoom 1x").clicked() {
-                ctx.send_viewport_cmd(ViewportCommand::InnerSize(
-                    [WIDTH as f32, HEIGHT as f32].into(),
-                ))
+            for zoom in 1..=3 {
+                if ui.button(format!("Zoom {zoom}x")).clicked() {
+                    let screen = &self.machine.screen;
+                    ctx.send_viewport_cmd(ViewportCommand::InnerSize(display::window_size(
+                        screen.width(),
+                        screen.height(),
+                        zoom,
+                    )))
+                }
             }
-            if ui.button("Zoom 2x").clicked() {
-                ctx.send_viewport_cmd(ViewportCommand::InnerSize(
-                    [(2 * WIDTH) as f32, (2 * HEIGHT) as f32].into(),
-                ))
+            if ui.button("Fullscreen (F11)").clicked() {
+                Self::toggle_fullscreen(ctx);
             }
-            if ui.button("Zoom 3x").clicked() {
-                ctx.send_viewport_cmd(ViewportCommand::InnerSize(
-                    [(3 * WIDTH) as f32, (3 * HEIGHT) as f32].into(),
-                ))
+            ui.menu_button("Filter", |ui| {
+                for filter in Filter::ALL {
+                    ui.radio_value(&mut self.filter, filter, filter.name());
+                }
+            });
+            ui.checkbox(&mut self.keep_aspect, "Keep 4:3 aspect ratio");
+            ui.menu_button("CRT", |ui| self.crt_menu(ui));
+            if ui.button("Palette...").clicked() {
+                self.dialogs.set_show_palette();
+            }
+            ui.menu_button("Overscan", |ui| {
+                for border in BORDER_SIZES {
+                    let label = if border == 0 {
+                        "None".to_string()
+                    } else {
+                        format!("{border} pixels")
+                    };
+                    if ui.radio(self.machine.border() == border, label).clicked() {
+                        self.machine.set_border(border);
+                    }
+                }
+            });
+            ui.separator();
+            if ui.button("Screenshot (F12)").clicked() {
+                self.take_screenshot();
             }
+            if self.machine.is_capturing_video() {
+                if ui.button("Stop video capture").clicked() {
+                    self.stop_video_capture();
+                }
+            } else {
+                if ui.button("Capture video to GIF").clicked() {
+                    self.start_new_video_capture(VideoFormat::Gif);
+                }
+                if ui.button("Capture video to PNG files").clicked() {
+                    self.start_new_video_capture(VideoFormat::PngSequence);
+                }
+            }
+            ui.menu_button("Capture scale", |ui| {
+                for scale in 1..=3 {
+                    ui.radio_value(&mut self.capture_scale, scale, format!("{scale}x"));
+                }
+            });
         });
     }
 
+    #[cfg(not(target_family = "wasm"))]
+    fn crt_menu(&mut self, ui: &mut Ui) {
+        let current = CrtPreset::matching(&self.crt);
+        for preset in CrtPreset::ALL {
+            if ui.radio(current == Some(preset), preset.name()).clicked() {
+                self.crt = preset.settings();
+            }
+        }
+        ui.separator();
+        let crt = &mut self.crt;
+        for (value, name) in [
+            (&mut crt.scanlines, "Scanlines"),
+            (&mut crt.mask, "Phosphor mask"),
+            (&mut crt.bloom, "Bloom"),
+            (&mut crt.curvature, "Curvature"),
+            (&mut crt.pal_blur, "PAL color blur"),
+        ] {
+            ui.add(egui::Slider::new(value, 0.0..=1.0).text(name));
+        }
+    }
+
     //Reset
     //     Soft Reset
     // Hard Reset
@@ -210,6 +515,20 @@ impl Gui {
             if ui.button("Debug").clicked() {
                 self.dialogs.set_show_debug();
             }
+            if ui.button("Memory").clicked() {
+                self.dialogs.set_show_memory();
+            }
+            if ui.button("Profiler").clicked() {
+                self.dialogs.set_show_profiler();
+            }
+            ui.menu_button("Illegal opcodes", |ui| {
+                let current = self.machine.illegal_opcodes();
+                for policy in IllegalOpcodePolicy::ALL {
+                    if ui.radio(current == policy, policy.name()).clicked() {
+                        self.machine.set_illegal_opcodes(policy);
+                    }
+                }
+            });
         });
     }
 
@@ -224,6 +543,20 @@ impl Gui {
         });
     }
 
+    /// The light pen follows the mouse pointer over the displayed image
+    fn update_light_pen(&mut self, ctx: &Context, rect: Rect, [width, height]: [usize; 2]) {
+        let (position, clic) =
+            ctx.input(|input| (input.pointer.hover_pos(), input.pointer.primary_down()));
+        let position = position
+            .filter(|position| rect.contains(*position))
+            .map(|position| {
+                let x = (position.x - rect.min.x) / rect.width() * width as f32;
+                let y = (position.y - rect.min.y) / rect.height() * height as f32;
+                (x as usize, y as usize)
+            });
+        self.machine.set_light_pen(position, clic);
+    }
+
     fn show_message(ui: &mut Ui, text: &str) {
         ui.vertical_centered(|ui| {
             ui.centered_and_justified(|ui| {
@@ -235,19 +568,42 @@ impl Gui {
         });
     }
 
+    #[cfg(not(target_family = "wasm"))]
+    fn pick_file(&mut self, action: FileAction) {
+        let mut fd = egui_file_dialog::FileDialog::new();
+        fd.pick_file();
+        self.file_dialog = Some((fd, action));
+    }
+
     #[cfg(not(target_family = "wasm"))]
     fn handle_file_dialog(&mut self, ctx: &Context) {
-        if let Some(fd) = &mut self.file_dialog {
+        if let Some((fd, action)) = &mut self.file_dialog {
             fd.update(ctx);
             if let Some(path) = fd.take_picked() {
-                match K7::try_from(path) {
-                    Ok(k7) => self.machine.set_k7(k7),
-                    Err(e) => warn!("{e}"),
+                match action {
+                    FileAction::OpenK7 => match K7::try_from(path) {
+                        Ok(k7) => self.machine.set_k7(k7),
+                        Err(e) => warn!("{e}"),
+                    },
+                    FileAction::ImportBasic => self.import_basic_listing(&path),
                 }
                 self.file_dialog = None;
             }
         }
     }
+
+    #[cfg(not(target_family = "wasm"))]
+    fn import_basic_listing(&mut self, path: &std::path::Path) {
+        let message = match std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|listing| self.machine.load_basic(&listing))
+        {
+            Ok(()) => format!("BASIC listing loaded from {}", path.display()),
+            Err(e) => format!("Failed to import the BASIC listing: {e}"),
+        };
+        info!("{message}");
+        self.message = Some(Message::new(message));
+    }
 }
 
 impl App for Gui {
@@ -255,25 +611,43 @@ impl App for Gui {
         let is_hovered = self.handle_input(ctx);
         self.build_menu_panel(ctx);
         self.dialogs.eventually_show_dialogs(ctx, &mut self.machine);
+        #[cfg(not(target_family = "wasm"))]
+        self.poll_watcher();
         self.update_texture(ctx);
 
         #[cfg(not(target_family = "wasm"))]
         self.handle_file_dialog(ctx);
 
-        let Some(image) = &self.image else { return };
-        let available_rect = ctx.available_rect();
-        let rect = Rect {
-            min: pos2(available_rect.left(), available_rect.top()),
-            max: pos2(available_rect.width(), available_rect.height()),
+        let Some(image) = self.image.clone() else {
+            return;
         };
+        let rect = image_rect(
+            ctx.available_rect(),
+            image.size(),
+            self.filter,
+            self.keep_aspect,
+        );
         let uv = Rect {
             min: Pos2::ZERO,
             max: pos2(1.0, 1.0),
         };
 
+        self.update_light_pen(ctx, rect, image.size());
+
         egui::CentralPanel::default()
+            .frame(egui::Frame::NONE.fill(Color32::BLACK))
             .show(ctx, |ui| {
-                ui.painter().image(image.into(), rect, uv, Color32::WHITE);
+                ui.painter()
+                    .image((&image).into(), rect, uv, Color32::WHITE);
+                // the plain picture stays visible if the shader can't be used
+                if !self.crt.is_pixel_exact() {
+                    ui.painter().add(self.crt_display.paint_callback(
+                        rect,
+                        image.id(),
+                        image.size(),
+                        self.crt,
+                    ));
+                }
                 if is_hovered {
                     Self::show_message(ui, "Drop a tape file here.");
                 } else if let Some(message) = &self.message {
@@ -288,4 +662,21 @@ impl App for Gui {
             .request_focus();
         ctx.request_repaint();
     }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, eframe::APP_KEY, &self.settings());
+    }
+
+    fn on_exit(&mut self, gl: Option<&eframe::glow::Context>) {
+        if let Some(gl) = gl {
+            self.crt_display.destroy(gl);
+        }
+        #[cfg(not(target_family = "wasm"))]
+        if let Err(e) = self.machine.stop_video_capture() {
+            warn!("Unable to save video {e}");
+        }
+        if let Err(e) = self.machine.stop_audio_recording() {
+            warn!("Unable to save audio {e}");
+        }
+    }
 }
@@ -0,0 +1,101 @@
+use crate::hardware::machine::Machine;
+use crate::hardware::screen::color::PalettePreset;
+use egui::Ui;
+#[cfg(not(target_family = "wasm"))]
+use {
+    crate::hardware::screen::color::{palette_to_string, parse_palette},
+    log::{info, warn},
+};
+
+#[cfg(not(target_family = "wasm"))]
+enum FileAction {
+    Import,
+    Export,
+}
+
+/// Edits the 16 colors of the machine, the picture is redrawn as soon as a color changes
+#[derive(Default)]
+pub(super) struct PaletteEditor {
+    #[cfg(not(target_family = "wasm"))]
+    file_dialog: Option<(egui_file_dialog::FileDialog, FileAction)>,
+    message: Option<String>,
+}
+
+impl PaletteEditor {
+    pub(super) fn ui(&mut self, ui: &mut Ui, machine: &mut Machine) {
+        let mut palette = *machine.palette();
+        let preset = PalettePreset::matching(&palette);
+        egui::ComboBox::from_label("Preset")
+            .selected_text(preset.map_or("Custom", PalettePreset::name))
+            .show_ui(ui, |ui| {
+                for preset in PalettePreset::ALL {
+                    if ui.selectable_label(false, preset.name()).clicked() {
+                        palette = preset.palette();
+                    }
+                }
+            });
+        egui::Grid::new("palette_grid").show(ui, |ui| {
+            for (i, color) in palette.iter_mut().enumerate() {
+                ui.label(format!("{i:X}"));
+                ui.color_edit_button_srgb(color);
+                if i % 4 == 3 {
+                    ui.end_row();
+                }
+            }
+        });
+        if palette != *machine.palette() {
+            machine.set_palette(palette);
+        }
+
+        #[cfg(not(target_family = "wasm"))]
+        self.import_export_ui(ui, machine);
+        if let Some(message) = &self.message {
+            ui.label(message);
+        }
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    fn import_export_ui(&mut self, ui: &mut Ui, machine: &mut Machine) {
+        ui.horizontal(|ui| {
+            if ui.button("Import").clicked() {
+                let mut fd = egui_file_dialog::FileDialog::new();
+                fd.pick_file();
+                self.file_dialog = Some((fd, FileAction::Import));
+            }
+            if ui.button("Export").clicked() {
+                let mut fd = egui_file_dialog::FileDialog::new().default_file_name("mo5.pal");
+                fd.save_file();
+                self.file_dialog = Some((fd, FileAction::Export));
+            }
+        });
+        let Some((fd, action)) = &mut self.file_dialog else {
+            return;
+        };
+        fd.update(ui.ctx());
+        let Some(path) = fd.take_picked() else {
+            return;
+        };
+        let result = match action {
+            FileAction::Import => std::fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|text| parse_palette(&text))
+                .map(|palette| machine.set_palette(palette))
+                .map(|_| format!("Palette imported from {}", path.display())),
+            FileAction::Export => std::fs::write(&path, palette_to_string(machine.palette()))
+                .map_err(|e| e.to_string())
+                .map(|_| format!("Palette exported to {}", path.display())),
+        };
+        let message = match result {
+            Ok(message) => {
+                info!("{message}");
+                message
+            }
+            Err(e) => {
+                warn!("Palette error {e}");
+                format!("Error: {e}")
+            }
+        };
+        self.message = Some(message);
+        self.file_dialog = None;
+    }
+}
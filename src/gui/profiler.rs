@@ -0,0 +1,205 @@
+use crate::hardware::machine::Machine;
+#[cfg(not(target_family = "wasm"))]
+use crate::hardware::profiler::CodeDataLog;
+use crate::hardware::profiler::{EXECUTED, READ, WRITTEN};
+use egui::{RichText, Ui};
+#[cfg(not(target_family = "wasm"))]
+use log::{info, warn};
+
+const HOTTEST: usize = 20;
+
+#[cfg(not(target_family = "wasm"))]
+enum FileAction {
+    Import,
+    Export,
+}
+
+/// Shows the subroutines and the instructions taking the most cycles, and controls the
+/// code/data log
+#[derive(Default)]
+pub(super) struct ProfilerView {
+    #[cfg(not(target_family = "wasm"))]
+    file_dialog: Option<(egui_file_dialog::FileDialog, FileAction)>,
+    #[cfg(not(target_family = "wasm"))]
+    message: Option<String>,
+}
+
+impl ProfilerView {
+    pub(super) fn ui(&mut self, ui: &mut Ui, machine: &mut Machine) {
+        self.code_data_ui(ui, machine);
+        ui.separator();
+        ui.horizontal(|ui| {
+            if machine.is_profiling() {
+                if ui.button("Stop profiling").clicked() {
+                    machine.stop_profiling();
+                }
+            } else if ui
+                .button("Start profiling")
+                .on_hover_text("Counts the cycles from zero")
+                .clicked()
+            {
+                machine.start_profiling();
+            }
+            if let Some(profiler) = machine.profiler() {
+                ui.label(format!("{} cycles", profiler.total_cycles()));
+            }
+        });
+        egui::ScrollArea::vertical()
+            .auto_shrink(false)
+            .show(ui, |ui| {
+                routines_ui(ui, machine);
+                ui.separator();
+                addresses_ui(ui, machine);
+            });
+    }
+
+    fn code_data_ui(&mut self, ui: &mut Ui, machine: &mut Machine) {
+        ui.horizontal(|ui| {
+            if machine.is_logging_code_data() {
+                if ui.button("Stop code/data log").clicked() {
+                    machine.stop_code_data_log();
+                }
+            } else if ui
+                .button("Start code/data log")
+                .on_hover_text("Marks the bytes executed, read or written by the CPU")
+                .clicked()
+            {
+                machine.start_code_data_log();
+            }
+            if ui.button("Clear").clicked() {
+                machine.set_code_data_log(None);
+            }
+            #[cfg(not(target_family = "wasm"))]
+            self.import_export_ui(ui, machine);
+        });
+        if let Some(log) = machine.code_data_log() {
+            ui.label(format!(
+                "{} bytes of code, {} read and {} written as data",
+                log.count(EXECUTED),
+                log.count(READ),
+                log.count(WRITTEN)
+            ));
+        }
+        #[cfg(not(target_family = "wasm"))]
+        if let Some(message) = &self.message {
+            ui.label(message);
+        }
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    fn import_export_ui(&mut self, ui: &mut Ui, machine: &mut Machine) {
+        if ui
+            .button("Import")
+            .on_hover_text("The disassembly shows the data bytes of the log as FCB")
+            .clicked()
+        {
+            let mut fd = egui_file_dialog::FileDialog::new();
+            fd.pick_file();
+            self.file_dialog = Some((fd, FileAction::Import));
+        }
+        if ui
+            .add_enabled(
+                machine.code_data_log().is_some(),
+                egui::Button::new("Export"),
+            )
+            .clicked()
+        {
+            let mut fd = egui_file_dialog::FileDialog::new().default_file_name("maurice.cdl");
+            fd.save_file();
+            self.file_dialog = Some((fd, FileAction::Export));
+        }
+        let Some((fd, action)) = &mut self.file_dialog else {
+            return;
+        };
+        fd.update(ui.ctx());
+        let Some(path) = fd.take_picked() else {
+            return;
+        };
+        let result = match action {
+            FileAction::Import => std::fs::read(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|bytes| CodeDataLog::from_bytes(&bytes))
+                .map(|log| {
+                    machine.set_code_data_log(Some(log));
+                    format!("Code/data log imported from {}", path.display())
+                }),
+            FileAction::Export => {
+                let bytes = machine
+                    .code_data_log()
+                    .map(|log| log.to_bytes())
+                    .unwrap_or_default();
+                std::fs::write(&path, bytes)
+                    .map_err(|e| e.to_string())
+                    .map(|_| format!("Code/data log exported to {}", path.display()))
+            }
+        };
+        self.message = Some(match result {
+            Ok(message) => {
+                info!("{message}");
+                message
+            }
+            Err(e) => {
+                warn!("Code/data log error {e}");
+                format!("Error: {e}")
+            }
+        });
+        self.file_dialog = None;
+    }
+}
+
+fn routines_ui(ui: &mut Ui, machine: &Machine) {
+    let Some(profiler) = machine.profiler() else {
+        return;
+    };
+    let total = profiler.total_cycles().max(1) as f64;
+    ui.label(RichText::new("Subroutines").strong());
+    egui::Grid::new("profiler_routines")
+        .striped(true)
+        .show(ui, |ui| {
+            for title in ["Address", "Calls", "Cycles", "%", "Own cycles"] {
+                ui.label(title);
+            }
+            ui.end_row();
+            for (address, routine) in profiler.hottest_routines(HOTTEST) {
+                let name = machine
+                    .symbols()
+                    .get(address)
+                    .map_or(format!("{address:04X}"), |label| {
+                        format!("{address:04X} {label}")
+                    });
+                ui.label(RichText::new(name).monospace());
+                ui.label(routine.calls.to_string());
+                ui.label(routine.cycles.to_string());
+                ui.label(format!("{:.1}", routine.cycles as f64 * 100.0 / total));
+                ui.label(routine.own_cycles.to_string());
+                ui.end_row();
+            }
+        });
+}
+
+fn addresses_ui(ui: &mut Ui, machine: &Machine) {
+    let Some(profiler) = machine.profiler() else {
+        return;
+    };
+    let total = profiler.total_cycles().max(1) as f64;
+    ui.label(RichText::new("Instructions").strong());
+    egui::Grid::new("profiler_addresses")
+        .striped(true)
+        .show(ui, |ui| {
+            for title in ["Instruction", "Executions", "Cycles", "%"] {
+                ui.label(title);
+            }
+            ui.end_row();
+            for address in profiler.hottest_addresses(HOTTEST) {
+                let instruction = &machine.disassemble(address, 1)[0];
+                ui.label(RichText::new(instruction.to_string_with(machine.symbols())).monospace());
+                ui.label(profiler.executions(address).to_string());
+                ui.label(profiler.cycles(address).to_string());
+                ui.label(format!(
+                    "{:.1}",
+                    profiler.cycles(address) as f64 * 100.0 / total
+                ));
+                ui.end_row();
+            }
+        });
+}
@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+
+use crate::gui::crt::CrtSettings;
+use crate::gui::display::Filter;
+use crate::hardware::M6809::IllegalOpcodePolicy;
+use crate::hardware::screen::color::{PALETTE, Palette};
+
+/// The preferences saved by eframe when the emulator exits and restored at startup
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Settings {
+    pub(crate) palette: Palette,
+    pub(crate) border: usize,
+    pub(crate) filter: Filter,
+    pub(crate) keep_aspect: bool,
+    pub(crate) crt: CrtSettings,
+    pub(crate) illegal_opcodes: IllegalOpcodePolicy,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            palette: PALETTE,
+            border: 0,
+            filter: Filter::default(),
+            keep_aspect: true,
+            crt: CrtSettings::default(),
+            illegal_opcodes: IllegalOpcodePolicy::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_settings_use_defaults() {
+        let settings: Settings = ron::from_str("(border: 16)").unwrap();
+        assert_eq!(
+            Settings {
+                border: 16,
+                ..Settings::default()
+            },
+            settings
+        );
+    }
+}
@@ -1,4009 +1,1488 @@
 #![allow(non_snake_case)]
 
+use crate::hardware::bus::Bus;
+use crate::hardware::debugger::Debugger;
+use crate::hardware::interrupts::Line;
 use crate::hardware::memory::Memory;
-use crate::hardware::screen::Screen;
-use crate::hardware::sound::Sound;
+use crate::hardware::profiler::{Flow, Profiler};
+use crate::hardware::trace::Tracer;
 use crate::int;
 use log::warn;
-use std::ops::Index;
-
-const SOUND_SIZE: usize = 1024;
+use serde::{Deserialize, Serialize};
+
+// the flags of CC
+const C: u8 = 0x01;
+const V: u8 = 0x02;
+const Z: u8 = 0x04;
+const N: u8 = 0x08;
+const I: u8 = 0x10;
+const H: u8 = 0x20;
+const F: u8 = 0x40;
+const E: u8 = 0x80;
 
 #[derive(Debug, Default)]
 pub struct M6809 {
-    // Sound emulation parameters
-    pub(crate) sound_buffer: SoundBuffer,
+    pub(crate) debugger: Debugger,
+    wait: Wait,
+    pub(crate) tracer: Option<Tracer>,
+    // kept when the profiling stops to show the results
+    pub(crate) profiler: Option<Profiler>,
+    pub(crate) profiling: bool,
+    pub(crate) illegal_opcodes: IllegalOpcodePolicy,
+    // the last illegal opcode run, for the GUI
+    pub(crate) illegal_opcode: Option<IllegalOpcode>,
+    // an illegal opcode breaks into the debugger
+    stopped: bool,
 
     cl: int,
+    // the opcode being run, without its page prefix
+    opcode: u8,
 
     // 8bits registers
-    A: int,
-    B: int,
-    DP: int,
-    CC: int,
+    a: u8,
+    b: u8,
+    dp: u8,
+    cc: u8,
 
     // 16bits registers
-    X: int,
-    Y: int,
-    U: int,
-    S: int,
-    pub(crate) PC: int,
-    D: int, // D is A+B
-
-    // fast CC bits (as ints)
-    res: int,
-    m1: int,
-    m2: int,
-    sign: int,
-    ovfl: int,
-    h1: int,
-    h2: int,
-    ccrest: int,
+    x: u16,
+    y: u16,
+    u: u16,
+    s: u16,
+    pub(crate) pc: u16,
 }
 
 impl M6809 {
-    pub fn new(mem: &Memory) -> Self {
+    pub fn new<B: Bus>(mem: &B) -> Self {
         let mut m6809 = M6809::default();
         m6809.reset(mem);
         m6809
     }
 
-    pub(crate) fn reset(&mut self, mem: &Memory) {
-        self.PC = mem.read_16(0xFFFE);
-        self.DP = 0x00;
-        self.S = 0x8000;
-        self.CC = 0x00;
+    pub(crate) fn reset<B: Bus>(&mut self, mem: &B) {
+        self.pc = mem.read_16(0xFFFE);
+        self.dp = 0x00;
+        self.s = 0x8000;
+        self.cc = 0x00;
+        self.wait = Wait::None;
     }
 
-    // recalculate A and B or D
-    const fn CALCD(&mut self) {
-        self.D = (self.A << 8) | self.B;
+    // D is A+B
+    const fn d(&self) -> u16 {
+        u16::from_be_bytes([self.a, self.b])
     }
 
-    const fn CALCAB(&mut self) {
-        self.A = self.D >> 8;
-        self.B = self.D & 0xFF;
+    const fn set_d(&mut self, d: u16) {
+        [self.a, self.b] = d.to_be_bytes();
     }
 
-    // basic 6809 addressing modes
-    const fn IMMED8(&mut self) -> int {
-        let M = self.PC;
-        self.PC += 1;
-        M
+    /// Returns the address of the operand and moves PC after it
+    #[inline(always)]
+    fn operand<B: Bus>(&mut self, mode: Mode, mem: &mut B) -> u16 {
+        let pc = self.pc;
+        match mode {
+            Mode::Inherent => pc,
+            Mode::Immediate8 => {
+                self.pc = pc.wrapping_add(1);
+                pc
+            }
+            Mode::Immediate16 => {
+                self.pc = pc.wrapping_add(2);
+                pc
+            }
+            Mode::Direct => {
+                self.pc = pc.wrapping_add(1);
+                u16::from_be_bytes([self.dp, mem.read(pc)])
+            }
+            Mode::Indexed => self.indexed(mem),
+            Mode::Extended => {
+                self.pc = pc.wrapping_add(2);
+                mem.read_16(pc)
+            }
+        }
     }
 
-    const fn IMMED16(&mut self) -> int {
-        let M = self.PC;
-        self.PC += 2;
-        M
+    /// The index register selected by the bits 5 and 6 of the postbyte
+    const fn index(&self, postbyte: u8) -> u16 {
+        match (postbyte >> 5) & 0x03 {
+            0 => self.x,
+            1 => self.y,
+            2 => self.u,
+            _ => self.s,
+        }
     }
 
-    fn DIREC(&mut self, mem: &mut Memory) -> int {
-        let M = (self.DP << 8) | mem.read(self.PC);
-        self.PC += 1;
-        M
+    const fn index_mut(&mut self, postbyte: u8) -> &mut u16 {
+        match (postbyte >> 5) & 0x03 {
+            0 => &mut self.x,
+            1 => &mut self.y,
+            2 => &mut self.u,
+            _ => &mut self.s,
+        }
     }
 
-    fn ETEND(&mut self, mem: &mut Memory) -> int {
-        let mut M = mem.read(self.PC) << 8;
-        self.PC += 1;
-        M |= mem.read(self.PC);
-        self.PC += 1;
-        M
+    /// The address of an indexed operand, the postbyte adds its own cycles. The indirect
+    /// modes take 3 more cycles than the direct ones.
+    fn indexed<B: Bus>(&mut self, mem: &mut B) -> u16 {
+        let postbyte = mem.read(self.pc);
+        self.pc = self.pc.wrapping_add(1);
+        if postbyte & 0x80 == 0 {
+            // 5 bits signed offset
+            let offset = ((postbyte << 3) as i8 >> 3) as u16;
+            self.cl += 1;
+            return self.index(postbyte).wrapping_add(offset);
+        }
+        let (address, cycles) = match postbyte & 0x1F {
+            // ,R+ ,R++ and [,R++]
+            0x00 | 0x01 | 0x11 => {
+                let step = 1 + (postbyte & 0x01) as u16;
+                let register = self.index_mut(postbyte);
+                let address = *register;
+                *register = address.wrapping_add(step);
+                (address, 1 + step as int)
+            }
+            // ,-R ,--R and [,--R]
+            0x02 | 0x03 | 0x13 => {
+                let step = 1 + (postbyte & 0x01) as u16;
+                let register = self.index_mut(postbyte);
+                *register = register.wrapping_sub(step);
+                (*register, 1 + step as int)
+            }
+            0x04 | 0x14 => (self.index(postbyte), 0),
+            0x05 | 0x15 => (self.index(postbyte).wrapping_add(self.b as i8 as u16), 1),
+            0x06 | 0x16 => (self.index(postbyte).wrapping_add(self.a as i8 as u16), 1),
+            0x08 | 0x18 => {
+                let offset = mem.read(self.pc) as i8 as u16;
+                self.pc = self.pc.wrapping_add(1);
+                (self.index(postbyte).wrapping_add(offset), 1)
+            }
+            0x09 | 0x19 => {
+                let offset = mem.read_16(self.pc);
+                self.pc = self.pc.wrapping_add(2);
+                (self.index(postbyte).wrapping_add(offset), 4)
+            }
+            0x0B | 0x1B => (self.index(postbyte).wrapping_add(self.d()), 4),
+            0x0C | 0x1C => {
+                let offset = mem.read(self.pc) as i8 as u16;
+                self.pc = self.pc.wrapping_add(1);
+                (self.pc.wrapping_add(offset), 1)
+            }
+            0x0D | 0x1D => {
+                let offset = mem.read_16(self.pc);
+                self.pc = self.pc.wrapping_add(2);
+                (self.pc.wrapping_add(offset), 5)
+            }
+            // [n16]
+            0x1F => {
+                let address = mem.read_16(self.pc);
+                self.pc = self.pc.wrapping_add(2);
+                (address, 2)
+            }
+            // no addressing mode
+            _ => return 0,
+        };
+        if postbyte & 0x10 == 0 {
+            self.cl += cycles;
+            address
+        } else {
+            self.cl += cycles + 3;
+            mem.read_16(address)
+        }
     }
 
-    fn INDEXE(&mut self, mem: &mut Memory) -> int {
-        let mut m = mem.read(self.PC);
-        self.PC += 1;
-        if m < 0x80 {
-            // effectue le complement a 2 sur la precision int
-            let delta = if (m & 0x10) == 0x10 {
-                ((-1 >> 5) << 5) | (m & 0x1F)
-            } else {
-                m & 0x1F
-            };
-            let reg = match m & 0xE0 {
-                0x00 => self.X,
-                0x20 => self.Y,
-                0x40 => self.U,
-                0x60 => self.S,
-                _ => return 0,
-            };
-            self.cl += 1;
-            return (reg + delta) & 0xFFFF;
+    const fn set_flag(&mut self, flag: u8, set: bool) {
+        if set {
+            self.cc |= flag;
+        } else {
+            self.cc &= !flag;
         }
-        match m {
-            0x80 => {
-                //i_d_P1_X
-                let M = self.X;
-                self.X = (self.X + 1) & 0xFFFF;
-                self.cl += 2;
-                M
-            }
-            0x81 => {
-                //i_d_P2_X
-                let M = self.X;
-                self.X = (self.X + 2) & 0xFFFF;
-                self.cl += 3;
-                M
-            }
-            0x82 => {
-                //i_d_M1_X
-                self.X = (self.X - 1) & 0xFFFF;
-                let M = self.X;
-                self.cl += 2;
-                M
-            }
-            0x83 => {
-                //i_d_M2_X
-                self.X = (self.X - 2) & 0xFFFF;
-                let M = self.X;
-                self.cl += 3;
-                M
-            }
-            0x84 => self.X, //i_d_X
-            0x85 => {
-                //i_d_B_X
-                let M = (self.X + signedChar(self.B)) & 0xFFFF;
-                self.cl += 1;
-                M
-            }
-            0x86 => {
-                //i_d_A_X;
-                let M = (self.X + signedChar(self.A)) & 0xFFFF;
-                self.cl += 1;
-                M
-            }
-            0x87 => 0, //i_undoc;	/* empty */
-            0x88 => {
-                //i_d_8_X;
-                self.m2 = mem.read(self.PC);
-                self.PC += 1;
-                let M = (self.X + signedChar(self.m2)) & 0xFFFF;
-                self.cl += 1;
-                M
-            }
-            0x89 => {
-                //i_d_16_X;
-                self.m2 = mem.read_16(self.PC);
-                self.PC += 2;
-                let M = (self.X + signed16bits(self.m2)) & 0xFFFF;
-                self.cl += 4;
-                M
-            }
-            0x8A => 0, //i_undoc;	/* empty */
-            0x8B => {
-                //i_d_D_X;
-                let M = (self.X + signed16bits((self.A << 8) | self.B)) & 0xFFFF;
-                self.cl += 4;
-                M
-            }
-            0x8C | 0xAC | 0xCC | 0xEC => {
-                //i_d_PC8;
-                m = mem.read(self.PC);
-                self.PC = (self.PC + 1) & 0xFFFF;
-                let M = (self.PC + signedChar(m)) & 0xFFFF;
-                self.cl += 1;
-                M
-            }
-            0x8D | 0xAD | 0xCD | 0xED => {
-                //i_d_PC16;
-                let mut M = mem.read_16(self.PC);
-                self.PC = (self.PC + 2) & 0xFFFF;
-                M = (self.PC + signed16bits(M)) & 0xFFFF;
-                self.cl += 5;
-                M
-            }
-            0x8E..=0x90 => 0, //i_undoc;	/* empty */
-            0x91 => {
-                //i_i_P2_X;
-                let M = mem.read_16(self.X);
-                self.X = (self.X + 2) & 0xFFFF;
-                self.cl += 6;
-                M
-            }
-            0x92 => 0, //i_undoc;	/* empty */
-            0x93 => {
-                //i_i_M2_X;
-                self.X = (self.X - 2) & 0xFFFF;
-                let M = mem.read_16(self.X);
-                self.cl += 6;
-                M
-            }
-            0x94 => {
-                //i_i_0_X;
-                let M = mem.read_16(self.X);
-                self.cl += 3;
-                M
-            }
-            0x95 => {
-                //i_i_B_X;
-                let mut M = (self.X + signedChar(self.B)) & 0xFFFF;
-                M = mem.read_16(M);
-                self.cl += 4;
-                M
-            }
-            0x96 => {
-                //i_i_A_X;
-                let mut M = (self.X + signedChar(self.A)) & 0xFFFF;
-                M = mem.read_16(M);
-                self.cl += 4;
-                M
-            }
-            0x97 => 0, //i_undoc;	/* empty */
-            0x98 => {
-                //i_i_8_X;
-                self.m2 = mem.read(self.PC);
-                self.PC = (self.PC + 1) & 0xFFFF;
-                let mut M = (self.X + signedChar(self.m2)) & 0xFFFF;
-                M = mem.read_16(M);
-                self.cl += 4;
-                M
-            }
-            0x99 => {
-                //i_i_16_X;
-                self.m2 = mem.read_16(self.PC);
-                self.PC = (self.PC + 2) & 0xFFFF;
-                let mut M = (self.X + signed16bits(self.m2)) & 0xFFFF;
-                M = mem.read_16(M);
-                self.cl += 7;
-                M
-            }
-            0x9A => 0, //i_undoc;	/* empty */
-            0x9B => {
-                //i_i_D_X;
-                let mut M = (self.X + signed16bits((self.A << 8) | self.B)) & 0xFFFF;
-                M = mem.read_16(M);
-                self.cl += 7;
-                M
-            }
-            0x9C | 0xBC | 0xDC | 0xFC => {
-                //i_i_PC8;
-                self.m2 = mem.read(self.PC);
-                self.PC = (self.PC + 1) & 0xFFFF;
-                let mut M = (self.PC + signedChar(self.m2)) & 0xFFFF;
-                M = mem.read_16(M);
-                self.cl += 4;
-                M
-            }
-            0x9D | 0xBD | 0xDD | 0xFD => {
-                //i_i_PC16;
-                self.m2 = mem.read_16(self.PC);
-                self.PC = (self.PC + 2) & 0xFFFF;
-                let mut M = (self.PC + signed16bits(self.m2)) & 0xFFFF;
-                M = mem.read_16(M);
-                self.cl += 8;
-                M
-            }
-            0x9E => 0, //i_undoc;	/* empty */
-            0x9F | 0xBF | 0xDF | 0xFF => {
-                //i_i_e16;
-                self.m2 = mem.read_16(self.PC);
-                self.PC = (self.PC + 2) & 0xFFFF;
-                let M = mem.read_16(self.m2);
-                self.cl += 5;
-                M
-                // Y
-            }
-            0xA0 => {
-                //i_d_P1_Y;
-                let M = self.Y;
-                self.Y = (self.Y + 1) & 0xFFFF;
-                self.cl += 2;
-                M
-            }
-            0xA1 => {
-                //i_d_P2_Y;
-                let M = self.Y;
-                self.Y = (self.Y + 2) & 0xFFFF;
-                self.cl += 3;
-                M
-            }
-            0xA2 => {
-                //i_d_M1_Y;
-                self.Y = (self.Y - 1) & 0xFFFF;
-                let M = self.Y;
-                self.cl += 2;
-                M
-            }
-            0xA3 => {
-                //i_d_M2_Y;
-                self.Y = (self.Y - 2) & 0xFFFF;
-                let M = self.Y;
-                self.cl += 3;
-                M
-            }
-            0xA4 => self.Y, //i_d_Y;
-            0xA5 => {
-                //i_d_B_Y;
-                let M = (self.Y + signedChar(self.B)) & 0xFFFF;
-                self.cl += 1;
-                M
-            }
-            0xA6 => {
-                //i_d_A_Y;
-                let M = (self.Y + signedChar(self.A)) & 0xFFFF;
-                self.cl += 1;
-                M
-            }
-            0xA7 => 0, //i_undoc;	/* empty */
-            0xA8 => {
-                //i_d_8_Y;
-                self.m2 = mem.read(self.PC);
-                self.PC += 1;
-                let M = (self.Y + signedChar(self.m2)) & 0xFFFF;
-                self.cl += 1;
-                M
-            }
-            0xA9 => {
-                //i_d_16_Y;
-                self.m2 = mem.read_16(self.PC);
-                self.PC += 2;
-                let M = (self.Y + signed16bits(self.m2)) & 0xFFFF;
-                self.cl += 4;
-                M
-            }
-            0xAA => 0, //i_undoc;	/* empty */
-            0xAB => {
-                //i_d_D_Y;
-                let M = (self.Y + signed16bits((self.A << 8) | self.B)) & 0xFFFF;
-                self.cl += 4;
-                M
-            }
-            0xAE..=0xB0 => 0, //i_undoc;	/* empty */
-            0xB1 => {
-                //i_i_P2_Y;
-                let M = mem.read_16(self.Y);
-                self.Y = (self.Y + 2) & 0xFFFF;
-                self.cl += 6;
-                M
-            }
-            0xB2 => 0, //i_undoc;	/* empty */
-            0xB3 => {
-                //i_i_M2_Y;
-                self.Y = (self.Y - 2) & 0xFFFF;
-                let M = mem.read_16(self.Y);
-                self.cl += 6;
-                M
-            }
-            0xB4 => {
-                //i_i_0_Y;
-                let M = mem.read_16(self.Y);
-                self.cl += 3;
-                M
-            }
-            0xB5 => {
-                //i_i_B_Y;
-                let mut M = (self.Y + signedChar(self.B)) & 0xFFFF;
-                M = mem.read_16(M);
-                self.cl += 4;
-                M
-            }
-            0xB6 => {
-                //i_i_A_Y;
-                let mut M = (self.Y + signedChar(self.A)) & 0xFFFF;
-                M = mem.read_16(M);
-                self.cl += 4;
-                M
-            }
-            0xB7 => 0, //i_undoc;	/* empty */
-            0xB8 => {
-                //i_i_8_Y;
-                self.m2 = mem.read(self.PC);
-                self.PC = (self.PC + 1) & 0xFFFF;
-                let mut M = (self.Y + signedChar(self.m2)) & 0xFFFF;
-                M = mem.read_16(M);
-                self.cl += 4;
-                M
-            }
-            0xB9 => {
-                //i_i_16_Y;
-                self.m2 = mem.read_16(self.PC);
-                self.PC = (self.PC + 2) & 0xFFFF;
-                let mut M = (self.Y + signed16bits(self.m2)) & 0xFFFF;
-                M = mem.read_16(M);
-                self.cl += 7;
-                M
-            }
-            0xBA => 0, //i_undoc;	/* empty */
-            0xBB => {
-                //i_i_D_Y;
-                let mut M = (self.Y + signed16bits((self.A << 8) | self.B)) & 0xFFFF;
-                M = mem.read_16(M);
-                self.cl += 7;
-                M
-            }
-            0xBE => 0, //i_undoc;	/* empty */ U
-            0xC0 => {
-                //i_d_P1_U;
-                let M = self.U;
-                self.U = (self.U + 1) & 0xFFFF;
-                self.cl += 2;
-                M
-            }
-            0xC1 => {
-                //i_d_P2_U;
-                let M = self.U;
-                self.U = (self.U + 2) & 0xFFFF;
-                self.cl += 3;
-                M
-            }
-            0xC2 => {
-                //i_d_M1_U;
-                self.U = (self.U - 1) & 0xFFFF;
-                let M = self.U;
-                self.cl += 2;
-                M
-            }
-            0xC3 => {
-                //i_d_M2_U;
-                self.U = (self.U - 2) & 0xFFFF;
-                let M = self.U;
-                self.cl += 3;
-                M
-            }
-            0xC4 => self.U, //i_d_U;
-            0xC5 => {
-                //i_d_B_U;
-                let M = (self.U + signedChar(self.B)) & 0xFFFF;
-                self.cl += 1;
-                M
-            }
-            0xC6 => {
-                //i_d_A_U;
-                let M = (self.U + signedChar(self.A)) & 0xFFFF;
-                self.cl += 1;
-                M
-            }
-            0xC7 => 0, //i_undoc;	/* empty */
-            0xC8 => {
-                //i_d_8_U;
-                self.m2 = mem.read(self.PC);
-                self.PC += 1;
-                let M = (self.U + signedChar(self.m2)) & 0xFFFF;
-                self.cl += 1;
-                M
-            }
-            0xC9 => {
-                //i_d_16_U;
-                self.m2 = mem.read_16(self.PC);
-                self.PC += 2;
-                let M = (self.U + signed16bits(self.m2)) & 0xFFFF;
-                self.cl += 4;
-                M
-            }
-            0xCA => 0, //i_undoc;	/* empty */
-            0xCB => {
-                //i_d_D_U;
-                let M = (self.U + signed16bits((self.A << 8) | self.B)) & 0xFFFF;
-                self.cl += 4;
-                M
-            }
-            0xCE..=0xD0 => 0, //i_undoc;	/* empty */
-            0xD1 => {
-                //i_i_P2_U;
-                let M = mem.read_16(self.U);
-                self.U = (self.U + 2) & 0xFFFF;
-                self.cl += 6;
-                M
-            }
-            0xD2 => 0, //i_undoc;	/* empty */
-            0xD3 => {
-                //i_i_M2_U;
-                self.U = (self.U - 2) & 0xFFFF;
-                let M = mem.read_16(self.U);
-                self.cl += 6;
-                M
-            }
-            0xD4 => {
-                //i_i_0_U;
-                let M = mem.read_16(self.U);
-                self.cl += 3;
-                M
-            }
-            0xD5 => {
-                //i_i_B_U;
-                let mut M = (self.U + signedChar(self.B)) & 0xFFFF;
-                M = mem.read_16(M);
-                self.cl += 4;
-                M
-            }
-            0xD6 => {
-                //i_i_A_U;
-                let mut M = (self.U + signedChar(self.A)) & 0xFFFF;
-                M = mem.read_16(M);
-                self.cl += 4;
-                M
-            }
-            0xD7 => 0, //i_undoc;	/* empty */
-            0xD8 => {
-                //i_i_8_U;
-                self.m2 = mem.read(self.PC);
-                self.PC = (self.PC + 1) & 0xFFFF;
-                let mut M = (self.U + signedChar(self.m2)) & 0xFFFF;
-                M = mem.read_16(M);
-                self.cl += 4;
-                M
-            }
-            0xD9 => {
-                //i_i_16_U;
-                self.m2 = mem.read_16(self.PC);
-                self.PC = (self.PC + 2) & 0xFFFF;
-                let mut M = (self.U + signed16bits(self.m2)) & 0xFFFF;
-                M = mem.read_16(M);
-                self.cl += 7;
-                M
-            }
-            0xDA => 0, //i_undoc;	/* empty */
-            0xDB => {
-                //i_i_D_U;
-                let mut M = (self.U + signed16bits((self.A << 8) | self.B)) & 0xFFFF;
-                M = mem.read_16(M);
-                self.cl += 7;
-                M
-            }
-            0xDE => 0, //i_undoc;	/* empty */// S
-            0xE0 => {
-                //i_d_P1_S;
-                let M = self.S;
-                self.S = (self.S + 1) & 0xFFFF;
-                self.cl += 2;
-                M
-            }
-            0xE1 => {
-                //i_d_P2_S;
-                let M = self.S;
-                self.S = (self.S + 2) & 0xFFFF;
-                self.cl += 3;
-                M
-            }
-            0xE2 => {
-                //i_d_M1_S;
-                self.S = (self.S - 1) & 0xFFFF;
-                let M = self.S;
-                self.cl += 2;
-                M
-            }
-            0xE3 => {
-                //i_d_M2_S;
-                self.S = (self.S - 2) & 0xFFFF;
-                let M = self.S;
-                self.cl += 3;
-                M
-            }
-            0xE4 => self.S, //i_d_S;
-            0xE5 => {
-                //i_d_B_S;
-                let M = (self.S + signedChar(self.B)) & 0xFFFF;
-                self.cl += 1;
-                M
-            }
-            0xE6 => {
-                //i_d_A_S;
-                let M = (self.S + signedChar(self.A)) & 0xFFFF;
-                self.cl += 1;
-                M
-            }
-            0xE7 => 0, //i_undoc;	/* empty */
-            0xE8 => {
-                //i_d_8_S;
-                self.m2 = mem.read(self.PC);
-                self.PC += 1;
-                let M = (self.S + signedChar(self.m2)) & 0xFFFF;
-                self.cl += 1;
-                M
-            }
-            0xE9 => {
-                //i_d_16_S;
-                self.m2 = mem.read_16(self.PC);
-                self.PC += 2;
-                let M = (self.S + signed16bits(self.m2)) & 0xFFFF;
-                self.cl += 4;
-                M
-            }
-            0xEA => 0, //i_undoc;	/* empty */
-            0xEB => {
-                //i_d_D_S;
-                let M = (self.S + signed16bits((self.A << 8) | self.B)) & 0xFFFF;
-                self.cl += 4;
-                M
-            }
-            0xEE..=0xF0 => 0, //i_undoc;	/* empty */
-            0xF1 => {
-                //i_i_P2_S;
-                let M = mem.read_16(self.S);
-                self.S = (self.S + 2) & 0xFFFF;
-                self.cl += 6;
-                M
-            }
-            0xF2 => 0, //i_undoc;	/* empty */
-            0xF3 => {
-                //i_i_M2_S;
-                self.S = (self.S - 2) & 0xFFFF;
-                let M = mem.read_16(self.S);
-                self.cl += 6;
-                M
-            }
-            0xF4 => {
-                //i_i_0_S;
-                let M = mem.read_16(self.S);
-                self.cl += 3;
-                M
-            }
-            0xF5 => {
-                //i_i_B_S;
-                let mut M = (self.S + signedChar(self.B)) & 0xFFFF;
-                M = mem.read_16(M);
-                self.cl += 4;
-                M
-            }
-            0xF6 => {
-                //i_i_A_S;
-                let mut M = (self.S + signedChar(self.A)) & 0xFFFF;
-                M = mem.read_16(M);
-                self.cl += 4;
-                M
-            }
-            0xF7 => 0, //i_undoc;	/* empty */
-            0xF8 => {
-                //i_i_8_S;
-                self.m2 = mem.read(self.PC);
-                self.PC = (self.PC + 1) & 0xFFFF;
-                let mut M = (self.S + signedChar(self.m2)) & 0xFFFF;
-                M = mem.read_16(M);
-                self.cl += 4;
-                M
-            }
-            0xF9 => {
-                //i_i_16_S;
-                self.m2 = mem.read_16(self.PC);
-                self.PC = (self.PC + 2) & 0xFFFF;
-                let mut M = (self.S + signed16bits(self.m2)) & 0xFFFF;
-                M = mem.read_16(M);
-                self.cl += 7;
-                M
-            }
-            0xFA => 0, //i_undoc;	/* empty */
-            0xFB => {
-                //i_i_D_S;
-                let mut M = (self.S + signed16bits((self.A << 8) | self.B)) & 0xFFFF;
-                M = mem.read_16(M);
-                self.cl += 7;
-                M
-            }
-            0xFE => 0, //i_undoc;	/* empty */
-            _ => {
-                warn!("Unknown opcode {m:02X}");
-                0
-            }
+    }
+
+    const fn set_nz8(&mut self, value: u8) {
+        self.cc = (self.cc & !(N | Z)) | ((value & 0x80) >> 4);
+        if value == 0 {
+            self.cc |= Z;
+        }
+    }
+
+    const fn set_nz16(&mut self, value: u16) {
+        self.cc = (self.cc & !(N | Z)) | ((value >> 12) as u8 & N);
+        if value == 0 {
+            self.cc |= Z;
         }
     }
 
-    // cc register recalculate from separate bits
-    const fn getcc(&mut self) -> int {
-        if (self.res & 0xff) == 0 {
-            self.CC = ((((self.h1 & 15) + (self.h2 & 15)) & 16) << 1)
-                | ((self.sign & 0x80) >> 4)
-                | (4)
-                | ((((!(self.m1 ^ self.m2)) & (self.m1 ^ self.ovfl)) & 0x80) >> 6)
-                | ((self.res & 0x100) >> 8)
-                | self.ccrest;
+    /// Loads, stores and logical operations set N and Z and clear V
+    const fn logic8(&mut self, value: u8) -> u8 {
+        self.set_nz8(value);
+        self.cc &= !V;
+        value
+    }
+
+    const fn logic16(&mut self, value: u16) -> u16 {
+        self.set_nz16(value);
+        self.cc &= !V;
+        value
+    }
+
+    const fn add8(&mut self, left: u8, right: u8, carry: u8) -> u8 {
+        let sum = left as u16 + right as u16 + carry as u16;
+        let result = sum as u8;
+        self.set_nz8(result);
+        self.set_flag(H, (left & 0x0F) + (right & 0x0F) + carry > 0x0F);
+        self.set_flag(V, (left ^ result) & (right ^ result) & 0x80 != 0);
+        self.set_flag(C, sum > 0xFF);
+        result
+    }
+
+    /// Subtractions and comparisons, H is not changed
+    const fn sub8(&mut self, left: u8, right: u8, borrow: u8) -> u8 {
+        let difference = (left as u16)
+            .wrapping_sub(right as u16)
+            .wrapping_sub(borrow as u16);
+        let result = difference as u8;
+        self.set_nz8(result);
+        self.set_flag(V, (left ^ right) & (left ^ result) & 0x80 != 0);
+        self.set_flag(C, difference > 0xFF);
+        result
+    }
+
+    const fn add16(&mut self, left: u16, right: u16) -> u16 {
+        let sum = left as u32 + right as u32;
+        let result = sum as u16;
+        self.set_nz16(result);
+        self.set_flag(V, (left ^ result) & (right ^ result) & 0x8000 != 0);
+        self.set_flag(C, sum > 0xFFFF);
+        result
+    }
+
+    const fn sub16(&mut self, left: u16, right: u16) -> u16 {
+        let difference = (left as u32).wrapping_sub(right as u32);
+        let result = difference as u16;
+        self.set_nz16(result);
+        self.set_flag(V, (left ^ right) & (left ^ result) & 0x8000 != 0);
+        self.set_flag(C, difference > 0xFFFF);
+        result
+    }
+
+    // the operations of the read-modify-write instructions
+    const fn neg8(&mut self, value: u8) -> u8 {
+        self.sub8(0, value, 0)
+    }
+
+    const fn com8(&mut self, value: u8) -> u8 {
+        let result = self.logic8(!value);
+        self.cc |= C;
+        result
+    }
+
+    /// The undocumented NEG or COM, depending on C
+    const fn xnc8(&mut self, value: u8) -> u8 {
+        if self.cc & C == 0 {
+            self.neg8(value)
         } else {
-            self.CC = ((((self.h1 & 15) + (self.h2 & 15)) & 16) << 1)
-                | ((self.sign & 0x80) >> 4)
-                | ((((!(self.m1 ^ self.m2)) & (self.m1 ^ self.ovfl)) & 0x80) >> 6)
-                | ((self.res & 0x100) >> 8)
-                | self.ccrest;
+            self.com8(value)
         }
+    }
+
+    const fn lsr8(&mut self, value: u8) -> u8 {
+        let result = value >> 1;
+        self.set_nz8(result);
+        self.set_flag(C, value & 0x01 != 0);
+        result
+    }
 
-        self.CC
+    const fn ror8(&mut self, value: u8) -> u8 {
+        let result = (value >> 1) | ((self.cc & C) << 7);
+        self.set_nz8(result);
+        self.set_flag(C, value & 0x01 != 0);
+        result
     }
 
-    // calculate CC fast bits from CC register
-    const fn setcc(&mut self, i: int) {
-        self.m1 = 0;
-        self.m2 = 0;
-        self.res = ((i & 1) << 8) | (4 - (i & 4));
-        self.ovfl = (i & 2) << 6;
-        self.sign = (i & 8) << 4;
-        self.h1 = (i & 32) >> 2;
-        self.h2 = self.h1;
-        self.ccrest = i & 0xd0;
+    const fn asr8(&mut self, value: u8) -> u8 {
+        let result = (value >> 1) | (value & 0x80);
+        self.set_nz8(result);
+        self.set_flag(C, value & 0x01 != 0);
+        result
     }
 
-    pub(crate) const fn readCC(&mut self) -> int {
-        self.getcc();
-        self.CC
+    const fn asl8(&mut self, value: u8) -> u8 {
+        let result = value << 1;
+        self.set_nz8(result);
+        self.set_flag(V, (value ^ result) & 0x80 != 0);
+        self.set_flag(C, value & 0x80 != 0);
+        result
     }
 
-    fn LOAD8(ADR: int, mem: &mut Memory) -> int {
-        mem.read(ADR)
+    const fn rol8(&mut self, value: u8) -> u8 {
+        let result = (value << 1) | (self.cc & C);
+        self.set_nz8(result);
+        self.set_flag(V, (value ^ result) & 0x80 != 0);
+        self.set_flag(C, value & 0x80 != 0);
+        result
+    }
+
+    const fn dec8(&mut self, value: u8) -> u8 {
+        let result = value.wrapping_sub(1);
+        self.set_nz8(result);
+        self.set_flag(V, value == 0x80);
+        result
+    }
+
+    const fn inc8(&mut self, value: u8) -> u8 {
+        let result = value.wrapping_add(1);
+        self.set_nz8(result);
+        self.set_flag(V, value == 0x7F);
+        result
+    }
+
+    const fn tst8(&mut self, value: u8) -> u8 {
+        self.logic8(value)
+    }
+
+    const fn clr8(&mut self, _: u8) -> u8 {
+        self.cc = (self.cc & !(N | V | C)) | Z;
+        0
+    }
+
+    fn modify<B: Bus>(&mut self, mem: &mut B, address: u16, operation: fn(&mut Self, u8) -> u8) {
+        let value = mem.read(address);
+        let result = operation(self, value);
+        mem.write(address, result);
     }
 
     // LDx
-    fn LD8(&mut self, M: int, c: int, mem: &mut Memory) -> int {
-        self.sign = mem.read(M);
-        self.m1 = self.ovfl;
-        self.res = (self.res & 0x100) | self.sign;
-        self.cl += c;
-        self.sign
+    fn lda<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.a = self.logic8(mem.read(address));
+    }
+
+    fn ldb<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.b = self.logic8(mem.read(address));
+    }
+
+    fn ldd<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        let d = self.logic16(mem.read_16(address));
+        self.set_d(d);
+    }
+
+    fn ldx<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.x = self.logic16(mem.read_16(address));
+    }
+
+    fn ldy<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.y = self.logic16(mem.read_16(address));
     }
 
-    fn LD16(&mut self, M: int, c: int, mem: &mut Memory) -> int {
-        let R = mem.read_16(M) & 0xFFFF;
-        self.m1 = self.ovfl;
-        self.sign = R >> 8;
-        self.res = (self.res & 0x100) | ((self.sign | R) & 0xFF);
-        self.cl += c;
-        R
+    fn ldu<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.u = self.logic16(mem.read_16(address));
+    }
+
+    fn lds<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.s = self.logic16(mem.read_16(address));
     }
 
     // STx
-    fn ST8(&mut self, R: int, adr: int, c: int, mem: &mut Memory) {
-        mem.write(adr, R);
-        self.m1 = self.ovfl;
-        self.sign = R;
-        self.res = (self.res & 0x100) | self.sign;
-        self.cl += c;
-    }
-
-    fn ST16(&mut self, R: int, adr: int, c: int, mem: &mut Memory) {
-        mem.write(adr, R >> 8);
-        mem.write(adr + 1, R & 0xFF);
-        self.m1 = self.ovfl;
-        self.sign = R >> 8;
-        self.res = (self.res & 0x100) | ((self.sign | R) & 0xFF);
-        self.cl += c;
-    }
-
-    // LEA
-    fn LEA(&mut self, mem: &mut Memory) -> int {
-        let R = self.INDEXE(mem);
-        self.res = (self.res & 0x100) | ((R | (R >> 8)) & 0xFF);
-        self.cl += 4;
-        R
-    }
-
-    // CLR
-    fn CLR(&mut self, M: int, c: int, mem: &mut Memory) {
-        mem.write(M, 0);
-        self.m1 = !self.m2;
-        self.sign = 0;
-        self.res = 0;
-        self.cl += c;
-    }
-
-    // EXG
-    fn EXG(&mut self, mem: &mut Memory) {
-        let m = mem.read(self.PC);
-        self.PC += 1;
-        let r1 = (m & 0xF0) >> 4;
-        // only for javac
-        // of match r1
-        let k: int = match r1 {
-            0x00 => (self.A << 8) | self.B,
-            0x01 => self.X,
-            0x02 => self.Y,
-            0x03 => self.U,
-            0x04 => self.S,
-            0x05 => self.PC,
-            0x06 => self.getcc(),
-            0x07 => self.getcc(),
-            0x08 => self.A,
-            0x09 => self.B,
-            0x0A => self.getcc(),
-            0x0B => self.DP,
-            0x0C => self.getcc(),
-            0x0D => self.getcc(),
-            0x0E => self.getcc(),
-            0x0F => self.getcc(),
-            _ => 0, // only for javac
-        };
-        let mut l = 0;
-        let r2 = m & 0x0F;
-        match r2 {
-            0x00 => {
-                l = (self.A << 8) | self.B;
-                self.A = (k >> 8) & 255;
-                self.B = k & 255;
-            }
-            0x01 => {
-                l = self.X;
-                self.X = k;
-            }
-            0x02 => {
-                l = self.Y;
-                self.Y = k;
-            }
-            0x03 => {
-                l = self.U;
-                self.U = k;
-            }
-            0x04 => {
-                l = self.S;
-                self.S = k;
-            }
-            0x05 => {
-                l = self.PC;
-                self.PC = k;
-            }
-            0x06 => {
-                l = self.getcc();
-                self.setcc(k);
-            }
-            0x07 => {
-                l = self.getcc();
-                self.setcc(k);
-            }
-            0x08 => {
-                l = self.A;
-                self.A = k & 0xff;
-            }
-            0x09 => {
-                l = self.B;
-                self.B = k & 0xff;
-            }
-            0x0A => {
-                l = self.getcc();
-                self.setcc(k);
-            }
-            0x0B => {
-                l = self.DP;
-                self.DP = k & 0xff;
-            }
-            0x0C => {
-                l = self.getcc();
-                self.setcc(k);
-            }
-            0x0D => {
-                l = self.getcc();
-                self.setcc(k);
-            }
-            0x0E => {
-                l = self.getcc();
-                self.setcc(k);
-            }
-            0x0F => {
-                l = self.getcc();
-                self.setcc(k);
-            }
-            _ => {}
-        } // of match r2
-        match r1 {
-            0x00 => {
-                self.A = (l >> 8) & 255;
-                self.B = l & 255;
-            }
-            0x01 => self.X = l,
-            0x02 => self.Y = l,
-            0x03 => self.U = l,
-            0x04 => self.S = l,
-            0x05 => self.PC = l,
-            0x06 => self.setcc(l),
-            0x07 => self.setcc(l),
-            0x08 => self.A = l & 0xff,
-            0x09 => self.B = l & 0xff,
-            0x0A => self.setcc(l),
-            0x0B => self.DP = l & 0xff,
-            0x0C => self.setcc(l),
-            0x0D => self.setcc(l),
-            0x0E => self.setcc(l),
-            0x0F => self.setcc(l),
-            _ => {}
-        } // of second match r1
-        self.cl += 8;
-    }
-
-    fn TFR(&mut self, mem: &mut Memory) {
-        let m = mem.read(self.PC);
-        self.PC += 1;
-        let r1 = (m & 0xF0) >> 4;
-        // only for javac
-        // of match r1
-        let k: int = match r1 {
-            0x00 => (self.A << 8) | self.B,
-            0x01 => self.X,
-            0x02 => self.Y,
-            0x03 => self.U,
-            0x04 => self.S,
-            0x05 => self.PC,
-            0x06 | 0x07 => self.getcc(),
-            0x08 => self.A,
-            0x09 => self.B,
-            0x0A => self.getcc(),
-            0x0B => self.DP,
-            0x0C | 0x0D | 0x0E | 0x0F => self.getcc(),
-            _ => 0,
-        };
-        let r2 = m & 0x0F;
-        match r2 {
-            0x00 => {
-                self.A = (k >> 8) & 255;
-                self.B = k & 255;
-            }
-            0x01 => self.X = k,
-            0x02 => self.Y = k,
-            0x03 => self.U = k,
-            0x04 => self.S = k,
-            0x05 => self.PC = k,
-            0x06 | 0x07 => self.setcc(k),
-            0x08 => self.A = k & 0xff,
-            0x09 => self.B = k & 0xff,
-            0x0A => self.setcc(k),
-            0x0B => self.DP = k & 0xff,
-            0x0C | 0x0D | 0x0E | 0x0F => self.setcc(k),
-            _ => {}
-        } // of match r2
-    }
-
-    fn PSHS(&mut self, mem: &mut Memory) {
-        let m = mem.read(self.PC);
-        self.PC += 1;
-        if (m & 0x80) != 0 {
-            self.S -= 1;
-            mem.write(self.S, self.PC & 0x00FF);
-            self.S -= 1;
-            mem.write(self.S, self.PC >> 8);
-            self.cl += 2;
-        }
-        if (m & 0x40) != 0 {
-            self.S -= 1;
-            mem.write(self.S, self.U & 0x00FF);
-            self.S -= 1;
-            mem.write(self.S, self.U >> 8);
-            self.cl += 2;
-        }
-        if (m & 0x20) != 0 {
-            self.S -= 1;
-            mem.write(self.S, self.Y & 0x00FF);
-            self.S -= 1;
-            mem.write(self.S, self.Y >> 8);
-            self.cl += 2;
-        }
-        if (m & 0x10) != 0 {
-            self.S -= 1;
-            mem.write(self.S, self.X & 0x00FF);
-            self.S -= 1;
-            mem.write(self.S, self.X >> 8);
-            self.cl += 2;
-        }
-        if (m & 0x08) != 0 {
-            self.S -= 1;
-            mem.write(self.S, self.DP);
-            self.cl += 1;
-        }
-        if (m & 0x04) != 0 {
-            self.S -= 1;
-            mem.write(self.S, self.B);
-            self.cl += 1;
-        }
-        if (m & 0x02) != 0 {
-            self.S -= 1;
-            mem.write(self.S, self.A);
-            self.cl += 1;
-        }
-        if (m & 0x01) != 0 {
-            self.S -= 1;
-            self.getcc();
-            mem.write(self.S, self.CC);
-            self.cl += 1;
-        }
-        self.cl += 5;
-    }
-
-    fn PSHU(&mut self, mem: &mut Memory) {
-        let m = mem.read(self.PC);
-        self.PC += 1;
-        if (m & 0x80) != 0 {
-            self.U -= 1;
-            mem.write(self.U, self.PC & 0x00FF);
-            self.U -= 1;
-            mem.write(self.U, self.PC >> 8);
-            self.cl += 2;
-        }
-        if (m & 0x40) != 0 {
-            self.U -= 1;
-            mem.write(self.U, self.S & 0x00FF);
-            self.U -= 1;
-            mem.write(self.U, self.S >> 8);
-            self.cl += 2;
-        }
-        if (m & 0x20) != 0 {
-            self.U -= 1;
-            mem.write(self.U, self.Y & 0x00FF);
-            self.U -= 1;
-            mem.write(self.U, self.Y >> 8);
-            self.cl += 2;
-        }
-        if (m & 0x10) != 0 {
-            self.U -= 1;
-            mem.write(self.U, self.X & 0x00FF);
-            self.U -= 1;
-            mem.write(self.U, self.X >> 8);
-            self.cl += 2;
-        }
-        if (m & 0x08) != 0 {
-            self.U -= 1;
-            mem.write(self.U, self.DP);
-            self.cl += 1;
-        }
-        if (m & 0x04) != 0 {
-            self.U -= 1;
-            mem.write(self.U, self.B);
-            self.cl += 1;
-        }
-        if (m & 0x02) != 0 {
-            self.U -= 1;
-            mem.write(self.U, self.A);
-            self.cl += 1;
-        }
-        if (m & 0x01) != 0 {
-            self.U -= 1;
-            self.getcc();
-            mem.write(self.U, self.CC);
-            self.cl += 1;
-        }
-        self.cl += 5;
+    fn sta<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        mem.write(address, self.logic8(self.a));
     }
 
-    fn PULS(&mut self, mem: &mut Memory) {
-        let m = mem.read(self.PC);
-        self.PC += 1;
-        if (m & 0x01) != 0 {
-            self.CC = mem.read(self.S);
-            self.setcc(self.CC);
-            self.S += 1;
-            self.cl += 1;
-        }
-        if (m & 0x02) != 0 {
-            self.A = mem.read(self.S);
-            self.S += 1;
-            self.cl += 1;
-        }
-        if (m & 0x04) != 0 {
-            self.B = mem.read(self.S);
-            self.S += 1;
-            self.cl += 1;
-        }
-        if (m & 0x08) != 0 {
-            self.DP = mem.read(self.S);
-            self.S += 1;
-            self.cl += 1;
-        }
-        if (m & 0x10) != 0 {
-            self.X = mem.read_16(self.S);
-            self.S += 2;
-            self.cl += 2;
-        }
-        if (m & 0x20) != 0 {
-            self.Y = mem.read_16(self.S);
-            self.S += 2;
-            self.cl += 2;
-        }
-        if (m & 0x40) != 0 {
-            self.U = mem.read_16(self.S);
-            self.S += 2;
-            self.cl += 2;
-        }
-        if (m & 0x80) != 0 {
-            self.PC = mem.read_16(self.S);
-            self.S += 2;
-            self.cl += 2;
-        }
-        self.cl += 5;
+    fn stb<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        mem.write(address, self.logic8(self.b));
     }
 
-    fn PULU(&mut self, mem: &mut Memory) {
-        let m = mem.read(self.PC);
-        self.PC += 1;
-        if (m & 0x01) != 0 {
-            self.CC = mem.read(self.U);
-            self.setcc(self.CC);
-            self.U += 1;
-            self.cl += 1;
-        }
-        if (m & 0x02) != 0 {
-            self.A = mem.read(self.U);
-            self.U += 1;
-            self.cl += 1;
-        }
-        if (m & 0x04) != 0 {
-            self.B = mem.read(self.U);
-            self.U += 1;
-            self.cl += 1;
-        }
-        if (m & 0x08) != 0 {
-            self.DP = mem.read(self.U);
-            self.U += 1;
-            self.cl += 1;
-        }
-        if (m & 0x10) != 0 {
-            self.X = mem.read_16(self.U);
-            self.U += 2;
-            self.cl += 2;
-        }
-        if (m & 0x20) != 0 {
-            self.Y = mem.read_16(self.U);
-            self.U += 2;
-            self.cl += 2;
-        }
-        if (m & 0x40) != 0 {
-            self.S = mem.read_16(self.U);
-            self.U += 2;
-            self.cl += 2;
-        }
-        if (m & 0x80) != 0 {
-            self.PC = mem.read_16(self.U);
-            self.U += 2;
-            self.cl += 2;
-        }
-        self.cl += 5;
-    }
-
-    const fn INCA(&mut self) {
-        self.m1 = self.A;
-        self.m2 = 0;
-        self.A = (self.A + 1) & 0xFF;
-        self.ovfl = self.A;
-        self.sign = self.A;
-        self.res = (self.res & 0x100) | self.sign;
-        self.cl += 2;
-    }
-
-    const fn INCB(&mut self) {
-        self.m1 = self.B;
-        self.m2 = 0;
-        self.B = (self.B + 1) & 0xFF;
-        self.ovfl = self.B;
-        self.sign = self.B;
-        self.res = (self.res & 0x100) | self.sign;
-        self.cl += 2;
-    }
-
-    fn INC(&mut self, adr: int, c: int, mem: &mut Memory) {
-        let mut val = mem.read(adr);
-        self.m1 = val;
-        self.m2 = 0;
-        val += 1;
-        mem.write(adr, val);
-        self.ovfl = val & 0xFF;
-        self.sign = self.ovfl;
-        self.res = (self.res & 0x100) | self.sign;
-        self.cl += c;
-    }
-
-    // DEC
-    const fn DECA(&mut self) {
-        self.m1 = self.A;
-        self.m2 = 0x80;
-        self.A = (self.A - 1) & 0xFF;
-        self.ovfl = self.A;
-        self.sign = self.A;
-        self.res = (self.res & 0x100) | self.sign;
-        self.cl += 2;
-    }
-
-    const fn DECB(&mut self) {
-        self.m1 = self.B;
-        self.m2 = 0x80;
-        self.B = (self.B - 1) & 0xFF;
-        self.ovfl = self.B;
-        self.sign = self.B;
-        self.res = (self.res & 0x100) | self.sign;
-        self.cl += 2;
-    }
-
-    fn DEC(&mut self, adr: int, c: int, mem: &mut Memory) {
-        let mut val = mem.read(adr);
-        self.m1 = val;
-        self.m2 = 0x80;
-        val -= 1;
-        mem.write(adr, val);
-        self.ovfl = val & 0xFF;
-        self.sign = self.ovfl;
-        self.res = (self.res & 0x100) | self.sign;
-        self.cl += c;
-    }
-
-    fn BIT(&mut self, R: int, adr: int, c: int, mem: &mut Memory) {
-        let val = mem.read(adr);
-        self.m1 = self.ovfl;
-        self.sign = R & val;
-        self.res = (self.res & 0x100) | self.sign;
-        self.cl += c;
-    }
-
-    fn CMP8(&mut self, R: int, adr: int, c: int, mem: &mut Memory) {
-        let val = mem.read(adr);
-        self.m1 = R;
-        self.m2 = -val;
-        self.ovfl = R - val;
-        self.res = self.ovfl;
-        self.sign = self.ovfl;
-        self.cl += c;
-    }
-
-    fn CMP16(&mut self, R: int, adr: int, c: int, mem: &mut Memory) {
-        let val = mem.read_16(adr);
-        self.m1 = R >> 8;
-        self.m2 = (-val) >> 8;
-        self.ovfl = ((R - val) >> 8) & 0xFFFFFF;
-        self.res = self.ovfl;
-        self.sign = self.ovfl;
-        self.res |= (R - val) & 0xFF;
-        self.cl += c;
-    }
-
-    // TST
-    const fn TSTAi(&mut self) {
-        self.m1 = self.ovfl;
-        self.sign = self.A;
-        self.res = (self.res & 0x100) | self.sign;
-        self.cl += 2;
-    }
-
-    const fn TSTBi(&mut self) {
-        self.m1 = self.ovfl;
-        self.sign = self.B;
-        self.res = (self.res & 0x100) | self.sign;
-        self.cl += 2;
-    }
-
-    fn TST(&mut self, adr: int, c: int, mem: &mut Memory) {
-        let val = mem.read(adr);
-        self.m1 = !self.m2;
-        self.sign = val;
-        self.res = (self.res & 0x100) | self.sign;
-        self.cl += c;
-    }
-
-    fn ANDA(&mut self, adr: int, c: int, mem: &mut Memory) {
-        let val = mem.read(adr);
-        self.m1 = self.ovfl;
-        self.A &= val;
-        self.sign = self.A;
-        self.res = (self.res & 0x100) | self.sign;
-        self.cl += c;
-    }
-
-    fn ANDB(&mut self, adr: int, c: int, mem: &mut Memory) {
-        let val = mem.read(adr);
-        self.m1 = self.ovfl;
-        self.B &= val;
-        self.sign = self.B;
-        self.res = (self.res & 0x100) | self.sign;
-        self.cl += c;
-    }
-
-    fn ANDCC(&mut self, adr: int, c: int, mem: &mut Memory) {
-        let val = mem.read(adr);
-        //	getcc();
-        self.CC &= val;
-        self.setcc(self.CC);
-        self.cl += c;
-    }
-
-    fn ORA(&mut self, adr: int, c: int, mem: &mut Memory) {
-        let val = mem.read(adr);
-        self.m1 = self.ovfl;
-        self.A |= val;
-        self.sign = self.A;
-        self.res = (self.res & 0x100) | self.sign;
-        self.cl += c;
-    }
-
-    fn ORB(&mut self, adr: int, c: int, mem: &mut Memory) {
-        let val = mem.read(adr);
-        self.m1 = self.ovfl;
-        self.B |= val;
-        self.sign = self.B;
-        self.res = (self.res & 0x100) | self.sign;
-        self.cl += c;
-    }
-
-    fn ORCC(&mut self, adr: int, c: int, mem: &mut Memory) {
-        let val = mem.read(adr);
-        self.getcc();
-        self.CC |= val;
-        self.setcc(self.CC);
-        self.cl += c;
-    }
-
-    fn EORA(&mut self, adr: int, c: int, mem: &mut Memory) {
-        let val = mem.read(adr);
-        self.m1 = self.ovfl;
-        self.A ^= val;
-        self.sign = self.A;
-        self.res = (self.res & 0x100) | self.sign;
-        self.cl += c;
-    }
-
-    fn EORB(&mut self, adr: int, c: int, mem: &mut Memory) {
-        let val = mem.read(adr);
-        self.m1 = self.ovfl;
-        self.B ^= val;
-        self.sign = self.B;
-        self.res = (self.res & 0x100) | self.sign;
-        self.cl += c;
-    }
-
-    const fn COMA(&mut self) {
-        self.m1 = self.ovfl;
-        self.A = (!self.A) & 0xFF;
-        self.sign = self.A;
-        self.res = self.sign | 0x100;
-        self.cl += 2;
-    }
-
-    const fn COMB(&mut self) {
-        self.m1 = self.ovfl;
-        self.B = (!self.B) & 0xFF;
-        self.sign = self.B;
-        self.res = self.sign | 0x100;
-        self.cl += 2;
-    }
-
-    fn COM(&mut self, adr: int, c: int, mem: &mut Memory) {
-        let mut val = mem.read(adr);
-        self.m1 = !self.m2;
-        val = (!val) & 0xFF;
-        mem.write(adr, val);
-        self.sign = val;
-        self.res = self.sign | 0x100;
-        self.cl += c;
-    }
-
-    const fn NEGA(&mut self) {
-        self.m1 = self.A;
-        self.m2 = -self.A;
-        self.A = -self.A;
-        self.ovfl = self.A;
-        self.res = self.A;
-        self.sign = self.A;
-        self.A &= 0xFF;
-        self.cl += 2;
-    }
-
-    const fn NEGB(&mut self) {
-        self.m1 = self.B;
-        self.m2 = -self.B;
-        self.B = -self.B;
-        self.ovfl = self.B;
-        self.res = self.B;
-        self.sign = self.B;
-        self.B &= 0xFF;
-        self.cl += 2;
-    }
-
-    fn NEG(&mut self, adr: int, c: int, mem: &mut Memory) {
-        let mut val = mem.read(adr);
-        self.m1 = val;
-        self.m2 = -val;
-        val = -val;
-        mem.write(adr, val);
-        self.ovfl = val;
-        self.res = val;
-        self.sign = val;
-        self.cl += c;
-    }
-
-    const fn ABX(&mut self) {
-        self.X = (self.X + self.B) & 0xFFFF;
-        self.cl += 3;
-    }
-
-    fn ADDA(&mut self, adr: int, c: int, mem: &mut Memory) {
-        let val = mem.read(adr);
-        self.m1 = self.A;
-        self.h1 = self.A;
-        self.m2 = val;
-        self.h2 = val;
-        self.A += val;
-        self.ovfl = self.A;
-        self.res = self.A;
-        self.sign = self.A;
-        self.A &= 0xFF;
-        self.cl += c;
-    }
-
-    fn ADDB(&mut self, adr: int, c: int, mem: &mut Memory) {
-        let val = mem.read(adr);
-        self.m1 = self.B;
-        self.h1 = self.B;
-        self.m2 = val;
-        self.h2 = val;
-        self.B += val;
-        self.ovfl = self.B;
-        self.res = self.B;
-        self.sign = self.B;
-        self.B &= 0xFF;
-        self.cl += c;
-    }
-
-    fn ADDD(&mut self, adr: int, c: int, mem: &mut Memory) {
-        let val = mem.read_16(adr);
-        self.m1 = self.A;
-        self.m2 = val >> 8;
-        self.D = (self.A << 8) + self.B + val;
-        self.A = self.D >> 8;
-        self.B = self.D & 0xFF;
-        self.ovfl = self.A;
-        self.res = self.A;
-        self.sign = self.A;
-        self.res |= self.B;
-        self.A &= 0xFF;
-        self.cl += c;
-    }
-
-    fn ADCA(&mut self, adr: int, c: int, mem: &mut Memory) {
-        let val = mem.read(adr);
-        self.m1 = self.A;
-        self.h1 = self.A;
-        self.m2 = val;
-        self.h2 = val + ((self.res & 0x100) >> 8);
-        self.A += self.h2;
-        self.ovfl = self.A;
-        self.res = self.A;
-        self.sign = self.A;
-        self.A &= 0xFF;
-        self.cl += c;
-    }
-
-    fn ADCB(&mut self, adr: int, c: int, mem: &mut Memory) {
-        let val = mem.read(adr);
-        self.m1 = self.B;
-        self.h1 = self.B;
-        self.m2 = val;
-        self.h2 = val + ((self.res & 0x100) >> 8);
-        self.B += self.h2;
-        self.ovfl = self.B;
-        self.res = self.B;
-        self.sign = self.B;
-        self.B &= 0xFF;
-        self.cl += c;
-    }
-
-    const fn MUL(&mut self) {
-        let k = self.A * self.B;
-        self.A = (k >> 8) & 0xFF;
-        self.B = k & 0xFF;
-        self.res = ((self.B & 0x80) << 1) | ((k | (k >> 8)) & 0xFF);
-        self.cl += 11;
-    }
-
-    fn SBCA(&mut self, adr: int, c: int, mem: &mut Memory) {
-        let val = mem.read(adr);
-        self.m1 = self.A;
-        self.m2 = -val;
-        self.A -= val + ((self.res & 0x100) >> 8);
-        self.ovfl = self.A;
-        self.res = self.A;
-        self.sign = self.A;
-        self.A &= 0xFF;
-        self.cl += c;
-    }
-
-    fn SBCB(&mut self, adr: int, c: int, mem: &mut Memory) {
-        let val = mem.read(adr);
-        self.m1 = self.B;
-        self.m2 = -val;
-        self.B -= val + ((self.res & 0x100) >> 8);
-        self.ovfl = self.B;
-        self.res = self.B;
-        self.sign = self.B;
-        self.B &= 0xFF;
-        self.cl += c;
-    }
-
-    fn SUBA(&mut self, adr: int, c: int, mem: &mut Memory) {
-        let val = mem.read(adr);
-        self.m1 = self.A;
-        self.m2 = -val;
-        self.A -= val;
-        self.ovfl = self.A;
-        self.res = self.A;
-        self.sign = self.A;
-        self.A &= 0xFF;
-        self.cl += c;
-    }
-
-    fn SUBB(&mut self, adr: int, c: int, mem: &mut Memory) {
-        let val = mem.read(adr);
-        self.m1 = self.B;
-        self.m2 = -val;
-        self.B -= val;
-        self.ovfl = self.B;
-        self.res = self.B;
-        self.sign = self.B;
-        self.B &= 0xFF;
-        self.cl += c;
-    }
-
-    fn SUBD(&mut self, adr: int, c: int, mem: &mut Memory) {
-        let val = mem.read_16(adr);
-        self.m1 = self.A;
-        self.m2 = (-val) >> 8;
-        self.D = (self.A << 8) + self.B - val;
-        self.A = self.D >> 8;
-        self.B = self.D & 0xFF;
-        self.ovfl = self.A;
-        self.res = self.A;
-        self.sign = self.A;
-        self.res |= self.B;
-        self.A &= 0xFF;
-        self.cl += c;
-    }
-
-    const fn SEX(&mut self) {
-        if (self.B & 0x80) == 0x80 {
-            self.A = 0xFF;
-        } else {
-            self.A = 0;
-        }
-        self.sign = self.B;
-        self.res = (self.res & 0x100) | self.sign;
-        self.cl += 2;
-    }
-
-    const fn ASLA(&mut self) {
-        self.m1 = self.A;
-        self.m2 = self.A;
-        self.A <<= 1;
-        self.ovfl = self.A;
-        self.sign = self.A;
-        self.res = self.A;
-        self.A &= 0xFF;
-        self.cl += 2;
-    }
-
-    const fn ASLB(&mut self) {
-        self.m1 = self.B;
-        self.m2 = self.B;
-        self.B <<= 1;
-        self.ovfl = self.B;
-        self.sign = self.B;
-        self.res = self.B;
-        self.B &= 0xFF;
-        self.cl += 2;
-    }
-
-    fn ASL(&mut self, adr: int, c: int, mem: &mut Memory) {
-        let mut val = mem.read(adr);
-        self.m1 = val;
-        self.m2 = val;
-        val <<= 1;
-        mem.write(adr, val);
-        self.ovfl = val;
-        self.sign = val;
-        self.res = val;
-        self.cl += c;
-    }
-
-    const fn ASRA(&mut self) {
-        self.res = (self.A & 1) << 8;
-        self.A = (self.A >> 1) | (self.A & 0x80);
-        self.sign = self.A;
-        self.res |= self.sign;
-        self.cl += 2;
-    }
-
-    const fn ASRB(&mut self) {
-        self.res = (self.B & 1) << 8;
-        self.B = (self.B >> 1) | (self.B & 0x80);
-        self.sign = self.B;
-        self.res |= self.sign;
-        self.cl += 2;
-    }
-
-    fn ASR(&mut self, adr: int, c: int, mem: &mut Memory) {
-        let mut val = mem.read(adr);
-        self.res = (val & 1) << 8;
-        val = (val >> 1) | (val & 0x80);
-        mem.write(adr, val);
-        self.sign = val;
-        self.res |= self.sign;
-        self.cl += c;
-    }
-
-    const fn LSRA(&mut self) {
-        self.res = (self.A & 1) << 8;
-        self.A >>= 1;
-        self.sign = 0;
-        self.res |= self.A;
-        self.cl += 2;
-    }
-
-    const fn LSRB(&mut self) {
-        self.res = (self.B & 1) << 8;
-        self.B >>= 1;
-        self.sign = 0;
-        self.res |= self.B;
-        self.cl += 2;
-    }
-
-    fn LSR(&mut self, adr: int, c: int, mem: &mut Memory) {
-        let mut val = mem.read(adr);
-        self.res = (val & 1) << 8;
-        val >>= 1;
-        mem.write(adr, val);
-        self.sign = 0;
-        self.res |= val;
-        self.cl += c;
-    }
-
-    const fn ROLA(&mut self) {
-        self.m1 = self.A;
-        self.m2 = self.A;
-        self.A = (self.A << 1) | ((self.res & 0x100) >> 8);
-        self.ovfl = self.A;
-        self.sign = self.A;
-        self.res = self.A;
-        self.A &= 0xFF;
-        self.cl += 2;
-    }
-
-    const fn ROLB(&mut self) {
-        self.m1 = self.B;
-        self.m2 = self.B;
-        self.B = (self.B << 1) | ((self.res & 0x100) >> 8);
-        self.ovfl = self.B;
-        self.sign = self.B;
-        self.res = self.B;
-        self.B &= 0xFF;
-        self.cl += 2;
-    }
-
-    fn ROL(&mut self, adr: int, c: int, mem: &mut Memory) {
-        let mut val = mem.read(adr);
-        self.m1 = val;
-        self.m2 = val;
-        val = (val << 1) | ((self.res & 0x100) >> 8);
-        mem.write(adr, val);
-        self.ovfl = val;
-        self.sign = val;
-        self.res = val;
-        self.cl += c;
-    }
-
-    const fn RORA(&mut self) {
-        let i = self.A;
-        self.A = (self.A | (self.res & 0x100)) >> 1;
-        self.sign = self.A;
-        self.res = ((i & 1) << 8) | self.sign;
-        self.cl += 2;
-    }
-
-    const fn RORB(&mut self) {
-        let i = self.B;
-        self.B = (self.B | (self.res & 0x100)) >> 1;
-        self.sign = self.B;
-        self.res = ((i & 1) << 8) | self.sign;
-        self.cl += 2;
-    }
-
-    fn ROR(&mut self, adr: int, c: int, mem: &mut Memory) {
-        let mut val;
-        let i = mem.read(adr);
-        val = i;
-        val = (val | (self.res & 0x100)) >> 1;
-        mem.write(adr, val);
-        self.sign = val;
-        self.res = ((i & 1) << 8) | self.sign;
-        self.cl += c;
-    }
-
-    fn BRA(&mut self, mem: &mut Memory) {
-        let m = mem.read(self.PC);
-        self.PC += 1;
-        self.PC += signedChar(m);
-        self.cl += 3;
-    }
-
-    fn LBRA(&mut self, mem: &mut Memory) {
-        let mut m = mem.read(self.PC);
-        self.PC += 1;
-        let mut off = m << 8;
-        m = mem.read(self.PC);
-        self.PC += 1;
-        off |= m;
-        self.PC = (self.PC + off) & 0xFFFF;
-        self.cl += 5;
-    }
-
-    fn JMPd(&mut self, mem: &mut Memory) {
-        let m = mem.read(self.PC);
-        self.PC += 1;
-        self.PC = (self.DP << 8) | m;
-        self.cl += 3;
-    }
-
-    fn JMPe(&mut self, mem: &mut Memory) {
-        self.PC = self.ETEND(mem);
-        self.cl += 4;
-    }
-
-    fn JMPx(&mut self, mem: &mut Memory) {
-        self.PC = self.INDEXE(mem);
-        self.cl += 3;
-    }
-
-    fn BSR(&mut self, mem: &mut Memory) {
-        let m = mem.read(self.PC);
-        self.PC += 1;
-        self.S -= 1;
-        mem.write(self.S, self.PC & 0x00FF);
-        self.S -= 1;
-        mem.write(self.S, self.PC >> 8);
-        self.PC += signedChar(m);
-        self.cl += 7;
-    }
-
-    fn LBSR(&mut self, mem: &mut Memory) {
-        let mut m = mem.read(self.PC);
-        self.PC += 1;
-        let mut off = m << 8;
-        m = mem.read(self.PC);
-        self.PC += 1;
-        off |= m;
-        self.S -= 1;
-        mem.write(self.S, self.PC & 0x00FF);
-        self.S -= 1;
-        mem.write(self.S, self.PC >> 8);
-        self.PC = (self.PC + off) & 0xFFFF;
-        self.cl += 9;
-    }
-
-    fn JSRd(&mut self, mem: &mut Memory) {
-        let m = mem.read(self.PC);
-        self.PC += 1;
-        self.S -= 1;
-        mem.write(self.S, self.PC & 0x00FF);
-        self.S -= 1;
-        mem.write(self.S, self.PC >> 8);
-        self.PC = (self.DP << 8) | m;
-        self.cl += 7;
-    }
-
-    fn JSRe(&mut self, mem: &mut Memory) {
-        let adr = self.ETEND(mem);
-        self.S -= 1;
-        mem.write(self.S, self.PC & 0x00FF);
-        self.S -= 1;
-        mem.write(self.S, self.PC >> 8);
-        self.PC = adr;
-        self.cl += 8;
-    }
-
-    fn JSRx(&mut self, mem: &mut Memory) {
-        let adr = self.INDEXE(mem);
-        self.S -= 1;
-        mem.write(self.S, self.PC & 0x00FF);
-        self.S -= 1;
-        mem.write(self.S, self.PC >> 8);
-        self.PC = adr;
-        self.cl += 7;
-    }
-
-    fn BRN(&mut self, mem: &mut Memory) {
-        mem.read(self.PC);
-        self.PC += 1;
-        self.cl += 3;
+    fn std<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        mem.write_16(address, self.logic16(self.d()));
     }
 
-    fn LBRN(&mut self, mem: &mut Memory) {
-        mem.read(self.PC);
-        self.PC += 1;
-        mem.read(self.PC);
-        self.PC += 1;
-        self.cl += 5;
+    fn stx<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        mem.write_16(address, self.logic16(self.x));
     }
 
-    const fn NOP(&mut self) {
-        self.cl += 2;
-    }
-
-    fn RTS(&mut self, mem: &mut Memory) {
-        self.PC = mem.read_16(self.S);
-        self.S += 2;
-        self.cl += 5;
+    fn sty<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        mem.write_16(address, self.logic16(self.y));
     }
 
-    /* Branchements conditionnels */
+    fn stu<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        mem.write_16(address, self.logic16(self.u));
+    }
 
-    fn BCC(&mut self, mem: &mut Memory) {
-        let m = mem.read(self.PC);
-        self.PC += 1;
-        if (self.res & 0x100) != 0x100 {
-            self.PC += signedChar(m);
-        }
-        self.cl += 3;
-    }
-
-    fn LBCC(&mut self, mem: &mut Memory) {
-        let mut m = mem.read(self.PC);
-        self.PC += 1;
-        let mut off = m << 8;
-        m = mem.read(self.PC);
-        self.PC += 1;
-        off |= m;
-        if (self.res & 0x100) != 0x100 {
-            self.PC = (self.PC + off) & 0xFFFF;
-            self.cl += 6;
-        } else {
-            self.cl += 5;
-        }
+    fn sts<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        mem.write_16(address, self.logic16(self.s));
     }
 
-    fn BCS(&mut self, mem: &mut Memory) {
-        let m = mem.read(self.PC);
-        self.PC += 1;
-        if (self.res & 0x100) == 0x100 {
-            self.PC += signedChar(m);
-        }
-        self.cl += 3;
-    }
-
-    fn LBCS(&mut self, mem: &mut Memory) {
-        let mut m = mem.read(self.PC);
-        self.PC += 1;
-        let mut off = m << 8;
-        m = mem.read(self.PC);
-        self.PC += 1;
-        off |= m;
-        if (self.res & 0x100) == 0x100 {
-            self.PC = (self.PC + off) & 0xFFFF;
-            self.cl += 6;
-        } else {
-            self.cl += 5;
-        }
+    // 8 bits arithmetic and logic
+    fn suba<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.a = self.sub8(self.a, mem.read(address), 0);
     }
 
-    fn BEQ(&mut self, mem: &mut Memory) {
-        let m = mem.read(self.PC);
-        self.PC += 1;
-        if (self.res & 0xff) == 0x00 {
-            self.PC += signedChar(m);
-        }
-        self.cl += 3;
-    }
-
-    fn LBEQ(&mut self, mem: &mut Memory) {
-        let mut m = mem.read(self.PC);
-        self.PC += 1;
-        let mut off = m << 8;
-        m = mem.read(self.PC);
-        self.PC += 1;
-        off |= m;
-        if (self.res & 0xff) == 0x00 {
-            self.PC = (self.PC + off) & 0xFFFF;
-        }
-        self.cl += 6;
+    fn subb<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.b = self.sub8(self.b, mem.read(address), 0);
     }
 
-    fn BNE(&mut self, mem: &mut Memory) {
-        let m = mem.read(self.PC);
-        self.PC += 1;
-        if (self.res & 0xff) != 0 {
-            self.PC += signedChar(m);
-        }
-        self.cl += 3;
-    }
-
-    fn LBNE(&mut self, mem: &mut Memory) {
-        let mut m = mem.read(self.PC);
-        self.PC += 1;
-        let mut off = m << 8;
-        m = mem.read(self.PC);
-        self.PC += 1;
-        off |= m;
-        if (self.res & 0xff) != 0 {
-            self.PC = (self.PC + off) & 0xFFFF;
-            self.cl += 6;
-        } else {
-            self.cl += 5;
-        }
+    fn cmpa<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.sub8(self.a, mem.read(address), 0);
     }
 
-    fn BGE(&mut self, mem: &mut Memory) {
-        let m = mem.read(self.PC);
-        self.PC += 1;
-        if ((self.sign ^ ((!(self.m1 ^ self.m2)) & (self.m1 ^ self.ovfl))) & 0x80) == 0 {
-            self.PC += signedChar(m);
-        }
-        self.cl += 3;
-    }
-
-    fn LBGE(&mut self, mem: &mut Memory) {
-        let mut m = mem.read(self.PC);
-        self.PC += 1;
-        let mut off = m << 8;
-        m = mem.read(self.PC);
-        self.PC += 1;
-        off |= m;
-        if ((self.sign ^ ((!(self.m1 ^ self.m2)) & (self.m1 ^ self.ovfl))) & 0x80) == 0 {
-            self.PC = (self.PC + off) & 0xFFFF;
-            self.cl += 6;
-        } else {
-            self.cl += 5;
-        }
+    fn cmpb<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.sub8(self.b, mem.read(address), 0);
     }
 
-    fn BLE(&mut self, mem: &mut Memory) {
-        let m = mem.read(self.PC);
-        self.PC += 1;
-        if ((self.res & 0xff) == 0)
-            || (((self.sign ^ ((!(self.m1 ^ self.m2)) & (self.m1 ^ self.ovfl))) & 0x80) != 0)
-        {
-            self.PC += signedChar(m);
-        }
-        self.cl += 3;
-    }
-
-    fn LBLE(&mut self, mem: &mut Memory) {
-        let mut m = mem.read(self.PC);
-        self.PC += 1;
-        let mut off = m << 8;
-        m = mem.read(self.PC);
-        self.PC += 1;
-        off |= m;
-        if ((self.res & 0xff) == 0)
-            || (((self.sign ^ ((!(self.m1 ^ self.m2)) & (self.m1 ^ self.ovfl))) & 0x80) != 0)
-        {
-            self.PC = (self.PC + off) & 0xFFFF;
-            self.cl += 6;
-        } else {
-            self.cl += 5;
-        }
+    fn sbca<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.a = self.sub8(self.a, mem.read(address), self.cc & C);
     }
 
-    fn BLS(&mut self, mem: &mut Memory) {
-        let m = mem.read(self.PC);
-        self.PC += 1;
-        if ((self.res & 0x100) != 0) || ((self.res & 0xff) == 0) {
-            self.PC += signedChar(m);
-        }
-        self.cl += 3;
-    }
-
-    fn LBLS(&mut self, mem: &mut Memory) {
-        let mut m = mem.read(self.PC);
-        self.PC += 1;
-        let mut off = m << 8;
-        m = mem.read(self.PC);
-        self.PC += 1;
-        off |= m;
-        if ((self.res & 0x100) != 0) || ((self.res & 0xff) == 0) {
-            self.PC = (self.PC + off) & 0xFFFF;
-            self.cl += 6;
-        } else {
-            self.cl += 5;
-        }
+    fn sbcb<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.b = self.sub8(self.b, mem.read(address), self.cc & C);
     }
 
-    fn BGT(&mut self, mem: &mut Memory) {
-        let m = mem.read(self.PC);
-        self.PC += 1;
-        if ((self.res & 0xff) != 0)
-            && (((self.sign ^ ((!(self.m1 ^ self.m2)) & (self.m1 ^ self.ovfl))) & 0x80) == 0)
-        {
-            self.PC += signedChar(m);
-        }
-        self.cl += 3;
-    }
-
-    fn LBGT(&mut self, mem: &mut Memory) {
-        let mut m = mem.read(self.PC);
-        self.PC += 1;
-        let mut off = m << 8;
-        m = mem.read(self.PC);
-        self.PC += 1;
-        off |= m;
-        if ((self.res & 0xff) != 0)
-            && (((self.sign ^ ((!(self.m1 ^ self.m2)) & (self.m1 ^ self.ovfl))) & 0x80) == 0)
-        {
-            self.PC = (self.PC + off) & 0xFFFF;
-            self.cl += 6;
-        } else {
-            self.cl += 5;
-        }
+    fn anda<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.a = self.logic8(self.a & mem.read(address));
     }
 
-    fn BLT(&mut self, mem: &mut Memory) {
-        let m = mem.read(self.PC);
-        self.PC += 1;
-        if ((self.sign ^ ((!(self.m1 ^ self.m2)) & (self.m1 ^ self.ovfl))) & 0x80) != 0 {
-            self.PC += signedChar(m);
-        }
-        self.cl += 3;
-    }
-
-    fn LBLT(&mut self, mem: &mut Memory) {
-        let mut m = mem.read(self.PC);
-        self.PC += 1;
-        let mut off = m << 8;
-        m = mem.read(self.PC);
-        self.PC += 1;
-        off |= m;
-        if ((self.sign ^ ((!(self.m1 ^ self.m2)) & (self.m1 ^ self.ovfl))) & 0x80) != 0 {
-            self.PC = (self.PC + off) & 0xFFFF;
-            self.cl += 6;
-        } else {
-            self.cl += 5;
-        }
+    fn andb<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.b = self.logic8(self.b & mem.read(address));
     }
 
-    fn BHI(&mut self, mem: &mut Memory) {
-        let m = mem.read(self.PC);
-        self.PC += 1;
-        if ((self.res & 0x100) == 0) && ((self.res & 0xff) != 0) {
-            self.PC += signedChar(m);
-        }
-        self.cl += 3;
-    }
-
-    fn LBHI(&mut self, mem: &mut Memory) {
-        let mut m = mem.read(self.PC);
-        self.PC += 1;
-        let mut off = m << 8;
-        m = mem.read(self.PC);
-        self.PC += 1;
-        off |= m;
-        if ((self.res & 0x100) == 0) && ((self.res & 0xff) != 0) {
-            self.PC = (self.PC + off) & 0xFFFF;
-            self.cl += 6;
-        }
-        self.cl += 5;
+    fn bita<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.logic8(self.a & mem.read(address));
     }
 
-    fn BMI(&mut self, mem: &mut Memory) {
-        let m = mem.read(self.PC);
-        self.PC += 1;
-        if (self.sign & 0x80) != 0 {
-            self.PC += signedChar(m);
-        }
-        self.cl += 3;
-    }
-
-    fn LBMI(&mut self, mem: &mut Memory) {
-        let mut m = mem.read(self.PC);
-        self.PC += 1;
-        let mut off = m << 8;
-        m = mem.read(self.PC);
-        self.PC += 1;
-        off |= m;
-        if (self.sign & 0x80) != 0 {
-            self.PC = (self.PC + off) & 0xFFFF;
-            self.cl += 6;
-        } else {
-            self.cl += 5;
-        }
+    fn bitb<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.logic8(self.b & mem.read(address));
     }
 
-    fn BPL(&mut self, mem: &mut Memory) {
-        let m = mem.read(self.PC);
-        self.PC += 1;
-        if (self.sign & 0x80) == 0 {
-            self.PC += signedChar(m);
-        }
-        self.cl += 3;
-    }
-
-    fn LBPL(&mut self, mem: &mut Memory) {
-        let mut m = mem.read(self.PC);
-        self.PC += 1;
-        let mut off = m << 8;
-        m = mem.read(self.PC);
-        self.PC += 1;
-        off |= m;
-        if (self.sign & 0x80) == 0 {
-            self.PC = (self.PC + off) & 0xFFFF;
-            self.cl += 6;
-        } else {
-            self.cl += 5;
-        }
+    fn eora<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.a = self.logic8(self.a ^ mem.read(address));
     }
 
-    fn BVS(&mut self, mem: &mut Memory) {
-        let m = mem.read(self.PC);
-        self.PC += 1;
-        if (((self.m1 ^ self.m2) & 0x80) == 0) && (((self.m1 ^ self.ovfl) & 0x80) != 0) {
-            self.PC += signedChar(m);
-        }
-        self.cl += 3;
-    }
-
-    fn LBVS(&mut self, mem: &mut Memory) {
-        let mut m = mem.read(self.PC);
-        self.PC += 1;
-        let mut off = m << 8;
-        m = mem.read(self.PC);
-        self.PC += 1;
-        off |= m;
-        if (((self.m1 ^ self.m2) & 0x80) == 0) && (((self.m1 ^ self.ovfl) & 0x80) != 0) {
-            self.PC = (self.PC + off) & 0xFFFF;
-            self.cl += 6;
-        } else {
-            self.cl += 5;
-        }
+    fn eorb<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.b = self.logic8(self.b ^ mem.read(address));
     }
 
-    fn BVC(&mut self, mem: &mut Memory) {
-        let m = mem.read(self.PC);
-        self.PC += 1;
-        if (((self.m1 ^ self.m2) & 0x80) != 0) || (((self.m1 ^ self.ovfl) & 0x80) == 0) {
-            self.PC += signedChar(m);
-        }
-        self.cl += 3;
-    }
-
-    fn LBVC(&mut self, mem: &mut Memory) {
-        let mut m = mem.read(self.PC);
-        self.PC += 1;
-        let mut off = m << 8;
-        m = mem.read(self.PC);
-        self.PC += 1;
-        off |= m;
-        if (((self.m1 ^ self.m2) & 0x80) != 0) || (((self.m1 ^ self.ovfl) & 0x80) == 0) {
-            self.PC = (self.PC + off) & 0xFFFF;
-            self.cl += 6;
-        } else {
-            self.cl += 5;
-        }
+    fn adca<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.a = self.add8(self.a, mem.read(address), self.cc & C);
     }
 
-    fn SWI(&mut self, mem: &mut Memory) {
-        self.getcc();
-        self.CC |= 0x80; /* bit E � 1 */
-        self.setcc(self.CC);
-        self.S -= 1;
-        mem.write(self.S, self.PC & 0x00FF);
-        self.S -= 1;
-        mem.write(self.S, self.PC >> 8);
-        self.S -= 1;
-        mem.write(self.S, self.U & 0x00FF);
-        self.S -= 1;
-        mem.write(self.S, self.U >> 8);
-        self.S -= 1;
-        mem.write(self.S, self.Y & 0x00FF);
-        self.S -= 1;
-        mem.write(self.S, self.Y >> 8);
-        self.S -= 1;
-        mem.write(self.S, self.X & 0x00FF);
-        self.S -= 1;
-        mem.write(self.S, self.X >> 8);
-        self.S -= 1;
-        mem.write(self.S, self.DP);
-        self.S -= 1;
-        mem.write(self.S, self.B);
-        self.S -= 1;
-        mem.write(self.S, self.A);
-        self.S -= 1;
-        mem.write(self.S, self.CC);
-
-        self.PC = mem.read_16(0xFFFA);
-        self.cl += 19;
-    }
-
-    fn RTI(&mut self, mem: &mut Memory) {
-        self.CC = mem.read(self.S);
-        self.setcc(self.CC);
-        self.S += 1;
-        if (self.CC & 0x80) == 0x80 {
-            self.A = mem.read(self.S);
-            self.S += 1;
-            self.B = mem.read(self.S);
-            self.S += 1;
-            self.DP = mem.read(self.S);
-            self.S += 1;
-            self.X = mem.read_16(self.S);
-            self.S += 2;
-            self.Y = mem.read_16(self.S);
-            self.S += 2;
-            self.U = mem.read_16(self.S);
-            self.S += 2;
-            self.cl += 15;
-        } else {
-            self.cl += 6;
-        }
+    fn adcb<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.b = self.add8(self.b, mem.read(address), self.cc & C);
+    }
 
-        self.PC = mem.read_16(self.S);
-        self.S += 2;
-    }
-
-    pub(crate) fn IRQ(&mut self, mem: &mut Memory) {
-        /* mise � 1 du bit E sur le CC */
-        self.getcc();
-        self.CC |= 0x80;
-        self.setcc(self.CC);
-        self.S -= 1;
-        mem.write(self.S, self.PC & 0x00FF);
-        self.S -= 1;
-        mem.write(self.S, self.PC >> 8);
-        self.S -= 1;
-        mem.write(self.S, self.U & 0x00FF);
-        self.S -= 1;
-        mem.write(self.S, self.U >> 8);
-        self.S -= 1;
-        mem.write(self.S, self.Y & 0x00FF);
-        self.S -= 1;
-        mem.write(self.S, self.Y >> 8);
-        self.S -= 1;
-        mem.write(self.S, self.X & 0x00FF);
-        self.S -= 1;
-        mem.write(self.S, self.X >> 8);
-        self.S -= 1;
-        mem.write(self.S, self.DP);
-        self.S -= 1;
-        mem.write(self.S, self.B);
-        self.S -= 1;
-        mem.write(self.S, self.A);
-        self.S -= 1;
-        mem.write(self.S, self.CC);
-        self.PC = mem.read_16(0xFFF8);
-        self.CC |= 0x10;
-        self.setcc(self.CC);
-        self.cl += 19;
-    }
-
-    const fn DAA(&mut self) {
-        let mut i = self.A + (self.res & 0x100);
-        if ((self.A & 15) > 9) || ((self.h1 & 15) + (self.h2 & 15) > 15) {
-            i += 6;
-        }
-        if i > 0x99 {
-            i += 0x60;
-        }
-        self.res = i;
-        self.sign = i;
-        self.A = i & 255;
-        self.cl += 2;
+    fn ora<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.a = self.logic8(self.a | mem.read(address));
     }
 
-    fn CWAI(&mut self, mem: &mut Memory) {
-        self.getcc();
-        self.CC &= mem.read(self.PC);
-        self.setcc(self.CC);
-        self.PC += 1;
-        self.cl += 20;
+    fn orb<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.b = self.logic8(self.b | mem.read(address));
     }
 
-    pub(crate) fn FetchUntil(
-        &mut self,
-        clock: int,
-        mem: &mut Memory,
-        screen: &mut Screen,
-        sound: &mut Sound,
-    ) -> int {
-        while self.cl < clock {
-            self.Fetch(mem, screen, sound);
-        }
-        self.cl -= clock;
-        self.cl
+    fn adda<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.a = self.add8(self.a, mem.read(address), 0);
     }
 
-    fn Fetch(&mut self, mem: &mut Memory, screen: &mut Screen, sound: &mut Sound) {
-        let opcode = mem.read(self.PC);
-        self.PC += 1;
-        // 	Sound emulation process
-        if self.sound_buffer.push(mem.sound_mem) {
-            sound.play_sound(self);
-        }
+    fn addb<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.b = self.add8(self.b, mem.read(address), 0);
+    }
 
-        match opcode {
-            // the mystery undocumented opcode
-            0x01 => {
-                self.PC += 1;
-                self.cl += 2;
-                // PER (instruction d'emulation de périphérique)
-            }
-            0x02 => mem.periph(self.PC, self.S, self.A, screen), // LDA
-            0x86 => {
-                let M = self.IMMED8();
-                self.A = self.LD8(M, 2, mem);
-            }
-            0x96 => {
-                let M = self.DIREC(mem);
-                self.A = self.LD8(M, 4, mem);
-            }
-            0xB6 => {
-                let M = self.ETEND(mem);
-                self.A = self.LD8(M, 5, mem);
-            }
-            0xA6 => {
-                let M = self.INDEXE(mem);
-                self.A = self.LD8(M, 4, mem);
-                // LDB
-            }
-            0xC6 => {
-                let M = self.IMMED8();
-                self.B = self.LD8(M, 2, mem);
-            }
-            0xD6 => {
-                let M = self.DIREC(mem);
-                self.B = self.LD8(M, 4, mem);
-            }
-            0xF6 => {
-                let M = self.ETEND(mem);
-                self.B = self.LD8(M, 5, mem);
-            }
-            0xE6 => {
-                let M = self.INDEXE(mem);
-                self.B = self.LD8(M, 4, mem);
-            }
-            // LDD
-            0xCC => {
-                let M = self.IMMED16();
-                self.D = self.LD16(M, 3, mem);
-                self.CALCAB();
-            }
-            0xDC => {
-                let M = self.DIREC(mem);
-                self.D = self.LD16(M, 5, mem);
-                self.CALCAB();
-            }
-            0xFC => {
-                let M = self.ETEND(mem);
-                self.D = self.LD16(M, 6, mem);
-                self.CALCAB();
-            }
-            0xEC => {
-                let M = self.INDEXE(mem);
-                self.D = self.LD16(M, 5, mem);
-                self.CALCAB();
-            }
-            // LDU
-            0xCE => {
-                let M = self.IMMED16();
-                self.U = self.LD16(M, 3, mem);
-            }
-            0xDE => {
-                let M = self.DIREC(mem);
-                self.U = self.LD16(M, 5, mem);
-            }
-            0xFE => {
-                let M = self.ETEND(mem);
-                self.U = self.LD16(M, 6, mem);
-            }
-            0xEE => {
-                let M = self.INDEXE(mem);
-                self.U = self.LD16(M, 5, mem);
-            }
-            // LDX
-            0x8E => {
-                let M = self.IMMED16();
-                self.X = self.LD16(M, 3, mem);
-            }
-            0x9E => {
-                let M = self.DIREC(mem);
-                self.X = self.LD16(M, 5, mem);
-            }
-            0xBE => {
-                let M = self.ETEND(mem);
-                self.X = self.LD16(M, 6, mem);
-            }
-            0xAE => {
-                let i = self.INDEXE(mem);
-                self.X = self.LD16(i, 5, mem);
-            }
-            // STA
-            0x97 => {
-                let M = self.DIREC(mem);
-                self.ST8(self.A, M, 4, mem);
-            }
-            0xB7 => {
-                let M = self.ETEND(mem);
-                self.ST8(self.A, M, 5, mem);
-            }
-            0xA7 => {
-                let M = self.INDEXE(mem);
-                self.ST8(self.A, M, 4, mem);
-            }
-            // STB
-            0xD7 => {
-                let M = self.DIREC(mem);
-                self.ST8(self.B, M, 4, mem);
-            }
-            0xF7 => {
-                let M = self.ETEND(mem);
-                self.ST8(self.B, M, 5, mem);
-            }
-            0xE7 => {
-                let M = self.INDEXE(mem);
-                self.ST8(self.B, M, 4, mem);
-            }
-            // STD
-            0xDD => {
-                self.CALCD();
-                let M = self.DIREC(mem);
-                self.ST16(self.D, M, 5, mem);
-            }
-            0xFD => {
-                self.CALCD();
-                let M = self.ETEND(mem);
-                self.ST16(self.D, M, 6, mem);
-            }
-            0xED => {
-                self.CALCD();
-                let M = self.INDEXE(mem);
-                self.ST16(self.D, M, 6, mem);
-            }
-            // STU
-            0xDF => {
-                let adr = self.DIREC(mem);
-                self.ST16(self.U, adr, 5, mem);
-            }
-            0xFF => {
-                let adr = self.ETEND(mem);
-                self.ST16(self.U, adr, 6, mem);
-            }
-            0xEF => {
-                let adr = self.INDEXE(mem);
-                self.ST16(self.U, adr, 5, mem);
-            }
-            // STX
-            0x9F => {
-                let M = self.DIREC(mem);
-                self.ST16(self.X, M, 5, mem);
-            }
-            0xBF => {
-                let M = self.ETEND(mem);
-                self.ST16(self.X, M, 6, mem);
-            }
-            0xAF => {
-                let M = self.INDEXE(mem);
-                self.ST16(self.X, M, 5, mem);
-            }
-            // LEAS
-            0x32 => self.S = self.INDEXE(mem),
-            // LEAU
-            0x33 => self.U = self.INDEXE(mem),
-            // LEAX
-            0x30 => self.X = self.LEA(mem),
-            // LEAY
-            0x31 => self.Y = self.LEA(mem),
-            // CLRA
-            0x4F => {
-                self.A = 0;
-                self.m1 = self.ovfl;
-                self.sign = 0;
-                self.res = 0;
-                self.cl += 2;
-            }
-            // CLRB
-            0x5F => {
-                self.B = 0;
-                self.m1 = self.ovfl;
-                self.sign = 0;
-                self.res = 0;
-                self.cl += 2;
-            }
-            // CLR
-            0x0F => {
-                let M = self.DIREC(mem);
-                self.CLR(M, 6, mem);
-            }
-            0x7F => {
-                let M = self.ETEND(mem);
-                self.CLR(M, 7, mem);
-            }
-            0x6F => {
-                let M = self.INDEXE(mem);
-                self.CLR(M, 6, mem);
-            }
-            0x1E => self.EXG(mem),
-            0x1F => self.TFR(mem),
-            // PSH/PUL
-            0x34 => self.PSHS(mem),
-            0x36 => self.PSHU(mem),
-            0x35 => self.PULS(mem),
-            0x37 => self.PULU(mem),
-            // INC
-            0x4C => self.INCA(),
-            0x5C => self.INCB(),
-            0x7C => {
-                let M = self.ETEND(mem);
-                self.INC(M, 7, mem);
-            }
-            0x0C => {
-                let M = self.DIREC(mem);
-                self.INC(M, 6, mem);
-            }
-            0x6C => {
-                let M = self.INDEXE(mem);
-                self.INC(M, 6, mem);
-            }
-            // DEC
-            0x4A => self.DECA(),
-            0x5A => self.DECB(),
-            0x7A => {
-                let M = self.ETEND(mem);
-                self.DEC(M, 7, mem);
-            }
-            0x0A => {
-                let M = self.DIREC(mem);
-                self.DEC(M, 6, mem);
-            }
-            0x6A => {
-                let M = self.INDEXE(mem);
-                self.DEC(M, 6, mem);
-            }
-            // BIT
-            0x85 => {
-                let M = self.IMMED8();
-                self.BIT(self.A, M, 2, mem);
-            }
-            0x95 => {
-                let M = self.DIREC(mem);
-                self.BIT(self.A, M, 4, mem);
-            }
-            0xB5 => {
-                let M = self.ETEND(mem);
-                self.BIT(self.A, M, 5, mem);
-            }
-            0xA5 => {
-                let M = self.INDEXE(mem);
-                self.BIT(self.A, M, 4, mem);
-            }
-            0xC5 => {
-                let M = self.IMMED8();
-                self.BIT(self.B, M, 2, mem);
-            }
-            0xD5 => {
-                let M = self.DIREC(mem);
-                self.BIT(self.B, M, 4, mem);
-            }
-            0xF5 => {
-                let M = self.ETEND(mem);
-                self.BIT(self.B, M, 5, mem);
-            }
-            0xE5 => {
-                let M = self.INDEXE(mem);
-                self.BIT(self.B, M, 4, mem);
-            }
-            // CMP
-            0x81 => {
-                let M = self.IMMED8();
-                self.CMP8(self.A, M, 2, mem);
-            }
-            0x91 => {
-                let M = self.DIREC(mem);
-                self.CMP8(self.A, M, 4, mem);
-            }
-            0xB1 => {
-                let M = self.ETEND(mem);
-                self.CMP8(self.A, M, 5, mem);
-            }
-            0xA1 => {
-                let M = self.INDEXE(mem);
-                self.CMP8(self.A, M, 4, mem);
-            }
-            0xC1 => {
-                let M = self.IMMED8();
-                self.CMP8(self.B, M, 2, mem);
-            }
-            0xD1 => {
-                let M = self.DIREC(mem);
-                self.CMP8(self.B, M, 4, mem);
-            }
-            0xF1 => {
-                let M = self.ETEND(mem);
-                self.CMP8(self.B, M, 5, mem);
-            }
-            0xE1 => {
-                let M = self.INDEXE(mem);
-                self.CMP8(self.B, M, 4, mem);
-            }
-            0x8C => {
-                let M = self.IMMED16();
-                self.CMP16(self.X, M, 5, mem);
-            }
-            0x9C => {
-                let M = self.DIREC(mem);
-                self.CMP16(self.X, M, 7, mem);
-            }
-            0xBC => {
-                let M = self.ETEND(mem);
-                self.CMP16(self.X, M, 8, mem);
-            }
-            0xAC => {
-                let M = self.INDEXE(mem);
-                self.CMP16(self.X, M, 7, mem);
-            }
-            // TST
-            0x4D => self.TSTAi(),
-            0x5D => self.TSTBi(),
-            0x0D => {
-                let M = self.DIREC(mem);
-                self.TST(M, 6, mem);
-            }
-            0x7D => {
-                let M = self.ETEND(mem);
-                self.TST(M, 7, mem);
-            }
-            0x6D => {
-                let M = self.INDEXE(mem);
-                self.TST(M, 6, mem);
-            }
-            // AND
-            0x84 => {
-                let M = self.IMMED8();
-                self.ANDA(M, 2, mem);
-            }
-            0x94 => {
-                let M = self.DIREC(mem);
-                self.ANDA(M, 4, mem);
-            }
-            0xB4 => {
-                let M = self.ETEND(mem);
-                self.ANDA(M, 5, mem);
-            }
-            0xA4 => {
-                let M = self.INDEXE(mem);
-                self.ANDA(M, 4, mem);
-            }
-            0xC4 => {
-                let M = self.IMMED8();
-                self.ANDB(M, 2, mem);
-            }
-            0xD4 => {
-                let M = self.DIREC(mem);
-                self.ANDB(M, 4, mem);
-            }
-            0xF4 => {
-                let M = self.ETEND(mem);
-                self.ANDB(M, 5, mem);
-            }
-            0xE4 => {
-                let M = self.INDEXE(mem);
-                self.ANDB(M, 4, mem);
-            }
-            0x1C => {
-                let M = self.IMMED8();
-                self.ANDCC(M, 3, mem);
-            }
-            // OR
-            0x8A => {
-                let M = self.IMMED8();
-                self.ORA(M, 2, mem);
-            }
-            0x9A => {
-                let M = self.DIREC(mem);
-                self.ORA(M, 4, mem);
-            }
-            0xBA => {
-                let M = self.ETEND(mem);
-                self.ORA(M, 5, mem);
-            }
-            0xAA => {
-                let M = self.INDEXE(mem);
-                self.ORA(M, 4, mem);
-            }
-            0xCA => {
-                let M = self.IMMED8();
-                self.ORB(M, 2, mem);
-            }
-            0xDA => {
-                let M = self.DIREC(mem);
-                self.ORB(M, 4, mem);
-            }
-            0xFA => {
-                let M = self.ETEND(mem);
-                self.ORB(M, 5, mem);
-            }
-            0xEA => {
-                let M = self.INDEXE(mem);
-                self.ORB(M, 4, mem);
-            }
-            0x1A => {
-                let M = self.IMMED8();
-                self.ORCC(M, 3, mem);
-            }
-            // EOR
-            0x88 => {
-                let M = self.IMMED8();
-                self.EORA(M, 2, mem);
-            }
-            0x98 => {
-                let M = self.DIREC(mem);
-                self.EORA(M, 4, mem);
-            }
-            0xB8 => {
-                let M = self.ETEND(mem);
-                self.EORA(M, 5, mem);
-            }
-            0xA8 => {
-                let M = self.INDEXE(mem);
-                self.EORA(M, 4, mem);
-            }
-            0xC8 => {
-                let M = self.IMMED8();
-                self.EORB(M, 2, mem);
-            }
-            0xD8 => {
-                let M = self.DIREC(mem);
-                self.EORB(M, 4, mem);
-            }
-            0xF8 => {
-                let M = self.ETEND(mem);
-                self.EORB(M, 5, mem);
-            }
-            0xE8 => {
-                let M = self.INDEXE(mem);
-                self.EORB(M, 4, mem);
-            }
-            // COM
-            0x43 => self.COMA(),
-            0x53 => self.COMB(),
-            0x03 => {
-                let M = self.DIREC(mem);
-                self.COM(M, 6, mem);
-            }
-            0x73 => {
-                let M = self.ETEND(mem);
-                self.COM(M, 7, mem);
-            }
-            0x63 => {
-                let M = self.INDEXE(mem);
-                self.COM(M, 6, mem);
-            }
-            // NEG
-            0x40 => self.NEGA(),
-            0x50 => self.NEGB(),
-            0x00 => {
-                let M = self.DIREC(mem);
-                self.NEG(M, 6, mem);
-            }
-            0x70 => {
-                let M = self.ETEND(mem);
-                self.NEG(M, 7, mem);
-            }
-            0x60 => {
-                let M = self.INDEXE(mem);
-                self.NEG(M, 6, mem);
-            }
-            0x3A => self.ABX(),
-            //ADD
-            0x8B => {
-                let M = self.IMMED8();
-                self.ADDA(M, 2, mem);
-            }
-            0x9B => {
-                let M = self.DIREC(mem);
-                self.ADDA(M, 4, mem);
-            }
-            0xBB => {
-                let M = self.ETEND(mem);
-                self.ADDA(M, 5, mem);
-            }
-            0xAB => {
-                let M = self.INDEXE(mem);
-                self.ADDA(M, 4, mem);
-            }
-            0xCB => {
-                let M = self.IMMED8();
-                self.ADDB(M, 2, mem);
-            }
-            0xDB => {
-                let M = self.DIREC(mem);
-                self.ADDB(M, 4, mem);
-            }
-            0xFB => {
-                let M = self.ETEND(mem);
-                self.ADDB(M, 5, mem);
-            }
-            0xEB => {
-                let M = self.INDEXE(mem);
-                self.ADDB(M, 4, mem);
-            }
-            0xC3 => {
-                let M = self.IMMED16();
-                self.ADDD(M, 4, mem);
-            }
-            0xD3 => {
-                let M = self.DIREC(mem);
-                self.ADDD(M, 6, mem);
-            }
-            0xF3 => {
-                let M = self.ETEND(mem);
-                self.ADDD(M, 7, mem);
-            }
-            0xE3 => {
-                let M = self.INDEXE(mem);
-                self.ADDD(M, 6, mem);
-            }
-            // ADC
-            0x89 => {
-                let M = self.IMMED8();
-                self.ADCA(M, 2, mem);
-            }
-            0x99 => {
-                let M = self.DIREC(mem);
-                self.ADCA(M, 4, mem);
-            }
-            0xB9 => {
-                let M = self.ETEND(mem);
-                self.ADCA(M, 5, mem);
-            }
-            0xA9 => {
-                let M = self.INDEXE(mem);
-                self.ADCA(M, 4, mem);
-            }
-            0xC9 => {
-                let M = self.IMMED8();
-                self.ADCB(M, 2, mem);
-            }
-            0xD9 => {
-                let M = self.DIREC(mem);
-                self.ADCB(M, 4, mem);
-            }
-            0xF9 => {
-                let M = self.ETEND(mem);
-                self.ADCB(M, 5, mem);
-            }
-            0xE9 => {
-                let M = self.INDEXE(mem);
-                self.ADCB(M, 4, mem);
-            }
-            0x3D => self.MUL(),
-            // SBC
-            0x82 => {
-                let M = self.IMMED8();
-                self.SBCA(M, 2, mem);
-            }
-            0x92 => {
-                let M = self.DIREC(mem);
-                self.SBCA(M, 4, mem);
-            }
-            0xB2 => {
-                let M = self.ETEND(mem);
-                self.SBCA(M, 5, mem);
-            }
-            0xA2 => {
-                let M = self.INDEXE(mem);
-                self.SBCA(M, 4, mem);
-            }
-            0xC2 => {
-                let M = self.IMMED8();
-                self.SBCB(M, 2, mem);
-            }
-            0xD2 => {
-                let M = self.DIREC(mem);
-                self.SBCB(M, 4, mem);
-            }
-            0xF2 => {
-                let M = self.ETEND(mem);
-                self.SBCB(M, 5, mem);
-            }
-            0xE2 => {
-                let M = self.INDEXE(mem);
-                self.SBCB(M, 4, mem);
-            }
-            //SUB
-            0x80 => {
-                let M = self.IMMED8();
-                self.SUBA(M, 2, mem);
-            }
-            0x90 => {
-                let M = self.DIREC(mem);
-                self.SUBA(M, 4, mem);
-            }
-            0xB0 => {
-                let M = self.ETEND(mem);
-                self.SUBA(M, 5, mem);
-            }
-            0xA0 => {
-                let M = self.INDEXE(mem);
-                self.SUBA(M, 4, mem);
-            }
-            0xC0 => {
-                let M = self.IMMED8();
-                self.SUBB(M, 2, mem);
-            }
-            0xD0 => {
-                let M = self.DIREC(mem);
-                self.SUBB(M, 4, mem);
-            }
-            0xF0 => {
-                let M = self.ETEND(mem);
-                self.SUBB(M, 5, mem);
-            }
-            0xE0 => {
-                let M = self.INDEXE(mem);
-                self.SUBB(M, 4, mem);
-            }
-            0x83 => {
-                let M = self.IMMED16();
-                self.SUBD(M, 4, mem);
-            }
-            0x93 => {
-                let M = self.DIREC(mem);
-                self.SUBD(M, 6, mem);
-            }
-            0xB3 => {
-                let M = self.ETEND(mem);
-                self.SUBD(M, 7, mem);
-            }
-            0xA3 => {
-                let M = self.INDEXE(mem);
-                self.SUBD(M, 6, mem);
-            }
-            0x1D => self.SEX(),
-            // ASL
-            0x48 => self.ASLA(),
-            0x58 => self.ASLB(),
-            0x08 => {
-                let M = self.DIREC(mem);
-                self.ASL(M, 6, mem);
-            }
-            0x78 => {
-                let M = self.ETEND(mem);
-                self.ASL(M, 7, mem);
-            }
-            0x68 => {
-                let M = self.INDEXE(mem);
-                self.ASL(M, 6, mem);
-            }
-            // ASR
-            0x47 => self.ASRA(),
-            0x57 => self.ASRB(),
-            0x07 => {
-                let M = self.DIREC(mem);
-                self.ASR(M, 6, mem);
-            }
-            0x77 => {
-                let M = self.ETEND(mem);
-                self.ASR(M, 7, mem);
-            }
-            0x67 => {
-                let M = self.INDEXE(mem);
-                self.ASR(M, 6, mem);
-            }
-            // LSR
-            0x44 => self.LSRA(),
-            0x54 => self.LSRB(),
-            0x04 => {
-                let M = self.DIREC(mem);
-                self.LSR(M, 6, mem);
-            }
-            0x74 => {
-                let M = self.ETEND(mem);
-                self.LSR(M, 7, mem);
-            }
-            0x64 => {
-                let M = self.INDEXE(mem);
-                self.LSR(M, 6, mem);
-            }
-            // ROL
-            0x49 => self.ROLA(),
-            0x59 => self.ROLB(),
-            0x09 => {
-                let M = self.DIREC(mem);
-                self.ROL(M, 6, mem);
-            }
-            0x79 => {
-                let M = self.ETEND(mem);
-                self.ROL(M, 7, mem);
-            }
-            0x69 => {
-                let M = self.INDEXE(mem);
-                self.ROL(M, 6, mem);
-            }
-            // ROR
-            0x46 => self.RORA(),
-            0x56 => self.RORB(),
-            0x06 => {
-                let M = self.DIREC(mem);
-                self.ROR(M, 6, mem);
-            }
-            0x76 => {
-                let M = self.ETEND(mem);
-                self.ROR(M, 7, mem);
+    // 16 bits arithmetic
+    fn subd<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        let d = self.sub16(self.d(), mem.read_16(address));
+        self.set_d(d);
+    }
+
+    fn addd<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        let d = self.add16(self.d(), mem.read_16(address));
+        self.set_d(d);
+    }
+
+    fn cmpd<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.sub16(self.d(), mem.read_16(address));
+    }
+
+    fn cmpx<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.sub16(self.x, mem.read_16(address));
+    }
+
+    fn cmpy<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.sub16(self.y, mem.read_16(address));
+    }
+
+    fn cmpu<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.sub16(self.u, mem.read_16(address));
+    }
+
+    fn cmps<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.sub16(self.s, mem.read_16(address));
+    }
+
+    // read-modify-write on memory, TST only reads and CLR only writes
+    fn neg<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.modify(mem, address, Self::neg8);
+    }
+
+    fn xnc<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.modify(mem, address, Self::xnc8);
+    }
+
+    fn com<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.modify(mem, address, Self::com8);
+    }
+
+    fn lsr<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.modify(mem, address, Self::lsr8);
+    }
+
+    fn ror<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.modify(mem, address, Self::ror8);
+    }
+
+    fn asr<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.modify(mem, address, Self::asr8);
+    }
+
+    fn asl<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.modify(mem, address, Self::asl8);
+    }
+
+    fn rol<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.modify(mem, address, Self::rol8);
+    }
+
+    fn dec<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.modify(mem, address, Self::dec8);
+    }
+
+    fn inc<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.modify(mem, address, Self::inc8);
+    }
+
+    fn tst<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.tst8(mem.read(address));
+    }
+
+    fn clr<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        let value = self.clr8(0);
+        mem.write(address, value);
+    }
+
+    // read-modify-write on A and B
+    fn nega<B: Bus>(&mut self, _: &mut B, _: u16) {
+        self.a = self.neg8(self.a);
+    }
+
+    fn negb<B: Bus>(&mut self, _: &mut B, _: u16) {
+        self.b = self.neg8(self.b);
+    }
+
+    fn xnca<B: Bus>(&mut self, _: &mut B, _: u16) {
+        self.a = self.xnc8(self.a);
+    }
+
+    fn xncb<B: Bus>(&mut self, _: &mut B, _: u16) {
+        self.b = self.xnc8(self.b);
+    }
+
+    fn coma<B: Bus>(&mut self, _: &mut B, _: u16) {
+        self.a = self.com8(self.a);
+    }
+
+    fn comb<B: Bus>(&mut self, _: &mut B, _: u16) {
+        self.b = self.com8(self.b);
+    }
+
+    fn lsra<B: Bus>(&mut self, _: &mut B, _: u16) {
+        self.a = self.lsr8(self.a);
+    }
+
+    fn lsrb<B: Bus>(&mut self, _: &mut B, _: u16) {
+        self.b = self.lsr8(self.b);
+    }
+
+    fn rora<B: Bus>(&mut self, _: &mut B, _: u16) {
+        self.a = self.ror8(self.a);
+    }
+
+    fn rorb<B: Bus>(&mut self, _: &mut B, _: u16) {
+        self.b = self.ror8(self.b);
+    }
+
+    fn asra<B: Bus>(&mut self, _: &mut B, _: u16) {
+        self.a = self.asr8(self.a);
+    }
+
+    fn asrb<B: Bus>(&mut self, _: &mut B, _: u16) {
+        self.b = self.asr8(self.b);
+    }
+
+    fn asla<B: Bus>(&mut self, _: &mut B, _: u16) {
+        self.a = self.asl8(self.a);
+    }
+
+    fn aslb<B: Bus>(&mut self, _: &mut B, _: u16) {
+        self.b = self.asl8(self.b);
+    }
+
+    fn rola<B: Bus>(&mut self, _: &mut B, _: u16) {
+        self.a = self.rol8(self.a);
+    }
+
+    fn rolb<B: Bus>(&mut self, _: &mut B, _: u16) {
+        self.b = self.rol8(self.b);
+    }
+
+    fn deca<B: Bus>(&mut self, _: &mut B, _: u16) {
+        self.a = self.dec8(self.a);
+    }
+
+    fn decb<B: Bus>(&mut self, _: &mut B, _: u16) {
+        self.b = self.dec8(self.b);
+    }
+
+    fn inca<B: Bus>(&mut self, _: &mut B, _: u16) {
+        self.a = self.inc8(self.a);
+    }
+
+    fn incb<B: Bus>(&mut self, _: &mut B, _: u16) {
+        self.b = self.inc8(self.b);
+    }
+
+    fn tsta<B: Bus>(&mut self, _: &mut B, _: u16) {
+        self.tst8(self.a);
+    }
+
+    fn tstb<B: Bus>(&mut self, _: &mut B, _: u16) {
+        self.tst8(self.b);
+    }
+
+    fn clra<B: Bus>(&mut self, _: &mut B, _: u16) {
+        self.a = self.clr8(self.a);
+    }
+
+    fn clrb<B: Bus>(&mut self, _: &mut B, _: u16) {
+        self.b = self.clr8(self.b);
+    }
+
+    // LEA, only LEAX and LEAY set Z
+    fn leax<B: Bus>(&mut self, _: &mut B, address: u16) {
+        self.x = address;
+        self.set_flag(Z, address == 0);
+    }
+
+    fn leay<B: Bus>(&mut self, _: &mut B, address: u16) {
+        self.y = address;
+        self.set_flag(Z, address == 0);
+    }
+
+    fn leas<B: Bus>(&mut self, _: &mut B, address: u16) {
+        self.s = address;
+    }
+
+    fn leau<B: Bus>(&mut self, _: &mut B, address: u16) {
+        self.u = address;
+    }
+
+    /// The register of EXG and TFR, the 8 bits ones are zero extended and the undefined
+    /// codes are CC
+    const fn register(&self, code: u8) -> u16 {
+        match code {
+            0x0 => self.d(),
+            0x1 => self.x,
+            0x2 => self.y,
+            0x3 => self.u,
+            0x4 => self.s,
+            0x5 => self.pc,
+            0x8 => self.a as u16,
+            0x9 => self.b as u16,
+            0xB => self.dp as u16,
+            _ => self.cc as u16,
+        }
+    }
+
+    const fn set_register(&mut self, code: u8, value: u16) {
+        match code {
+            0x0 => self.set_d(value),
+            0x1 => self.x = value,
+            0x2 => self.y = value,
+            0x3 => self.u = value,
+            0x4 => self.s = value,
+            0x5 => self.pc = value,
+            0x8 => self.a = value as u8,
+            0x9 => self.b = value as u8,
+            0xB => self.dp = value as u8,
+            _ => self.cc = value as u8,
+        }
+    }
+
+    fn exg<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        let postbyte = mem.read(address);
+        let (r1, r2) = (postbyte >> 4, postbyte & 0x0F);
+        let (k, l) = (self.register(r1), self.register(r2));
+        self.set_register(r2, k);
+        self.set_register(r1, l);
+    }
+
+    fn tfr<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        let postbyte = mem.read(address);
+        self.set_register(postbyte & 0x0F, self.register(postbyte >> 4));
+    }
+
+    /// Pushes the registers of the postbyte on a stack, `other` is the other stack pointer.
+    /// Returns the new stack pointer.
+    fn push_registers<B: Bus>(&self, mem: &mut B, postbyte: u8, mut stack: u16, other: u16) -> u16 {
+        for (bit, value) in [
+            (0x80, self.pc),
+            (0x40, other),
+            (0x20, self.y),
+            (0x10, self.x),
+        ] {
+            if postbyte & bit != 0 {
+                for byte in value.to_le_bytes() {
+                    stack = stack.wrapping_sub(1);
+                    mem.write(stack, byte);
+                }
             }
-            0x66 => {
-                let M = self.INDEXE(mem);
-                self.ROR(M, 6, mem);
+        }
+        for (bit, value) in [
+            (0x08, self.dp),
+            (0x04, self.b),
+            (0x02, self.a),
+            (0x01, self.cc),
+        ] {
+            if postbyte & bit != 0 {
+                stack = stack.wrapping_sub(1);
+                mem.write(stack, value);
             }
+        }
+        stack
+    }
+
+    /// Pulls the registers of the postbyte from a stack, returns the new stack pointer and the
+    /// other stack pointer
+    fn pull_registers<B: Bus>(
+        &mut self,
+        mem: &mut B,
+        postbyte: u8,
+        mut stack: u16,
+        mut other: u16,
+    ) -> (u16, u16) {
+        let mut pull = |mem: &B| {
+            let value = mem.read(stack);
+            stack = stack.wrapping_add(1);
+            value
+        };
+        if postbyte & 0x01 != 0 {
+            self.cc = pull(mem);
+        }
+        if postbyte & 0x02 != 0 {
+            self.a = pull(mem);
+        }
+        if postbyte & 0x04 != 0 {
+            self.b = pull(mem);
+        }
+        if postbyte & 0x08 != 0 {
+            self.dp = pull(mem);
+        }
+        if postbyte & 0x10 != 0 {
+            self.x = u16::from_be_bytes([pull(mem), pull(mem)]);
+        }
+        if postbyte & 0x20 != 0 {
+            self.y = u16::from_be_bytes([pull(mem), pull(mem)]);
+        }
+        if postbyte & 0x40 != 0 {
+            other = u16::from_be_bytes([pull(mem), pull(mem)]);
+        }
+        if postbyte & 0x80 != 0 {
+            self.pc = u16::from_be_bytes([pull(mem), pull(mem)]);
+        }
+        (stack, other)
+    }
+
+    /// The cycles of PSH and PUL: one per byte
+    const fn stacked_bytes(postbyte: u8) -> int {
+        ((postbyte & 0xF0).count_ones() * 2 + (postbyte & 0x0F).count_ones()) as int
+    }
+
+    fn pshs<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        let postbyte = mem.read(address);
+        self.s = self.push_registers(mem, postbyte, self.s, self.u);
+        self.cl += Self::stacked_bytes(postbyte);
+    }
+
+    fn pshu<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        let postbyte = mem.read(address);
+        self.u = self.push_registers(mem, postbyte, self.u, self.s);
+        self.cl += Self::stacked_bytes(postbyte);
+    }
+
+    fn puls<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        let postbyte = mem.read(address);
+        (self.s, self.u) = self.pull_registers(mem, postbyte, self.s, self.u);
+        self.cl += Self::stacked_bytes(postbyte);
+    }
+
+    fn pulu<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        let postbyte = mem.read(address);
+        (self.u, self.s) = self.pull_registers(mem, postbyte, self.u, self.s);
+        self.cl += Self::stacked_bytes(postbyte);
+    }
+
+    /// The condition of a branch, given by the low nibble of its opcode
+    const fn condition(&self) -> bool {
+        let cc = self.cc;
+        let n_xor_v = ((cc & N) >> 3) != ((cc & V) >> 1);
+        let taken = match self.opcode & 0x0E {
             // BRA
-            0x20 => self.BRA(mem),
-            0x16 => self.LBRA(mem),
-            // JMP
-            0x0E => self.JMPd(mem),
-            0x7E => self.JMPe(mem),
-            0x6E => self.JMPx(mem),
-            // BSR
-            0x8D => self.BSR(mem),
-            0x17 => self.LBSR(mem),
-            // JSR
-            0x9D => self.JSRd(mem),
-            0xBD => self.JSRe(mem),
-            0xAD => self.JSRx(mem),
-            0x12 => self.NOP(),
-            0x39 => self.RTS(mem),
-            // Bxx
-            0x21 => self.BRN(mem),
-            0x24 => self.BCC(mem),
-            0x25 => self.BCS(mem),
-            0x27 => self.BEQ(mem),
-            0x26 => self.BNE(mem),
-            0x2C => self.BGE(mem),
-            0x2F => self.BLE(mem),
-            0x23 => self.BLS(mem),
-            0x2E => self.BGT(mem),
-            0x2D => self.BLT(mem),
-            0x22 => self.BHI(mem),
-            0x2B => self.BMI(mem),
-            0x2A => self.BPL(mem),
-            0x28 => self.BVC(mem),
-            0x29 => self.BVS(mem),
-            0x3F => self.SWI(mem),
-            0x3B => self.RTI(mem),
-            0x19 => self.DAA(),
-            0x3C => self.CWAI(mem),
-            // extended mode
-            0x10 => {
-                let opcode0x10 = mem.read(self.PC);
-                self.PC += 1;
-                match opcode0x10 {
-                    // LDS
-                    0xCE => {
-                        let M = self.IMMED16();
-                        self.S = self.LD16(M, 3, mem);
-                    }
-                    0xDE => {
-                        let M = self.DIREC(mem);
-                        self.S = self.LD16(M, 5, mem);
-                    }
-                    0xFE => {
-                        let M = self.ETEND(mem);
-                        self.S = self.LD16(M, 6, mem);
-                    }
-                    0xEE => {
-                        let M = self.INDEXE(mem);
-                        self.S = self.LD16(M, 5, mem);
-                    }
-                    // LDY
-                    0x8E => {
-                        let M = self.IMMED16();
-                        self.Y = self.LD16(M, 3, mem);
-                    }
-                    0x9E => {
-                        let M = self.DIREC(mem);
-                        self.Y = self.LD16(M, 5, mem);
-                    }
-                    0xBE => {
-                        let M = self.ETEND(mem);
-                        self.Y = self.LD16(M, 6, mem);
-                    }
-                    0xAE => {
-                        let M = self.INDEXE(mem);
-                        self.Y = self.LD16(M, 5, mem);
-                    }
-                    // STS
-                    0xDF => {
-                        let M = self.DIREC(mem);
-                        self.ST16(self.S, M, 5, mem);
-                    }
-                    0xFF => {
-                        let M = self.ETEND(mem);
-                        self.ST16(self.S, M, 6, mem);
-                    }
-                    0xEF => {
-                        let M = self.INDEXE(mem);
-                        self.ST16(self.S, M, 5, mem);
-                    }
-                    // STY
-                    0x9F => {
-                        let M = self.DIREC(mem);
-                        self.ST16(self.Y, M, 5, mem);
-                    }
-                    0xBF => {
-                        let M = self.ETEND(mem);
-                        self.ST16(self.Y, M, 6, mem);
-                    }
-                    0xAF => {
-                        let M = self.INDEXE(mem);
-                        self.ST16(self.Y, M, 5, mem);
-                    }
-                    // CMP
-                    0x83 => {
-                        self.CALCD();
-                        let M = self.IMMED16();
-                        self.CMP16(self.D, M, 5, mem);
-                    }
-                    0x93 => {
-                        self.CALCD();
-                        let M = self.DIREC(mem);
-                        self.CMP16(self.D, M, 7, mem);
-                    }
-                    0xB3 => {
-                        self.CALCD();
-                        let M = self.ETEND(mem);
-                        self.CMP16(self.D, M, 8, mem);
-                    }
-                    0xA3 => {
-                        self.CALCD();
-                        let M = self.INDEXE(mem);
-                        self.CMP16(self.D, M, 7, mem);
-                    }
-                    0x8C => {
-                        let M = self.IMMED16();
-                        self.CMP16(self.Y, M, 5, mem);
-                    }
-                    0x9C => {
-                        let M = self.DIREC(mem);
-                        self.CMP16(self.Y, M, 7, mem);
-                    }
-                    0xBC => {
-                        let M = self.ETEND(mem);
-                        self.CMP16(self.Y, M, 8, mem);
-                    }
-                    0xAC => {
-                        let M = self.INDEXE(mem);
-                        self.CMP16(self.Y, M, 7, mem);
-                    }
-                    // Bxx
-                    0x21 => self.LBRN(mem),
-                    0x24 => self.LBCC(mem),
-                    0x25 => self.LBCS(mem),
-                    0x27 => self.LBEQ(mem),
-                    0x26 => self.LBNE(mem),
-                    0x2C => self.LBGE(mem),
-                    0x2F => self.LBLE(mem),
-                    0x23 => self.LBLS(mem),
-                    0x2E => self.LBGT(mem),
-                    0x2D => self.LBLT(mem),
-                    0x22 => self.LBHI(mem),
-                    0x2B => self.LBMI(mem),
-                    0x2A => self.LBPL(mem),
-                    0x28 => self.LBVC(mem),
-                    0x29 => self.LBVS(mem),
-                    _ => {
-                        eprintln!("opcode 10 {opcode0x10:02X} not implemented");
-                        eprintln!("{}", self.print_state());
-                    }
-                } // of case opcode0x10
-            }
-            0x11 => {
-                let opcode0x11 = mem.read(self.PC);
-                self.PC += 1;
-
-                match opcode0x11 {
-                    // CMP
-                    0x8C => {
-                        let M = self.IMMED16();
-                        self.CMP16(self.S, M, 5, mem);
-                    }
-                    0x9C => {
-                        let M = self.DIREC(mem);
-                        self.CMP16(self.S, M, 7, mem);
-                    }
-                    0xBC => {
-                        let M = self.ETEND(mem);
-                        self.CMP16(self.S, M, 8, mem);
-                    }
-                    0xAC => {
-                        let M = self.INDEXE(mem);
-                        self.CMP16(self.S, M, 7, mem);
-                    }
-                    0x83 => {
-                        let M = self.IMMED16();
-                        self.CMP16(self.U, M, 5, mem);
-                    }
-                    0x93 => {
-                        let M = self.DIREC(mem);
-                        self.CMP16(self.U, M, 7, mem);
-                    }
-                    0xB3 => {
-                        let M = self.ETEND(mem);
-                        self.CMP16(self.U, M, 8, mem);
-                    }
-                    0xA3 => {
-                        let M = self.INDEXE(mem);
-                        self.CMP16(self.U, M, 7, mem);
-                    }
-                    _ => {
-                        eprintln!("opcode 11{opcode0x11:02X} not implemented");
-                        eprintln!("{}", self.print_state());
-                    }
-                } // of case opcode 0x11
-            }
-            _ => {
-                eprintln!("opcode {opcode:02X} not implemented");
-                eprintln!("{}", self.print_state());
-            }
-        } // of case opcode
-    } // of method fetch()
+            0x0 => true,
+            // BHI
+            0x2 => cc & (C | Z) == 0,
+            // BCC
+            0x4 => cc & C == 0,
+            // BNE
+            0x6 => cc & Z == 0,
+            // BVC
+            0x8 => cc & V == 0,
+            // BPL
+            0xA => cc & N == 0,
+            // BGE
+            0xC => !n_xor_v,
+            // BGT
+            _ => !n_xor_v && cc & Z == 0,
+        };
+        // the odd opcodes are the opposite conditions
+        taken ^ (self.opcode & 0x01 != 0)
+    }
 
-    // UNASSEMBLE/DEBUG PART
-    pub(crate) fn print_state(&mut self) -> String {
-        self.CC = self.getcc();
-        let s = format!(
-            "A={:02X} B={:02X} X={:04X} Y={:04X}\nPC={:04X} DP={:02X} U={:04X} S={:04X} CC={:02X}",
-            self.A, self.B, self.X, self.Y, self.PC, self.DP, self.U, self.S, self.CC
-        );
-        s
+    fn branch<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        let offset = mem.read(address) as i8 as u16;
+        if self.condition() {
+            self.pc = self.pc.wrapping_add(offset);
+        }
     }
-}
 
-// force sign extension in a portable but ugly maneer
-const fn signedChar(v: int) -> int {
-    if (v & 0x80) == 0 {
-        return v & 0xFF;
+    /// The long branches take one more cycle when they branch
+    fn long_branch<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        let offset = mem.read_16(address);
+        if self.condition() {
+            self.pc = self.pc.wrapping_add(offset);
+            self.cl += 1;
+        }
     }
-    let mut delta = -1; // delta is 0xFFFF.... independently of 32/64bits
-    delta = (delta >> 8) << 8; // force last 8bits to 0
-    (v & 0xFF) | delta // result is now signed
-}
 
-// force sign extension in a portable but ugly maneer
-const fn signed16bits(v: int) -> int {
-    if (v & 0x8000) == 0 {
-        return v & 0xFFFF;
+    fn lbra<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.pc = self.pc.wrapping_add(mem.read_16(address));
     }
-    let mut delta = -1; // delta is 0xFFFF.... independently of 32/64bits
-    delta = (delta >> 16) << 16; // force last 16bits to 0
-    (v & 0xFFFF) | delta // result is now signed
-}
 
-const fn regx(m: int) -> &'static str {
-    const MASK: i32 = 0x60;
-    match m & MASK {
-        0x00 => "?X",
-        0x20 => "?X",
-        0x40 => "?U",
-        0x60 => "?S",
-        _ => "?",
+    fn bsr<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        let offset = mem.read(address) as i8 as u16;
+        self.s = self.push_registers(mem, 0x80, self.s, self.u);
+        self.pc = self.pc.wrapping_add(offset);
     }
-}
 
-fn r_tfr(m: int) -> String {
-    let mut output = String::with_capacity(5);
-    match m & 0xF0 {
-        0x80 => output.push_str("A,"),
-        0x90 => output.push_str("B,"),
-        0xA0 => output.push_str("CC,"),
-        0x00 => output.push_str("D,"),
-        0xB0 => output.push_str("DP,"),
-        0x50 => output.push_str("PC,"),
-        0x40 => output.push_str("S,"),
-        0x30 => output.push_str("U,"),
-        0x10 => output.push_str("X,"),
-        0x20 => output.push_str("Y,"),
-        _ => {}
-    };
-    match m & 0x0F {
-        0x8 => output.push('A'),
-        0x9 => output.push('B'),
-        0xA => output.push_str("CC"),
-        0x0 => output.push('D'),
-        0xB => output.push_str("DP"),
-        0x5 => output.push_str("PC"),
-        0x4 => output.push('S'),
-        0x3 => output.push('U'),
-        0x1 => output.push('X'),
-        0x2 => output.push('Y'),
-        _ => {}
-    }
-    output
-}
+    fn lbsr<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        let offset = mem.read_16(address);
+        self.s = self.push_registers(mem, 0x80, self.s, self.u);
+        self.pc = self.pc.wrapping_add(offset);
+    }
+
+    fn jmp<B: Bus>(&mut self, _: &mut B, address: u16) {
+        self.pc = address;
+    }
 
-fn r_pile(m: int) -> String {
-    let mut output = String::new();
-    if (m & 0x80) != 0 {
-        output.push_str("PC,");
+    fn jsr<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.s = self.push_registers(mem, 0x80, self.s, self.u);
+        self.pc = address;
     }
-    if (m & 0x40) != 0 {
-        output.push_str("U/S,");
+
+    fn rts<B: Bus>(&mut self, mem: &mut B, _: u16) {
+        (self.s, self.u) = self.pull_registers(mem, 0x80, self.s, self.u);
     }
-    if (m & 0x20) != 0 {
-        output.push_str("Y,");
+
+    fn nop<B: Bus>(&mut self, _: &mut B, _: u16) {}
+
+    fn abx<B: Bus>(&mut self, _: &mut B, _: u16) {
+        self.x = self.x.wrapping_add(self.b as u16);
     }
-    if (m & 0x10) != 0 {
-        output.push_str("X,");
+
+    fn mul<B: Bus>(&mut self, _: &mut B, _: u16) {
+        let product = self.a as u16 * self.b as u16;
+        self.set_d(product);
+        self.set_flag(Z, product == 0);
+        self.set_flag(C, self.b & 0x80 != 0);
     }
-    if (m & 0x08) != 0 {
-        output.push_str("DP,");
+
+    /// Sign extends B into A, V is not changed
+    fn sex<B: Bus>(&mut self, _: &mut B, _: u16) {
+        self.a = if self.b & 0x80 == 0 { 0x00 } else { 0xFF };
+        self.set_nz8(self.b);
     }
-    if (m & 0x04) != 0 {
-        output.push_str("B,");
+
+    /// Decimal adjust after an addition, C is kept when already set
+    fn daa<B: Bus>(&mut self, _: &mut B, _: u16) {
+        let mut correction = 0;
+        if self.a & 0x0F > 0x09 || self.cc & H != 0 {
+            correction |= 0x06;
+        }
+        if self.a > 0x99 || self.cc & C != 0 {
+            correction |= 0x60;
+        }
+        let result = self.a as u16 + correction;
+        self.a = result as u8;
+        self.set_nz8(self.a);
+        if result > 0xFF {
+            self.cc |= C;
+        }
     }
-    if (m & 0x02) != 0 {
-        output.push_str("A,");
+
+    fn andcc<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.cc &= mem.read(address);
     }
-    if (m & 0x01) != 0 {
-        output.push_str("CC");
+
+    fn orcc<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.cc |= mem.read(address);
+    }
+
+    /// Pushes all the registers on S with the E flag set, for RTI to pull them all
+    fn push_entire_state<B: Bus>(&mut self, mem: &mut B) {
+        self.cc |= E;
+        self.s = self.push_registers(mem, 0xFF, self.s, self.u);
+    }
+
+    /// Sets the interrupt masks of CC and jumps to the routine of the vector
+    fn vector<B: Bus>(&mut self, vector: u16, masks: u8, mem: &B) {
+        self.cc |= masks;
+        self.pc = mem.read_16(vector);
+    }
+
+    fn swi<B: Bus>(&mut self, mem: &mut B, _: u16) {
+        self.push_entire_state(mem);
+        self.vector(0xFFFA, I | F, mem);
+    }
+
+    fn swi2<B: Bus>(&mut self, mem: &mut B, _: u16) {
+        self.push_entire_state(mem);
+        self.vector(0xFFF4, 0, mem);
+    }
+
+    fn swi3<B: Bus>(&mut self, mem: &mut B, _: u16) {
+        self.push_entire_state(mem);
+        self.vector(0xFFF2, 0, mem);
+    }
+
+    /// The undocumented software reset, it stacks the registers like SWI
+    fn reset_opcode<B: Bus>(&mut self, mem: &mut B, _: u16) {
+        self.push_entire_state(mem);
+        self.vector(0xFFFE, I | F, mem);
+    }
+
+    /// RTI pulls all the registers when E is set, which takes 9 more cycles
+    fn rti<B: Bus>(&mut self, mem: &mut B, _: u16) {
+        (self.s, self.u) = self.pull_registers(mem, 0x01, self.s, self.u);
+        if self.cc & E != 0 {
+            (self.s, self.u) = self.pull_registers(mem, 0x7E, self.s, self.u);
+            self.cl += 9;
+        }
+        (self.s, self.u) = self.pull_registers(mem, 0x80, self.s, self.u);
+    }
+
+    /// Clears the masks of CC, stacks the registers and waits for an interrupt
+    fn cwai<B: Bus>(&mut self, mem: &mut B, address: u16) {
+        self.cc &= mem.read(address);
+        self.push_entire_state(mem);
+        self.wait = Wait::Cwai;
     }
-    output
-}
 
-pub(crate) fn unassemble(start: int, maxLines: int, mem: &Memory) -> String {
-    let mut MNEMO = ["ILL -"; 256];
-    let mut MNEMO10 = ["ILL -"; 256];
-    let mut MNEMO11 = ["ILL -"; 256];
-
-    /* LDA opcode */
-    MNEMO[0x86] = "LDA i";
-    MNEMO[0x96] = "LDA d";
-    MNEMO[0xB6] = "LDA e";
-    MNEMO[0xA6] = "LDA x";
-
-    /* LDB opcode */
-    MNEMO[0xC6] = "LDB i";
-    MNEMO[0xD6] = "LDB d";
-    MNEMO[0xF6] = "LDB e";
-    MNEMO[0xE6] = "LDB x";
-
-    /* LDD opcode */
-    MNEMO[0xCC] = "LDD I";
-    MNEMO[0xDC] = "LDD d";
-    MNEMO[0xFC] = "LDD e";
-    MNEMO[0xEC] = "LDD x";
-
-    /* LDU opcode */
-    MNEMO[0xCE] = "LDU I";
-    MNEMO[0xDE] = "LDU d";
-    MNEMO[0xFE] = "LDU e";
-    MNEMO[0xEE] = "LDU x";
-
-    /* LDX opcode */
-    MNEMO[0x8E] = "LDX I";
-    MNEMO[0x9E] = "LDX d";
-    MNEMO[0xBE] = "LDX e";
-    MNEMO[0xAE] = "LDX x";
-
-    /* LDS opcode */
-    MNEMO10[0xCE] = "LDS I";
-    MNEMO10[0xDE] = "LDS d";
-    MNEMO10[0xFE] = "LDS e";
-    MNEMO10[0xEE] = "LDS x";
-
-    /* LDY opcode */
-    MNEMO10[0x8E] = "LDY I";
-    MNEMO10[0x9E] = "LDY d";
-    MNEMO10[0xBE] = "LDY e";
-    MNEMO10[0xAE] = "LDY x";
-
-    /* STA opcode */
-    MNEMO[0x97] = "STA d";
-    MNEMO[0xB7] = "STA e";
-    MNEMO[0xA7] = "STA x";
-
-    /* STB opcode */
-    MNEMO[0xD7] = "STB d";
-    MNEMO[0xF7] = "STB e";
-    MNEMO[0xE7] = "STB x";
-
-    /* STD opcode */
-    MNEMO[0xDD] = "STD d";
-    MNEMO[0xFD] = "STD e";
-    MNEMO[0xED] = "STD x";
-
-    /* STS opcode */
-    MNEMO10[0xDF] = "STS d";
-    MNEMO10[0xFF] = "STS e";
-    MNEMO10[0xEF] = "STS x";
-
-    /* STU opcode */
-    MNEMO[0xDF] = "STU d";
-    MNEMO[0xFF] = "STU e";
-    MNEMO[0xEF] = "STU x";
-
-    /* STX opcode */
-    MNEMO[0x9F] = "STX d";
-    MNEMO[0xBF] = "STX e";
-    MNEMO[0xAF] = "STX x";
-
-    /* STY opcode */
-    MNEMO10[0x9F] = "STY d";
-    MNEMO10[0xBF] = "STY e";
-    MNEMO10[0xAF] = "STY x";
-
-    /* LEA opcode */
-    MNEMO[0x32] = "LEASx";
-    MNEMO[0x33] = "LEAUx";
-    MNEMO[0x30] = "LEAXx";
-    MNEMO[0x31] = "LEAYx";
-
-    /* CLR opcode */
-    MNEMO[0x0F] = "CLR d";
-    MNEMO[0x7F] = "CLR e";
-    MNEMO[0x6F] = "CLR x";
-    MNEMO[0x4F] = "CLRA-";
-    MNEMO[0x5F] = "CLRB-";
-
-    /* EXG */
-    MNEMO[0x1E] = "EXG r";
-
-    /* TFR */
-    MNEMO[0x1F] = "TFR r";
-
-    /* PSH */
-    MNEMO[0x34] = "PSHSR";
-    MNEMO[0x36] = "PSHUR";
-
-    /* PUL */
-    MNEMO[0x35] = "PULSR";
-    MNEMO[0x37] = "PULUR";
-
-    /* INC */
-    MNEMO[0x4C] = "INCA-";
-    MNEMO[0x5C] = "INCB-";
-    MNEMO[0x7C] = "INC e";
-    MNEMO[0x0C] = "INC d";
-    MNEMO[0x6C] = "INC x";
-
-    /* DEC */
-    MNEMO[0x4A] = "DECA-";
-    MNEMO[0x5A] = "DECB-";
-    MNEMO[0x7A] = "DEC e";
-    MNEMO[0x0A] = "DEC d";
-    MNEMO[0x6A] = "DEC x";
-
-    /* BIT */
-    MNEMO[0x85] = "BITAi";
-    MNEMO[0x95] = "BITAd";
-    MNEMO[0xB5] = "BITAe";
-    MNEMO[0xA5] = "BITAx";
-    MNEMO[0xC5] = "BITBi";
-    MNEMO[0xD5] = "BITBd";
-    MNEMO[0xF5] = "BITBe";
-    MNEMO[0xE5] = "BITBx";
-
-    /* CMP */
-    MNEMO[0x81] = "CMPAi";
-    MNEMO[0x91] = "CMPAd";
-    MNEMO[0xB1] = "CMPAe";
-    MNEMO[0xA1] = "CMPAx";
-    MNEMO[0xC1] = "CMPBi";
-    MNEMO[0xD1] = "CMPBd";
-    MNEMO[0xF1] = "CMPBe";
-    MNEMO[0xE1] = "CMPBx";
-    MNEMO10[0x83] = "CMPDI";
-    MNEMO10[0x93] = "CMPDd";
-    MNEMO10[0xB3] = "CMPDe";
-    MNEMO10[0xA3] = "CMPDx";
-    MNEMO11[0x8C] = "CMPSI";
-    MNEMO11[0x9C] = "CMPSd";
-    MNEMO11[0xBC] = "CMPSe";
-    MNEMO11[0xAC] = "CMPSx";
-    MNEMO11[0x83] = "CMPUI";
-    MNEMO11[0x93] = "CMPUd";
-    MNEMO11[0xB3] = "CMPUe";
-    MNEMO11[0xA3] = "CMPUx";
-    MNEMO[0x8C] = "CMPXI";
-    MNEMO[0x9C] = "CMPXd";
-    MNEMO[0xBC] = "CMPXe";
-    MNEMO[0xAC] = "CMPXx";
-    MNEMO10[0x8C] = "CMPYI";
-    MNEMO10[0x9C] = "CMPYd";
-    MNEMO10[0xBC] = "CMPYe";
-    MNEMO10[0xAC] = "CMPYx";
-
-    /* TST */
-    MNEMO[0x4D] = "TSTA-";
-    MNEMO[0x5D] = "TSTB-";
-    MNEMO[0x0D] = "TST d";
-    MNEMO[0x7D] = "TST e";
-    MNEMO[0x6D] = "TST x";
-
-    /* AND */
-    MNEMO[0x84] = "ANDAi";
-    MNEMO[0x94] = "ANDAd";
-    MNEMO[0xB4] = "ANDAe";
-    MNEMO[0xA4] = "ANDAx";
-    MNEMO[0xC4] = "ANDBi";
-    MNEMO[0xD4] = "ANDBd";
-    MNEMO[0xF4] = "ANDBe";
-    MNEMO[0xE4] = "ANDBx";
-    MNEMO[0x1C] = "& CCi";
-
-    /* OR */
-    MNEMO[0x8A] = "ORA i";
-    MNEMO[0x9A] = "ORA d";
-    MNEMO[0xBA] = "ORA e";
-    MNEMO[0xAA] = "ORA x";
-    MNEMO[0xCA] = "ORB i";
-    MNEMO[0xDA] = "ORB d";
-    MNEMO[0xFA] = "ORB e";
-    MNEMO[0xEA] = "ORB x";
-    MNEMO[0x1A] = "ORCCi";
-
-    /* EOR */
-    MNEMO[0x88] = "EORAi";
-    MNEMO[0x98] = "EORAd";
-    MNEMO[0xB8] = "EORAe";
-    MNEMO[0xA8] = "EORAx";
-    MNEMO[0xC8] = "EORBi";
-    MNEMO[0xD8] = "EORBd";
-    MNEMO[0xF8] = "EORBe";
-    MNEMO[0xE8] = "EORBx";
-
-    /* COM */
-    MNEMO[0x03] = "COM d";
-    MNEMO[0x73] = "COM e";
-    MNEMO[0x63] = "COM x";
-    MNEMO[0x43] = "COMA-";
-    MNEMO[0x53] = "COMB-";
-
-    /* NEG */
-    MNEMO[0x00] = "NEG d";
-    MNEMO[0x70] = "NEG e";
-    MNEMO[0x60] = "NEG x";
-    MNEMO[0x40] = "NEGA-";
-    MNEMO[0x50] = "NEGB-";
-
-    /* ABX */
-    MNEMO[0x3A] = "ABX -";
-
-    /* ADC */
-    MNEMO[0x89] = "ADCAi";
-    MNEMO[0x99] = "ADCAd";
-    MNEMO[0xB9] = "ADCAe";
-    MNEMO[0xA9] = "ADCAx";
-    MNEMO[0xC9] = "ADCBi";
-    MNEMO[0xD9] = "ADCBd";
-    MNEMO[0xF9] = "ADCBe";
-    MNEMO[0xE9] = "ADCBx";
-
-    /* ADD */
-    MNEMO[0x8B] = "ADDAi";
-    MNEMO[0x9B] = "ADDAd";
-    MNEMO[0xBB] = "ADDAe";
-    MNEMO[0xAB] = "ADDAx";
-    MNEMO[0xCB] = "ADDBi";
-    MNEMO[0xDB] = "ADDBd";
-    MNEMO[0xFB] = "ADDBe";
-    MNEMO[0xEB] = "ADDBx";
-    MNEMO[0xC3] = "ADDDI";
-    MNEMO[0xD3] = "ADDDd";
-    MNEMO[0xF3] = "ADDDe";
-    MNEMO[0xE3] = "ADDDx";
-
-    /* MUL */
-    MNEMO[0x3D] = "MUL -";
-
-    /* SBC */
-    MNEMO[0x82] = "SBCAi";
-    MNEMO[0x92] = "SBCAd";
-    MNEMO[0xB2] = "SBCAe";
-    MNEMO[0xA2] = "SBCAx";
-    MNEMO[0xC2] = "SBCBi";
-    MNEMO[0xD2] = "SBCBd";
-    MNEMO[0xF2] = "SBCBe";
-    MNEMO[0xE2] = "SBCBx";
-
-    /* SUB */
-    MNEMO[0x80] = "SUBAi";
-    MNEMO[0x90] = "SUBAd";
-    MNEMO[0xB0] = "SUBAe";
-    MNEMO[0xA0] = "SUBAx";
-    MNEMO[0xC0] = "SUBBi";
-    MNEMO[0xD0] = "SUBBd";
-    MNEMO[0xF0] = "SUBBe";
-    MNEMO[0xE0] = "SUBBx";
-    MNEMO[0x83] = "SUBDI";
-    MNEMO[0x93] = "SUBDd";
-    MNEMO[0xB3] = "SUBDe";
-    MNEMO[0xA3] = "SUBDx";
-
-    /* SEX */
-    MNEMO[0x1D] = "SEX -";
-
-    /* ASL */
-    MNEMO[0x08] = "ASL d";
-    MNEMO[0x78] = "ASL e";
-    MNEMO[0x68] = "ASL x";
-    MNEMO[0x48] = "ASLA-";
-    MNEMO[0x58] = "ASLB-";
-
-    /* ASR */
-    MNEMO[0x07] = "ASR d";
-    MNEMO[0x77] = "ASR e";
-    MNEMO[0x67] = "ASR x";
-    MNEMO[0x47] = "ASRA-";
-    MNEMO[0x57] = "ASRB-";
-
-    /* LSR */
-    MNEMO[0x04] = "LSR d";
-    MNEMO[0x74] = "LSR e";
-    MNEMO[0x64] = "LSR x";
-    MNEMO[0x44] = "LSRA-";
-    MNEMO[0x54] = "LSRB-";
-
-    /* ROL */
-    MNEMO[0x09] = "ROL d";
-    MNEMO[0x79] = "ROL e";
-    MNEMO[0x69] = "ROL x";
-    MNEMO[0x49] = "ROLA-";
-    MNEMO[0x59] = "ROLB-";
-
-    /* ROR */
-    MNEMO[0x06] = "ROR d";
-    MNEMO[0x76] = "ROR e";
-    MNEMO[0x66] = "ROR x";
-    MNEMO[0x46] = "RORA-";
-    MNEMO[0x56] = "RORB-";
-
-    /* BRA */
-    MNEMO[0x20] = "BRA o";
-    MNEMO[0x16] = "LBRAO";
-
-    /* JMP */
-    MNEMO[0x0E] = "JMP d";
-    MNEMO[0x7E] = "JMP e";
-    MNEMO[0x6E] = "JMP x";
-
-    /* BSR */
-    MNEMO[0x8D] = "BSR o";
-    MNEMO[0x17] = "LBSRO";
-
-    /* JSR */
-    MNEMO[0x9D] = "JSR d";
-    MNEMO[0xBD] = "JSR e";
-    MNEMO[0xAD] = "JSR x";
-
-    /* BRN */
-    MNEMO[0x21] = "BRN o";
-    MNEMO10[0x21] = "LBRNO";
-
-    /* NOP */
-    MNEMO[0x12] = "NOP -";
-
-    /* RTS */
-    MNEMO[0x39] = "RTS -";
-
-    /* BCC */
-    MNEMO[0x24] = "BCC o";
-    MNEMO10[0x24] = "LBCCO";
-
-    /* BCS */
-    MNEMO[0x25] = "BCS o";
-    MNEMO10[0x25] = "LBCSO";
-
-    /* BEQ */
-    MNEMO[0x27] = "BEQ o";
-    MNEMO10[0x27] = "LBEQO";
-
-    /* BNE */
-    MNEMO[0x26] = "BNE o";
-    MNEMO10[0x26] = "LBNEO";
-
-    /* BGE */
-    MNEMO[0x2C] = "BGE o";
-    MNEMO10[0x2C] = "LBGEO";
-
-    /* BLE */
-    MNEMO[0x2F] = "BLE o";
-    MNEMO10[0x2F] = "LBLEO";
-
-    /* BLS */
-    MNEMO[0x23] = "BLS o";
-    MNEMO10[0x23] = "LBLSO";
-
-    /* BGT */
-    MNEMO[0x2E] = "BGT o";
-    MNEMO10[0x2E] = "LBGTO";
-
-    /* BLT */
-    MNEMO[0x2D] = "BLT o";
-    MNEMO10[0x2D] = "LBLTO";
-
-    /* BHI */
-    MNEMO[0x22] = "BHI o";
-    MNEMO10[0x22] = "LBHIO";
-
-    /* BMI */
-    MNEMO[0x2B] = "BMI o";
-    MNEMO10[0x2B] = "LBMIO";
-
-    /* BPL */
-    MNEMO[0x2A] = "BPL o";
-    MNEMO10[0x2A] = "LBPLO";
-
-    /* BVC */
-    MNEMO[0x28] = "BVC o";
-    MNEMO10[0x28] = "LBVCO";
-
-    /* BVS */
-    MNEMO[0x29] = "BVS o";
-    MNEMO10[0x29] = "LBVSO";
-
-    /* SWI1&3 */
-    MNEMO[0x3F] = "SWI i";
-    MNEMO11[0x3F] = "SWI3-";
-
-    /* RTI */
-    MNEMO[0x3B] = "RTI -";
-
-    let mut _where = start;
-
-    let mut output = String::new();
-    for _ in 0..maxLines {
-        let mut mm = mem.read(_where);
-        _where += 1;
-
-        let mut output1 = format!("{:04X}.{:02X} ", _where - 1, mm);
-        let mut output2 = String::new();
-
-        let mnemo;
-        if mm == 0x10 {
-            mm = mem.read(_where);
-            _where += 1;
-            mnemo = MNEMO10[mm as usize];
-            output1.push_str(&format!("{mm:02X} "));
-            output2.push_str(&mnemo[0..4]);
-            output2.push(' ');
-        } else if mm == 0x11 {
-            mm = mem.read(_where);
-            _where += 1;
-            mnemo = MNEMO11[mm as usize];
-            output1.push_str(&format!("{mm:02X} "));
-            output2.push_str(&mnemo[0..4]);
-            output2.push(' ');
+    /// Waits for an interrupt, a masked one only ends the wait
+    fn sync<B: Bus>(&mut self, _: &mut B, _: u16) {
+        self.wait = Wait::Sync;
+    }
+
+    /// A $10 or $11 prefix followed by an opcode of the other page is ignored
+    fn ignore_prefix<B: Bus>(&mut self, _: &mut B, _: u16) {
+        self.pc = self.pc.wrapping_sub(1);
+    }
+
+    /// Applies the policy to an opcode which has no behaviour on the 6809
+    fn illegal<B: Bus>(&mut self, _: &mut B, _: u16) {
+        let address = self.pc.wrapping_sub(1) as int;
+        let opcode = self.opcode as int;
+        if self.illegal_opcode.map(|illegal| illegal.address) != Some(address) {
+            warn!("Illegal opcode {opcode:02X} at {address:04X}");
+        }
+        self.illegal_opcode = Some(IllegalOpcode { address, opcode });
+        let policy = if self.debugger.take_illegal_resume(address) {
+            IllegalOpcodePolicy::Ignore
+        } else {
+            self.illegal_opcodes
+        };
+        match policy {
+            IllegalOpcodePolicy::Ignore => self.cl += 2,
+            // the 6809 stops fetching until a reset
+            IllegalOpcodePolicy::Halt => {
+                self.wait = Wait::Halt;
+                self.cl += 2;
+            }
+            IllegalOpcodePolicy::Break => {
+                self.pc = address as u16;
+                self.debugger
+                    .illegal_opcode(IllegalOpcode { address, opcode });
+                self.stopped = true;
+            }
+        }
+    }
+
+    /// Takes the interrupt asserted on the lines if it is not masked, the NMI first, then the
+    /// FIRQ and the IRQ. Returns true if the CPU did not run an instruction: it took an
+    /// interrupt, or it waits for one until the end of the period.
+    #[inline(always)]
+    fn interrupt<B: Bus>(&mut self, clock: int, mem: &mut B) -> bool {
+        if self.wait == Wait::None && !mem.interrupts().is_pending() {
+            return false;
+        }
+        if self.wait == Wait::Halt {
+            self.cl = self.cl.max(clock);
+            return true;
+        }
+        // CWAI has already stacked the registers
+        let stacked = self.wait == Wait::Cwai;
+        let firq = mem.interrupts().is_asserted(Line::Firq);
+        let irq = mem.interrupts().is_asserted(Line::Irq);
+        if mem.interrupts().take_nmi() {
+            if !stacked {
+                self.push_entire_state(mem);
+                self.cl += 19;
+            }
+            self.vector(0xFFFC, I | F, mem);
+        } else if firq && self.cc & F == 0 {
+            if !stacked {
+                // only PC and CC, with the E flag cleared
+                self.cc &= !E;
+                self.s = self.push_registers(mem, 0x81, self.s, self.u);
+                self.cl += 10;
+            }
+            self.vector(0xFFF6, I | F, mem);
+        } else if irq && self.cc & I == 0 {
+            if !stacked {
+                self.push_entire_state(mem);
+                self.cl += 19;
+            }
+            self.vector(0xFFF8, I, mem);
+        } else if self.wait == Wait::Sync && (firq || irq) {
+            // a masked interrupt ends SYNC, the program goes on
+            self.wait = Wait::None;
+            return false;
+        } else if self.wait != Wait::None {
+            // the lines only change between two periods
+            self.cl = self.cl.max(clock);
+            return true;
         } else {
-            mnemo = MNEMO[mm as usize];
-            output2.push_str(&mnemo[0..4]);
-            output2.push(' ');
+            return false;
         }
-        match mnemo.chars().nth(4).unwrap() {
-            'I' => {
-                mm = mem.read(_where);
-                _where += 1;
-                output1.push_str(&format!("{mm:02X} "));
-                output2.push_str(&format!("#x{mm:02X}"));
-                mm = mem.read(_where);
-                _where += 1;
-                output1.push_str(&format!("{mm:02X} "));
-                output2.push_str(&format!("{mm:02X}"));
+        self.wait = Wait::None;
+        true
+    }
+
+    /// Runs the instructions and the interrupts until the clock reaches the end of the period,
+    /// the cycles run over are kept for the next one. Returns None if the CPU stopped on an
+    /// illegal opcode.
+    pub fn run<B: Bus>(&mut self, clock: int, mem: &mut B) -> Option<int> {
+        while self.cl < clock {
+            if !self.interrupt(clock, mem) {
+                self.Fetch(mem);
+                if self.stopped {
+                    self.stopped = false;
+                    return None;
+                }
             }
-            'i' => {
-                mm = mem.read(_where);
-                _where += 1;
-                output1.push_str(&format!("{mm:02X} "));
-                output2.push_str(&format!("#x{mm:02X}"));
+        }
+        self.cl -= clock;
+        Some(self.cl)
+    }
+
+    /// Runs a period on the MO5, through the debugger, the tracer and the profiler when they
+    /// are active
+    pub(crate) fn FetchUntil(&mut self, clock: int, mem: &mut Memory) -> Option<int> {
+        if !self.debugger.is_active()
+            && !mem.is_watching()
+            && !mem.is_logging_code_data()
+            && !self.profiling
+            && self.tracer.is_none()
+        {
+            return self.run(clock, mem);
+        }
+        // the cycles already run are kept to resume the same period
+        while self.cl < clock {
+            if self
+                .debugger
+                .should_stop(self.pc as int, self.s as int, mem)
+            {
+                return None;
+            }
+            if self.interrupt(clock, mem) {
+                continue;
+            }
+            let pc = self.pc as int;
+            let start = self.cl;
+            mem.log_execution(pc);
+            let flow = self.profiling.then(|| Flow::of(pc, mem));
+            if self.tracer.is_some() {
+                let registers = self.registers();
+                if let Some(tracer) = &mut self.tracer {
+                    tracer.trace(&registers, self.cl, mem);
+                }
             }
-            'e' => {
-                mm = mem.read(_where);
-                _where += 1;
-                output1.push_str(&format!("{mm:02X} "));
-                output2.push_str(&format!("x{mm:02X}"));
-                mm = mem.read(_where);
-                _where += 1;
-                output1.push_str(&format!("{mm:02X} "));
-                output2.push_str(&format!("{mm:02X}"));
+            self.Fetch(mem);
+            if self.stopped {
+                self.stopped = false;
+                return None;
             }
-            'd' => {
-                mm = mem.read(_where);
-                _where += 1;
-                output1.push_str(&format!("{mm:02X} "));
-                output2.push_str(&format!("x{mm:02X}"));
+            if let (Some(profiler), Some(flow)) = (&mut self.profiler, flow) {
+                profiler.executed(pc, flow, self.cl - start, self.pc as int);
             }
-            'o' => {
-                mm = mem.read(_where);
-                _where += 1;
-                output1.push_str(&format!("{mm:02X} "));
-                output2.push_str(&format!(
-                    "{} (x{:04X})",
-                    signedChar(mm),
-                    (_where + signedChar(mm)) & 0xFFFF
-                ));
+            if let Some(hit) = mem.take_watch_hit() {
+                self.debugger.watchpoint_hit(hit);
+                return None;
             }
-            'O' => {
-                mm = mem.read(_where) << 8;
-                _where += 1;
-                mm |= mem.read(_where);
-                _where += 1;
-                output1.push_str(&format!("{mm:04X} "));
-                output2.push_str(&format!(
-                    "{} (=x{:04X})",
-                    signed16bits(mm),
-                    (_where + signed16bits(mm)) & 0xFFFF
-                ));
+        }
+        self.cl -= clock;
+        if let Some(tracer) = &mut self.tracer {
+            tracer.end_period(clock);
+        }
+        Some(self.cl)
+    }
+
+    /// Runs an instruction: the opcode selects in the tables its handler, its addressing mode
+    /// and its cycles
+    #[inline(always)]
+    fn Fetch<B: Bus>(&mut self, mem: &mut B) {
+        mem.tick(self.cl);
+        let opcode = mem.read(self.pc);
+        self.pc = self.pc.wrapping_add(1);
+
+        let op = match opcode {
+            // PER (instruction d'emulation de périphérique)
+            0x02 => {
+                let mut registers = self.registers();
+                mem.trap(&mut registers);
+                return self.set_registers(&registers);
             }
-            'x' => {
-                let mmx = mem.read(_where);
-                _where += 1;
-                output1.push_str(&format!("{mm:02X} "));
-                if (mmx & 0x80) == 0 {
-                    if (mmx & 0x10) != 0 {
-                        output2.push('-');
-                    }
-                    output2.push_str(&format!("{},{}", mmx & 0x0F, regx(mmx)));
+            0x10 | 0x11 => {
+                let page = if opcode == 0x10 {
+                    &Op::PAGE_10
                 } else {
-                    match mmx & 0x1F {
-                        0x04 => {
-                            output2.push_str(&format!(",{}", regx(mmx)));
-                            break;
-                        }
-                        0x14 => {
-                            output2.push_str(&format!("[,{}]", regx(mmx)));
-                            break;
-                        }
-                        0x08 => {
-                            mm = mem.read(_where);
-                            _where += 1;
-                            output1.push_str(&format!("{mm:02X} "));
-                            output2.push_str(&format!("{},{}", signedChar(mm), regx(mmx)));
-                            break;
-                        }
-                        0x18 => {
-                            mm = mem.read(_where);
-                            _where += 1;
-                            output1.push_str(&format!("{mm:02X} "));
-                            output2.push_str(&format!("[{},{}]", signedChar(mm), regx(mmx)));
-                            break;
-                        }
-                        0x09 => {
-                            mm = mem.read(_where) << 8;
-                            _where += 1;
-                            mm |= mem.read(_where);
-                            _where += 1;
-                            output1.push_str(&format!("{mm:04X} "));
-                            output2.push_str(&format!("{},{}", signed16bits(mm), regx(mmx)));
-                            break;
-                        }
-                        0x19 => {
-                            mm = mem.read(_where) << 8;
-                            _where += 1;
-                            mm |= mem.read(_where);
-                            _where += 1;
-                            output1.push_str(&format!("{mm:04X} "));
-                            output2.push_str(&format!("[{},{}]", signed16bits(mm), regx(mmx)));
-                            break;
-                        }
-                        0x06 => {
-                            output2.push_str(&format!("A,{}", regx(mmx)));
-                            break;
-                        }
-                        0x16 => {
-                            output2.push_str(&format!("[A,{}]", regx(mmx)));
-                            break;
-                        }
-                        0x05 => {
-                            output2.push_str(&format!("B,{}", regx(mmx)));
-                            break;
-                        }
-                        0x15 => {
-                            output2.push_str(&format!("[B,{}]", regx(mmx)));
-                            break;
-                        }
-                        0x0B => {
-                            output2.push_str(&format!("D,{}", regx(mmx)));
-                            break;
-                        }
-                        0x1B => {
-                            output2.push_str(&format!("[D,{}]", regx(mmx)));
-                            break;
-                        }
-                        0x00 => {
-                            output2.push_str(&format!(",{}+", regx(mmx)));
-                            break;
-                        }
-                        0x01 => {
-                            output2.push_str(&format!(",{}++", regx(mmx)));
-                            break;
-                        }
-                        0x11 => {
-                            output2.push_str(&format!("[,{}++", regx(mmx)));
-                            break;
-                        }
-                        0x02 => {
-                            output2.push_str(&format!(",-{}", regx(mmx)));
-                            break;
-                        }
-                        0x03 => {
-                            output2.push_str(&format!(",--{}", regx(mmx)));
-                            break;
-                        }
-                        0x13 => {
-                            output2.push_str(&format!("[,--{}]", regx(mmx)));
-                            break;
-                        }
-                        0x0C => {
-                            mm = mem.read(_where);
-                            _where += 1;
-                            output1.push_str(&format!("{mm:02X} "));
-                            output2.push_str(&format!("{},PC", signedChar(mm)));
-                            break;
-                        }
-                        0x1C => {
-                            mm = mem.read(_where);
-                            _where += 1;
-                            output1.push_str(&format!("{mm:02X} "));
-                            output2.push_str(&format!("[{},PC]", signedChar(mm)));
-                            break;
-                        }
-                        0x0D => {
-                            mm = mem.read(_where) << 8;
-                            _where += 1;
-                            mm |= mem.read(_where);
-                            _where += 1;
-                            output1.push_str(&format!("{mm:04X} "));
-                            output2.push_str(&format!("{},PC]", signed16bits(mm)));
-                            break;
-                        }
-                        0x1D => {
-                            mm = mem.read(_where) << 8;
-                            _where += 1;
-                            mm |= mem.read(_where);
-                            _where += 1;
-                            output1.push_str(&format!("{mm:04X} "));
-                            output2.push_str(&format!("[{},PC]", signed16bits(mm)));
-                            break;
-                        }
-                        0x1F => {
-                            mm = mem.read(_where) << 8;
-                            _where += 1;
-                            mm |= mem.read(_where);
-                            _where += 1;
-                            output1.push_str(&format!("{mm:02X} {mm:04X} "));
-                            output2.push_str(&format!("[x{mm:04X}]"));
-                            break;
-                        }
-                        _ => output2.push_str("Illegal !"),
-                    }
-                }
-            }
-            'r' => {
-                mm = mem.read(_where);
-                _where += 1;
-                output1.push_str(&format!("{mm:02X} "));
-                output2.push_str(&r_tfr(mm));
+                    &Op::PAGE_11
+                };
+                self.opcode = mem.read(self.pc);
+                self.pc = self.pc.wrapping_add(1);
+                page[self.opcode as usize]
             }
-            'R' => {
-                mm = mem.read(_where);
-                _where += 1;
-                output1.push_str(&format!("{mm:02X} "));
-                output2.push_str(&r_pile(mm));
+            _ => {
+                self.opcode = opcode;
+                Op::PAGE_0[opcode as usize]
             }
-            _ => {}
-        }
+        };
+        let address = self.operand(op.mode, mem);
+        self.cl += op.cycles as int;
+        (op.run)(self, mem, address);
+    }
 
-        let lll = output1.len();
-        for _ in 0..32 - lll {
-            output1.push(' ');
-        }
-        output.push_str(&output1);
-        output.push_str(&output2);
-        output.push('\n');
-    } // of for ... maxLines
-    output
+    // UNASSEMBLE/DEBUG PART
+    pub fn registers(&self) -> Registers {
+        Registers {
+            a: self.a,
+            b: self.b,
+            dp: self.dp,
+            cc: self.cc,
+            x: self.x,
+            y: self.y,
+            u: self.u,
+            s: self.s,
+            pc: self.pc,
+        }
+    }
+
+    pub fn set_registers(&mut self, registers: &Registers) {
+        self.a = registers.a;
+        self.b = registers.b;
+        self.dp = registers.dp;
+        self.cc = registers.cc;
+        self.x = registers.x;
+        self.y = registers.y;
+        self.u = registers.u;
+        self.s = registers.s;
+        self.pc = registers.pc;
+    }
 }
 
-#[derive(Debug)]
-pub(crate) struct SoundBuffer {
-    buffer: [u8; SOUND_SIZE],
-    pos: usize,
+/// How an instruction finds its operand, the handler gets its address
+#[derive(Debug, Clone, Copy)]
+enum Mode {
+    /// No operand, the address is not used
+    Inherent,
+    /// The operand follows the opcode
+    Immediate8,
+    Immediate16,
+    /// DP gives the high byte of the address
+    Direct,
+    /// A postbyte gives the register, the offset and the indirection
+    Indexed,
+    Extended,
 }
 
-impl Default for SoundBuffer {
-    fn default() -> Self {
-        Self {
-            buffer: [0; SOUND_SIZE],
-            pos: 0,
-        }
+type Handler<B> = fn(&mut M6809, &mut B, u16);
+
+/// An entry of the opcode tables, the cycles do not include the ones of the indexed mode
+struct Op<B> {
+    run: Handler<B>,
+    mode: Mode,
+    cycles: u8,
+}
+
+// derived, they would need B to be Clone and Copy
+impl<B> Clone for Op<B> {
+    fn clone(&self) -> Self {
+        *self
     }
 }
 
-impl SoundBuffer {
-    const fn push(&mut self, value: u8) -> bool {
-        self.buffer[self.pos] = value;
-        self.pos = (self.pos + 1) % SOUND_SIZE;
-        self.pos == 0
+impl<B> Copy for Op<B> {}
+
+impl<B: Bus> Op<B> {
+    /// The opcodes without prefix, $02 is the trap of the emulator and $10 and $11 the prefixes
+    const PAGE_0: [Self; 256] = page_0();
+    /// The opcodes prefixed by $10
+    const PAGE_10: [Self; 256] = page_10();
+    /// The opcodes prefixed by $11
+    const PAGE_11: [Self; 256] = page_11();
+
+    const fn new(run: Handler<B>, mode: Mode, cycles: u8) -> Self {
+        Self { run, mode, cycles }
     }
 }
 
-impl Index<usize> for SoundBuffer {
-    type Output = u8;
+/// Sets an instruction in the immediate, direct, indexed and extended modes from its
+/// immediate opcode. The direct and indexed modes take 2 more cycles, the extended one 3.
+const fn modes<B: Bus>(
+    table: &mut [Op<B>; 256],
+    opcode: usize,
+    run: Handler<B>,
+    immediate: Mode,
+    cycles: u8,
+) {
+    table[opcode] = Op::new(run, immediate, cycles);
+    table[opcode + 0x10] = Op::new(run, Mode::Direct, cycles + 2);
+    table[opcode + 0x20] = Op::new(run, Mode::Indexed, cycles + 2);
+    table[opcode + 0x30] = Op::new(run, Mode::Extended, cycles + 3);
+}
+
+/// Sets a read-modify-write instruction on the memory, A and B from its direct opcode
+const fn read_modify_write<B: Bus>(
+    table: &mut [Op<B>; 256],
+    opcode: usize,
+    run: Handler<B>,
+    on_a: Handler<B>,
+    on_b: Handler<B>,
+) {
+    table[opcode] = Op::new(run, Mode::Direct, 6);
+    table[opcode + 0x40] = Op::new(on_a, Mode::Inherent, 2);
+    table[opcode + 0x50] = Op::new(on_b, Mode::Inherent, 2);
+    table[opcode + 0x60] = Op::new(run, Mode::Indexed, 6);
+    table[opcode + 0x70] = Op::new(run, Mode::Extended, 7);
+}
+
+const fn page_0<B: Bus>() -> [Op<B>; 256] {
+    use Mode::*;
+    let mut table = [Op::new(M6809::illegal, Inherent, 0); 256];
+    // $x1, $x5 and $xB are undocumented aliases, the $x2 are the undocumented XNC
+    read_modify_write(&mut table, 0x00, M6809::neg, M6809::nega, M6809::negb);
+    read_modify_write(&mut table, 0x01, M6809::neg, M6809::nega, M6809::negb);
+    read_modify_write(&mut table, 0x02, M6809::xnc, M6809::xnca, M6809::xncb);
+    read_modify_write(&mut table, 0x03, M6809::com, M6809::coma, M6809::comb);
+    read_modify_write(&mut table, 0x04, M6809::lsr, M6809::lsra, M6809::lsrb);
+    read_modify_write(&mut table, 0x05, M6809::lsr, M6809::lsra, M6809::lsrb);
+    read_modify_write(&mut table, 0x06, M6809::ror, M6809::rora, M6809::rorb);
+    read_modify_write(&mut table, 0x07, M6809::asr, M6809::asra, M6809::asrb);
+    read_modify_write(&mut table, 0x08, M6809::asl, M6809::asla, M6809::aslb);
+    read_modify_write(&mut table, 0x09, M6809::rol, M6809::rola, M6809::rolb);
+    read_modify_write(&mut table, 0x0A, M6809::dec, M6809::deca, M6809::decb);
+    read_modify_write(&mut table, 0x0B, M6809::dec, M6809::deca, M6809::decb);
+    read_modify_write(&mut table, 0x0C, M6809::inc, M6809::inca, M6809::incb);
+    read_modify_write(&mut table, 0x0D, M6809::tst, M6809::tsta, M6809::tstb);
+    read_modify_write(&mut table, 0x0F, M6809::clr, M6809::clra, M6809::clrb);
+    table[0x4E] = Op::new(M6809::clra, Inherent, 2);
+    table[0x5E] = Op::new(M6809::clrb, Inherent, 2);
+    table[0x0E] = Op::new(M6809::jmp, Direct, 3);
+    table[0x6E] = Op::new(M6809::jmp, Indexed, 3);
+    table[0x7E] = Op::new(M6809::jmp, Extended, 4);
+
+    table[0x12] = Op::new(M6809::nop, Inherent, 2);
+    table[0x13] = Op::new(M6809::sync, Inherent, 4);
+    table[0x16] = Op::new(M6809::lbra, Immediate16, 5);
+    table[0x17] = Op::new(M6809::lbsr, Immediate16, 9);
+    table[0x19] = Op::new(M6809::daa, Inherent, 2);
+    table[0x1A] = Op::new(M6809::orcc, Immediate8, 3);
+    table[0x1B] = Op::new(M6809::nop, Inherent, 2);
+    table[0x1C] = Op::new(M6809::andcc, Immediate8, 3);
+    table[0x1D] = Op::new(M6809::sex, Inherent, 2);
+    table[0x1E] = Op::new(M6809::exg, Immediate8, 8);
+    table[0x1F] = Op::new(M6809::tfr, Immediate8, 6);
+
+    let mut opcode = 0x20;
+    while opcode < 0x30 {
+        table[opcode] = Op::new(M6809::branch, Immediate8, 3);
+        opcode += 1;
+    }
+
+    table[0x30] = Op::new(M6809::leax, Indexed, 4);
+    table[0x31] = Op::new(M6809::leay, Indexed, 4);
+    table[0x32] = Op::new(M6809::leas, Indexed, 4);
+    table[0x33] = Op::new(M6809::leau, Indexed, 4);
+    table[0x34] = Op::new(M6809::pshs, Immediate8, 5);
+    table[0x35] = Op::new(M6809::puls, Immediate8, 5);
+    table[0x36] = Op::new(M6809::pshu, Immediate8, 5);
+    table[0x37] = Op::new(M6809::pulu, Immediate8, 5);
+    table[0x38] = Op::new(M6809::andcc, Immediate8, 4);
+    table[0x39] = Op::new(M6809::rts, Inherent, 5);
+    table[0x3A] = Op::new(M6809::abx, Inherent, 3);
+    table[0x3B] = Op::new(M6809::rti, Inherent, 6);
+    table[0x3C] = Op::new(M6809::cwai, Immediate8, 20);
+    table[0x3D] = Op::new(M6809::mul, Inherent, 11);
+    table[0x3E] = Op::new(M6809::reset_opcode, Inherent, 19);
+    table[0x3F] = Op::new(M6809::swi, Inherent, 19);
+
+    // the stores of an immediate value are undocumented, they write over the operand
+    modes(&mut table, 0x80, M6809::suba, Immediate8, 2);
+    modes(&mut table, 0x81, M6809::cmpa, Immediate8, 2);
+    modes(&mut table, 0x82, M6809::sbca, Immediate8, 2);
+    modes(&mut table, 0x83, M6809::subd, Immediate16, 4);
+    modes(&mut table, 0x84, M6809::anda, Immediate8, 2);
+    modes(&mut table, 0x85, M6809::bita, Immediate8, 2);
+    modes(&mut table, 0x86, M6809::lda, Immediate8, 2);
+    modes(&mut table, 0x87, M6809::sta, Immediate8, 2);
+    modes(&mut table, 0x88, M6809::eora, Immediate8, 2);
+    modes(&mut table, 0x89, M6809::adca, Immediate8, 2);
+    modes(&mut table, 0x8A, M6809::ora, Immediate8, 2);
+    modes(&mut table, 0x8B, M6809::adda, Immediate8, 2);
+    modes(&mut table, 0x8C, M6809::cmpx, Immediate16, 4);
+    modes(&mut table, 0x8E, M6809::ldx, Immediate16, 3);
+    modes(&mut table, 0x8F, M6809::stx, Immediate16, 3);
+    table[0x8D] = Op::new(M6809::bsr, Immediate8, 7);
+    table[0x9D] = Op::new(M6809::jsr, Direct, 7);
+    table[0xAD] = Op::new(M6809::jsr, Indexed, 7);
+    table[0xBD] = Op::new(M6809::jsr, Extended, 8);
+
+    modes(&mut table, 0xC0, M6809::subb, Immediate8, 2);
+    modes(&mut table, 0xC1, M6809::cmpb, Immediate8, 2);
+    modes(&mut table, 0xC2, M6809::sbcb, Immediate8, 2);
+    modes(&mut table, 0xC3, M6809::addd, Immediate16, 4);
+    modes(&mut table, 0xC4, M6809::andb, Immediate8, 2);
+    modes(&mut table, 0xC5, M6809::bitb, Immediate8, 2);
+    modes(&mut table, 0xC6, M6809::ldb, Immediate8, 2);
+    modes(&mut table, 0xC7, M6809::stb, Immediate8, 2);
+    modes(&mut table, 0xC8, M6809::eorb, Immediate8, 2);
+    modes(&mut table, 0xC9, M6809::adcb, Immediate8, 2);
+    modes(&mut table, 0xCA, M6809::orb, Immediate8, 2);
+    modes(&mut table, 0xCB, M6809::addb, Immediate8, 2);
+    modes(&mut table, 0xCC, M6809::ldd, Immediate16, 3);
+    modes(&mut table, 0xCD, M6809::std, Immediate16, 3);
+    modes(&mut table, 0xCE, M6809::ldu, Immediate16, 3);
+    modes(&mut table, 0xCF, M6809::stu, Immediate16, 3);
+    table[0xCD] = Op::new(M6809::illegal, Inherent, 0);
+    table
+}
+
+const fn page_10<B: Bus>() -> [Op<B>; 256] {
+    use Mode::*;
+    let mut table = [Op::new(M6809::ignore_prefix, Inherent, 1); 256];
+    let mut opcode = 0x21;
+    while opcode < 0x30 {
+        table[opcode] = Op::new(M6809::long_branch, Immediate16, 5);
+        opcode += 1;
+    }
+    table[0x3F] = Op::new(M6809::swi2, Inherent, 20);
+    modes(&mut table, 0x83, M6809::cmpd, Immediate16, 5);
+    modes(&mut table, 0x8C, M6809::cmpy, Immediate16, 5);
+    modes(&mut table, 0x8E, M6809::ldy, Immediate16, 4);
+    modes(&mut table, 0x8F, M6809::sty, Immediate16, 4);
+    modes(&mut table, 0xCE, M6809::lds, Immediate16, 4);
+    modes(&mut table, 0xCF, M6809::sts, Immediate16, 4);
+    table[0x8F] = Op::new(M6809::ignore_prefix, Inherent, 1);
+    table[0xCF] = Op::new(M6809::ignore_prefix, Inherent, 1);
+    table
+}
+
+const fn page_11<B: Bus>() -> [Op<B>; 256] {
+    use Mode::*;
+    let mut table = [Op::new(M6809::ignore_prefix, Inherent, 1); 256];
+    table[0x3F] = Op::new(M6809::swi3, Inherent, 20);
+    modes(&mut table, 0x83, M6809::cmpu, Immediate16, 5);
+    modes(&mut table, 0x8C, M6809::cmps, Immediate16, 5);
+    table
+}
+
+/// What the CPU waits for instead of running instructions
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Wait {
+    #[default]
+    None,
+    /// CWAI stacked the registers, an interrupt which is not masked goes on
+    Cwai,
+    /// SYNC, any interrupt goes on
+    Sync,
+    /// An illegal opcode, only a reset goes on
+    Halt,
+}
+
+/// What the CPU does with the opcodes which have no behaviour, the undocumented ones are
+/// always run like the 6809 does
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IllegalOpcodePolicy {
+    /// Runs them as a NOP
+    #[default]
+    Ignore,
+    /// Stops the CPU until a reset, like the 6809
+    Halt,
+    /// Stops the machine in the debugger before the opcode
+    Break,
+}
+
+impl IllegalOpcodePolicy {
+    pub const ALL: [Self; 3] = [Self::Ignore, Self::Halt, Self::Break];
 
-    fn index(&self, index: usize) -> &Self::Output {
-        self.buffer.index(index)
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Ignore => "Ignore",
+            Self::Halt => "Halt",
+            Self::Break => "Break",
+        }
     }
 }
 
+/// An illegal opcode run by the CPU
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IllegalOpcode {
+    pub address: int,
+    pub opcode: int,
+}
+
+/// The registers as seen by the programmer
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Registers {
+    pub a: u8,
+    pub b: u8,
+    pub dp: u8,
+    pub cc: u8,
+    pub x: u16,
+    pub y: u16,
+    pub u: u16,
+    pub s: u16,
+    pub pc: u16,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::hardware::debugger::Stop;
+    use crate::hardware::disassembler::decode;
+    use crate::hardware::interrupts::{InterruptLines, Source};
     use rstest::rstest;
 
     #[rstest]
@@ -4017,9 +1496,226 @@ mod tests {
     #[case(0xFFFE, -2)]
     #[case(0xFF00, -256)]
     #[case(0x8000, -32768)] // 2^15, valeur min négative
-    fn test_signed16bits_zero(#[case] input: u16, #[case] expected: i16) {
-        // Le zéro devrait rester zéro après conversion
-        assert_eq!(signed16bits(input as int), expected.into());
+    fn test_indexe_16bit_offset(#[case] input: u16, #[case] expected: i16) {
+        let mut mem = Memory::default();
+        let mut cpu = M6809::new(&mem);
+        // the offset of n16,X is signed
+        cpu.x = 0x8000;
+        cpu.pc = 0x2000;
+        mem.write(0x2000, 0x89);
+        mem.write_16(0x2001, input);
+        assert_eq!(
+            0x8000u16.wrapping_add_signed(expected),
+            cpu.indexed(&mut mem)
+        );
+        assert_eq!(4, cpu.cl);
+    }
+
+    fn cpu_with_vectors(mem: &mut Memory) -> M6809 {
+        for (vector, routine) in [
+            (0xFFF2, 0xF300),
+            (0xFFF4, 0xF400),
+            (0xFFF6, 0xF600),
+            (0xFFF8, 0xF800),
+            (0xFFFC, 0xFC00),
+        ] {
+            mem.set(vector, routine >> 8);
+            mem.set(vector + 1, routine & 0xFF);
+        }
+        let mut cpu = M6809::new(mem);
+        cpu.pc = 0x6000;
+        cpu.s = 0x9000;
+        cpu.cc = 0x00;
+        cpu
+    }
+
+    #[test]
+    fn test_firq() {
+        let mut mem = Memory::default();
+        let mut cpu = cpu_with_vectors(&mut mem);
+        mem.interrupts.set(Line::Firq, Source::Extension, true);
+        assert!(cpu.interrupt(100, &mut mem));
+        assert_eq!(0xF600, cpu.pc);
+        assert_eq!(0x8FFD, cpu.s);
+        assert_eq!(10, cpu.cl);
+        assert_eq!(0x50, cpu.cc & 0x50);
+        // E is cleared in the stacked CC, RTI only pulls PC
+        assert_eq!(0x00, mem.read(0x8FFD) & 0x80);
+        // the line is still asserted but FIRQ is now masked
+        assert!(!cpu.interrupt(100, &mut mem));
+    }
+
+    #[test]
+    fn test_masked_irq() {
+        let mut mem = Memory::default();
+        let mut cpu = cpu_with_vectors(&mut mem);
+        cpu.cc = 0x10;
+        mem.interrupts.set(Line::Irq, Source::Pia, true);
+        assert!(!cpu.interrupt(100, &mut mem));
+        assert_eq!(0x6000, cpu.pc);
+        cpu.cc = 0x00;
+        assert!(cpu.interrupt(100, &mut mem));
+        assert_eq!(0xF800, cpu.pc);
+        assert_eq!(0x9000 - 12, cpu.s);
+        assert_eq!(19, cpu.cl);
+        assert_eq!(0x10, cpu.cc & 0x50);
+    }
+
+    #[test]
+    fn test_nmi() {
+        let mut mem = Memory::default();
+        let mut cpu = cpu_with_vectors(&mut mem);
+        cpu.cc = 0x50;
+        mem.interrupts.set(Line::Nmi, Source::Extension, true);
+        assert!(cpu.interrupt(100, &mut mem));
+        assert_eq!(0xFC00, cpu.pc);
+        // taken once per edge
+        assert!(!cpu.interrupt(100, &mut mem));
+    }
+
+    #[test]
+    fn test_cwai() {
+        let mut mem = Memory::default();
+        let mut cpu = cpu_with_vectors(&mut mem);
+        cpu.cc = 0x50;
+        // CWAI #$EF clears I
+        mem.write(0x6000, 0x3C);
+        mem.write(0x6001, 0xEF);
+        cpu.Fetch(&mut mem);
+        assert_eq!(0x9000 - 12, cpu.s);
+        assert_eq!(0x6002, mem.read_16(0x9000 - 2));
+        // waits until the end of the period
+        assert!(cpu.interrupt(100, &mut mem));
+        assert_eq!(100, cpu.cl);
+        mem.interrupts.set(Line::Irq, Source::Pia, true);
+        assert!(cpu.interrupt(200, &mut mem));
+        assert_eq!(0xF800, cpu.pc);
+        // the registers are not stacked twice
+        assert_eq!(0x9000 - 12, cpu.s);
+        assert_eq!(Wait::None, cpu.wait);
+    }
+
+    #[test]
+    fn test_sync() {
+        let mut mem = Memory::default();
+        let mut cpu = cpu_with_vectors(&mut mem);
+        cpu.cc = 0x10;
+        mem.write(0x6000, 0x13);
+        cpu.Fetch(&mut mem);
+        assert!(cpu.interrupt(100, &mut mem));
+        // a masked interrupt ends the wait without being taken
+        mem.interrupts.set(Line::Irq, Source::Pia, true);
+        assert!(!cpu.interrupt(200, &mut mem));
+        assert_eq!(0x6001, cpu.pc);
+        assert_eq!(Wait::None, cpu.wait);
+    }
+
+    #[rstest]
+    #[case(0x10, 0xF400)]
+    #[case(0x11, 0xF300)]
+    fn test_swi2_swi3(#[case] page: u8, #[case] routine: u16) {
+        let mut mem = Memory::default();
+        let mut cpu = cpu_with_vectors(&mut mem);
+        mem.write(0x6000, page);
+        mem.write(0x6001, 0x3F);
+        cpu.Fetch(&mut mem);
+        assert_eq!(routine, cpu.pc);
+        assert_eq!(0x9000 - 12, cpu.s);
+        assert_eq!(20, cpu.cl);
+        // the interrupts stay enabled
+        assert_eq!(0x00, cpu.cc & 0x50);
+    }
+
+    #[rstest]
+    #[case::neg_alias(&[0x41], 0xFF, 2)]
+    #[case::xnc_as_neg(&[0x42], 0xFF, 2)]
+    #[case::lsr_alias(&[0x45], 0x00, 2)]
+    #[case::dec_alias(&[0x4B], 0x00, 2)]
+    #[case::clr_alias(&[0x4E], 0x00, 2)]
+    #[case::ignored_prefix(&[0x10, 0x86, 0x33], 0x33, 3)]
+    fn test_undocumented_opcodes(#[case] code: &[u8], #[case] a: u8, #[case] cycles: int) {
+        let mut mem = Memory::default();
+        let mut cpu = cpu_with_vectors(&mut mem);
+        for (i, byte) in code.iter().enumerate() {
+            mem.write(0x6000 + i as u16, *byte);
+        }
+        cpu.a = 0x01;
+        while cpu.pc < 0x6000 + code.len() as u16 {
+            cpu.Fetch(&mut mem);
+        }
+        assert_eq!(a, cpu.a);
+        assert_eq!(cycles, cpu.cl);
+    }
+
+    #[test]
+    fn test_undocumented_side_effects() {
+        let mut mem = Memory::default();
+        let mut cpu = cpu_with_vectors(&mut mem);
+        mem.set(0xFFFE, 0xF0);
+        mem.set(0xFFFF, 0x00);
+        // SEC / XNCA / STA #$00 / STX #$0000 / RESET
+        for (i, byte) in [0x1A, 0x01, 0x42, 0x87, 0x00, 0x8F, 0x00, 0x00, 0x3E]
+            .iter()
+            .enumerate()
+        {
+            mem.write(0x6000 + i as u16, *byte);
+        }
+        cpu.a = 0x01;
+        cpu.x = 0x1234;
+        for _ in 0..5 {
+            cpu.Fetch(&mut mem);
+        }
+        // COMA as C was set
+        assert_eq!(0xFE, cpu.a);
+        assert_eq!(0xFE, mem.read(0x6004));
+        assert_eq!(0x1234, mem.read_16(0x6006));
+        assert_eq!(0xF000, cpu.pc);
+        assert_eq!(0x9000 - 12, cpu.s);
+        assert_eq!(0x50, cpu.cc & 0x50);
+    }
+
+    #[rstest]
+    #[case::ignore(IllegalOpcodePolicy::Ignore)]
+    #[case::halt(IllegalOpcodePolicy::Halt)]
+    #[case::break_(IllegalOpcodePolicy::Break)]
+    fn test_illegal_opcode(#[case] policy: IllegalOpcodePolicy) {
+        let mut mem = Memory::default();
+        let mut cpu = cpu_with_vectors(&mut mem);
+        mem.write(0x6000, 0x14);
+        for address in 0x6001..0x6020 {
+            mem.write(address, 0x12);
+        }
+        cpu.illegal_opcodes = policy;
+        let result = cpu.FetchUntil(20, &mut mem);
+        assert_eq!(
+            Some(IllegalOpcode {
+                address: 0x6000,
+                opcode: 0x14
+            }),
+            cpu.illegal_opcode
+        );
+        match policy {
+            IllegalOpcodePolicy::Ignore => {
+                assert!(result.is_some());
+                assert!(cpu.pc > 0x6001);
+            }
+            IllegalOpcodePolicy::Halt => {
+                assert!(result.is_some());
+                assert_eq!(0x6001, cpu.pc);
+            }
+            IllegalOpcodePolicy::Break => {
+                assert!(result.is_none());
+                assert_eq!(0x6000, cpu.pc);
+                assert!(matches!(
+                    cpu.debugger.last_stop(),
+                    Some(Stop::IllegalOpcode(_))
+                ));
+                // resuming runs over the opcode
+                cpu.debugger.resume();
+                assert!(cpu.FetchUntil(20, &mut mem).is_some());
+                assert!(cpu.pc > 0x6001);
+            }
+        }
     }
 
     #[test]
@@ -4029,33 +1725,33 @@ mod tests {
         let mut cpu = M6809::new(&mem);
 
         // Test X + 5 (0x05: 000 00101 -> X, +5)
-        cpu.X = 0x1000;
-        cpu.PC = 0x2000;
+        cpu.x = 0x1000;
+        cpu.pc = 0x2000;
         mem.write(0x2000, 0x05);
-        let addr = cpu.INDEXE(&mut mem);
+        let addr = cpu.indexed(&mut mem);
         assert_eq!(addr, 0x1005);
-        assert_eq!(cpu.PC, 0x2001);
+        assert_eq!(cpu.pc, 0x2001);
 
         // Test Y - 1 (0x3F: 001 11111 -> Y, -1 because 0x1F is -1 in 5-bit signed)
         // 5-bit signed: 0x10 is -16, 0x1F is -1
-        cpu.Y = 0x1000;
-        cpu.PC = 0x2000;
+        cpu.y = 0x1000;
+        cpu.pc = 0x2000;
         mem.write(0x2000, 0x3F);
-        let addr = cpu.INDEXE(&mut mem);
+        let addr = cpu.indexed(&mut mem);
         assert_eq!(addr, 0x0FFF);
 
         // Test U + 15 (0x4F: 010 01111 -> U, +15)
-        cpu.U = 0x1000;
-        cpu.PC = 0x2000;
+        cpu.u = 0x1000;
+        cpu.pc = 0x2000;
         mem.write(0x2000, 0x4F);
-        let addr = cpu.INDEXE(&mut mem);
+        let addr = cpu.indexed(&mut mem);
         assert_eq!(addr, 0x100F);
 
         // Test S - 16 (0x70: 011 10000 -> S, -16)
-        cpu.S = 0x1000;
-        cpu.PC = 0x2000;
+        cpu.s = 0x1000;
+        cpu.pc = 0x2000;
         mem.write(0x2000, 0x70);
-        let addr = cpu.INDEXE(&mut mem);
+        let addr = cpu.indexed(&mut mem);
         assert_eq!(addr, 0x0FF0);
     }
 
@@ -4065,36 +1761,36 @@ mod tests {
         let mut cpu = M6809::new(&mem);
 
         // Post-increment X by 1 (0x80)
-        cpu.X = 0x1000;
-        cpu.PC = 0x2000;
+        cpu.x = 0x1000;
+        cpu.pc = 0x2000;
         mem.write(0x2000, 0x80);
-        let addr = cpu.INDEXE(&mut mem);
+        let addr = cpu.indexed(&mut mem);
         assert_eq!(addr, 0x1000);
-        assert_eq!(cpu.X, 0x1001);
+        assert_eq!(cpu.x, 0x1001);
 
         // Post-increment X by 2 (0x81)
-        cpu.X = 0x1000;
-        cpu.PC = 0x2000;
+        cpu.x = 0x1000;
+        cpu.pc = 0x2000;
         mem.write(0x2000, 0x81);
-        let addr = cpu.INDEXE(&mut mem);
+        let addr = cpu.indexed(&mut mem);
         assert_eq!(addr, 0x1000);
-        assert_eq!(cpu.X, 0x1002);
+        assert_eq!(cpu.x, 0x1002);
 
         // Pre-decrement X by 1 (0x82)
-        cpu.X = 0x1000;
-        cpu.PC = 0x2000;
+        cpu.x = 0x1000;
+        cpu.pc = 0x2000;
         mem.write(0x2000, 0x82);
-        let addr = cpu.INDEXE(&mut mem);
+        let addr = cpu.indexed(&mut mem);
         assert_eq!(addr, 0x0FFF);
-        assert_eq!(cpu.X, 0x0FFF);
+        assert_eq!(cpu.x, 0x0FFF);
 
         // Pre-decrement X by 2 (0x83)
-        cpu.X = 0x1000;
-        cpu.PC = 0x2000;
+        cpu.x = 0x1000;
+        cpu.pc = 0x2000;
         mem.write(0x2000, 0x83);
-        let addr = cpu.INDEXE(&mut mem);
+        let addr = cpu.indexed(&mut mem);
         assert_eq!(addr, 0x0FFE);
-        assert_eq!(cpu.X, 0x0FFE);
+        assert_eq!(cpu.x, 0x0FFE);
     }
 
     #[test]
@@ -4103,26 +1799,26 @@ mod tests {
         let mut cpu = M6809::new(&mem);
 
         // B offset (0x85)
-        cpu.X = 0x1000;
-        cpu.B = 0x05;
-        cpu.PC = 0x2000;
+        cpu.x = 0x1000;
+        cpu.b = 0x05;
+        cpu.pc = 0x2000;
         mem.write(0x2000, 0x85);
-        assert_eq!(cpu.INDEXE(&mut mem), 0x1005);
+        assert_eq!(cpu.indexed(&mut mem), 0x1005);
 
         // A offset (0x86)
-        cpu.X = 0x1000;
-        cpu.A = 0xFF; // -1
-        cpu.PC = 0x2000;
+        cpu.x = 0x1000;
+        cpu.a = 0xFF; // -1
+        cpu.pc = 0x2000;
         mem.write(0x2000, 0x86);
-        assert_eq!(cpu.INDEXE(&mut mem), 0x0FFF);
+        assert_eq!(cpu.indexed(&mut mem), 0x0FFF);
 
         // D offset (0x8B)
-        cpu.X = 0x1000;
-        cpu.A = 0x00;
-        cpu.B = 0x10;
-        cpu.PC = 0x2000;
+        cpu.x = 0x1000;
+        cpu.a = 0x00;
+        cpu.b = 0x10;
+        cpu.pc = 0x2000;
         mem.write(0x2000, 0x8B);
-        assert_eq!(cpu.INDEXE(&mut mem), 0x1010);
+        assert_eq!(cpu.indexed(&mut mem), 0x1010);
     }
 
     #[test]
@@ -4131,21 +1827,21 @@ mod tests {
         let mut cpu = M6809::new(&mem);
 
         // 8-bit offset (0x88)
-        cpu.X = 0x1000;
-        cpu.PC = 0x2000;
+        cpu.x = 0x1000;
+        cpu.pc = 0x2000;
         mem.write(0x2000, 0x88);
         mem.write(0x2001, 0x12);
-        assert_eq!(cpu.INDEXE(&mut mem), 0x1012);
-        assert_eq!(cpu.PC, 0x2002);
+        assert_eq!(cpu.indexed(&mut mem), 0x1012);
+        assert_eq!(cpu.pc, 0x2002);
 
         // 16-bit offset (0x89)
-        cpu.X = 0x1000;
-        cpu.PC = 0x2000;
+        cpu.x = 0x1000;
+        cpu.pc = 0x2000;
         mem.write(0x2000, 0x89);
         mem.write(0x2001, 0x12);
         mem.write(0x2002, 0x34);
-        assert_eq!(cpu.INDEXE(&mut mem), 0x1000 + 0x1234);
-        assert_eq!(cpu.PC, 0x2003);
+        assert_eq!(cpu.indexed(&mut mem), 0x1000 + 0x1234);
+        assert_eq!(cpu.pc, 0x2003);
     }
 
     #[test]
@@ -4154,25 +1850,25 @@ mod tests {
         let mut cpu = M6809::new(&mem);
 
         // PC 8-bit offset (0x8C)
-        cpu.PC = 0x2000;
+        cpu.pc = 0x2000;
         mem.write(0x2000, 0x8C);
         mem.write(0x2001, 0x05); // Offset +5 from PC after reading offset byte
         // After reading 0x8C, PC is 0x2001.
-        // Inside INDEXE for 0x8C: m = mem.read(0x2001) [0x05], PC = 0x2002.
-        // Result is PC + signedChar(m) = 0x2002 + 5 = 0x2007.
-        assert_eq!(cpu.INDEXE(&mut mem), 0x2007);
-        assert_eq!(cpu.PC, 0x2002);
+        // Inside indexed for 0x8C: the offset is read at 0x2001 [0x05], PC = 0x2002.
+        // Result is PC + 5 = 0x2007.
+        assert_eq!(cpu.indexed(&mut mem), 0x2007);
+        assert_eq!(cpu.pc, 0x2002);
 
         // PC 16-bit offset (0x8D)
-        cpu.PC = 0x2000;
+        cpu.pc = 0x2000;
         mem.write(0x2000, 0x8D);
         mem.write(0x2001, 0x10);
         mem.write(0x2002, 0x00);
         // After reading 0x8D, PC is 0x2001.
-        // Inside INDEXE for 0x8D: M = read_16(0x2001) [0x1000], PC = 0x2003.
-        // Result is PC + signed16bits(M) = 0x2003 + 0x1000 = 0x3003.
-        assert_eq!(cpu.INDEXE(&mut mem), 0x3003);
-        assert_eq!(cpu.PC, 0x2003);
+        // Inside indexed for 0x8D: the offset is read at 0x2001 [0x1000], PC = 0x2003.
+        // Result is PC + 0x1000 = 0x3003.
+        assert_eq!(cpu.indexed(&mut mem), 0x3003);
+        assert_eq!(cpu.pc, 0x2003);
     }
 
     #[test]
@@ -4181,21 +1877,125 @@ mod tests {
         let mut cpu = M6809::new(&mem);
 
         // Indirect with 8-bit offset from X (0x98)
-        cpu.X = 0x1000;
-        cpu.PC = 0x2000;
+        cpu.x = 0x1000;
+        cpu.pc = 0x2000;
         mem.write(0x2000, 0x98);
         mem.write(0x2001, 0x02); // Offset 2 -> 0x1002
         mem.write(0x1002, 0xAB);
         mem.write(0x1003, 0xCD); // [0x1002] contains 0xABCD
-        assert_eq!(cpu.INDEXE(&mut mem), 0xABCD);
+        assert_eq!(cpu.indexed(&mut mem), 0xABCD);
 
         // Indirect Extended (0x9F)
-        cpu.PC = 0x2000;
+        cpu.pc = 0x2000;
         mem.write(0x2000, 0x9F);
         mem.write(0x2001, 0x30);
         mem.write(0x2002, 0x00); // Address 0x3000
         mem.write(0x3000, 0xCA);
         mem.write(0x3001, 0xFE); // [0x3000] contains 0xCAFE
-        assert_eq!(cpu.INDEXE(&mut mem), 0xCAFE);
+        assert_eq!(cpu.indexed(&mut mem), 0xCAFE);
+    }
+
+    #[test]
+    fn test_tables() {
+        // the cycles of the opcodes which have a behaviour
+        assert_eq!(2, Op::<Memory>::PAGE_0[0x86].cycles);
+        assert_eq!(5, Op::<Memory>::PAGE_0[0xB6].cycles);
+        assert_eq!(7, Op::<Memory>::PAGE_0[0x70].cycles);
+        assert_eq!(8, Op::<Memory>::PAGE_0[0xBD].cycles);
+        assert_eq!(7, Op::<Memory>::PAGE_10[0xBE].cycles);
+        assert_eq!(8, Op::<Memory>::PAGE_11[0xB3].cycles);
+        // the immediate stores of S and Y do not exist
+        assert_eq!(1, Op::<Memory>::PAGE_10[0xCF].cycles);
+    }
+
+    #[test]
+    fn test_disassembler_tables() {
+        let mut mem = Memory::default();
+        let pages = [
+            (None, &Op::<Memory>::PAGE_0),
+            (Some(0x10), &Op::PAGE_10),
+            (Some(0x11), &Op::PAGE_11),
+        ];
+        for (prefix, table) in pages {
+            for (opcode, op) in table.iter().enumerate() {
+                // the trap is run before the table, the prefixes count with the next opcode
+                if prefix.is_none() && matches!(opcode, 0x02 | 0x10 | 0x11) {
+                    continue;
+                }
+                // ,X without extra cycles, no register pushed or pulled, zeros for the others
+                let postbyte = if (0x34..=0x37).contains(&opcode) {
+                    0
+                } else {
+                    0x84
+                };
+                let bytes = prefix.into_iter().chain([opcode as u8, postbyte, 0]);
+                for (address, byte) in (0x6000..).zip(bytes) {
+                    mem.write(address, byte);
+                }
+                let instruction = decode(0x6000, &mem);
+                // the opcodes unknown to the disassembler, a prefix alone if it is prefixed
+                if instruction.mnemonic == "ILL" || instruction.mnemonic == "FCB" {
+                    continue;
+                }
+                let name = format!("{prefix:02X?} {opcode:02X} {}", instruction.mnemonic);
+                assert_eq!(op.cycles as int, instruction.cycles, "{name}");
+                let operands = match op.mode {
+                    Mode::Inherent => 0,
+                    Mode::Immediate8 | Mode::Direct | Mode::Indexed => 1,
+                    Mode::Immediate16 | Mode::Extended => 2,
+                };
+                let length = usize::from(prefix.is_some()) + 1 + operands;
+                assert_eq!(length, instruction.bytes.len(), "{name}");
+            }
+        }
+    }
+
+    /// A flat RAM without devices, the trap loads A with the number of traps run
+    #[derive(Default)]
+    struct Ram {
+        bytes: Vec<u8>,
+        interrupts: InterruptLines,
+        traps: u8,
+    }
+
+    impl Bus for Ram {
+        fn read(&self, address: u16) -> u8 {
+            self.bytes[address as usize]
+        }
+
+        fn write(&mut self, address: u16, value: u8) {
+            self.bytes[address as usize] = value;
+        }
+
+        fn trap(&mut self, registers: &mut Registers) {
+            self.traps += 1;
+            registers.a = self.traps;
+        }
+
+        fn interrupts(&self) -> &InterruptLines {
+            &self.interrupts
+        }
+    }
+
+    #[test]
+    fn test_other_bus() {
+        let mut ram = Ram {
+            bytes: vec![0; 0x10000],
+            ..Ram::default()
+        };
+        // trap, STA $2000, trap, STA $2001, BRA *
+        let program = [0x02, 0xB7, 0x20, 0x00, 0x02, 0xB7, 0x20, 0x01, 0x20, 0xFE];
+        ram.bytes[0x1000..0x1000 + program.len()].copy_from_slice(&program);
+        ram.write_16(0xFFFE, 0x1000);
+        let mut cpu = M6809::new(&ram);
+        // 2 STA and 3 BRA, the last one ends 2 cycles after the period
+        assert_eq!(Some(2), cpu.run(20, &mut ram));
+        assert_eq!([1, 2], ram.bytes[0x2000..0x2002]);
+        assert_eq!(0x1008, cpu.pc);
+        ram.interrupts.set(Line::Nmi, Source::Extension, true);
+        ram.write_16(0xFFFC, 0x1008);
+        cpu.run(40, &mut ram);
+        // the NMI stacked the registers below $8000
+        assert_eq!(0x8000 - 12, cpu.s);
     }
 }
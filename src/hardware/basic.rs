@@ -0,0 +1,480 @@
+use crate::bios;
+use crate::hardware::k7::{FileKind, K7, TapeFile};
+use crate::hardware::memory::Memory;
+use crate::int;
+use std::collections::BTreeMap;
+
+/// The pointers to the start of the BASIC program and to its end, where the variables start
+pub const PROGRAM_START: u16 = 0x2113;
+pub const PROGRAM_END: u16 = 0x2115;
+/// The pointer to the end of the variables
+const VARIABLES_END: u16 = 0x2117;
+/// The pointers to the start of the string space, to its last string and to its end
+const STRINGS_START: u16 = 0x2119;
+const STRINGS_FREE: u16 = 0x211B;
+const STRINGS_END: u16 = 0x211F;
+/// Where the program starts after a reset
+const DEFAULT_START: u16 = 0x25A4;
+const LAST_LINE: u16 = 63999;
+const MAX_LINE_LENGTH: usize = 255;
+
+/// The keyword tables of the ROM, the last letter of each keyword has bit 7 set
+const STATEMENTS: usize = 0xC0A0;
+const FUNCTIONS: usize = 0xC1CC;
+/// The functions are a prefix followed by their token
+const FUNCTION_PREFIX: u8 = 0xFF;
+const FIRST_TOKEN: u8 = 0x80;
+
+const DATA: u8 = 0x83;
+const REM: u8 = 0x8C;
+/// `'`, stored after a `:` like ELSE
+const QUOTE: u8 = 0x8D;
+const ELSE: u8 = 0x8F;
+
+/// The keywords of BASIC 1.0, by token. The unused tokens have no keyword.
+pub struct Keywords {
+    statements: Vec<Option<String>>,
+    functions: Vec<Option<String>>,
+}
+
+impl Keywords {
+    /// The keywords read from the ROM
+    pub fn mo5() -> Self {
+        Self {
+            statements: Self::table(&bios::BIOS[STATEMENTS - 0xC000..FUNCTIONS - 0xC000]),
+            functions: Self::table(&bios::BIOS[FUNCTIONS - 0xC000..]),
+        }
+    }
+
+    fn table(bytes: &[u8]) -> Vec<Option<String>> {
+        let mut keywords = Vec::new();
+        let mut keyword = String::new();
+        for byte in bytes.iter().take_while(|byte| **byte != 0) {
+            keyword.push((byte & 0x7F) as char);
+            if byte & 0x80 != 0 {
+                // the unused tokens are a single DEL
+                keywords.push(Some(keyword.clone()).filter(|keyword| keyword != "\x7F"));
+                keyword.clear();
+            }
+        }
+        keywords
+    }
+
+    pub fn statement(&self, token: u8) -> Option<&str> {
+        let index = token.checked_sub(FIRST_TOKEN)?;
+        self.statements.get(index as usize)?.as_deref()
+    }
+
+    pub fn function(&self, token: u8) -> Option<&str> {
+        let index = token.checked_sub(FIRST_TOKEN)?;
+        self.functions.get(index as usize)?.as_deref()
+    }
+
+    /// The text of the tokenized line, without its number. The bytes that are not printable and
+    /// the unknown tokens are written in hexadecimal between braces.
+    pub fn detokenize_line(&self, line: &[u8]) -> String {
+        let mut text = String::new();
+        // after REM and ' the line is kept as typed, after DATA up to a colon out of the strings
+        let mut literal = None;
+        let mut quoted = false;
+        let mut bytes = line.iter().copied().peekable();
+        while let Some(byte) = bytes.next() {
+            if literal.is_some() || quoted || byte < FIRST_TOKEN {
+                if byte == b':' && !quoted && literal == Some(DATA) {
+                    literal = None;
+                }
+                if byte == b':'
+                    && literal.is_none()
+                    && !quoted
+                    && matches!(bytes.peek(), Some(&(QUOTE | ELSE)))
+                {
+                    continue;
+                }
+                if byte == b'"' && matches!(literal, None | Some(DATA)) {
+                    quoted = !quoted;
+                }
+                push_char(&mut text, byte);
+            } else if byte == FUNCTION_PREFIX {
+                let token = bytes.next();
+                match token.and_then(|token| self.function(token)) {
+                    Some(keyword) => text.push_str(keyword),
+                    None => {
+                        push_hex(&mut text, byte);
+                        token
+                            .into_iter()
+                            .for_each(|token| push_hex(&mut text, token));
+                    }
+                }
+            } else {
+                match self.statement(byte) {
+                    Some(keyword) => text.push_str(keyword),
+                    None => push_hex(&mut text, byte),
+                }
+                literal = matches!(byte, DATA | REM | QUOTE).then_some(byte);
+            }
+        }
+        text
+    }
+
+    /// The token of the keyword at the start of the text and the length of the keyword. The
+    /// keywords are tried in the order of the ROM like BASIC does, GOTO is GO then TO.
+    fn token_at(&self, text: &str) -> Option<(Vec<u8>, usize)> {
+        if let Some((token, length)) = find_keyword(&self.statements, text) {
+            return Some((vec![token], length));
+        }
+        find_keyword(&self.functions, text)
+            .map(|(token, length)| (vec![FUNCTION_PREFIX, token], length))
+    }
+
+    /// The tokens of a line without its number, the reverse of [`Self::detokenize_line`]
+    pub fn tokenize_line(&self, text: &str) -> Result<Vec<u8>, String> {
+        let mut line = Vec::new();
+        let mut literal = None;
+        let mut quoted = false;
+        let mut rest = text;
+        while let Some(c) = rest.chars().next() {
+            if !c.is_ascii() {
+                return Err(format!("Unsupported character {c}"));
+            }
+            if let Some(byte) = parse_hex(rest) {
+                line.push(byte);
+                rest = &rest[4..];
+                continue;
+            }
+            let token = if literal.is_some() || quoted || c == '"' {
+                None
+            } else {
+                self.token_at(rest)
+            };
+            match token {
+                Some((token, length)) => {
+                    if matches!(token[..], [QUOTE | ELSE]) {
+                        line.push(b':');
+                    }
+                    literal = match token[..] {
+                        [token @ (DATA | REM | QUOTE)] => Some(token),
+                        _ => None,
+                    };
+                    line.extend(token);
+                    rest = &rest[length..];
+                }
+                None => {
+                    if c == '"' && matches!(literal, None | Some(DATA)) {
+                        quoted = !quoted;
+                    }
+                    if c == ':' && !quoted && literal == Some(DATA) {
+                        literal = None;
+                    }
+                    line.push(c as u8);
+                    rest = &rest[1..];
+                }
+            }
+        }
+        Ok(line)
+    }
+
+    /// The tokenized program of a listing, the reverse of [`Self::detokenize`]. The lines are
+    /// sorted and a line typed twice keeps its last text, like when typing them.
+    pub fn tokenize(&self, listing: &str, start: u16) -> Result<Vec<u8>, String> {
+        let mut lines = BTreeMap::new();
+        for (index, text) in listing.lines().enumerate() {
+            let text = text.trim();
+            if text.is_empty() {
+                continue;
+            }
+            let digits = text.len() - text.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            let number = text[..digits]
+                .parse::<u16>()
+                .ok()
+                .filter(|number| *number <= LAST_LINE)
+                .ok_or_else(|| format!("Invalid line number at line {}: {text}", index + 1))?;
+            let body = text[digits..].trim_start_matches(' ');
+            let line = self
+                .tokenize_line(body)
+                .map_err(|e| format!("{e} in the line {number}"))?;
+            if line.len() > MAX_LINE_LENGTH {
+                return Err(format!("The line {number} is too long"));
+            }
+            lines.insert(number, line);
+        }
+        let mut program = Vec::new();
+        for (number, line) in lines {
+            let next = start as usize + program.len() + 4 + line.len() + 1;
+            program.extend((next as u16).to_be_bytes());
+            program.extend(number.to_be_bytes());
+            program.extend(line);
+            program.push(0);
+        }
+        program.extend([0, 0]);
+        Ok(program)
+    }
+
+    /// The listing of a tokenized program: each line is the address of the next one, the line
+    /// number, the tokens and 0. The program ends with a null address.
+    pub fn detokenize(&self, program: &[u8]) -> Result<String, String> {
+        let mut listing = String::new();
+        let mut rest = program;
+        loop {
+            let (number, body) = match rest {
+                [0, 0, ..] => return Ok(listing),
+                [_, _, high, low, body @ ..] => ((*high as int) << 8 | *low as int, body),
+                _ => return Err("The program is truncated".to_string()),
+            };
+            let end = body
+                .iter()
+                .position(|byte| *byte == 0)
+                .ok_or_else(|| format!("The line {number} is truncated"))?;
+            listing.push_str(&format!(
+                "{number} {}\n",
+                self.detokenize_line(&body[..end])
+            ));
+            rest = &body[end + 1..];
+        }
+    }
+}
+
+/// The first keyword of the table starting the text, with its token and its length
+fn find_keyword(table: &[Option<String>], text: &str) -> Option<(u8, usize)> {
+    table
+        .iter()
+        .zip(FIRST_TOKEN..)
+        .find_map(|(keyword, token)| {
+            let keyword = keyword.as_deref()?;
+            let start = text.get(..keyword.len())?;
+            start
+                .eq_ignore_ascii_case(keyword)
+                .then_some((token, keyword.len()))
+        })
+}
+
+fn push_char(text: &mut String, byte: u8) {
+    if (0x20..0x7F).contains(&byte) {
+        text.push(byte as char);
+    } else {
+        push_hex(text, byte);
+    }
+}
+
+fn push_hex(text: &mut String, byte: u8) {
+    text.push_str(&format!("{{{byte:02X}}}"));
+}
+
+/// The byte written in hexadecimal between braces at the start of the text
+fn parse_hex(text: &str) -> Option<u8> {
+    let hex = text.strip_prefix('{')?.get(..3)?.strip_suffix('}')?;
+    u8::from_str_radix(hex, 16).ok()
+}
+
+fn pointer(mem: &Memory, address: u16) -> int {
+    mem.peek(address as int) << 8 | mem.peek(address as int + 1)
+}
+
+fn set_pointer(mem: &mut Memory, address: u16, value: int) {
+    mem.set(address as int, value >> 8);
+    mem.set(address as int + 1, value & 0xFF);
+}
+
+/// The listing of the program in memory
+pub fn list_memory(mem: &Memory) -> Result<String, String> {
+    let (start, end) = (pointer(mem, PROGRAM_START), pointer(mem, PROGRAM_END));
+    if start >= end || end > 0xA000 {
+        return Err(format!(
+            "No BASIC program between {start:04X} and {end:04X}"
+        ));
+    }
+    let program: Vec<u8> = (start..end)
+        .map(|address| mem.peek(address) as u8)
+        .collect();
+    Keywords::mo5().detokenize(&program)
+}
+
+/// Replaces the program in memory by the listing, like LOAD the variables are cleared
+pub fn load_memory(mem: &mut Memory, listing: &str) -> Result<(), String> {
+    let start = pointer(mem, PROGRAM_START);
+    if start == 0 {
+        return Err("BASIC is not started".to_string());
+    }
+    let program = Keywords::mo5().tokenize(listing, start as u16)?;
+    let end = start + program.len() as int;
+    if end >= pointer(mem, STRINGS_START) {
+        return Err("The program does not fit in memory".to_string());
+    }
+    for (address, byte) in (start..).zip(program) {
+        mem.set(address, byte as int);
+    }
+    set_pointer(mem, PROGRAM_END, end);
+    set_pointer(mem, VARIABLES_END, end);
+    set_pointer(mem, STRINGS_FREE, pointer(mem, STRINGS_END));
+    Ok(())
+}
+
+/// A tape containing the listing saved as a tokenized BASIC file, the name is up to 8 characters
+pub fn tape(name: &str, listing: &str) -> Result<K7, String> {
+    let program = Keywords::mo5().tokenize(listing, DEFAULT_START)?;
+    let mut data = vec![0xFF];
+    data.extend((program.len() as u16).to_be_bytes());
+    data.extend(program);
+    let file = TapeFile {
+        name: format!("{}.BAS", name.to_uppercase()),
+        kind: FileKind::Basic,
+        ascii: false,
+        data,
+    };
+    K7::with_files(format!("{name}.k7"), &[file])
+}
+
+/// The listing of a BASIC file of a tape. The tokenized files are `FF`, the length of the
+/// program and the program.
+pub fn list_file(file: &TapeFile) -> Result<String, String> {
+    if file.kind != FileKind::Basic {
+        return Err(format!("{} is not a BASIC file", file.name));
+    }
+    if file.ascii {
+        return Ok(String::from_utf8_lossy(&file.data).replace('\r', "\n"));
+    }
+    match file.data.as_slice() {
+        [0xFF, high, low, program @ ..] => {
+            let length = ((*high as usize) << 8 | *low as usize).min(program.len());
+            Keywords::mo5().detokenize(&program[..length])
+        }
+        _ => Err(format!("{} is not a tokenized BASIC file", file.name)),
+    }
+}
+
+/// The listing of the BASIC file of the given name, or of the first one
+pub fn list_tape(k7: &K7, name: Option<&str>) -> Result<String, String> {
+    let files = k7.files()?;
+    let file = files
+        .iter()
+        .filter(|file| file.kind == FileKind::Basic)
+        .find(|file| name.is_none_or(|name| file.name.eq_ignore_ascii_case(name)))
+        .ok_or_else(|| match name {
+            Some(name) => format!("No BASIC file {name} on {}", k7.name()),
+            None => format!("No BASIC file on {}", k7.name()),
+        })?;
+    list_file(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hardware::machine::Machine;
+    use rstest::rstest;
+
+    // 10 PRINT 1 and 20 A=2 as saved by SAVE"AB"
+    const TAPE: [u8; 45] = [
+        0x01, 0x01, 0x3C, 0x5A, 0x00, 0x10, 0x41, 0x42, 0x20, 0x20, 0x20, 0x20, 0x20, 0x20, 0x42,
+        0x41, 0x53, 0x00, 0x00, 0x00, 0xE7, 0x01, 0x3C, 0x5A, 0x01, 0x17, 0xFF, 0x00, 0x12, 0x25,
+        0xAC, 0x00, 0x0A, 0xAB, 0x20, 0x31, 0x00, 0x25, 0xB4, 0x00, 0x14, 0x41, 0xD4, 0x32, 0x00,
+    ];
+
+    #[rstest]
+    #[case(&[0xAB, 0x20, 0x31], "PRINT 1")]
+    #[case(&[0x41, 0xD4, 0xFF, 0x82, 0x28, 0x31, 0x29, 0xC7, 0xFF, 0x9A], "A=ABS(1)+LEFT$")]
+    #[case(&[0x87, 0xBB, 0x20, 0x31, 0x30], "GOTO 10")]
+    #[case(&[0x89, 0x20, 0x41, 0x20, 0xC4, 0x20, 0x31, 0x20, 0x3A, 0x8F, 0x20, 0x32], "IF A THEN 1 ELSE 2")]
+    #[case(&[0xAB, 0x20, 0x31, 0x3A, 0x3A, 0x8D, 0x48, 0x3A, 0x58], "PRINT 1:'H:X")]
+    #[case(&[0x83, 0x20, 0x22, 0x3A, 0x22, 0x3A, 0x50, 0x52], "DATA \":\":PR")]
+    #[case(&[0x83, 0x20, 0x31, 0x3A, 0xAB, 0x20, 0x32], "DATA 1:PRINT 2")]
+    #[case(&[0x83, 0x20, 0x31, 0x3A, 0x3A, 0x8D, 0x8C], "DATA 1:'{8C}")]
+    #[case(&[0xAB, 0x22, 0x8C, 0x22, 0x8C], "PRINT\"{8C}\"REM")]
+    #[case(&[0x86, 0xFF, 0x92, 0x07], "{86}{FF}{92}{07}")]
+    fn test_line(#[case] line: &[u8], #[case] text: &str) {
+        let keywords = Keywords::mo5();
+        assert_eq!(text, keywords.detokenize_line(line));
+        assert_eq!(Ok(line.to_vec()), keywords.tokenize_line(text));
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let keywords = Keywords::mo5();
+        let program = keywords.tokenize("20 A=2\r\n\n10 print 1\n20  A=3\n", 0x25A4);
+        assert_eq!(
+            Ok(vec![
+                0x25, 0xAC, 0x00, 0x0A, 0xAB, 0x20, 0x31, 0x00, 0x25, 0xB4, 0x00, 0x14, 0x41, 0xD4,
+                0x33, 0x00, 0x00, 0x00
+            ]),
+            program
+        );
+        // DATA ends at a colon out of the strings, REM at the end of the line
+        assert_eq!(
+            Ok(vec![
+                0x83, 0x22, 0x3A, 0x22, 0x3A, 0xAB, 0x3A, 0x8C, 0x3A, 0x50, 0x52, 0x49, 0x4E, 0x54
+            ]),
+            keywords.tokenize_line("DATA\":\":PRINT:REM:PRINT")
+        );
+        assert!(keywords.tokenize("PRINT 1", 0x25A4).is_err());
+        assert!(keywords.tokenize("64000 PRINT 1", 0x25A4).is_err());
+        assert!(keywords.tokenize("10 PRINT \"é\"", 0x25A4).is_err());
+    }
+
+    #[test]
+    fn test_tape() {
+        let k7 = tape("ab", "10 PRINT 1\n20 A=2").unwrap();
+        // the same file as SAVE
+        let mut bytes = TAPE.to_vec();
+        bytes.extend([0x00, 0x00, 0xE4, 0x01, 0x3C, 0x5A, 0xFF, 0x02, 0x00]);
+        assert_eq!(K7::new("test.k7".to_string(), bytes).files(), k7.files());
+        assert_eq!(Ok("10 PRINT 1\n20 A=2\n".to_string()), list_tape(&k7, None));
+        assert!(tape("too_long_name", "10 PRINT 1").is_err());
+    }
+
+    #[test]
+    fn test_keywords() {
+        let keywords = Keywords::mo5();
+        assert_eq!(Some("END"), keywords.statement(0x80));
+        assert_eq!(Some("<"), keywords.statement(0xD5));
+        assert_eq!(None, keywords.statement(0xD6));
+        assert_eq!(Some("SGN"), keywords.function(0x80));
+        assert_eq!(Some("PTRIG"), keywords.function(0xA6));
+        assert_eq!(None, keywords.function(0xA7));
+        // unused
+        assert_eq!(None, keywords.function(0x92));
+    }
+
+    #[test]
+    fn test_list_tape() {
+        let mut bytes = TAPE.to_vec();
+        // the checksum and the end block
+        bytes.extend([0x00, 0x00, 0xE4, 0x01, 0x3C, 0x5A, 0xFF, 0x02, 0x00]);
+        let k7 = K7::new("test.k7".to_string(), bytes);
+        assert_eq!(Ok("10 PRINT 1\n20 A=2\n".to_string()), list_tape(&k7, None));
+        assert_eq!(
+            Ok("10 PRINT 1\n20 A=2\n".to_string()),
+            list_tape(&k7, Some("ab.bas"))
+        );
+        assert!(list_tape(&k7, Some("CD.BAS")).is_err());
+        let truncated = K7::new("test.k7".to_string(), TAPE.to_vec());
+        assert!(list_tape(&truncated, None).is_err());
+    }
+
+    #[test]
+    fn test_list_memory() {
+        let mut mem = Memory::default();
+        assert!(list_memory(&mem).is_err());
+        let program = [0x25, 0xAC, 0x00, 0x0A, 0xAB, 0x20, 0x31, 0x00, 0x00, 0x00];
+        for (address, byte) in (0x25A4..).zip(program) {
+            mem.set(address, byte);
+        }
+        mem.set(PROGRAM_START as int, 0x25);
+        mem.set(PROGRAM_START as int + 1, 0xA4);
+        mem.set(PROGRAM_END as int, 0x25);
+        mem.set(PROGRAM_END as int + 1, 0xAE);
+        assert_eq!(Ok("10 PRINT 1\n".to_string()), list_memory(&mem));
+    }
+
+    #[test]
+    fn test_load_memory() {
+        let mut machine = Machine::default();
+        assert!(load_memory(&mut machine.mem, "10 PRINT 1").is_err());
+        machine.run_frames(100);
+        load_memory(&mut machine.mem, "10 FOR I=1 TO 9\n20 NEXT").unwrap();
+        assert_eq!(
+            Ok("10 FOR I=1 TO 9\n20 NEXT\n".to_string()),
+            list_memory(&machine.mem)
+        );
+        assert_eq!(0x25A4, pointer(&machine.mem, PROGRAM_START));
+        assert_eq!(0x25BA, pointer(&machine.mem, PROGRAM_END));
+        assert_eq!(0x25BA, pointer(&machine.mem, VARIABLES_END));
+    }
+}
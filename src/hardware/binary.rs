@@ -0,0 +1,200 @@
+use crate::hardware::bus::Bus;
+use crate::hardware::k7::{FileKind, K7, TapeFile};
+use crate::hardware::memory::Memory;
+use crate::int;
+
+const SEGMENT: u8 = 0x00;
+const END: u8 = 0xFF;
+/// The I/O and the ROM follow the RAM
+const RAM_END: usize = 0xA000;
+
+/// Bytes loaded at an address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub address: u16,
+    pub data: Vec<u8>,
+}
+
+/// A machine code program, like the ones loaded by LOADM
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Binary {
+    pub segments: Vec<Segment>,
+    /// The address the program starts at
+    pub exec: Option<u16>,
+}
+
+/// The registers set to run a loaded program
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    /// The start address of the program by default
+    pub pc: Option<u16>,
+    /// Unchanged by default
+    pub s: Option<u16>,
+}
+
+impl Binary {
+    /// A raw binary, it starts at the address it is loaded at
+    pub fn raw(data: Vec<u8>, address: u16) -> Self {
+        Self {
+            segments: vec![Segment { address, data }],
+            exec: Some(address),
+        }
+    }
+
+    /// Reads a Thomson BIN file. Each segment is `00`, its length, its address and its data,
+    /// the file ends with `FF 00 00` and the start address.
+    pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+        let mut segments = Vec::new();
+        let mut rest = bytes;
+        loop {
+            match rest {
+                [SEGMENT, length_high, length_low, high, low, data @ ..] => {
+                    let length = u16::from_be_bytes([*length_high, *length_low]) as usize;
+                    let address = u16::from_be_bytes([*high, *low]);
+                    let data = data
+                        .get(..length)
+                        .ok_or_else(|| format!("The segment at {address:04X} is truncated"))?;
+                    segments.push(Segment {
+                        address,
+                        data: data.to_vec(),
+                    });
+                    rest = &rest[5 + length..];
+                }
+                [END, 0x00, 0x00, high, low, ..] => {
+                    return Ok(Self {
+                        segments,
+                        exec: Some(u16::from_be_bytes([*high, *low])),
+                    });
+                }
+                [] => return Err("The binary file has no end record".to_string()),
+                _ => {
+                    let offset = bytes.len() - rest.len();
+                    return Err(format!("Invalid binary file record at {offset:04X}"));
+                }
+            }
+        }
+    }
+
+    /// The Thomson BIN file of the program
+    pub fn to_bin(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for segment in &self.segments {
+            bytes.push(SEGMENT);
+            bytes.extend((segment.data.len() as u16).to_be_bytes());
+            bytes.extend(segment.address.to_be_bytes());
+            bytes.extend(&segment.data);
+        }
+        bytes.extend([END, 0x00, 0x00]);
+        bytes.extend(self.exec.unwrap_or_default().to_be_bytes());
+        bytes
+    }
+
+    /// Writes the segments in memory, they must all fit in RAM
+    pub fn load(&self, mem: &mut Memory) -> Result<(), String> {
+        if let Some(segment) = self
+            .segments
+            .iter()
+            .find(|segment| segment.address as usize + segment.data.len() > RAM_END)
+        {
+            return Err(format!(
+                "The segment at {:04X} does not fit in RAM",
+                segment.address
+            ));
+        }
+        for segment in &self.segments {
+            for (address, byte) in (segment.address..).zip(&segment.data) {
+                mem.write(address, *byte);
+            }
+        }
+        Ok(())
+    }
+
+    /// A tape containing the program as a binary file, the name is up to 8 characters
+    pub fn tape(&self, name: &str) -> Result<K7, String> {
+        let file = TapeFile {
+            name: format!("{}.BIN", name.to_uppercase()),
+            kind: FileKind::Binary,
+            ascii: false,
+            data: self.to_bin(),
+        };
+        K7::with_files(format!("{name}.k7"), &[file])
+    }
+}
+
+/// Reads a hexadecimal address like `F003` or `$F003`, the load addresses of the binaries and
+/// the addresses of the trace are given this way
+pub fn parse_address(text: &str) -> Result<int, String> {
+    int::from_str_radix(text.trim().trim_start_matches('$'), 16)
+        .ok()
+        .filter(|address| (0..=0xFFFF).contains(address))
+        .ok_or_else(|| format!("Invalid address {text}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // SAVEM"A",&H9000,&H9003,&H9001
+    const BIN: [u8; 14] = [
+        0x00, 0x00, 0x04, 0x90, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF, 0x00, 0x00, 0x90, 0x01,
+    ];
+
+    #[test]
+    fn test_parse() {
+        let binary = Binary::parse(&BIN).unwrap();
+        assert_eq!(
+            vec![Segment {
+                address: 0x9000,
+                data: vec![0; 4]
+            }],
+            binary.segments
+        );
+        assert_eq!(Some(0x9001), binary.exec);
+        assert_eq!(BIN.to_vec(), binary.to_bin());
+        assert!(Binary::parse(&BIN[..12]).is_err());
+        assert!(Binary::parse(&BIN[..4]).is_err());
+        assert!(Binary::parse(&[0x01]).is_err());
+    }
+
+    #[test]
+    fn test_load() {
+        let mut mem = Memory::default();
+        mem.reset();
+        let binary = Binary {
+            segments: vec![
+                Segment {
+                    address: 0x6000,
+                    data: vec![0x12, 0x34],
+                },
+                Segment {
+                    address: 0x9FFF,
+                    data: vec![0x56],
+                },
+            ],
+            exec: None,
+        };
+        binary.load(&mut mem).unwrap();
+        assert_eq!(0x12, mem.peek(0x6000));
+        assert_eq!(0x34, mem.peek(0x6001));
+        assert_eq!(0x56, mem.peek(0x9FFF));
+        let rom = Binary::raw(vec![0, 0], 0x9FFF);
+        assert!(rom.load(&mut mem).is_err());
+        assert_eq!(0x56, mem.peek(0x9FFF));
+    }
+
+    #[test]
+    fn test_tape() {
+        let k7 = Binary::parse(&BIN).unwrap().tape("a").unwrap();
+        let files = k7.files().unwrap();
+        assert_eq!("A.BIN", files[0].name);
+        assert_eq!(FileKind::Binary, files[0].kind);
+        assert_eq!(BIN.to_vec(), files[0].data);
+    }
+
+    #[test]
+    fn test_parse_address() {
+        assert_eq!(Ok(0xF003), parse_address("$F003"));
+        assert_eq!(Ok(0x6000), parse_address(" 6000"));
+        assert!(parse_address("10000").is_err());
+    }
+}
@@ -0,0 +1,34 @@
+use crate::hardware::M6809::Registers;
+use crate::hardware::interrupts::InterruptLines;
+use crate::int;
+
+/// What the 6809 sees of the machine: the memory and the devices of its address space, the
+/// interrupt lines and the trap of the emulator. The MO5 one is
+/// [`Memory`](crate::hardware::memory::Memory).
+pub trait Bus {
+    /// Reads a byte, with the side effects of the devices
+    fn read(&self, address: u16) -> u8;
+
+    fn write(&mut self, address: u16, value: u8);
+
+    /// Reads a big endian word, the address wraps at the end of the address space
+    fn read_16(&self, address: u16) -> u16 {
+        u16::from_be_bytes([self.read(address), self.read(address.wrapping_add(1))])
+    }
+
+    fn write_16(&mut self, address: u16, value: u16) {
+        let [high, low] = value.to_be_bytes();
+        self.write(address, high);
+        self.write(address.wrapping_add(1), low);
+    }
+
+    /// Runs the $02 opcode, which the emulator patches in the ROM to emulate a device. The
+    /// registers are the ones after the opcode, the changes are kept by the CPU.
+    fn trap(&mut self, _registers: &mut Registers) {}
+
+    /// Called before each instruction with the cycles already run in the current period
+    fn tick(&mut self, _clock: int) {}
+
+    /// The lines sampled by the CPU between two instructions
+    fn interrupts(&self) -> &InterruptLines;
+}
@@ -0,0 +1,185 @@
+//! Runs the test vectors of `conformance.ron` on the 6809, one instruction per vector,
+//! and checks the registers, the memory written and the cycles.
+
+use crate::hardware::M6809::{M6809, Registers};
+use crate::hardware::bus::Bus;
+use crate::hardware::disassembler::decode;
+use crate::hardware::memory::Memory;
+use crate::int;
+use serde::Deserialize;
+use std::collections::BTreeSet;
+
+const VECTORS: &str = include_str!("conformance.ron");
+const START: u16 = 0x6000;
+const STACK: u16 = 0x7F00;
+
+/// The opcodes which are not in the Motorola documentation, the emulator trap $02 and the
+/// page prefixes
+const UNDOCUMENTED: [int; 31] = [
+    0x01, 0x02, 0x05, 0x0B, 0x1B, 0x38, 0x3E, 0x41, 0x42, 0x45, 0x4B, 0x4E, 0x51, 0x52, 0x55, 0x5B,
+    0x5E, 0x61, 0x62, 0x65, 0x6B, 0x71, 0x72, 0x75, 0x7B, 0x87, 0x8F, 0xC7, 0xCF, 0x10, 0x11,
+];
+
+/// Registers of a vector, the missing ones are not changed
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct State {
+    a: Option<u8>,
+    b: Option<u8>,
+    dp: Option<u8>,
+    cc: Option<u8>,
+    x: Option<u16>,
+    y: Option<u16>,
+    u: Option<u16>,
+    s: Option<u16>,
+    pc: Option<u16>,
+}
+
+impl State {
+    fn apply(&self, registers: &mut Registers) {
+        registers.a = self.a.unwrap_or(registers.a);
+        registers.b = self.b.unwrap_or(registers.b);
+        registers.dp = self.dp.unwrap_or(registers.dp);
+        registers.cc = self.cc.unwrap_or(registers.cc);
+        registers.x = self.x.unwrap_or(registers.x);
+        registers.y = self.y.unwrap_or(registers.y);
+        registers.u = self.u.unwrap_or(registers.u);
+        registers.s = self.s.unwrap_or(registers.s);
+        registers.pc = self.pc.unwrap_or(registers.pc);
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Vector {
+    name: String,
+    code: Vec<u8>,
+    #[serde(default)]
+    initial: State,
+    /// Blocks of bytes written before the instruction
+    #[serde(default)]
+    memory: Vec<(u16, Vec<u8>)>,
+    #[serde(default)]
+    expected: State,
+    /// Blocks of bytes expected after the instruction
+    #[serde(default)]
+    written: Vec<(u16, Vec<u8>)>,
+    /// The flags of CC which are not compared
+    #[serde(default)]
+    undefined: u8,
+    cycles: int,
+}
+
+impl Vector {
+    /// Runs the instruction, returns the differences with the expected state
+    fn run(&self) -> Vec<String> {
+        let mut mem = Memory::default();
+        mem.reset();
+        for (address, bytes) in &self.memory {
+            write(&mut mem, *address, bytes);
+        }
+        write(&mut mem, START, &self.code);
+        let mut initial = Registers {
+            s: STACK,
+            pc: START,
+            ..Registers::default()
+        };
+        self.initial.apply(&mut initial);
+        let mut cpu = M6809::new(&mem);
+        cpu.set_registers(&initial);
+
+        // a period of one cycle runs a single instruction
+        let late = cpu.FetchUntil(1, &mut mem);
+        let mut errors = Vec::new();
+        let cycles = late.map_or(0, |late| late + 1);
+        if cycles != self.cycles {
+            errors.push(format!("{cycles} cycles instead of {}", self.cycles));
+        }
+        let mut expected = Registers {
+            pc: START + self.code.len() as u16,
+            ..initial
+        };
+        self.expected.apply(&mut expected);
+        let mut actual = cpu.registers();
+        actual.cc &= !self.undefined;
+        expected.cc &= !self.undefined;
+        if actual != expected {
+            errors.push(format!("{actual:X?} instead of {expected:X?}"));
+        }
+        for (address, bytes) in &self.written {
+            for (i, byte) in bytes.iter().enumerate() {
+                let address = *address as int + i as int;
+                let value = mem.peek(address);
+                if value != *byte as int {
+                    errors.push(format!(
+                        "{value:02X} at {address:04X} instead of {byte:02X}"
+                    ));
+                }
+            }
+        }
+        errors
+    }
+
+    /// The page and the opcode of the instruction
+    fn opcode(&self) -> (int, int) {
+        match self.code[..] {
+            [page @ (0x10 | 0x11), opcode, ..] => (page as int, opcode as int),
+            [opcode, ..] => (0, opcode as int),
+            [] => panic!("{} has no code", self.name),
+        }
+    }
+}
+
+fn write(mem: &mut Memory, address: u16, bytes: &[u8]) {
+    for (i, byte) in bytes.iter().enumerate() {
+        mem.write(address.wrapping_add(i as u16), *byte);
+    }
+}
+
+fn vectors() -> Vec<Vector> {
+    ron::from_str(VECTORS).expect("invalid conformance vectors")
+}
+
+#[test]
+fn test_conformance() {
+    let failures: Vec<String> = vectors()
+        .iter()
+        .flat_map(|vector| {
+            vector
+                .run()
+                .into_iter()
+                .map(|error| format!("{}: {error}", vector.name))
+        })
+        .collect();
+    assert!(
+        failures.is_empty(),
+        "{} failures:\n{}",
+        failures.len(),
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn test_every_documented_opcode() {
+    let tested: BTreeSet<(int, int)> = vectors().iter().map(Vector::opcode).collect();
+    let mut mem = Memory::default();
+    let mut missing = Vec::new();
+    for page in [0x00, 0x10, 0x11] {
+        for opcode in 0..=0xFF {
+            if page == 0 && UNDOCUMENTED.contains(&opcode) {
+                continue;
+            }
+            let code = if page == 0 {
+                [opcode as u8, 0x00]
+            } else {
+                [page as u8, opcode as u8]
+            };
+            write(&mut mem, START, &code);
+            let instruction = decode(START as int, &mem);
+            let documented = instruction.mnemonic != "ILL" && instruction.mnemonic != "FCB";
+            if documented && !tested.contains(&(page, opcode)) {
+                missing.push(format!("{page:02X} {opcode:02X} {}", instruction.mnemonic));
+            }
+        }
+    }
+    assert!(missing.is_empty(), "No vector for {}", missing.join(", "));
+}
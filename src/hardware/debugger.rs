@@ -0,0 +1,337 @@
+use crate::hardware::M6809::IllegalOpcode;
+use crate::hardware::disassembler::length;
+use crate::hardware::memory::{Bank, Memory};
+use crate::int;
+use std::collections::BTreeSet;
+
+const RTS: int = 0x39;
+const RTI: int = 0x3B;
+
+/// What the debugger waits for before stopping the CPU
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    /// The next instruction
+    Into,
+    /// The PC reaching the address with the stack at this level or above. This steps over the
+    /// subroutine calls and runs to the cursor.
+    To { address: int, stack: int },
+    /// A RTS or RTI leaving the subroutine which was running with this stack
+    Out { stack: int },
+}
+
+/// The accesses a watchpoint is waiting for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    /// Read or write
+    Any,
+}
+
+impl Access {
+    pub const ALL: [Self; 3] = [Self::Read, Self::Write, Self::Any];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Read => "Read",
+            Self::Write => "Write",
+            Self::Any => "Access",
+        }
+    }
+}
+
+/// Stops the CPU after an instruction accessing the addresses from `start` to `end`
+/// included. The hardware registers of the page A7xx are watched like the memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub start: int,
+    pub end: int,
+    pub access: Access,
+    /// Only this value read or written
+    pub value: Option<int>,
+    /// Only when the address is mapped to this bank, like the POINT or COLOR video banks
+    pub bank: Option<Bank>,
+}
+
+impl Watchpoint {
+    pub(crate) fn matches(&self, address: int, value: int, write: bool, bank: Bank) -> bool {
+        (self.start..=self.end).contains(&address)
+            && match self.access {
+                Access::Read => !write,
+                Access::Write => write,
+                Access::Any => true,
+            }
+            && self.value.is_none_or(|v| v == value)
+            && self.bank.is_none_or(|b| b == bank)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchHit {
+    pub address: int,
+    pub value: int,
+    pub write: bool,
+}
+
+/// Why the debugger stopped the CPU
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    Breakpoint(int),
+    Step,
+    Watchpoint(WatchHit),
+    IllegalOpcode(IllegalOpcode),
+}
+
+/// Stops the CPU before an instruction, it is checked by [`crate::hardware::M6809::M6809`]
+/// only when there is a breakpoint or a step to wait for.
+#[derive(Debug, Default)]
+pub struct Debugger {
+    breakpoints: BTreeSet<int>,
+    step: Option<Step>,
+    // the instruction the CPU stopped before is run when resuming, even on a breakpoint
+    resuming: bool,
+    // the last instruction was a RTS or RTI
+    returning: bool,
+    last_stop: Option<Stop>,
+    // the illegal opcode the CPU stopped before is ignored when resuming
+    illegal_resume: Option<int>,
+}
+
+impl Debugger {
+    pub(crate) fn is_active(&self) -> bool {
+        self.step.is_some() || !self.breakpoints.is_empty()
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = int> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    pub fn has_breakpoint(&self, address: int) -> bool {
+        self.breakpoints.contains(&address)
+    }
+
+    pub fn toggle_breakpoint(&mut self, address: int) {
+        let address = address & 0xFFFF;
+        if !self.breakpoints.remove(&address) {
+            self.breakpoints.insert(address);
+        }
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    pub const fn last_stop(&self) -> Option<Stop> {
+        self.last_stop
+    }
+
+    /// Called when the CPU starts again after a stop
+    pub(crate) fn resume(&mut self) {
+        self.resuming = self.is_active();
+        self.illegal_resume = match self.last_stop {
+            Some(Stop::IllegalOpcode(illegal)) => Some(illegal.address),
+            _ => None,
+        };
+        self.last_stop = None;
+    }
+
+    /// The CPU stops before the illegal opcode, the step in progress is cancelled
+    pub(crate) fn illegal_opcode(&mut self, illegal: IllegalOpcode) {
+        self.step = None;
+        self.returning = false;
+        self.last_stop = Some(Stop::IllegalOpcode(illegal));
+    }
+
+    /// Returns true if the CPU resumes on the illegal opcode at `address`
+    pub(crate) fn take_illegal_resume(&mut self, address: int) -> bool {
+        self.illegal_resume.take() == Some(address)
+    }
+
+    /// The CPU stops after the instruction which hit the watchpoint, the step in progress is
+    /// cancelled
+    pub(crate) fn watchpoint_hit(&mut self, hit: WatchHit) {
+        self.step = None;
+        self.returning = false;
+        self.last_stop = Some(Stop::Watchpoint(hit));
+    }
+
+    pub(crate) fn step_into(&mut self) {
+        self.step = Some(Step::Into);
+    }
+
+    /// A subroutine call is run until it returns, any other instruction is stepped into
+    pub(crate) fn step_over(&mut self, pc: int, stack: int, mem: &Memory) {
+        self.step = Some(match call_length(pc, mem) {
+            Some(length) => Step::To {
+                address: (pc + length) & 0xFFFF,
+                stack,
+            },
+            None => Step::Into,
+        });
+    }
+
+    pub(crate) fn step_out(&mut self, stack: int) {
+        self.step = Some(Step::Out { stack });
+    }
+
+    pub(crate) fn run_to(&mut self, address: int) {
+        self.step = Some(Step::To {
+            address: address & 0xFFFF,
+            stack: 0,
+        });
+    }
+
+    /// Cancels the step in progress, the breakpoints are kept
+    pub(crate) fn cancel_step(&mut self) {
+        self.step = None;
+    }
+
+    /// Returns true if the CPU must stop before running the instruction at `pc`
+    pub(crate) fn should_stop(&mut self, pc: int, stack: int, mem: &Memory) -> bool {
+        let stop = if self.resuming {
+            self.resuming = false;
+            None
+        } else if self.breakpoints.contains(&pc) {
+            Some(Stop::Breakpoint(pc))
+        } else {
+            let done = match self.step {
+                None => false,
+                Some(Step::Into) => true,
+                Some(Step::To {
+                    address,
+                    stack: level,
+                }) => pc == address && stack >= level,
+                Some(Step::Out { stack: level }) => self.returning && stack > level,
+            };
+            done.then_some(Stop::Step)
+        };
+        if stop.is_some() {
+            self.step = None;
+            self.returning = false;
+            self.last_stop = stop;
+            return true;
+        } else if let Some(Step::Out { .. }) = self.step {
+            self.returning = matches!(mem.peek(pc), RTS | RTI);
+        }
+        false
+    }
+}
+
+/// Returns the length of the instruction at `pc` if it is a subroutine call
+pub(crate) fn call_length(pc: int, mem: &Memory) -> Option<int> {
+    match mem.peek(pc) {
+        // BSR, JSR direct
+        0x8D | 0x9D => Some(2),
+        // LBSR, JSR extended
+        0x17 | 0xBD => Some(3),
+        // JSR indexed
+        0xAD => Some(length(pc, mem)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hardware::bus::Bus;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(&[0x8D, 0x10], Some(2))]
+    #[case(&[0x17, 0x01, 0x00], Some(3))]
+    #[case(&[0xBD, 0x60, 0x00], Some(3))]
+    #[case(&[0xAD, 0x84], Some(2))]
+    #[case(&[0xAD, 0x88, 0x10], Some(3))]
+    #[case(&[0xAD, 0x9F, 0x60, 0x00], Some(4))]
+    #[case(&[0x86, 0x01], None)]
+    fn test_call_length(#[case] code: &[u8], #[case] expected: Option<int>) {
+        let mut mem = Memory::default();
+        for (i, byte) in code.iter().enumerate() {
+            mem.write(0x6000 + i as u16, *byte);
+        }
+        assert_eq!(expected, call_length(0x6000, &mem));
+    }
+
+    #[test]
+    fn test_resume_from_breakpoint() {
+        let mem = Memory::default();
+        let mut debugger = Debugger::default();
+        assert!(!debugger.is_active());
+        debugger.toggle_breakpoint(0x6000);
+        assert!(debugger.should_stop(0x6000, 0x8000, &mem));
+        debugger.resume();
+        assert!(!debugger.should_stop(0x6000, 0x8000, &mem));
+        assert!(!debugger.should_stop(0x6002, 0x8000, &mem));
+        assert!(debugger.should_stop(0x6000, 0x8000, &mem));
+        debugger.toggle_breakpoint(0x6000);
+        assert!(!debugger.is_active());
+    }
+
+    #[test]
+    fn test_watchpoints() {
+        let mut mem = Memory::default();
+        mem.reset();
+        mem.add_watchpoint(Watchpoint {
+            start: 0x6000,
+            end: 0x60FF,
+            access: Access::Write,
+            value: Some(0x42),
+            bank: None,
+        });
+        mem.write(0x6010, 0x41);
+        assert_eq!(None, mem.take_watch_hit());
+        mem.read(0x6010);
+        assert_eq!(None, mem.take_watch_hit());
+        mem.write(0x6010, 0x42);
+        let hit = WatchHit {
+            address: 0x6010,
+            value: 0x42,
+            write: true,
+        };
+        assert_eq!(Some(hit), mem.take_watch_hit());
+        assert_eq!(None, mem.take_watch_hit());
+        mem.remove_watchpoint(0);
+        assert!(!mem.is_watching());
+    }
+
+    #[test]
+    fn test_watchpoint_on_hardware() {
+        let mut mem = Memory::default();
+        mem.reset();
+        mem.add_watchpoint(Watchpoint {
+            start: 0xA7C0,
+            end: 0xA7C3,
+            access: Access::Any,
+            value: None,
+            bank: None,
+        });
+        mem.peek(0xA7C1);
+        assert_eq!(None, mem.take_watch_hit());
+        mem.read(0xA7C1);
+        assert!(mem.take_watch_hit().is_some_and(|hit| !hit.write));
+        mem.write(0xA7C2, 0x04);
+        assert!(mem.take_watch_hit().is_some_and(|hit| hit.write));
+    }
+
+    #[test]
+    fn test_watchpoint_on_video_bank() {
+        let mut mem = Memory::default();
+        mem.reset();
+        mem.add_watchpoint(Watchpoint {
+            start: 0x0000,
+            end: 0x1F3F,
+            access: Access::Write,
+            value: None,
+            bank: Some(Bank::Color),
+        });
+        // bit 0 of the PIA port A selects the POINT bank
+        mem.write(0xA7C2, 0x04);
+        mem.write(0xA7C0, 0x01);
+        mem.write(0x0100, 0xFF);
+        assert_eq!(None, mem.take_watch_hit());
+        mem.write(0xA7C0, 0x00);
+        assert_eq!(Bank::Color, mem.bank_of(0x0100));
+        mem.write(0x0100, 0xFF);
+        assert!(mem.take_watch_hit().is_some());
+    }
+}
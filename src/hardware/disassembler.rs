@@ -0,0 +1,642 @@
+use crate::hardware::memory::Memory;
+use crate::hardware::symbols::Symbols;
+use crate::int;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Inherent,
+    Immediate8,
+    Immediate16,
+    Direct,
+    Extended,
+    Indexed,
+    Relative8,
+    Relative16,
+    /// The registers of TFR and EXG
+    Registers,
+    /// The registers pushed on or pulled from S
+    StackS,
+    /// The registers pushed on or pulled from U
+    StackU,
+}
+
+/// A decoded instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub address: int,
+    pub bytes: Vec<u8>,
+    pub mnemonic: &'static str,
+    pub operands: String,
+    /// Cycles with the extra ones of the indexed mode and of the stacked registers, the
+    /// taken long branches need one more
+    pub cycles: int,
+    /// The address the instruction may jump to, when it is known without running it
+    pub target: Option<int>,
+    // the address written in the operands, replaced by its label if there is one
+    reference: Option<int>,
+}
+
+impl Instruction {
+    pub fn len(&self) -> int {
+        self.bytes.len() as int
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn next_address(&self) -> int {
+        (self.address + self.len()) & 0xFFFF
+    }
+
+    /// The operands with the address replaced by its label
+    pub fn operands_with(&self, symbols: &Symbols) -> String {
+        match self.reference.and_then(|address| symbols.get(address)) {
+            Some(label) => {
+                let address = format!("${:04X}", self.reference.unwrap_or_default());
+                self.operands.replacen(&address, label, 1)
+            }
+            None => self.operands.clone(),
+        }
+    }
+
+    /// Formats the instruction like `F003 8E 20 00    LDX   #$2000`
+    pub fn to_string_with(&self, symbols: &Symbols) -> String {
+        let bytes: Vec<String> = self.bytes.iter().map(|b| format!("{b:02X}")).collect();
+        format!(
+            "{:04X} {:<14} {:<5} {}",
+            self.address,
+            bytes.join(" "),
+            self.mnemonic,
+            self.operands_with(symbols)
+        )
+        .trim_end()
+        .to_string()
+    }
+}
+
+/// Decodes `count` instructions from `start`, the memory is read without side effect. The
+/// bytes the code/data log knows as data are shown as FCB.
+pub fn disassemble(start: int, count: usize, mem: &Memory) -> Vec<Instruction> {
+    let mut address = start & 0xFFFF;
+    let mut instructions = Vec::with_capacity(count);
+    for _ in 0..count {
+        let instruction = match mem.code_data_log() {
+            Some(log) if log.is_data(address) => data_byte(address, mem),
+            _ => decode(address, mem),
+        };
+        address = instruction.next_address();
+        instructions.push(instruction);
+    }
+    instructions
+}
+
+/// Decodes the instruction at the given address
+pub fn decode(address: int, mem: &Memory) -> Instruction {
+    let mut reader = Reader {
+        mem,
+        address,
+        bytes: Vec::with_capacity(5),
+    };
+    let page = page(address, mem);
+    if page != 0 {
+        reader.byte();
+    }
+    let opcode = reader.byte();
+    if opcode == 0x10 || opcode == 0x11 {
+        // a prefix ignored by the CPU
+        return Instruction {
+            cycles: 1,
+            ..data_byte(address & 0xFFFF, mem)
+        };
+    }
+    let (mnemonic, mode, mut cycles) =
+        opcode_info(page, opcode).unwrap_or(("ILL", Mode::Inherent, 0));
+    let mut reference = None;
+    let mut target = None;
+    let operands = match mode {
+        Mode::Inherent => String::new(),
+        Mode::Immediate8 => format!("#${:02X}", reader.byte()),
+        Mode::Immediate16 => format!("#${:04X}", reader.word()),
+        Mode::Direct => format!("<${:02X}", reader.byte()),
+        Mode::Extended => {
+            let address = reader.word();
+            reference = Some(address);
+            if is_jump(mnemonic) {
+                target = Some(address);
+            }
+            format!("${address:04X}")
+        }
+        Mode::Indexed => {
+            let (operands, extra, indirect) = indexed(&mut reader);
+            cycles += extra;
+            reference = indirect;
+            operands
+        }
+        Mode::Relative8 => {
+            let offset = reader.byte() as i8 as int;
+            let address = (reader.address + offset) & 0xFFFF;
+            reference = Some(address);
+            target = Some(address);
+            format!("${address:04X}")
+        }
+        Mode::Relative16 => {
+            let offset = reader.word() as i16 as int;
+            let address = (reader.address + offset) & 0xFFFF;
+            reference = Some(address);
+            target = Some(address);
+            format!("${address:04X}")
+        }
+        Mode::Registers => {
+            let postbyte = reader.byte();
+            format!(
+                "{},{}",
+                transfer_register(postbyte >> 4),
+                transfer_register(postbyte & 0x0F)
+            )
+        }
+        Mode::StackS | Mode::StackU => {
+            let postbyte = reader.byte();
+            let other = if mode == Mode::StackS { "U" } else { "S" };
+            let mut registers = Vec::new();
+            for (bit, name, size) in [
+                (0x01, "CC", 1),
+                (0x02, "A", 1),
+                (0x04, "B", 1),
+                (0x08, "DP", 1),
+                (0x10, "X", 2),
+                (0x20, "Y", 2),
+                (0x40, other, 2),
+                (0x80, "PC", 2),
+            ] {
+                if postbyte & bit != 0 {
+                    registers.push(name);
+                    cycles += size;
+                }
+            }
+            registers.join(",")
+        }
+    };
+    Instruction {
+        address: address & 0xFFFF,
+        bytes: reader.bytes,
+        mnemonic,
+        operands,
+        cycles,
+        target,
+        reference,
+    }
+}
+
+fn data_byte(address: int, mem: &Memory) -> Instruction {
+    let value = mem.peek(address);
+    Instruction {
+        address,
+        bytes: vec![value as u8],
+        mnemonic: "FCB",
+        operands: format!("${value:02X}"),
+        cycles: 0,
+        target: None,
+        reference: None,
+    }
+}
+
+/// Returns the number of bytes of the instruction at the given address, without decoding
+/// its operands
+pub fn length(address: int, mem: &Memory) -> int {
+    let page = page(address, mem);
+    let length = if page == 0 { 1 } else { 2 };
+    let opcode = mem.peek(address + length - 1);
+    let mode = opcode_info(page, opcode).map_or(Mode::Inherent, |(_, mode, _)| mode);
+    length
+        + match mode {
+            Mode::Inherent => 0,
+            Mode::Immediate8
+            | Mode::Direct
+            | Mode::Relative8
+            | Mode::Registers
+            | Mode::StackS
+            | Mode::StackU => 1,
+            Mode::Immediate16 | Mode::Extended | Mode::Relative16 => 2,
+            Mode::Indexed => 1 + index_length(mem.peek(address + length)),
+        }
+}
+
+/// Returns the page of the instruction at the given address, the CPU ignores a prefix
+/// followed by an opcode which is not in its page
+fn page(address: int, mem: &Memory) -> int {
+    let opcode = mem.peek(address);
+    if (opcode == 0x10 || opcode == 0x11) && opcode_info(opcode, mem.peek(address + 1)).is_some() {
+        opcode
+    } else {
+        0
+    }
+}
+
+/// Returns the number of bytes following the postbyte of an indexed address
+const fn index_length(postbyte: int) -> int {
+    if postbyte & 0x80 == 0 {
+        return 0;
+    }
+    match postbyte & 0x0F {
+        0x08 | 0x0C => 1,
+        0x09 | 0x0D | 0x0F => 2,
+        _ => 0,
+    }
+}
+
+struct Reader<'a> {
+    mem: &'a Memory,
+    address: int,
+    bytes: Vec<u8>,
+}
+
+impl Reader<'_> {
+    fn byte(&mut self) -> int {
+        let value = self.mem.peek(self.address);
+        self.address = (self.address + 1) & 0xFFFF;
+        self.bytes.push(value as u8);
+        value
+    }
+
+    fn word(&mut self) -> int {
+        (self.byte() << 8) | self.byte()
+    }
+}
+
+const fn is_jump(mnemonic: &str) -> bool {
+    matches!(mnemonic.as_bytes(), b"JMP" | b"JSR")
+}
+
+/// Returns the operands of an indexed address, the extra cycles and the address of the
+/// extended indirect mode
+fn indexed(reader: &mut Reader) -> (String, int, Option<int>) {
+    let postbyte = reader.byte();
+    let register = ["X", "Y", "U", "S"][((postbyte >> 5) & 3) as usize];
+    if postbyte & 0x80 == 0 {
+        let offset = ((postbyte & 0x1F) as i8) << 3 >> 3;
+        return (format!("{},{register}", signed(offset as int)), 1, None);
+    }
+    let (operands, extra) = match postbyte & 0x0F {
+        0x00 => (format!(",{register}+"), 2),
+        0x01 => (format!(",{register}++"), 3),
+        0x02 => (format!(",-{register}"), 2),
+        0x03 => (format!(",--{register}"), 3),
+        0x04 => (format!(",{register}"), 0),
+        0x05 => (format!("B,{register}"), 1),
+        0x06 => (format!("A,{register}"), 1),
+        0x08 => (
+            format!("{},{register}", signed(reader.byte() as i8 as int)),
+            1,
+        ),
+        0x09 => (
+            format!("{},{register}", signed(reader.word() as i16 as int)),
+            4,
+        ),
+        0x0B => (format!("D,{register}"), 4),
+        0x0C => (format!("{},PCR", signed(reader.byte() as i8 as int)), 1),
+        0x0D => (format!("{},PCR", signed(reader.word() as i16 as int)), 5),
+        0x0F => {
+            let address = reader.word();
+            return (format!("[${address:04X}]"), 5, Some(address));
+        }
+        _ => return ("?".to_string(), 0, None),
+    };
+    if postbyte & 0x10 != 0 {
+        (format!("[{operands}]"), extra + 3, None)
+    } else {
+        (operands, extra, None)
+    }
+}
+
+fn signed(value: int) -> String {
+    if value < 0 {
+        format!("-${:02X}", -value)
+    } else {
+        format!("${value:02X}")
+    }
+}
+
+const fn transfer_register(code: int) -> &'static str {
+    match code {
+        0x0 => "D",
+        0x1 => "X",
+        0x2 => "Y",
+        0x3 => "U",
+        0x4 => "S",
+        0x5 => "PC",
+        0x8 => "A",
+        0x9 => "B",
+        0xA => "CC",
+        0xB => "DP",
+        _ => "?",
+    }
+}
+
+// the read-modify-write instructions of the rows 0, 4, 5, 6 and 7, with the undocumented
+// ones: NEG or COM depending on C, and the aliases of NEG, LSR, DEC and CLR
+const MEMORY_OPS: [Option<&str>; 16] = [
+    Some("NEG"),
+    Some("NEG"),
+    Some("XNC"),
+    Some("COM"),
+    Some("LSR"),
+    Some("LSR"),
+    Some("ROR"),
+    Some("ASR"),
+    Some("ASL"),
+    Some("ROL"),
+    Some("DEC"),
+    Some("DEC"),
+    Some("INC"),
+    Some("TST"),
+    Some("JMP"),
+    Some("CLR"),
+];
+const A_OPS: [Option<&str>; 16] = [
+    Some("NEGA"),
+    Some("NEGA"),
+    Some("XNCA"),
+    Some("COMA"),
+    Some("LSRA"),
+    Some("LSRA"),
+    Some("RORA"),
+    Some("ASRA"),
+    Some("ASLA"),
+    Some("ROLA"),
+    Some("DECA"),
+    Some("DECA"),
+    Some("INCA"),
+    Some("TSTA"),
+    Some("CLRA"),
+    Some("CLRA"),
+];
+const B_OPS: [Option<&str>; 16] = [
+    Some("NEGB"),
+    Some("NEGB"),
+    Some("XNCB"),
+    Some("COMB"),
+    Some("LSRB"),
+    Some("LSRB"),
+    Some("RORB"),
+    Some("ASRB"),
+    Some("ASLB"),
+    Some("ROLB"),
+    Some("DECB"),
+    Some("DECB"),
+    Some("INCB"),
+    Some("TSTB"),
+    Some("CLRB"),
+    Some("CLRB"),
+];
+// the rows 8 to B and C to F, by low nibble
+const A_REGISTER_OPS: [&str; 16] = [
+    "SUBA", "CMPA", "SBCA", "SUBD", "ANDA", "BITA", "LDA", "STA", "EORA", "ADCA", "ORA", "ADDA",
+    "CMPX", "JSR", "LDX", "STX",
+];
+const B_REGISTER_OPS: [&str; 16] = [
+    "SUBB", "CMPB", "SBCB", "ADDD", "ANDB", "BITB", "LDB", "STB", "EORB", "ADCB", "ORB", "ADDB",
+    "LDD", "STD", "LDU", "STU",
+];
+const BRANCHES: [&str; 16] = [
+    "BRA", "BRN", "BHI", "BLS", "BCC", "BCS", "BNE", "BEQ", "BVC", "BVS", "BPL", "BMI", "BGE",
+    "BLT", "BGT", "BLE",
+];
+const LONG_BRANCHES: [&str; 16] = [
+    "LBRA", "LBRN", "LBHI", "LBLS", "LBCC", "LBCS", "LBNE", "LBEQ", "LBVC", "LBVS", "LBPL", "LBMI",
+    "LBGE", "LBLT", "LBGT", "LBLE",
+];
+
+/// The addressing mode of the rows 8 to F, from the bits 4 and 5 of the opcode
+const fn register_mode(opcode: int) -> Mode {
+    match (opcode >> 4) & 3 {
+        0 => Mode::Immediate8,
+        1 => Mode::Direct,
+        2 => Mode::Indexed,
+        _ => Mode::Extended,
+    }
+}
+
+/// Returns the mnemonic, the addressing mode and the cycles of an opcode of the given page
+/// (0, 0x10 or 0x11)
+fn opcode_info(page: int, opcode: int) -> Option<(&'static str, Mode, int)> {
+    let low = (opcode & 0x0F) as usize;
+    // column of the cycles tables: immediate, direct, indexed, extended
+    let column = ((opcode >> 4) & 3) as usize;
+    match page {
+        0x10 => {
+            let (mnemonic, cycles) = match opcode {
+                0x21..=0x2F => return Some((LONG_BRANCHES[low], Mode::Relative16, 5)),
+                0x3F => return Some(("SWI2", Mode::Inherent, 20)),
+                0x83 | 0x93 | 0xA3 | 0xB3 => ("CMPD", [5, 7, 7, 8]),
+                0x8C | 0x9C | 0xAC | 0xBC => ("CMPY", [5, 7, 7, 8]),
+                0x8E | 0x9E | 0xAE | 0xBE => ("LDY", [4, 6, 6, 7]),
+                0x9F | 0xAF | 0xBF => ("STY", [4, 6, 6, 7]),
+                0xCE | 0xDE | 0xEE | 0xFE => ("LDS", [4, 6, 6, 7]),
+                0xDF | 0xEF | 0xFF => ("STS", [4, 6, 6, 7]),
+                _ => return None,
+            };
+            Some((mnemonic, wide(register_mode(opcode)), cycles[column]))
+        }
+        0x11 => {
+            let (mnemonic, cycles) = match opcode {
+                0x3F => return Some(("SWI3", Mode::Inherent, 20)),
+                0x83 | 0x93 | 0xA3 | 0xB3 => ("CMPU", [5, 7, 7, 8]),
+                0x8C | 0x9C | 0xAC | 0xBC => ("CMPS", [5, 7, 7, 8]),
+                _ => return None,
+            };
+            Some((mnemonic, wide(register_mode(opcode)), cycles[column]))
+        }
+        _ => match opcode {
+            // the trap used by the emulator to run the peripherals
+            0x02 => Some(("PER", Mode::Inherent, 0)),
+            0x00..=0x0F => {
+                let cycles = if opcode == 0x0E { 3 } else { 6 };
+                MEMORY_OPS[low].map(|mnemonic| (mnemonic, Mode::Direct, cycles))
+            }
+            0x12 => Some(("NOP", Mode::Inherent, 2)),
+            0x13 => Some(("SYNC", Mode::Inherent, 4)),
+            0x16 => Some(("LBRA", Mode::Relative16, 5)),
+            0x17 => Some(("LBSR", Mode::Relative16, 9)),
+            0x19 => Some(("DAA", Mode::Inherent, 2)),
+            0x1A => Some(("ORCC", Mode::Immediate8, 3)),
+            0x1B => Some(("NOP", Mode::Inherent, 2)),
+            0x1C => Some(("ANDCC", Mode::Immediate8, 3)),
+            0x1D => Some(("SEX", Mode::Inherent, 2)),
+            0x1E => Some(("EXG", Mode::Registers, 8)),
+            0x1F => Some(("TFR", Mode::Registers, 6)),
+            0x20..=0x2F => Some((BRANCHES[low], Mode::Relative8, 3)),
+            0x30 => Some(("LEAX", Mode::Indexed, 4)),
+            0x31 => Some(("LEAY", Mode::Indexed, 4)),
+            0x32 => Some(("LEAS", Mode::Indexed, 4)),
+            0x33 => Some(("LEAU", Mode::Indexed, 4)),
+            0x34 => Some(("PSHS", Mode::StackS, 5)),
+            0x35 => Some(("PULS", Mode::StackS, 5)),
+            0x36 => Some(("PSHU", Mode::StackU, 5)),
+            0x37 => Some(("PULU", Mode::StackU, 5)),
+            0x38 => Some(("ANDCC", Mode::Immediate8, 4)),
+            0x39 => Some(("RTS", Mode::Inherent, 5)),
+            0x3A => Some(("ABX", Mode::Inherent, 3)),
+            0x3B => Some(("RTI", Mode::Inherent, 6)),
+            0x3C => Some(("CWAI", Mode::Immediate8, 20)),
+            0x3D => Some(("MUL", Mode::Inherent, 11)),
+            0x3E => Some(("RESET", Mode::Inherent, 19)),
+            0x3F => Some(("SWI", Mode::Inherent, 19)),
+            0x40..=0x4F => A_OPS[low].map(|mnemonic| (mnemonic, Mode::Inherent, 2)),
+            0x50..=0x5F => B_OPS[low].map(|mnemonic| (mnemonic, Mode::Inherent, 2)),
+            0x60..=0x6F => {
+                let cycles = if opcode == 0x6E { 3 } else { 6 };
+                MEMORY_OPS[low].map(|mnemonic| (mnemonic, Mode::Indexed, cycles))
+            }
+            0x70..=0x7F => {
+                let cycles = if opcode == 0x7E { 4 } else { 7 };
+                MEMORY_OPS[low].map(|mnemonic| (mnemonic, Mode::Extended, cycles))
+            }
+            0x8D => Some(("BSR", Mode::Relative8, 7)),
+            // no call with an immediate value, the stores write over their operand
+            0xCD => None,
+            0x80..=0xFF => {
+                let mnemonic = if opcode < 0xC0 {
+                    A_REGISTER_OPS[low]
+                } else {
+                    B_REGISTER_OPS[low]
+                };
+                let cycles = match mnemonic.as_bytes() {
+                    b"SUBD" | b"CMPX" | b"ADDD" => [4, 6, 6, 7],
+                    b"LDX" | b"STX" | b"LDD" | b"STD" | b"LDU" | b"STU" => [3, 5, 5, 6],
+                    b"JSR" => [0, 7, 7, 8],
+                    _ => [2, 4, 4, 5],
+                };
+                let mode = match mnemonic.as_bytes() {
+                    b"SUBD" | b"CMPX" | b"ADDD" | b"LDX" | b"STX" | b"LDD" | b"LDU" | b"STU" => {
+                        wide(register_mode(opcode))
+                    }
+                    _ => register_mode(opcode),
+                };
+                Some((mnemonic, mode, cycles[column]))
+            }
+            _ => None,
+        },
+    }
+}
+
+/// The immediate mode of the 16 bits registers reads a word
+const fn wide(mode: Mode) -> Mode {
+    match mode {
+        Mode::Immediate8 => Mode::Immediate16,
+        mode => mode,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hardware::bus::Bus;
+    use rstest::rstest;
+
+    fn decode_bytes(code: &[u8]) -> Instruction {
+        let mut mem = Memory::default();
+        for (i, byte) in code.iter().enumerate() {
+            mem.write(0x6000 + i as u16, *byte);
+        }
+        decode(0x6000, &mem)
+    }
+
+    #[rstest]
+    #[case(&[0x86, 0x12], "LDA", "#$12", 2)]
+    #[case(&[0xCC, 0x12, 0x34], "LDD", "#$1234", 3)]
+    #[case(&[0x10, 0x8E, 0x12, 0x34], "LDY", "#$1234", 4)]
+    #[case(&[0x10, 0xCE, 0x90, 0x00], "LDS", "#$9000", 4)]
+    #[case(&[0x96, 0x10], "LDA", "<$10", 4)]
+    #[case(&[0xB7, 0xA7, 0xC0], "STA", "$A7C0", 5)]
+    #[case(&[0xA6, 0x05], "LDA", "$05,X", 5)]
+    #[case(&[0xA6, 0x7F], "LDA", "-$01,S", 5)]
+    #[case(&[0xA6, 0xA1], "LDA", ",Y++", 7)]
+    #[case(&[0xA6, 0x98, 0xF0], "LDA", "[-$10,X]", 8)]
+    #[case(&[0xAD, 0x9F, 0xF0, 0x03], "JSR", "[$F003]", 12)]
+    #[case(&[0x34, 0x16], "PSHS", "A,B,X", 9)]
+    #[case(&[0x36, 0xC0], "PSHU", "S,PC", 9)]
+    #[case(&[0x1F, 0x89], "TFR", "A,B", 6)]
+    #[case(&[0x1E, 0x12], "EXG", "X,Y", 8)]
+    #[case(&[0x11, 0x3F], "SWI3", "", 20)]
+    #[case(&[0x4F], "CLRA", "", 2)]
+    #[case(&[0x14], "ILL", "", 0)]
+    #[case(&[0x87, 0x05], "STA", "#$05", 2)]
+    #[case(&[0x10], "FCB", "$10", 1)]
+    fn test_decode(
+        #[case] code: &[u8],
+        #[case] mnemonic: &str,
+        #[case] operands: &str,
+        #[case] cycles: int,
+    ) {
+        let instruction = decode_bytes(code);
+        assert_eq!(mnemonic, instruction.mnemonic);
+        assert_eq!(operands, instruction.operands);
+        assert_eq!(cycles, instruction.cycles);
+        assert_eq!(code, instruction.bytes.as_slice());
+    }
+
+    #[rstest]
+    #[case(&[0x20, 0xFE], Some(0x6000))]
+    #[case(&[0x8D, 0x04], Some(0x6006))]
+    #[case(&[0x16, 0x10, 0x00], Some(0x7003))]
+    #[case(&[0x10, 0x27, 0xFF, 0xFC], Some(0x6000))]
+    #[case(&[0x7E, 0xF0, 0x03], Some(0xF003))]
+    #[case(&[0xB6, 0xF0, 0x03], None)]
+    #[case(&[0x6E, 0x84], None)]
+    fn test_branch_target(#[case] code: &[u8], #[case] target: Option<int>) {
+        assert_eq!(target, decode_bytes(code).target);
+    }
+
+    #[test]
+    fn test_labels() {
+        let symbols = Symbols::parse("START EQU $6000\nPIA = $A7C0").unwrap();
+        let instruction = decode_bytes(&[0x20, 0xFE]);
+        assert_eq!("BRA   START", &instruction.to_string_with(&symbols)[20..]);
+        let instruction = decode_bytes(&[0xB7, 0xA7, 0xC0]);
+        assert_eq!("PIA", instruction.operands_with(&symbols));
+        assert_eq!("$A7C0", instruction.operands_with(&Symbols::default()));
+    }
+
+    #[test]
+    fn test_length() {
+        let mut mem = Memory::default();
+        for page in [0x00, 0x10, 0x11] {
+            for opcode in 0..=0xFF {
+                for postbyte in [0x84, 0x88, 0x89, 0x9F] {
+                    let code = [page, opcode, postbyte, 0x00, 0x00];
+                    let code = if page == 0 { &code[1..] } else { &code[..] };
+                    for (i, byte) in code.iter().enumerate() {
+                        mem.write(0x6000 + i as u16, *byte);
+                    }
+                    let expected = decode(0x6000, &mem).len();
+                    assert_eq!(expected, length(0x6000, &mem), "{page:02X} {opcode:02X}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_disassemble_data() {
+        let mut mem = Memory::default();
+        mem.reset();
+        mem.write(0x6000, 0x39);
+        mem.write(0x6001, 0x39);
+        mem.start_code_data_log();
+        mem.read(0x6001);
+        let instructions = disassemble(0x6000, 2, &mem);
+        assert_eq!("RTS", instructions[0].mnemonic);
+        assert_eq!("FCB", instructions[1].mnemonic);
+        assert_eq!("$39", instructions[1].operands);
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let mut mem = Memory::default();
+        mem.reset();
+        let instructions = disassemble(0xFFFF, 2, &mem);
+        // the address wraps around
+        assert_eq!(0xFFFF, instructions[0].address);
+        assert_eq!(instructions[0].next_address(), instructions[1].address);
+    }
+}
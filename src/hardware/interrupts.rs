@@ -0,0 +1,107 @@
+use std::cell::Cell;
+
+/// The interrupt inputs of the 6809
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Line {
+    Irq,
+    Firq,
+    /// Taken when it goes from released to asserted
+    Nmi,
+}
+
+/// The devices driving the interrupt lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// The system PIA, at the frame rate
+    Pia,
+    /// The cartridges and the interfaces of the extension port
+    Extension,
+}
+
+impl Source {
+    const fn bit(self) -> u8 {
+        match self {
+            Self::Pia => 0x01,
+            Self::Extension => 0x02,
+        }
+    }
+}
+
+/// The interrupt lines wired to the CPU, a line stays asserted while one of its sources
+/// holds it. The CPU samples them between two instructions.
+#[derive(Debug, Default)]
+pub struct InterruptLines {
+    irq: Cell<u8>,
+    firq: Cell<u8>,
+    nmi: Cell<u8>,
+    nmi_edge: Cell<bool>,
+}
+
+impl InterruptLines {
+    const fn line(&self, line: Line) -> &Cell<u8> {
+        match line {
+            Line::Irq => &self.irq,
+            Line::Firq => &self.firq,
+            Line::Nmi => &self.nmi,
+        }
+    }
+
+    pub fn set(&self, line: Line, source: Source, asserted: bool) {
+        let cell = self.line(line);
+        let old = cell.get();
+        let new = if asserted {
+            old | source.bit()
+        } else {
+            old & !source.bit()
+        };
+        if line == Line::Nmi && old == 0 && new != 0 {
+            self.nmi_edge.set(true);
+        }
+        cell.set(new);
+    }
+
+    pub fn is_asserted(&self, line: Line) -> bool {
+        self.line(line).get() != 0
+    }
+
+    /// Returns true if the CPU has an interrupt to check
+    pub(crate) fn is_pending(&self) -> bool {
+        self.irq.get() | self.firq.get() != 0 || self.nmi_edge.get()
+    }
+
+    pub(crate) fn take_nmi(&self) -> bool {
+        self.nmi_edge.replace(false)
+    }
+
+    pub(crate) fn release_all(&self) {
+        self.irq.set(0);
+        self.firq.set(0);
+        self.nmi.set(0);
+        self.nmi_edge.set(false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines() {
+        let lines = InterruptLines::default();
+        assert!(!lines.is_pending());
+        lines.set(Line::Irq, Source::Pia, true);
+        lines.set(Line::Irq, Source::Extension, true);
+        lines.set(Line::Irq, Source::Pia, false);
+        // still held by the extension
+        assert!(lines.is_asserted(Line::Irq));
+        lines.set(Line::Irq, Source::Extension, false);
+        assert!(!lines.is_pending());
+
+        lines.set(Line::Nmi, Source::Extension, true);
+        assert!(lines.take_nmi());
+        // the NMI is taken once per edge
+        lines.set(Line::Nmi, Source::Pia, true);
+        assert!(!lines.take_nmi());
+        assert!(lines.is_asserted(Line::Nmi));
+    }
+}
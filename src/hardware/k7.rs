@@ -2,7 +2,133 @@ use egui::DroppedFile;
 use log::info;
 use std::fs;
 use std::io::{Cursor, Read};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// The bytes starting every block, after the leader of 0x01 bytes
+const SYNC: [u8; 2] = [0x3C, 0x5A];
+const HEADER_BLOCK: u8 = 0x00;
+const DATA_BLOCK: u8 = 0x01;
+const END_BLOCK: u8 = 0xFF;
+/// The 0x01 bytes written before each block
+const LEADER: usize = 16;
+/// The most data in a block, its length byte is then 0
+const BLOCK_SIZE: usize = 254;
+
+/// The type of a file, given by its header block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    Basic,
+    Data,
+    Binary,
+}
+
+impl From<FileKind> for u8 {
+    fn from(kind: FileKind) -> Self {
+        kind as u8
+    }
+}
+
+impl TryFrom<u8> for FileKind {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Basic),
+            1 => Ok(Self::Data),
+            2 => Ok(Self::Binary),
+            _ => Err(format!("Unknown file type {value:02X}")),
+        }
+    }
+}
+
+/// A file saved on a tape, its data joins the data blocks following the header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TapeFile {
+    /// The name and the extension, like `GAME.BAS`
+    pub name: String,
+    pub kind: FileKind,
+    /// Saved as text rather than in the binary format of its type
+    pub ascii: bool,
+    pub data: Vec<u8>,
+}
+
+impl TapeFile {
+    fn from_header(data: &[u8]) -> Result<Self, String> {
+        if data.len() < 13 {
+            return Err("The header block is too short".to_string());
+        }
+        let field = |bytes: &[u8]| String::from_utf8_lossy(bytes).trim_end().to_string();
+        let name = field(&data[..8]);
+        let extension = field(&data[8..11]);
+        Ok(Self {
+            name: if extension.is_empty() {
+                name
+            } else {
+                format!("{name}.{extension}")
+            },
+            kind: FileKind::try_from(data[11])?,
+            ascii: data[12] == 0xFF,
+            data: Vec::new(),
+        })
+    }
+
+    /// The data of the header block, the name is up to 8 characters and the extension up to 3
+    fn header(&self) -> Result<Vec<u8>, String> {
+        let (name, extension) = self.name.split_once('.').unwrap_or((&self.name, ""));
+        if name.len() > 8 || extension.len() > 3 || !self.name.is_ascii() {
+            return Err(format!("Invalid tape file name {}", self.name));
+        }
+        let mut header = format!("{name:8}{extension:3}").into_bytes();
+        header.extend([self.kind.into(), if self.ascii { 0xFF } else { 0x00 }, 0x00]);
+        Ok(header)
+    }
+}
+
+/// Writes a block with its leader, its length and its checksum
+fn push_block(bytes: &mut Vec<u8>, kind: u8, data: &[u8]) {
+    bytes.extend([0x01; LEADER]);
+    bytes.extend(SYNC);
+    bytes.extend([kind, (data.len() + 2) as u8]);
+    bytes.extend(data);
+    bytes.push(data.iter().fold(0u8, |sum, byte| sum.wrapping_sub(*byte)));
+}
+
+/// The name of a host file on a tape: its name without extension, cut to 8 characters
+pub fn tape_name(path: &Path) -> String {
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default()
+        .chars()
+        .take(8)
+        .collect()
+}
+
+/// The blocks of a tape with their type. A block is `3C 5A`, its type, its length including
+/// the length and the checksum bytes, the data, and a checksum making their sum 0.
+fn blocks(bytes: &[u8]) -> Result<Vec<(u8, &[u8])>, String> {
+    let mut blocks = Vec::new();
+    let mut position = 0;
+    while let Some(sync) = bytes[position..].windows(2).position(|w| w == SYNC) {
+        let start = position + sync + SYNC.len();
+        let truncated = || format!("The block at {start:04X} is truncated");
+        let header = bytes.get(start..start + 2).ok_or_else(truncated)?;
+        let (kind, length) = (header[0], header[1]);
+        // a length of 0 is a full block of 254 bytes
+        let end = start + 2 + length.wrapping_sub(2) as usize;
+        let data = bytes.get(start + 2..end).ok_or_else(truncated)?;
+        let checksum = *bytes.get(end).ok_or_else(truncated)?;
+        if data
+            .iter()
+            .fold(checksum, |sum, byte| sum.wrapping_add(*byte))
+            != 0
+        {
+            return Err(format!("Bad checksum in the block at {start:04X}"));
+        }
+        blocks.push((kind, data));
+        position = end + 1;
+    }
+    Ok(blocks)
+}
 
 #[derive(Debug)]
 pub struct K7 {
@@ -12,6 +138,31 @@ pub struct K7 {
 }
 
 impl K7 {
+    pub fn new(name: String, bytes: Vec<u8>) -> Self {
+        Self {
+            name,
+            len: bytes.len() as u32,
+            bytes: Cursor::new(bytes),
+        }
+    }
+
+    /// A tape containing the files, each one is a header, its data blocks and an end block
+    pub fn with_files(name: String, files: &[TapeFile]) -> Result<Self, String> {
+        let mut bytes = Vec::new();
+        for file in files {
+            push_block(&mut bytes, HEADER_BLOCK, &file.header()?);
+            for data in file.data.chunks(BLOCK_SIZE) {
+                push_block(&mut bytes, DATA_BLOCK, data);
+            }
+            push_block(&mut bytes, END_BLOCK, &[]);
+        }
+        Ok(Self::new(name, bytes))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.bytes.get_ref()).map_err(|e| e.to_string())
+    }
+
     pub(crate) const fn len(&self) -> u32 {
         self.len
     }
@@ -29,6 +180,38 @@ impl K7 {
         self.bytes.read_exact(&mut b).ok();
         Some(b[0])
     }
+
+    /// Whether the next byte starts the leader of a block, a K7 file drops the blank tape
+    /// recorded before it
+    pub(crate) fn at_block(&self) -> bool {
+        let bytes = self.bytes.get_ref();
+        let position = self.bytes.position() as usize;
+        bytes.get(position) == Some(&0x01)
+            && (position == 0 || bytes.get(position - 1) != Some(&0x01))
+    }
+
+    /// The files saved on the tape, in order
+    pub fn files(&self) -> Result<Vec<TapeFile>, String> {
+        let mut files = Vec::new();
+        let mut current: Option<TapeFile> = None;
+        for (kind, data) in blocks(self.bytes.get_ref())? {
+            match kind {
+                HEADER_BLOCK => {
+                    files.extend(current.take());
+                    current = Some(TapeFile::from_header(data)?);
+                }
+                DATA_BLOCK => match &mut current {
+                    Some(file) => file.data.extend_from_slice(data),
+                    None => return Err("A data block comes before any header".to_string()),
+                },
+                END_BLOCK => files.extend(current.take()),
+                _ => return Err(format!("Unknown block type {kind:02X}")),
+            }
+        }
+        // a tape may end without the end block
+        files.extend(current);
+        Ok(files)
+    }
 }
 
 impl TryFrom<String> for K7 {
@@ -87,3 +270,42 @@ impl TryFrom<&DroppedFile> for K7 {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_files() {
+        let files = [
+            TapeFile {
+                name: "GAME.BAS".to_string(),
+                kind: FileKind::Basic,
+                ascii: false,
+                data: (0..=255).cycle().take(600).collect(),
+            },
+            TapeFile {
+                name: "SCORES".to_string(),
+                kind: FileKind::Data,
+                ascii: true,
+                data: Vec::new(),
+            },
+        ];
+        let k7 = K7::with_files("test.k7".to_string(), &files).unwrap();
+        assert_eq!(Ok(files.to_vec()), k7.files());
+        let mut long = files[1].clone();
+        long.name = "SCORES.TEXT".to_string();
+        assert!(K7::with_files("test.k7".to_string(), &[long]).is_err());
+    }
+
+    #[test]
+    fn test_at_block() {
+        let mut k7 = K7::new("test.k7".to_string(), vec![0x01, 0x01, 0x3C, 0x01, 0x01]);
+        let mut blocks = Vec::new();
+        for _ in 0..5 {
+            blocks.push(k7.at_block());
+            k7.read();
+        }
+        assert_eq!(vec![true, false, false, true, false], blocks);
+    }
+}
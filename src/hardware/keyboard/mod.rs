@@ -21,10 +21,8 @@ impl Keyboard {
             MO5VirtualKeyCode::Left => key_memory(0x52, press, mem),
             MO5VirtualKeyCode::Right => key_memory(0x32, press, mem),
             MO5VirtualKeyCode::Down => key_memory(0x42, press, mem),
-            MO5VirtualKeyCode::LControl => key_memory(0x6a, press, mem),
             MO5VirtualKeyCode::Escape => key_memory(0x66, press, mem),
-            MO5VirtualKeyCode::LShift => key_memory(0x70, press, mem), //Sh
-            MO5VirtualKeyCode::F11 => key_memory(0x72, press, mem),    // Ba
+            MO5VirtualKeyCode::F11 => key_memory(0x72, press, mem), // Ba
             MO5VirtualKeyCode::Key1 => self.handle_shiftable_key(0x5e, press, mem),
             MO5VirtualKeyCode::Key2 => self.handle_shiftable_key(0x4e, press, mem),
             MO5VirtualKeyCode::Key3 => self.handle_shiftable_key(0x3e, press, mem),
@@ -47,7 +45,6 @@ impl Keyboard {
             MO5VirtualKeyCode::I => key_memory(0x18, press, mem),
             MO5VirtualKeyCode::O => key_memory(0x28, press, mem),
             MO5VirtualKeyCode::P => key_memory(0x38, press, mem),
-            MO5VirtualKeyCode::Caret => key_memory(0x48, press, mem),
             // Base(VirtualKeyCode::Dollar) => { key_memory(0x58, press, mem); }
             MO5VirtualKeyCode::Q => key_memory(0x56, press, mem),
             MO5VirtualKeyCode::S => key_memory(0x46, press, mem),
@@ -68,8 +65,6 @@ impl Keyboard {
             MO5VirtualKeyCode::N => key_memory(0x00, press, mem),
             MO5VirtualKeyCode::Comma => key_memory(0x10, press, mem),
             MO5VirtualKeyCode::Period => key_memory(0x20, press, mem),
-            MO5VirtualKeyCode::At => key_memory(0x30, press, mem),
-            MO5VirtualKeyCode::Asterisk => key_memory(0x58, press, mem),
             MO5VirtualKeyCode::Space => key_memory(0x40, press, mem),
         }
     }
@@ -91,6 +86,70 @@ impl Keyboard {
     }
 }
 
+/// The shift key, pressed with the key of a shifted character
+pub(crate) const SHIFT: usize = 0x70;
+
+/// The key typing the character and whether it is shifted. The letters are typed in capitals,
+/// `\n` is the Return key.
+pub(crate) fn key_of(c: char) -> Option<(usize, bool)> {
+    let key = match c.to_ascii_uppercase() {
+        'A' => (0x5a, false),
+        'B' => (0x44, false),
+        'C' => (0x64, false),
+        'D' => (0x36, false),
+        'E' => (0x3a, false),
+        'F' => (0x26, false),
+        'G' => (0x16, false),
+        'H' => (0x06, false),
+        'I' => (0x18, false),
+        'J' => (0x04, false),
+        'K' => (0x14, false),
+        'L' => (0x24, false),
+        'M' => (0x34, false),
+        'N' => (0x00, false),
+        'O' => (0x28, false),
+        'P' => (0x38, false),
+        'Q' => (0x56, false),
+        'R' => (0x2a, false),
+        'S' => (0x46, false),
+        'T' => (0x1a, false),
+        'U' => (0x08, false),
+        'V' => (0x54, false),
+        'W' => (0x60, false),
+        'X' => (0x50, false),
+        'Y' => (0x0a, false),
+        'Z' => (0x4a, false),
+        '1' | '!' => (0x5e, c == '!'),
+        '2' | '"' => (0x4e, c == '"'),
+        '3' | '#' => (0x3e, c == '#'),
+        '4' | '$' => (0x2e, c == '$'),
+        '5' | '%' => (0x1e, c == '%'),
+        '6' | '&' => (0x0e, c == '&'),
+        '7' | '\'' => (0x0c, c == '\''),
+        '8' | '(' => (0x1c, c == '('),
+        '9' | ')' => (0x2c, c == ')'),
+        '0' => (0x3c, false),
+        ',' | '<' => (0x10, c == '<'),
+        '.' | '>' => (0x20, c == '>'),
+        '/' | '?' => (0x48, c == '?'),
+        '@' | '^' => (0x30, c == '^'),
+        '*' | ':' => (0x58, c == ':'),
+        '+' | ';' => (0x5c, c == ';'),
+        '-' | '=' => (0x4c, c == '='),
+        ' ' => (0x40, false),
+        '\n' => (0x68, false),
+        _ => return None,
+    };
+    Some(key)
+}
+
+/// The keys typing the text
+pub(crate) fn keys_of(text: &str) -> Result<Vec<(usize, bool)>, String> {
+    text.chars()
+        .map(|c| key_of(c).ok_or_else(|| format!("No key types {c:?}")))
+        .collect()
+}
+
 fn key_memory(key: usize, press: bool, mem: &mut Memory) {
     if press {
         mem.set_key(key);
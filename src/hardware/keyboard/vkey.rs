@@ -8,9 +8,7 @@ pub enum MO5VirtualKeyCode {
     Left,
     Right,
     Down,
-    LControl,
     Escape,
-    LShift,
     F11,
     Key1,
     Key2,
@@ -49,13 +47,9 @@ pub enum MO5VirtualKeyCode {
     X,
     Y,
     Z,
-
     Comma,
     Period,
-    At,
-    Asterisk,
     Space,
-    Caret,
     Minus,
 }
 
@@ -72,9 +66,7 @@ impl TryFrom<egui::Key> for MO5VirtualKeyCode {
             egui::Key::ArrowLeft => Ok(MO5VirtualKeyCode::Left),
             egui::Key::ArrowRight => Ok(MO5VirtualKeyCode::Right),
             egui::Key::ArrowDown => Ok(MO5VirtualKeyCode::Down),
-            // egui::Key::Ctrl => Ok(MO5VirtualKeyCode::LControl),
             egui::Key::Escape => Ok(MO5VirtualKeyCode::Escape),
-            // egui::Key::LShift => Ok(MO5VirtualKeyCode::LShift),
             egui::Key::F11 => Ok(MO5VirtualKeyCode::F11),
             egui::Key::Num1 => Ok(MO5VirtualKeyCode::Key1),
             egui::Key::Num2 => Ok(MO5VirtualKeyCode::Key2),
@@ -115,10 +107,7 @@ impl TryFrom<egui::Key> for MO5VirtualKeyCode {
             egui::Key::Z => Ok(MO5VirtualKeyCode::Z),
             egui::Key::Comma => Ok(MO5VirtualKeyCode::Comma),
             egui::Key::Period => Ok(MO5VirtualKeyCode::Period),
-            // egui::Key::At => Ok(MO5VirtualKeyCode::At),
-            // egui::Key::Asterisk => Ok(MO5VirtualKeyCode::Asterisk),
             egui::Key::Space => Ok(MO5VirtualKeyCode::Space),
-            // egui::Key::Caret => Ok(MO5VirtualKeyCode::Caret),
             egui::Key::Minus => Ok(MO5VirtualKeyCode::Minus),
             _ => Err(()),
         }
@@ -1,14 +1,81 @@
-use crate::hardware::keyboard::Keyboard;
+#[cfg(not(target_family = "wasm"))]
+use crate::capture::{VideoFormat, VideoRecorder};
+use crate::hardware::keyboard::{self, Keyboard};
 
-use crate::hardware::M6809::{M6809, unassemble};
+use crate::hardware::M6809::{IllegalOpcode, IllegalOpcodePolicy, M6809, Registers};
+use crate::hardware::basic;
+use crate::hardware::binary::{Binary, Entry};
+use crate::hardware::debugger::{Debugger, Watchpoint};
+use crate::hardware::disassembler::{Instruction, disassemble};
+use crate::hardware::interrupts::{Line, Source};
 use crate::hardware::k7::K7;
 use crate::hardware::memory::Memory;
-use crate::hardware::screen::Screen;
-use crate::hardware::sound::Sound;
+use crate::hardware::profiler::{CodeDataLog, Profiler};
+use crate::hardware::scheduler::Scheduler;
+use crate::hardware::screen::color::Palette;
+use crate::hardware::screen::{
+    CYCLES_PER_FRAME, CYCLES_PER_LINE, HEIGHT, Screen, WINDOW_END, WINDOW_START,
+};
+use crate::hardware::sound::{SAMPLE_RATE, Sound};
+use crate::hardware::symbols::Symbols;
+use crate::hardware::trace::{TraceFilter, Tracer};
 use crate::int;
 use crate::raw_image::RawImage;
 use chrono::{DateTime, Local};
-use log::{debug, info};
+use log::{debug, info, warn};
+use std::collections::VecDeque;
+
+/// The MO5 screen is refreshed at 50Hz, each frame lasts 20ms
+pub const FRAMES_PER_SECOND: u32 = 50;
+
+/// Cycles of the frame signal on CB1 at the end of the frame, they belong to the top border
+/// of the next frame
+const IRQ_CYCLES: int = 300;
+
+const CYCLES_PER_SECOND: u64 = CYCLES_PER_FRAME as u64 * FRAMES_PER_SECOND as u64;
+/// The tape plays 1200 bits per second
+const K7_BIT_CYCLES: u64 = CYCLES_PER_SECOND / 1200;
+/// The blank tape played before each block, longer than the ROM waits for the motor
+const K7_GAP_CYCLES: u64 = CYCLES_PER_SECOND;
+
+/// Cycles from a reset to the BASIC prompt
+const BOOT_CYCLES: u64 = 100 * CYCLES_PER_FRAME as u64;
+/// Cycles a typed key is held, then released before the next one
+const KEY_CYCLES: u64 = 4 * CYCLES_PER_FRAME as u64;
+
+/// The reset buttons of the MO5
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Reset {
+    /// Restarts the program, the memory is kept
+    #[default]
+    Soft,
+    /// Like switching the power off and on
+    Hard,
+}
+
+impl Reset {
+    pub const ALL: [Self; 2] = [Self::Soft, Self::Hard];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Soft => "soft",
+            Self::Hard => "hard",
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|reset| reset.name().eq_ignore_ascii_case(text.trim()))
+            .ok_or_else(|| format!("Invalid reset {text}, soft or hard"))
+    }
+}
+
+/// What is done once BASIC started after a restart
+struct Startup {
+    binary: Option<Binary>,
+    keys: Vec<(usize, bool)>,
+}
 
 pub struct Machine {
     // Emulation Objects
@@ -17,9 +84,22 @@ pub struct Machine {
     pub(crate) screen: Screen,
     sound: Sound,
     pub(crate) keyboard: Keyboard,
-    pub(crate) irq: bool,
+    symbols: Symbols,
+    /// The events of the devices, the CPU runs until the next one
+    pub(crate) scheduler: Scheduler<Machine>,
+    // cycles run since the machine was created
+    time: u64,
+    // false between two frames, the debugger may stop the CPU in the middle of a frame
+    in_frame: bool,
+    // frames run, including the ones stopped by the debugger
+    frame: u64,
+    // the keys left to type with their shift
+    typing: VecDeque<(usize, bool)>,
+    startup: Option<Startup>,
     pub(crate) last_time: DateTime<Local>,
     pub(crate) running: bool,
+    #[cfg(not(target_family = "wasm"))]
+    video: Option<VideoRecorder>,
     #[cfg(target_arch = "wasm32")]
     waiting: web_time::Instant,
     #[cfg(target_arch = "wasm32")]
@@ -29,27 +109,37 @@ pub struct Machine {
 impl Default for Machine {
     fn default() -> Self {
         info!("Machine created");
-        let screen = Screen::new(get_ratio());
+        let screen = Screen::default();
         info!("Machine created");
         let mut mem = Memory::default();
         info!("Memory created");
         mem.reset();
         let micro = M6809::new(&mem);
         info!("CPU created");
-        Self {
+        let mut machine = Self {
             mem,
             micro,
             screen,
             sound: Sound::default(),
             keyboard: Keyboard::default(),
+            symbols: Symbols::mo5(),
             last_time: Local::now(),
-            irq: false,
+            scheduler: Scheduler::default(),
+            time: 0,
+            in_frame: false,
+            frame: 0,
+            typing: VecDeque::new(),
+            startup: None,
             running: true,
+            #[cfg(not(target_family = "wasm"))]
+            video: None,
             #[cfg(target_arch = "wasm32")]
             waiting: web_time::Instant::now(),
             #[cfg(target_arch = "wasm32")]
             sleeptime: 0,
-        }
+        };
+        machine.schedule_devices();
+        machine
     }
 }
 
@@ -58,8 +148,13 @@ impl Machine {
         #[cfg(debug_assertions)]
         debug!("run_loop");
         if self.running {
-            self.run();
+            let complete = self.run();
+            self.frame += 1;
             self.screen.paint(&mut self.mem);
+            #[cfg(not(target_family = "wasm"))]
+            if complete {
+                self.capture_frame();
+            }
             let raw_image = self.screen.get_pixels();
             Some(raw_image)
         } else {
@@ -67,67 +162,236 @@ impl Machine {
         }
     }
 
+    /// Runs the given number of frames as fast as possible, without synchronizing with the
+    /// real time. This is used when running without gui.
+    pub fn run_frames(&mut self, frames: u32) {
+        for _ in 0..frames {
+            if !self.full_speed() {
+                break;
+            }
+            #[cfg(not(target_family = "wasm"))]
+            if self.video.is_some() {
+                self.screen.paint(&mut self.mem);
+                self.capture_frame();
+            }
+        }
+    }
+
+    /// Returns the current picture
+    pub fn screenshot(&mut self) -> RawImage<'_> {
+        self.screen.paint(&mut self.mem);
+        self.screen.get_pixels()
+    }
+
+    /// Runs a frame, returns false if the debugger stopped the CPU before the end of the frame
     #[cfg(not(target_family = "wasm"))]
-    pub(crate) fn run(&mut self) {
-        self.full_speed();
-        self.synchronize();
+    pub(crate) fn run(&mut self) -> bool {
+        let complete = self.full_speed();
+        if complete {
+            self.synchronize();
+        }
+        complete
     }
 
     #[cfg(target_arch = "wasm32")]
-    pub(crate) fn run(&mut self) {
-        if self.waiting.elapsed().as_millis() > self.sleeptime {
-            self.full_speed();
+    pub(crate) fn run(&mut self) -> bool {
+        if self.waiting.elapsed().as_millis() <= self.sleeptime {
+            return false;
+        }
+        let complete = self.full_speed();
+        if complete {
             self.synchronize();
         }
+        complete
     }
 
-    // the emulator main loop
-    fn full_speed(&mut self) {
-        // let cl;
-
-        // Mise a jour du crayon optique a partir des donnée de la souris souris
-        self.mem.light_pen_clic = self.screen.mouse_clic;
-        self.mem.light_pen_x = self.screen.mouse_x;
-        self.mem.light_pen_y = self.screen.mouse_y;
+    // the emulator main loop, returns false if the debugger stopped the CPU. The frame is
+    // then resumed where it stopped by the next call.
+    fn full_speed(&mut self) -> bool {
+        if !self.in_frame {
+            // Mise a jour du crayon optique a partir des donnée de la souris souris
+            self.mem.set_light_pen_clic(self.screen.mouse_clic);
+            self.mem.light_pen_x = self.screen.mouse_x;
+            self.mem.light_pen_y = self.screen.mouse_y;
+            if let Some(tracer) = &mut self.micro.tracer {
+                tracer.start_frame();
+            }
+            self.in_frame = true;
+        }
+        while self.in_frame {
+            if !self.run_to_next_event() {
+                return false;
+            }
+        }
+        true
+    }
 
-        self.mem.set(0xA7E7, 0x00);
-        self.mem.GA3 = 0x00;
-        /* 3.9 ms haut �cran (+0.3 irq)*/
-        if self.irq {
-            self.irq = false;
-            self.micro
-                .FetchUntil(3800, &mut self.mem, &mut self.screen, &mut self.sound);
-        } else {
-            self.micro
-                .FetchUntil(4100, &mut self.mem, &mut self.screen, &mut self.sound);
+    /// Runs the CPU until the next event, then runs the events due.
+    /// Returns false if the debugger stopped the CPU, the period is then run again from the
+    /// cycle where it stopped.
+    fn run_to_next_event(&mut self) -> bool {
+        let next = self.scheduler.next_time().unwrap_or(self.time);
+        if next > self.time {
+            self.mem.beam_start = (self.time % CYCLES_PER_FRAME as u64) as int;
+            let period = self
+                .micro
+                .FetchUntil((next - self.time) as int, &mut self.mem);
+            if let Some(led) = self.mem.take_k7_led() {
+                self.screen.led = led;
+                self.screen.show_led = 10;
+            }
+            if period.is_none() {
+                info!("Machine stopped by the debugger at {:04X}", self.micro.pc);
+                self.running = false;
+                return false;
+            }
+            self.time = next;
         }
+        while let Some((time, handler)) = self.scheduler.pop_due(self.time) {
+            handler(self, time);
+        }
+        true
+    }
+
+    /// The current cycle, counted from the start of the machine
+    pub const fn time(&self) -> u64 {
+        self.time
+    }
+
+    /// Runs the handler when the CPU reaches the cycle, a device added out of the machine
+    /// schedules its events with it. A periodic device schedules its next event from its
+    /// handler, which may keep the state of the device.
+    pub fn schedule(
+        &mut self,
+        time: u64,
+        name: &'static str,
+        handler: impl FnOnce(&mut Machine, u64) + 'static,
+    ) {
+        self.scheduler.schedule(time, name, handler);
+    }
+
+    /// Removes the events of the given name
+    pub fn cancel(&mut self, name: &str) {
+        self.scheduler.cancel(name);
+    }
+
+    /// The periodic events of the MO5: the gate array flags the window, each line of the
+    /// picture is painted as soon as the beam has scanned it, so that the changes made by the
+    /// program while the picture is drawn are displayed like on the real hardware, and the
+    /// frame signal ends the frame. The sound output is sampled and the tape plays its bits.
+    fn schedule_devices(&mut self) {
+        let start = WINDOW_START as u64;
+        let frame = CYCLES_PER_FRAME as u64;
+        self.scheduler
+            .schedule(start, "window start", Self::window_start);
+        self.scheduler
+            .schedule(start + CYCLES_PER_LINE as u64, "line end", Self::line_end);
+        self.scheduler
+            .schedule(WINDOW_END as u64, "window end", Self::window_end);
+        self.scheduler
+            .schedule(frame, "frame signal", Self::frame_signal);
+        self.scheduler
+            .schedule(0, "sound sample", Self::sound_sample);
+        self.scheduler
+            .schedule(0, "tape bit", |machine, time| machine.tape_bit(time, None));
+    }
 
-        /* 13ms fenetre */
+    fn window_start(&mut self, time: u64) {
         self.mem.set(0xA7E7, 0x80);
         self.mem.GA3 = 0x80;
-        self.micro
-            .FetchUntil(13100, &mut self.mem, &mut self.screen, &mut self.sound);
+        self.scheduler.schedule(
+            time + CYCLES_PER_FRAME as u64,
+            "window start",
+            Self::window_start,
+        );
+    }
 
+    fn line_end(&mut self, time: u64) {
+        let cycle = (time % CYCLES_PER_FRAME as u64) as int;
+        let line = (cycle - WINDOW_START) / CYCLES_PER_LINE - 1;
+        self.screen.paint_line(&mut self.mem, line as usize);
+        let next = if line + 1 < HEIGHT as int {
+            time + CYCLES_PER_LINE as u64
+        } else {
+            time + (CYCLES_PER_FRAME - (HEIGHT as int - 1) * CYCLES_PER_LINE) as u64
+        };
+        self.scheduler.schedule(next, "line end", Self::line_end);
+    }
+
+    fn window_end(&mut self, time: u64) {
         self.mem.set(0xA7E7, 0x00);
         self.mem.GA3 = 0x00;
-        self.micro
-            .FetchUntil(2800, &mut self.mem, &mut self.screen, &mut self.sound);
-
-        if (self.mem.CRB & 0x01) == 0x01 {
-            self.irq = true;
-            /* Positionne le bit 7 de CRB */
-            self.mem.CRB |= 0x80;
-            self.mem.set(0xA7C3, self.mem.CRB);
-            let cc = self.micro.readCC();
-            if (cc & 0x10) == 0 {
-                self.micro.IRQ(&mut self.mem);
+        self.scheduler.schedule(
+            time + CYCLES_PER_FRAME as u64,
+            "window end",
+            Self::window_end,
+        );
+    }
+
+    /// The frame signal on CB1 interrupts the CPU when the ROM enabled it
+    fn frame_signal(&mut self, time: u64) {
+        self.mem.frame_signal(true);
+        self.scheduler.schedule(
+            time + IRQ_CYCLES as u64,
+            "frame signal end",
+            Self::frame_signal_end,
+        );
+        self.scheduler.schedule(
+            time + CYCLES_PER_FRAME as u64,
+            "frame signal",
+            Self::frame_signal,
+        );
+    }
+
+    /// The frame ends with the signal
+    fn frame_signal_end(&mut self, _: u64) {
+        self.mem.frame_signal(false);
+        self.in_frame = false;
+    }
+
+    /// The sound output is sampled at the rate of the sound card
+    fn sound_sample(&mut self, time: u64) {
+        self.sound.sample(self.mem.sound_mem);
+        let next = time * SAMPLE_RATE as u64 / CYCLES_PER_SECOND + 1;
+        self.scheduler.schedule(
+            (next * CYCLES_PER_SECOND).div_ceil(SAMPLE_RATE as u64),
+            "sound sample",
+            Self::sound_sample,
+        );
+    }
+
+    /// While the motor runs the tape plays its bits on PA7: each bit starts with an edge, a 1
+    /// has another one in its middle. The routine of the ROM reading them is patched by
+    /// default, the signal is read by the ROM once it is unpatched and by the custom loaders.
+    /// `gap` is the end of the blank tape played before a block.
+    fn tape_bit(&mut self, time: u64, gap: Option<u64>) {
+        let mut gap = gap;
+        if self.mem.k7_motor_on() {
+            match gap {
+                None if self.mem.k7_at_block() => {
+                    self.mem.blank_k7();
+                    gap = Some(time + K7_GAP_CYCLES);
+                }
+                Some(end) if time < end => {}
+                _ => {
+                    gap = None;
+                    if let Some(bit) = self.mem.next_k7_bit() {
+                        self.mem.toggle_k7_level();
+                        if bit {
+                            self.scheduler.schedule(
+                                time + K7_BIT_CYCLES / 2,
+                                "tape edge",
+                                |machine, _| machine.mem.toggle_k7_level(),
+                            );
+                        }
+                    }
+                }
             }
-            /* 300 cycles sous interrupt */
-            self.micro
-                .FetchUntil(300, &mut self.mem, &mut self.screen, &mut self.sound);
-            self.mem.CRB &= 0x7F;
-            self.mem.set(0xA7C3, self.mem.CRB);
         }
+        self.scheduler
+            .schedule(time + K7_BIT_CYCLES, "tape bit", move |machine, time| {
+                machine.tape_bit(time, gap)
+            });
     }
 
     fn synchronize(&mut self) {
@@ -151,6 +415,83 @@ impl Machine {
         self.last_time = Local::now();
     }
 
+    /// Sets the size of the overscan area displayed around the picture, in MO5 pixels
+    pub fn set_border(&mut self, border: usize) {
+        info!("Machine::set_border({border})");
+        self.screen.set_border(border, &mut self.mem);
+    }
+
+    pub const fn palette(&self) -> &Palette {
+        self.screen.palette()
+    }
+
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.screen.set_palette(palette, &mut self.mem);
+    }
+
+    pub const fn border(&self) -> usize {
+        self.screen.border()
+    }
+
+    /// Points the light pen at the given coordinates of the image, including the border,
+    /// or nowhere if the pointer is out of the image
+    pub(crate) fn set_light_pen(&mut self, position: Option<(usize, usize)>, clic: bool) {
+        self.screen.set_mouse(position, clic);
+    }
+
+    /// Installs a high-level emulation of the routine at the address, see [`Memory::patch`]
+    pub fn patch(
+        &mut self,
+        address: u16,
+        code: &[u8],
+        handler: impl FnMut(&mut Memory, &mut Registers) + 'static,
+    ) -> Result<(), String> {
+        self.mem.patch(address, code, handler)
+    }
+
+    /// Removes the trap at the address and restores the original code
+    pub fn unpatch(&mut self, address: u16) -> Result<(), String> {
+        self.mem.unpatch(address)
+    }
+
+    /// The addresses of the traps installed, the tape and the light pen ones by default
+    pub fn traps(&self) -> Vec<u16> {
+        self.mem.traps()
+    }
+
+    /// The listing of the BASIC program in memory
+    pub fn basic_listing(&self) -> Result<String, String> {
+        basic::list_memory(&self.mem)
+    }
+
+    /// Replaces the BASIC program in memory by the listing
+    pub fn load_basic(&mut self, listing: &str) -> Result<(), String> {
+        basic::load_memory(&mut self.mem, listing)
+    }
+
+    /// Writes the program in memory, then runs it when an entry is given
+    pub fn load_binary(&mut self, binary: &Binary, entry: Option<Entry>) -> Result<(), String> {
+        let start = match entry {
+            Some(entry) => Some((
+                entry
+                    .pc
+                    .or(binary.exec)
+                    .ok_or("The binary has no start address")?,
+                entry.s,
+            )),
+            None => None,
+        };
+        binary.load(&mut self.mem)?;
+        if let Some((pc, s)) = start {
+            let mut registers = self.registers();
+            registers.pc = pc;
+            registers.s = s.unwrap_or(registers.s);
+            self.set_registers(&registers);
+            self.start();
+        }
+        Ok(())
+    }
+
     pub fn set_k7(&mut self, k7: K7) {
         info!("Machine::set_k7_data()");
         self.mem.set_k7(k7);
@@ -168,9 +509,161 @@ impl Machine {
 
     pub(crate) fn start(&mut self) {
         info!("Machine::start()");
+        self.micro.debugger.resume();
         self.running = true;
     }
 
+    /// Counts the calls to [`Self::run_loop`] which ran the CPU, the debugger changes it
+    /// at each step
+    pub const fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    pub const fn is_running(&self) -> bool {
+        self.running
+    }
+
+    pub const fn debugger(&self) -> &Debugger {
+        &self.micro.debugger
+    }
+
+    pub fn toggle_breakpoint(&mut self, address: int) {
+        self.micro.debugger.toggle_breakpoint(address);
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        self.mem.watchpoints()
+    }
+
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        self.mem.add_watchpoint(watchpoint);
+    }
+
+    pub fn remove_watchpoint(&mut self, index: usize) {
+        self.mem.remove_watchpoint(index);
+    }
+
+    /// Stops before the next instruction
+    pub fn step_into(&mut self) {
+        self.micro.debugger.step_into();
+        self.start();
+    }
+
+    /// Stops after the next instruction, or when the subroutine it calls returns
+    pub fn step_over(&mut self) {
+        let stack = self.micro.registers().s as int;
+        self.micro
+            .debugger
+            .step_over(self.micro.pc as int, stack, &self.mem);
+        self.start();
+    }
+
+    /// Stops when the current subroutine returns with RTS or RTI
+    pub fn step_out(&mut self) {
+        let stack = self.micro.registers().s as int;
+        self.micro.debugger.step_out(stack);
+        self.start();
+    }
+
+    /// Stops before the instruction at the given address
+    pub fn run_to(&mut self, address: int) {
+        self.micro.debugger.run_to(address);
+        self.start();
+    }
+
+    /// Stops the machine, the step in progress is cancelled
+    pub fn pause(&mut self) {
+        self.micro.debugger.cancel_step();
+        self.stop();
+    }
+
+    pub fn registers(&mut self) -> Registers {
+        self.micro.registers()
+    }
+
+    pub fn set_registers(&mut self, registers: &Registers) {
+        info!("Machine::set_registers({registers:?})");
+        self.micro.set_registers(registers);
+    }
+
+    pub fn reset(&mut self, reset: Reset) {
+        match reset {
+            Reset::Soft => self.reset_soft(),
+            Reset::Hard => self.reset_hard(),
+        }
+    }
+
+    /// Resets the machine, then once BASIC started loads and runs the binary and types the keys
+    pub fn restart(
+        &mut self,
+        reset: Reset,
+        binary: Option<Binary>,
+        keys: &str,
+    ) -> Result<(), String> {
+        let keys = keyboard::keys_of(keys)?;
+        for event in ["startup", "key press", "key release"] {
+            self.scheduler.cancel(event);
+        }
+        self.typing.clear();
+        self.mem.rem_key_slice(0, 127);
+        self.reset(reset);
+        self.startup = Some(Startup { binary, keys });
+        self.scheduler
+            .schedule(self.time + BOOT_CYCLES, "startup", Self::startup);
+        Ok(())
+    }
+
+    fn startup(&mut self, time: u64) {
+        let Some(startup) = self.startup.take() else {
+            return;
+        };
+        if let Some(binary) = startup.binary
+            && let Err(e) = self.load_binary(&binary, Some(Entry::default()))
+        {
+            warn!("Unable to load the binary {e}");
+        }
+        self.type_keys(time, startup.keys);
+    }
+
+    /// Types the text on the keyboard, a key every few frames. Return is `\n`.
+    pub fn type_text(&mut self, text: &str) -> Result<(), String> {
+        let keys = keyboard::keys_of(text)?;
+        self.type_keys(self.time, keys);
+        Ok(())
+    }
+
+    fn type_keys(&mut self, time: u64, keys: Vec<(usize, bool)>) {
+        let idle = self.typing.is_empty();
+        self.typing.extend(keys);
+        if idle && !self.typing.is_empty() {
+            self.scheduler.schedule(time, "key press", Self::press_key);
+        }
+    }
+
+    fn press_key(&mut self, time: u64) {
+        if let Some(&(key, shift)) = self.typing.front() {
+            if shift {
+                self.mem.set_key(keyboard::SHIFT);
+            }
+            self.mem.set_key(key);
+            self.scheduler
+                .schedule(time + KEY_CYCLES, "key release", Self::release_key);
+        }
+    }
+
+    fn release_key(&mut self, time: u64) {
+        if let Some((key, shift)) = self.typing.pop_front() {
+            self.mem.rem_key(key);
+            if shift {
+                self.mem.rem_key(keyboard::SHIFT);
+            }
+        }
+        if !self.typing.is_empty() {
+            self.scheduler
+                .schedule(time + KEY_CYCLES, "key press", Self::press_key);
+        }
+    }
+
     // soft reset method ("reinit prog" button on original MO5)
     pub(crate) fn reset_soft(&mut self) {
         info!("Machine::reset_soft()");
@@ -190,37 +683,467 @@ impl Machine {
         self.running = true;
     }
 
-    pub(crate) fn dump_registers(&mut self) -> String {
-        self.micro.print_state()
+    pub fn start_audio_recording(&mut self, name: &str) -> Result<(), String> {
+        info!("Machine::start_audio_recording({name})");
+        self.sound.start_recording(name)
     }
 
-    pub(crate) fn unassemble_from_pc(&self, nblines: int, mem: &Memory) -> String {
-        unassemble(self.micro.PC, nblines, mem)
+    pub fn stop_audio_recording(&mut self) -> Result<Option<String>, String> {
+        info!("Machine::stop_audio_recording()");
+        self.sound.stop_recording()
+    }
+
+    pub const fn is_recording_audio(&self) -> bool {
+        self.sound.is_recording()
+    }
+
+    /// Starts capturing every frame, the sound is recorded in a WAV file along the video
+    #[cfg(not(target_family = "wasm"))]
+    pub fn start_video_capture(
+        &mut self,
+        name: &str,
+        format: VideoFormat,
+        scale: usize,
+    ) -> Result<(), String> {
+        info!("Machine::start_video_capture({name})");
+        self.stop_video_capture()?;
+        let video = VideoRecorder::new(name, format, scale)?;
+        self.sound.start_video_track(&video.audio_name())?;
+        self.video = Some(video);
+        Ok(())
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn stop_video_capture(&mut self) -> Result<Option<String>, String> {
+        info!("Machine::stop_video_capture()");
+        match self.video.take() {
+            Some(video) => {
+                self.sound.stop_video_track()?;
+                video.finish().map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub const fn is_capturing_video(&self) -> bool {
+        self.video.is_some()
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    fn capture_frame(&mut self) {
+        if let Some(video) = &mut self.video
+            && let Err(e) = video.push(&self.screen.get_pixels())
+        {
+            warn!("Unable to capture video {e}");
+            self.video = None;
+            self.sound.stop_video_track().ok();
+        }
+    }
+
+    /// Writes every instruction run by the CPU matching the filter to a file
+    pub fn start_trace(&mut self, name: &str, filter: TraceFilter) -> Result<(), String> {
+        info!("Machine::start_trace({name}, {filter:?})");
+        self.stop_trace()?;
+        self.micro.tracer = Some(Tracer::create(name, filter)?);
+        Ok(())
+    }
+
+    pub fn stop_trace(&mut self) -> Result<Option<String>, String> {
+        info!("Machine::stop_trace()");
+        match self.micro.tracer.take() {
+            Some(tracer) => tracer.finish(),
+            None => Ok(None),
+        }
+    }
+
+    pub const fn is_tracing(&self) -> bool {
+        self.micro.tracer.is_some()
+    }
+
+    /// Asserts or releases an interrupt line of the CPU for a device
+    pub fn set_interrupt(&mut self, line: Line, source: Source, asserted: bool) {
+        self.mem.interrupts.set(line, source, asserted);
+    }
+
+    pub const fn illegal_opcodes(&self) -> IllegalOpcodePolicy {
+        self.micro.illegal_opcodes
+    }
+
+    pub fn set_illegal_opcodes(&mut self, policy: IllegalOpcodePolicy) {
+        info!("Machine::set_illegal_opcodes({policy:?})");
+        self.micro.illegal_opcodes = policy;
+    }
+
+    /// Returns the last illegal opcode run by the CPU since the previous call
+    pub const fn take_illegal_opcode(&mut self) -> Option<IllegalOpcode> {
+        self.micro.illegal_opcode.take()
+    }
+
+    /// Starts counting the executions and the cycles of each address and subroutine from
+    /// zero
+    pub fn start_profiling(&mut self) {
+        info!("Machine::start_profiling()");
+        self.micro.profiler = Some(Profiler::default());
+        self.micro.profiling = true;
+    }
+
+    /// Stops the profiling, the results are kept
+    pub fn stop_profiling(&mut self) {
+        info!("Machine::stop_profiling()");
+        self.micro.profiling = false;
+    }
+
+    pub const fn is_profiling(&self) -> bool {
+        self.micro.profiling
+    }
+
+    pub const fn profiler(&self) -> Option<&Profiler> {
+        self.micro.profiler.as_ref()
+    }
+
+    /// Starts marking the bytes executed, read or written, the log goes on from the previous
+    /// or imported one
+    pub fn start_code_data_log(&mut self) {
+        info!("Machine::start_code_data_log()");
+        self.mem.start_code_data_log();
+    }
+
+    pub fn stop_code_data_log(&mut self) {
+        info!("Machine::stop_code_data_log()");
+        self.mem.stop_code_data_log();
+    }
+
+    pub const fn is_logging_code_data(&self) -> bool {
+        self.mem.is_logging_code_data()
+    }
+
+    pub const fn code_data_log(&self) -> Option<&CodeDataLog> {
+        self.mem.code_data_log()
+    }
+
+    /// Replaces the code/data log, the disassembly shows the data bytes it knows as FCB
+    pub fn set_code_data_log(&mut self, log: Option<CodeDataLog>) {
+        info!("Machine::set_code_data_log()");
+        self.mem.set_code_data_log(log);
     }
-}
 
-fn get_ratio() -> usize {
-    crate::hardware::screen::DEFAULT_PIXEL_SIZE
+    /// Decodes `count` instructions from the given address
+    pub fn disassemble(&self, address: int, count: usize) -> Vec<Instruction> {
+        disassemble(address, count, &self.mem)
+    }
+
+    /// The names of the addresses: the MO5 ones and the ones loaded from files
+    pub const fn symbols(&self) -> &Symbols {
+        &self.symbols
+    }
+
+    pub fn add_symbols(&mut self, symbols: Symbols) {
+        info!("Machine::add_symbols({} symbols)", symbols.len());
+        self.symbols.extend(symbols);
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::hardware::bus::Bus;
+    use crate::hardware::debugger::{Access, Stop, WatchHit};
+    use crate::hardware::profiler::{EXECUTED, READ, WRITTEN};
+    use crate::hardware::screen::WIDTH;
     use rstest::rstest;
 
     #[rstest]
     fn test_loop() {
         let mut machine = Machine::default();
         let mut image = None;
-        for i in 0..10 {
+        for _ in 0..10 {
             image = Some(machine.run_loop());
         }
         let image = image.flatten().expect("I need an image to test");
-        let result = image
-            .data
-            .into_iter()
-            .map(|pixel| *pixel as u64)
-            .sum::<u64>();
-        assert_eq!(276459696, result);
+        // the sum of the RGB bytes of the 320x200 picture, 9 times less than the 3x scaled one
+        // plus the cursor drawn by the frame interrupt: the line 7 of the first character is
+        // cyan instead of blue, 8 pixels with 240 more green
+        let cursor = 7 * WIDTH * 3;
+        assert_eq!([0, 240, 240], image.data[cursor..cursor + 3]);
+        let result = image.data.iter().map(|pixel| *pixel as u64).sum::<u64>();
+        assert_eq!(276442416 / 9 + 8 * 240, result);
+    }
+
+    // 6000 BSR 6006 / 6002 LDA #1 / 6004 BRA 6004 / 6006 LDA #2 / 6008 RTS
+    fn machine_with_program() -> Machine {
+        let mut machine = Machine::default();
+        let program = [0x8D, 0x04, 0x86, 0x01, 0x20, 0xFE, 0x86, 0x02, 0x39];
+        for (i, byte) in program.into_iter().enumerate() {
+            machine.mem.write(0x6000 + i as u16, byte);
+        }
+        let registers = Registers {
+            pc: 0x6000,
+            s: 0x9000,
+            cc: 0x50,
+            ..machine.registers()
+        };
+        machine.set_registers(&registers);
+        machine
+    }
+
+    #[test]
+    fn test_load_binary() {
+        let mut machine = Machine::default();
+        let pc = machine.registers().pc;
+        // LDA #$2A, STA $7000, BRA *
+        let program = vec![0x86, 0x2A, 0xB7, 0x70, 0x00, 0x20, 0xFE];
+        let mut binary = Binary::raw(program, 0x6000);
+        machine.load_binary(&binary, None).unwrap();
+        assert_eq!(0xB7, machine.mem.peek(0x6002));
+        assert_eq!(pc, machine.registers().pc);
+
+        binary.exec = None;
+        assert!(
+            machine
+                .load_binary(&binary, Some(Entry::default()))
+                .is_err()
+        );
+        machine.pause();
+        let entry = Entry {
+            pc: Some(0x6000),
+            s: Some(0x9000),
+        };
+        machine.load_binary(&binary, Some(entry)).unwrap();
+        assert!(machine.is_running());
+        machine.run_loop();
+        assert_eq!(0x2A, machine.mem.peek(0x7000));
+        assert_eq!(0x6005, machine.registers().pc);
+        assert_eq!(0x9000, machine.registers().s);
+    }
+
+    #[test]
+    fn test_restart() {
+        let mut machine = Machine::default();
+        machine.run_frames(10);
+        assert!(
+            machine
+                .restart(Reset::Hard, None, "10 PRINT \"€\"")
+                .is_err()
+        );
+        machine.restart(Reset::Hard, None, "10 print 1\n").unwrap();
+        machine.run_frames(200);
+        assert_eq!(Ok("10 PRINT 1\n".to_string()), machine.basic_listing());
+
+        // LDA #$2A, STA $7000, BRA *
+        let program = vec![0x86, 0x2A, 0xB7, 0x70, 0x00, 0x20, 0xFE];
+        let binary = Binary::raw(program, 0x6000);
+        machine.restart(Reset::Soft, Some(binary), "").unwrap();
+        machine.run_frames(99);
+        assert_eq!(0, machine.mem.peek(0x7000));
+        machine.run_frames(2);
+        assert_eq!(0x2A, machine.mem.peek(0x7000));
+    }
+
+    #[test]
+    fn test_registers() {
+        let mut machine = machine_with_program();
+        let registers = Registers {
+            a: 0x12,
+            b: 0x34,
+            dp: 0x20,
+            cc: 0xD5,
+            x: 0x1234,
+            y: 0x5678,
+            u: 0x9ABC,
+            s: 0xDEF0,
+            pc: 0x6002,
+        };
+        machine.set_registers(&registers);
+        assert_eq!(registers, machine.registers());
+    }
+
+    #[test]
+    fn test_breakpoint() {
+        let mut machine = machine_with_program();
+        machine.toggle_breakpoint(0x6006);
+        machine.run_loop();
+        assert!(!machine.is_running());
+        assert_eq!(0x6006, machine.registers().pc);
+        assert_eq!(0x8FFE, machine.registers().s);
+        // resuming runs the instruction on the breakpoint
+        machine.start();
+        machine.run_loop();
+        assert!(machine.is_running());
+        assert_eq!(0x6004, machine.registers().pc);
+    }
+
+    #[test]
+    fn test_watchpoint() {
+        let mut machine = machine_with_program();
+        machine.add_watchpoint(Watchpoint {
+            start: 0x8FFE,
+            end: 0x8FFF,
+            access: Access::Write,
+            value: None,
+            bank: None,
+        });
+        machine.run_loop();
+        assert!(!machine.is_running());
+        // stopped after the BSR pushing the return address
+        assert_eq!(0x6006, machine.registers().pc);
+        assert!(matches!(
+            machine.debugger().last_stop(),
+            Some(Stop::Watchpoint(WatchHit { write: true, .. }))
+        ));
+    }
+
+    #[test]
+    fn test_steps() {
+        let mut machine = machine_with_program();
+        machine.step_into();
+        machine.run_loop();
+        assert!(!machine.is_running());
+        assert_eq!(0x6006, machine.registers().pc);
+
+        machine.step_out();
+        machine.run_loop();
+        assert_eq!(0x6002, machine.registers().pc);
+        assert_eq!(0x02, machine.registers().a);
+        assert_eq!(0x9000, machine.registers().s);
+
+        machine.run_to(0x6004);
+        machine.run_loop();
+        assert_eq!(0x6004, machine.registers().pc);
+        assert_eq!(0x01, machine.registers().a);
+    }
+
+    #[test]
+    fn test_step_over() {
+        let mut machine = machine_with_program();
+        machine.step_over();
+        machine.run_loop();
+        assert!(!machine.is_running());
+        assert_eq!(0x6002, machine.registers().pc);
+        assert_eq!(0x02, machine.registers().a);
+        machine.step_over();
+        machine.run_loop();
+        assert_eq!(0x6004, machine.registers().pc);
+    }
+
+    #[test]
+    fn test_trace() {
+        let mut machine = machine_with_program();
+        let path =
+            std::env::temp_dir().join(format!("maurice_test_trace_{}.txt", std::process::id()));
+        let name = path.to_str().unwrap();
+        let filter = TraceFilter {
+            after: Some(0x6006),
+            ..TraceFilter::default()
+        };
+        machine.start_trace(name, filter).unwrap();
+        assert!(machine.is_tracing());
+        machine.run_loop();
+        assert_eq!(Ok(Some(name.to_string())), machine.stop_trace());
+        let trace = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        let lines: Vec<&str> = trace.lines().collect();
+        assert!(lines[0].starts_with("6006 86 02          LDA   #$02"));
+        assert!(lines[1].starts_with("6008 39"));
+        assert!(lines[1].contains(" A=02 "));
+        assert!(lines[2].starts_with("6002 86 01"));
+        assert!(lines[3..].iter().all(|line| line.starts_with("6004 20 FE")));
+    }
+
+    #[test]
+    fn test_profiling() {
+        let mut machine = machine_with_program();
+        machine.start_profiling();
+        machine.start_code_data_log();
+        machine.run_loop();
+        machine.stop_profiling();
+        machine.stop_code_data_log();
+        let profiler = machine.profiler().unwrap();
+        let routines = profiler.hottest_routines(1);
+        assert_eq!(0x6006, routines[0].0);
+        assert_eq!(1, routines[0].1.calls);
+        assert_eq!(7, routines[0].1.cycles);
+        assert_eq!(vec![0x6004], profiler.hottest_addresses(1));
+        let log = machine.code_data_log().unwrap();
+        assert_eq!(EXECUTED, log.flags(0x6008));
+        // the return address pushed by BSR
+        assert_eq!(WRITTEN | READ, log.flags(0x8FFF));
+        assert!(!machine.is_profiling());
+        assert!(!machine.is_logging_code_data());
+    }
+
+    #[test]
+    fn test_recordings() {
+        let dir = std::env::temp_dir();
+        let audio = dir.join(format!("maurice_test_audio_{}.wav", std::process::id()));
+        let video = dir.join(format!("maurice_test_video_{}.gif", std::process::id()));
+        let mut machine = Machine::default();
+        machine
+            .start_audio_recording(audio.to_str().unwrap())
+            .unwrap();
+        machine
+            .start_video_capture(video.to_str().unwrap(), VideoFormat::Gif, 1)
+            .unwrap();
+        // a sample at the start of each period of the sample rate
+        let samples_until = |time: u64| (time * SAMPLE_RATE as u64 / CYCLES_PER_SECOND + 1) as u32;
+        machine.run_frames(5);
+        let video_samples = samples_until(machine.time());
+        machine.stop_video_capture().unwrap();
+        assert!(machine.is_recording_audio());
+        machine.run_frames(5);
+        let audio_samples = samples_until(machine.time());
+        assert_eq!(
+            Some(audio.to_str().unwrap().to_string()),
+            machine.stop_audio_recording().unwrap()
+        );
+        let samples = |path: &std::path::Path| {
+            let bytes = std::fs::read(path).unwrap();
+            std::fs::remove_file(path).unwrap();
+            u32::from_le_bytes(bytes[40..44].try_into().unwrap())
+        };
+        assert_eq!(audio_samples, samples(&audio));
+        assert_eq!(video_samples, samples(&video.with_extension("wav")));
+        std::fs::remove_file(&video).unwrap();
+    }
+
+    #[test]
+    fn test_tape_signal() {
+        let mut machine = Machine::default();
+        // the ROM reads the bits and drives the motor itself
+        machine.unpatch(0xF168).unwrap();
+        machine.unpatch(0xF18B).unwrap();
+        machine.set_k7(basic::tape("ab", "10 PRINT 1\n20 A=2").unwrap());
+        machine.run_frames(100);
+        machine.type_text("LOAD\n").unwrap();
+        machine.run_frames(50);
+        assert!(machine.mem.k7_motor_on());
+        machine.run_frames(300);
+        assert!(!machine.mem.k7_motor_on());
+        assert_eq!(
+            Ok("10 PRINT 1\n20 A=2\n".to_string()),
+            machine.basic_listing()
+        );
+    }
+
+    #[test]
+    fn test_schedule() {
+        let mut machine = Machine::default();
+        // a device outside of the machine counting the frames in its handler
+        fn tick(machine: &mut Machine, time: u64, count: u8) {
+            machine.mem.write(0x7000, count);
+            machine.schedule(
+                time + CYCLES_PER_FRAME as u64,
+                "device",
+                move |machine, time| tick(machine, time, count + 1),
+            );
+        }
+        machine.schedule(machine.time(), "device", |machine, time| {
+            tick(machine, time, 0)
+        });
+        machine.run_frames(10);
+        assert_eq!(10, machine.mem.peek(0x7000));
+        machine.cancel("device");
+        machine.run_frames(10);
+        assert_eq!(10, machine.mem.peek(0x7000));
     }
 }
@@ -1,18 +1,118 @@
 #![allow(non_snake_case)]
 
+use std::cell::Cell;
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::{BufWriter, Write};
 
+use crate::hardware::M6809::Registers;
+use crate::hardware::bus::Bus;
+use crate::hardware::debugger::{WatchHit, Watchpoint};
+use crate::hardware::disassembler::length;
+use crate::hardware::interrupts::{InterruptLines, Line, Source};
 use crate::hardware::k7::K7;
-use crate::hardware::screen::Screen;
+use crate::hardware::pia::{Pia6821, Port};
+use crate::hardware::profiler::CodeDataLog;
+use crate::hardware::screen::{CYCLES_PER_LINE, HEIGHT, WINDOW_START};
+use crate::hardware::traps::{Trap, TrapHandler};
 use crate::{bios, int};
 use chrono::Local;
 use log::{debug, info, warn};
 
+/// The size of the pages of the mapper
+const PAGE_SIZE: usize = 0x1000;
+
+/// The banks of the memory, the CPU sees them through the mapper
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bank {
+    Point,
+    Color,
+    Ram1,
+    Ram2,
+    LineA,
+    LineB,
+    Rom,
+}
+
+impl Bank {
+    pub const ALL: [Self; 7] = [
+        Self::Point,
+        Self::Color,
+        Self::Ram1,
+        Self::Ram2,
+        Self::LineA,
+        Self::LineB,
+        Self::Rom,
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Point => "POINT",
+            Self::Color => "COLOR",
+            Self::Ram1 => "RAM1",
+            Self::Ram2 => "RAM2",
+            Self::LineA => "LINEA",
+            Self::LineB => "LINEB",
+            Self::Rom => "ROM",
+        }
+    }
+
+    /// The pages of 4KB of the bank
+    pub const fn pages(self) -> std::ops::Range<usize> {
+        match self {
+            Self::Point => 0..2,
+            Self::Color => 2..4,
+            Self::Ram1 => 4..8,
+            Self::Ram2 => 8..12,
+            Self::LineA => 12..13,
+            Self::LineB => 13..14,
+            Self::Rom => 14..18,
+        }
+    }
+
+    /// Returns the bank holding the given page of 4KB
+    const fn of_page(page: usize) -> Self {
+        match page {
+            0..=1 => Self::Point,
+            2..=3 => Self::Color,
+            4..=7 => Self::Ram1,
+            8..=11 => Self::Ram2,
+            12 => Self::LineA,
+            13 => Self::LineB,
+            _ => Self::Rom,
+        }
+    }
+}
+
+/// An address space of the memory viewer: what the CPU sees through the mapper, or a bank
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Space {
+    Cpu,
+    Bank(Bank),
+}
+
+impl Space {
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Cpu => "CPU",
+            Self::Bank(bank) => bank.name(),
+        }
+    }
+
+    pub const fn size(self) -> usize {
+        match self {
+            Self::Cpu => 0x10000,
+            Self::Bank(bank) => {
+                let pages = bank.pages();
+                (pages.end - pages.start) * PAGE_SIZE
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Memory {
     // Lightpen parameters
-    pub(crate) light_pen_clic: bool,
     pub(crate) light_pen_x: int,
     pub(crate) light_pen_y: int,
 
@@ -23,30 +123,51 @@ pub struct Memory {
     // 12			LINEA 	1
     // 13 			LINEB 	1
     // 14 15 16 17 	ROM 	4
-    mem: Vec<Vec<int>>,
-    mapper: [int; 16],
+    mem: Box<[u8]>,
+    // the page seen by the CPU in each 4KB of its address space
+    mapper: [usize; 16],
     key: Vec<bool>,
     dirty: Vec<bool>,
 
-    /* Registres du 6821 */
-    ORA: int,
-    ORB: int,
-    DDRA: int,
-    DDRB: int,
-    CRA: int,
-    pub(crate) CRB: int,
+    /// The system PIA at A7C0: the video bank, the border, the light pen and the tape on A,
+    /// the sound and the keyboard on B, the frame signal on CB1
+    pub(crate) pia: Pia6821,
+    /// The PIA of the game extension at A7CC, for the joysticks and the music
+    pub(crate) extension_pia: Pia6821,
     pub(crate) sound_mem: u8,
 
+    /// The interrupt lines of the CPU
+    pub(crate) interrupts: InterruptLines,
+
     /* Registre du Gate Array */
-    GA0: int,
-    GA1: int,
-    GA2: int,
     pub(crate) GA3: int,
 
+    /* Position du faisceau */
+    // cycle of the frame at which the CPU started its current run
+    pub(crate) beam_start: int,
+    // cycles executed by the CPU since beam_start, updated at each instruction
+    pub(crate) cpu_clock: int,
+
+    // checked at each access by the CPU when not empty
+    watchpoints: Vec<Watchpoint>,
+    // the first watchpoint hit by the current instruction
+    watch_hit: Cell<Option<WatchHit>>,
+    // kept when the logging stops, for the disassembler and the export
+    code_data: Option<CodeDataLog>,
+    logging_code_data: bool,
+
+    // the routines emulated by the trap opcode, by the address of the opcode
+    traps: BTreeMap<u16, Trap>,
+
     k7_bit: u8,
     k7_char: u8,
 
     k7_in: Option<K7>,
+    // the level of the tape signal on PA7, high when no bit is played
+    k7_level: bool,
+    light_pen_button: bool,
+    // the last byte read on the tape, for the LED of the screen
+    k7_led: Option<u8>,
     k7_out: Option<BufWriter<File>>,
     is_file_opened_out: bool,
     k7_out_name: Option<String>,
@@ -55,27 +176,31 @@ pub struct Memory {
 impl Default for Memory {
     fn default() -> Self {
         Memory {
-            light_pen_clic: false,
             light_pen_x: 0,
             light_pen_y: 0,
-            mem: vec![vec![0; 0x1000]; 18],
+            mem: vec![0; 18 * PAGE_SIZE].into_boxed_slice(),
             mapper: [0, 1, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17],
             key: vec![false; 256],
             dirty: vec![false; 200],
-            ORA: 0,
-            ORB: 0,
-            DDRA: 0,
-            DDRB: 0,
-            CRA: 0,
-            CRB: 0,
+            pia: Pia6821::default(),
+            // the joysticks pull their lines up when they are released
+            extension_pia: Pia6821::with_inputs(0xFF, 0xFF),
             sound_mem: 0,
-            GA0: 0,
-            GA1: 0,
-            GA2: 0,
+            interrupts: InterruptLines::default(),
             GA3: 0,
+            beam_start: 0,
+            cpu_clock: 0,
+            watchpoints: Vec::new(),
+            watch_hit: Cell::new(None),
+            code_data: None,
+            logging_code_data: false,
+            traps: Memory::k7_traps(),
             k7_bit: 0,
             k7_char: 0,
             k7_in: None,
+            k7_level: true,
+            light_pen_button: false,
+            k7_led: None,
             k7_out: None,
             is_file_opened_out: false,
             k7_out_name: None,
@@ -84,69 +209,132 @@ impl Default for Memory {
 }
 
 impl Memory {
-    // read with io
-    pub(crate) fn read(&self, address: int) -> int {
-        let page = ((address & 0xF000) >> 12) as usize;
-        self.mem[self.mapper[page] as usize][(address & 0xFFF) as usize]
+    /// The offset in the pages of the byte the CPU sees at the address
+    #[inline(always)]
+    const fn offset(&self, address: u16) -> usize {
+        (self.mapper[(address >> 12) as usize] * PAGE_SIZE) | (address & 0xFFF) as usize
     }
 
-    pub(crate) fn read_16(&self, address: int) -> int {
-        let b1 = self.read(address);
-        let b2 = self.read(address + 1);
-        (b1 << 8) + b2
+    /// Reads like the CPU without triggering the watchpoints, for the debugger
+    pub(crate) fn peek(&self, address: int) -> int {
+        self.byte(address as u16) as int
     }
 
-    // write with io
-    pub(crate) fn write(&mut self, address: int, value: int) {
-        let page = ((address & 0xF000) >> 12) as usize;
-
-        if (self.mapper[page] >= 14) && (self.mapper[page] <= 17) {
-            return; // Protection en écriture de la ROM
+    #[inline(always)]
+    fn byte(&self, address: u16) -> u8 {
+        if address >> 12 == 0xA {
+            if (address & 0xFFFC) == 0xA7E4 {
+                return self.gate_array(address) as u8;
+            }
+            if let Some((pia, register)) = self.pia_at(address) {
+                return pia.peek(register);
+            }
         }
+        self.mem[self.offset(address)]
+    }
 
-        if address < 0x1F40 {
-            self.dirty[(address / 40) as usize] = true;
-        }
-        if page == 0x0A {
-            self.hardware(address, value);
-        } else {
-            self.mem[self.mapper[page] as usize][(address & 0xFFF) as usize] = value & 0xFF;
+    /// The gate array counters follow the beam:
+    /// - A7E4 : bits 8 to 1 of the line counter
+    /// - A7E5 : bit 0 of the line counter in bit 7, cycle in the line in bits 5 to 0
+    /// - A7E6 : line of the picture being scanned, 0xFF in the borders
+    /// - A7E7 : bit 7 set in the window, bit 5 set while the line displays pixels
+    fn gate_array(&self, address: u16) -> int {
+        let cycle = self.beam_start + self.cpu_clock;
+        let line = cycle / CYCLES_PER_LINE;
+        let column = cycle % CYCLES_PER_LINE;
+        match address {
+            0xA7E4 => (line >> 1) & 0xFF,
+            0xA7E5 => ((line & 1) << 7) | column,
+            0xA7E6 => {
+                let picture_line = (cycle - WINDOW_START).div_euclid(CYCLES_PER_LINE);
+                if (0..HEIGHT as int).contains(&picture_line) {
+                    picture_line
+                } else {
+                    0xFF
+                }
+            }
+            _ => {
+                if column < 40 {
+                    self.GA3 | 0x20
+                } else {
+                    self.GA3
+                }
+            }
         }
     }
 
     // write with io without Protection
-    fn write_p(&mut self, address: int, value: int) {
+    fn write_p(&mut self, address: u16, value: u8) {
         if address < 0x1F40 {
             self.dirty[(address / 40) as usize] = true;
         }
-        let page = (address & 0xF000) >> 12;
-        if page == 0x0A {
+        if address >> 12 == 0x0A {
             self.hardware(address, value);
         } else {
-            self.mem[self.mapper[page as usize] as usize][(address & 0xFFF) as usize] =
-                value & 0xFF;
+            self.mem[self.offset(address)] = value;
         }
     }
 
     // read without io
     fn get(&mut self, address: int) -> int {
-        let page = (address & 0xF000) >> 12;
-        self.mem[self.mapper[page as usize] as usize][(address & 0xFFF) as usize]
+        self.mem[self.offset(address as u16)] as int
     }
 
     pub(crate) fn set(&mut self, address: int, value: int) {
-        let page = (address & 0xF000) >> 12;
-        self.mem[self.mapper[page as usize] as usize][(address & 0xFFF) as usize] = value & 0xFF;
+        self.mem[self.offset(address as u16)] = value as u8;
     }
 
     pub(crate) fn POINT(&mut self, address: int) -> int {
-        let page = (address & 0xF000) >> 12;
-        self.mem[page as usize][(address & 0xFFF) as usize]
+        self.mem[(address & 0xFFFF) as usize] as int
     }
 
     pub(crate) fn COLOR(&mut self, address: int) -> int {
-        let page = (address & 0xF000) >> 12;
-        self.mem[(page + 2) as usize][(address & 0xFFF) as usize]
+        self.mem[2 * PAGE_SIZE + (address & 0xFFFF) as usize] as int
+    }
+
+    /// Reads a byte of the space without side effect, the offset must be below its length
+    pub fn peek_space(&self, space: Space, offset: usize) -> u8 {
+        match space {
+            Space::Cpu => self.peek(offset as int) as u8,
+            Space::Bank(bank) => self.mem[bank.pages().start * PAGE_SIZE + offset],
+        }
+    }
+
+    /// Changes a byte of the space, ROM included. The hardware registers are not updated.
+    pub fn poke_space(&mut self, space: Space, offset: usize, value: u8) {
+        let (index, video) = match space {
+            Space::Cpu => (self.offset(offset as u16), offset < 0x2000),
+            Space::Bank(bank) => (
+                bank.pages().start * PAGE_SIZE + offset,
+                matches!(bank, Bank::Point | Bank::Color),
+            ),
+        };
+        self.mem[index] = value;
+        let line = (offset % 0x2000) / 40;
+        if video && line < self.dirty.len() {
+            self.dirty[line] = true;
+        }
+    }
+
+    /// Copies the bytes of the space from `start`, up to its end
+    pub fn read_space(&self, space: Space, start: usize, len: usize) -> Vec<u8> {
+        (start..(start + len).min(space.size()))
+            .map(|offset| self.peek_space(space, offset))
+            .collect()
+    }
+
+    pub fn write_space(&mut self, space: Space, start: usize, data: &[u8]) -> Result<(), String> {
+        if start + data.len() > space.size() {
+            return Err(format!(
+                "{} bytes at {start:04X} exceed the {} space",
+                data.len(),
+                space.name()
+            ));
+        }
+        for (i, value) in data.iter().enumerate() {
+            self.poke_space(space, start + i, *value);
+        }
+        Ok(())
     }
 
     pub(crate) fn is_dirty(&mut self, line: usize) -> bool {
@@ -155,34 +343,123 @@ impl Memory {
         ret
     }
 
-    pub(crate) fn set_all_dirty(&mut self) {
+    /// Returns the bank the CPU sees at the given address
+    pub const fn bank_of(&self, address: int) -> Bank {
+        Bank::of_page(self.mapper[((address & 0xF000) >> 12) as usize])
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        info!("Memory::add_watchpoint({watchpoint:?})");
+        self.watchpoints.push(watchpoint);
+    }
+
+    pub fn remove_watchpoint(&mut self, index: usize) {
+        if index < self.watchpoints.len() {
+            self.watchpoints.remove(index);
+        }
+    }
+
+    pub(crate) fn is_watching(&self) -> bool {
+        !self.watchpoints.is_empty()
+    }
+
+    /// Returns the watchpoint hit since the last call, if any
+    pub(crate) fn take_watch_hit(&self) -> Option<WatchHit> {
+        self.watch_hit.take()
+    }
+
+    /// Starts marking the bytes executed, read and written, a log already started goes on
+    pub(crate) fn start_code_data_log(&mut self) {
+        self.code_data.get_or_insert_default();
+        self.logging_code_data = true;
+    }
+
+    /// Stops the logging, the log is kept
+    pub(crate) const fn stop_code_data_log(&mut self) {
+        self.logging_code_data = false;
+    }
+
+    pub(crate) fn set_code_data_log(&mut self, log: Option<CodeDataLog>) {
+        self.logging_code_data &= log.is_some();
+        self.code_data = log;
+    }
+
+    pub const fn code_data_log(&self) -> Option<&CodeDataLog> {
+        self.code_data.as_ref()
+    }
+
+    pub(crate) const fn is_logging_code_data(&self) -> bool {
+        self.logging_code_data
+    }
+
+    /// Marks the bytes of the instruction the CPU is about to run
+    pub(crate) fn log_execution(&self, pc: int) {
+        if self.logging_code_data
+            && let Some(log) = &self.code_data
+        {
+            log.execute(pc, length(pc, self));
+        }
+    }
+
+    fn watch(&self, address: int, value: int, write: bool) {
+        let address = address & 0xFFFF;
+        if self.watch_hit.get().is_some() {
+            return;
+        }
+        let bank = self.bank_of(address);
+        if self
+            .watchpoints
+            .iter()
+            .any(|watchpoint| watchpoint.matches(address, value, write, bank))
+        {
+            self.watch_hit.set(Some(WatchHit {
+                address,
+                value,
+                write,
+            }));
+        }
+    }
+
+    pub fn set_all_dirty(&mut self) {
         self.dirty.fill(true);
     }
 
+    /// The border color is selected by the bits 1 to 4 of the PIA port A
+    pub(crate) const fn border_color(&self) -> usize {
+        ((self.pia.pins(Port::A) >> 1) & 0x0F) as usize
+    }
+
     pub(crate) fn reset(&mut self) {
         for i in 0..0xFFFF {
             self.set(i, 0x00);
         }
         self.load_rom();
-        self.CRA = 0x00;
-        self.CRB = 0x00;
-        self.DDRA = 0x5F;
-        self.DDRB = 0x7F;
-
-        self.mem[0xA + 2][0x7CC] = 0xFF;
-        self.mem[0xA + 2][0x7CD] = 0xFF;
-        self.mem[0xA + 2][0x7CE] = 0xFF;
-        self.mem[0xA + 2][0x7CF] = 0xFF;
+        self.pia.reset();
+        self.extension_pia.reset();
+        self.interrupts.release_all();
+        // the directions set by the ROM
+        self.pia.write(0, 0x5F);
+        self.pia.write(2, 0x7F);
+        self.update_keyboard();
+        self.update_port_a();
 
-        self.patch_k7();
+        // the ROM was reloaded
+        let addresses: Vec<u16> = self.traps.keys().copied().collect();
+        for address in addresses {
+            self.apply_trap(address);
+        }
     }
 
     #[cfg(not(target_family = "wasm"))]
     fn load_rom(&mut self) {
         let embedded_bios = bios::BIOS;
         let starting_address = 0xC000;
-        for i in starting_address..0x10000 {
-            self.write_p(i, embedded_bios[(i - starting_address) as usize] as int);
+        for i in starting_address..=0xFFFF {
+            self.write_p(i, embedded_bios[(i - starting_address) as usize]);
         }
         //
         // let u = "bios/mo5.rom";
@@ -203,74 +480,100 @@ impl Memory {
     #[cfg(target_arch = "wasm32")]
     fn load_rom(&mut self) {
         let starting_address = 0xC000;
-        for i in starting_address..0x10000 {
-            self.write_p(i, bios::BIOS[(i - starting_address) as usize] as int);
+        for i in starting_address..=0xFFFF {
+            self.write_p(i, bios::BIOS[(i - starting_address) as usize]);
+        }
+    }
+
+    /// The PIA mapped at an address and its register. The MO5 wires A0 to RS1 and A1 to RS0:
+    /// the ports come before the controls.
+    const fn pia_at(&self, address: u16) -> Option<(&Pia6821, u8)> {
+        let register = (((address & 1) << 1) | ((address >> 1) & 1)) as u8;
+        match address & 0xFFFC {
+            0xA7C0 => Some((&self.pia, register)),
+            0xA7CC => Some((&self.extension_pia, register)),
+            _ => None,
         }
     }
 
-    fn hardware(&mut self, ADR: int, mut OP: int) {
-        /* 6821 système */
-        /* acces à ORA ou DDRA */
-        if ADR == 0xA7C0 {
-            if (self.CRA & 0x04) == 0x04
-            /* Accès à ORA */
-            {
-                if (OP & 0x01) == 0x01 {
+    fn hardware(&mut self, address: u16, value: u8) {
+        let register = (((address & 1) << 1) | ((address >> 1) & 1)) as u8;
+        match address & 0xFFFC {
+            0xA7C0 => {
+                self.pia.write(register, value);
+                if self.pia.pins(Port::A) & 0x01 == 0x01 {
                     self.mapper[0] = 0;
                     self.mapper[1] = 1;
                 } else {
                     self.mapper[0] = 2;
                     self.mapper[1] = 3;
                 }
-                /* Mise à jour de ORA selon le masque DDRA */
-                OP |= 0x80 + 0x20; // gestion de ,l'inter optique
-                self.ORA = (self.ORA & (self.DDRA ^ 0xFF)) | (OP & self.DDRA);
-                if self.light_pen_clic {
-                    self.mem[0xA + 2][0x7C0] = self.ORA | 0x20;
-                } else {
-                    self.mem[0xA + 2][0x7C0] = self.ORA & (0xFF - 0x20);
-                }
-            } else {
-                self.DDRA = OP;
-                self.mem[0xA + 2][0x7C0] = OP;
+                self.sound_mem = (self.pia.pins(Port::B) & 1) << 5;
+                self.update_keyboard();
             }
-        } else if ADR == 0xA7C1
-        /* accès à ORB ou DDRB */
-        {
-            if (self.CRB & 0x04) == 0x04
-            /* Accès à ORB */
-            {
-                self.ORB = (self.ORB & (self.DDRB ^ 0xFF)) | (OP & self.DDRB);
+            0xA7CC => self.extension_pia.write(register, value),
+            _ => {}
+        }
+        self.update_pia_irq();
+    }
 
-                /* GESTION HARD DU CLAVIER */
+    /// PB7 reads the key selected by PB1 to PB6, low when it is pressed
+    fn update_keyboard(&mut self) {
+        let pressed = self.key[(self.pia.pins(Port::B) & 0x7E) as usize];
+        self.pia
+            .set_input(Port::B, if pressed { 0x00 } else { 0x80 });
+    }
 
-                if self.key[(self.ORB & 0x7E) as usize] {
-                    self.ORB &= 0x7F;
-                } else {
-                    self.ORB |= 0x80;
-                }
+    /// The light pen button is read on PA5
+    pub(crate) fn set_light_pen_clic(&mut self, clic: bool) {
+        self.light_pen_button = clic;
+        self.update_port_a();
+    }
 
-                self.mem[0xA + 2][0x7C1] = self.ORB;
-                self.sound_mem = ((self.ORB & 1) << 5) as u8;
-            } else {
-                self.DDRB = OP;
-                self.mem[0xA + 2][0x7C1] = OP;
-            }
-        } else if ADR == 0xA7C2 {
-            /* accès à CRA */
-            self.CRA = (self.CRA & 0xD0) | (OP & 0x3F);
-            self.mem[0xA + 2][0x7C2] = self.CRA;
-        } else if ADR == 0xA7C3 {
-            /* accès à CRB */
-            self.CRB = (self.CRB & 0xD0) | (OP & 0x3F);
-            self.mem[0xA + 2][0x7C3] = self.CRB;
-        }
+    /// PA5 reads the light pen button and PA7 the tape
+    fn update_port_a(&mut self) {
+        let button = if self.light_pen_button { 0x20 } else { 0x00 };
+        let tape = if self.k7_level { 0x80 } else { 0x00 };
+        self.pia.set_input(Port::A, button | tape);
+    }
+
+    /// The ROM starts the motor of the tape by setting CA2 low
+    pub(crate) const fn k7_motor_on(&self) -> bool {
+        matches!(self.pia.c2_output(Port::A), Some(false))
+    }
+
+    /// An edge of the tape signal
+    pub(crate) fn toggle_k7_level(&mut self) {
+        self.k7_level = !self.k7_level;
+        self.update_port_a();
+    }
+
+    /// The signal rests high on blank tape, where the ROM checks that a tape is playing
+    pub(crate) fn blank_k7(&mut self) {
+        self.k7_level = true;
+        self.update_port_a();
+    }
+
+    /// The frame signal is wired to CB1, the ROM sets its interrupt on the rising edge
+    pub(crate) fn frame_signal(&mut self, active: bool) {
+        self.pia.set_c1(Port::B, active);
+        self.update_pia_irq();
+    }
+
+    /// The interrupt outputs of the system PIA are wired to IRQ, the ones of the extension
+    /// go through the extension port
+    fn update_pia_irq(&self) {
+        let pia = self.pia.irq(Port::A) || self.pia.irq(Port::B);
+        self.interrupts.set(Line::Irq, Source::Pia, pia);
+        let extension = self.extension_pia.irq(Port::A) || self.extension_pia.irq(Port::B);
+        self.interrupts.set(Line::Irq, Source::Extension, extension);
     }
 
     pub(crate) fn set_key(&mut self, i: usize) {
         if !self.key[i] {
             debug!("key down:{}", i);
             self.key[i] = true;
+            self.update_keyboard();
         }
     }
 
@@ -278,12 +581,19 @@ impl Memory {
         if self.key[i] {
             debug!("key up:{}", i);
             self.key[i] = false;
+            self.update_keyboard();
         }
     }
 
     pub(crate) fn rem_key_slice(&mut self, start: usize, end: usize) {
         debug!("key up from {start} to {}", end - 1);
         self.key[start..end].fill(false);
+        self.update_keyboard();
+    }
+
+    /// Returns the byte read on the tape since the last call, if any
+    pub(crate) const fn take_k7_led(&mut self) -> Option<u8> {
+        self.k7_led.take()
     }
 
     pub(crate) fn rewind_k7(&mut self) {
@@ -344,30 +654,35 @@ impl Memory {
         self.is_file_opened_out
     }
 
-    fn readbit(&mut self, screen: &mut Screen) -> int {
-        if self.k7_in.is_none() {
-            return 0;
-        }
-
+    /// The next bit of the tape, from the highest bit of each byte
+    pub(crate) fn next_k7_bit(&mut self) -> Option<bool> {
+        let k7 = self.k7_in.as_mut()?;
         /* doit_on lire un caractere ? */
         if self.k7_bit == 0x00 {
-            if let Some(k7) = &mut self.k7_in {
-                match k7.read() {
-                    None => {
-                        warn!("End of file");
-                        return 0;
-                    }
-                    Some(char) => self.k7_char = char,
-                }
-            } else {
-                return 0;
-            }
-
+            self.k7_char = k7.read()?;
             self.k7_bit = 0x80;
         }
+        let bit = self.k7_char & self.k7_bit != 0;
+        self.k7_bit >>= 1;
+        Some(bit)
+    }
+
+    /// Whether the next bit is the first one of a block
+    pub(crate) fn k7_at_block(&self) -> bool {
+        self.k7_bit == 0 && self.k7_in.as_ref().is_some_and(K7::at_block)
+    }
+
+    fn readbit(&mut self) {
+        if self.k7_in.is_none() {
+            return;
+        }
+        let Some(bit) = self.next_k7_bit() else {
+            warn!("End of file");
+            return;
+        };
         let mut octet = self.get(0x2045);
 
-        if (self.k7_char & self.k7_bit) == 0 {
+        if !bit {
             octet <<= 1;
             // A=0x00;
             self.set(0xF16A, 0x00);
@@ -378,83 +693,202 @@ impl Memory {
         }
         /* positionne l'octet dans la page 0 du moniteur */
         self.set(0x2045, octet & 0xFF);
-        screen.led = (octet & 0xff) as u8;
-        screen.show_led = 10;
-        self.k7_bit >>= 1;
-        0
+        self.k7_led = Some((octet & 0xff) as u8);
     }
 
-    pub(crate) fn periph(&mut self, PC: int, S: int, A: int, screen: &mut Screen) {
-        if PC == 0xF169 {
-            self.readbit(screen);
-        }
-        /* Write K7 byte */
-        /* Merci  Olivier Tardieu pour le dsassemblage de la routine en ROM */
-        if PC == 0xF1B0 {
-            self.create_k7file(); // To do if necessary
+    /// Writes the byte of A on the tape
+    fn k7_write_byte(&mut self, registers: &mut Registers) {
+        /* Merci  Olivier Tardieu pour le dsassemblage de la routine en ROM */
+        self.create_k7file(); // To do if necessary
 
-            if !self.is_file_opened_out {
-                return;
-            }
-
-            let data_out = [A as u8];
-            if let Some(k7fos) = &mut self.k7_out
-                && let Err(result) = k7fos.write(&data_out)
-            {
-                eprintln!("Error writing to file: {}", result);
-            }
+        if !self.is_file_opened_out {
+            return;
         }
 
-        /* Motor On/Off/Test */
-        if PC == 0xF18C {
-            /* Mise � 0 du bit C*/
-            let mut c = self.get(S);
-            c &= 0xFE;
-            self.write(S, c);
-            //println!("motor ");
-        }
-        if PC == 0xf549 {
-            self.write(S + 6, self.light_pen_x >> 8);
-            self.write(S + 7, self.light_pen_x & 255);
-            self.write(S + 8, self.light_pen_y >> 8);
-            self.write(S + 9, self.light_pen_y & 255);
+        let data_out = [registers.a];
+        if let Some(k7fos) = &mut self.k7_out
+            && let Err(result) = k7fos.write(&data_out)
+        {
+            eprintln!("Error writing to file: {}", result);
         }
     }
 
-    fn patch_k7(&mut self) {
-        /*
+    /// Motor On/Off/Test
+    fn k7_motor(&mut self, registers: &mut Registers) {
+        /* Mise � 0 du bit C*/
+        let mut c = self.get(registers.s as int);
+        c &= 0xFE;
+        self.write(registers.s, c as u8);
+    }
 
-            PATCH une partie des fonctions du moniteur
+    fn light_pen(&mut self, registers: &mut Registers) {
+        self.write_16(registers.s.wrapping_add(6), self.light_pen_x as u16);
+        self.write_16(registers.s.wrapping_add(8), self.light_pen_y as u16);
+    }
 
-            la squence 02 39 correspond
-            Illegal (instruction)
-            NOP
-            le TRAP active la gestion des
-            priphriques, la valeur du
-            PC  ce moment permet de determiner
-            la fonction  effectuer
+    /*
 
-        */
-        // Crayon optique
-        self.set(0xf548, 0x02); // PER instruction émulateur
-        self.set(0xf549, 0x39); // RTS
+        PATCH une partie des fonctions du moniteur
+
+        la squence 02 39 correspond
+        Illegal (instruction)
+        NOP
+        le TRAP active la gestion des
+        priphriques, la valeur du
+        PC  ce moment permet de determiner
+        la fonction  effectuer
+
+    */
+    fn k7_traps() -> BTreeMap<u16, Trap> {
+        let traps: [(u16, &[u8], TrapHandler); 4] = [
+            // the bit is returned by LDA immediate, then RTS
+            (
+                0xF168,
+                &[0x02, 0x86, 0x00, 0x39],
+                Box::new(|mem, _| mem.readbit()),
+            ),
+            (0xF18B, &[0x02, 0x39], Box::new(Self::k7_motor)),
+            (0xF1AF, &[0x02, 0x39], Box::new(Self::k7_write_byte)),
+            // Crayon optique
+            (0xF548, &[0x02, 0x39], Box::new(Self::light_pen)),
+        ];
+        traps
+            .into_iter()
+            .filter_map(|(address, code, handler)| {
+                Trap::new(code, handler).ok().map(|trap| (address, trap))
+            })
+            .collect()
+    }
+
+    /// Installs a trap: the code, which starts with the trap opcode, is patched at the address
+    /// and the handler runs when the CPU reaches it. The ROM can be patched, the original
+    /// bytes are restored by [`Memory::unpatch`].
+    pub fn patch(
+        &mut self,
+        address: u16,
+        code: &[u8],
+        handler: impl FnMut(&mut Memory, &mut Registers) + 'static,
+    ) -> Result<(), String> {
+        let trap = Trap::new(code, Box::new(handler))?;
+        let range = trap.range(address);
+        if range.end > 0x10000 {
+            return Err(format!("The patch at {address:04X} ends after FFFF"));
+        }
+        if let Some(other) = self.traps.iter().find_map(|(other, installed)| {
+            let patched = installed.range(*other);
+            (patched.start < range.end && range.start < patched.end).then_some(*other)
+        }) {
+            return Err(format!(
+                "The patch at {address:04X} overlaps the trap at {other:04X}"
+            ));
+        }
+        self.traps.insert(address, trap);
+        self.apply_trap(address);
+        Ok(())
+    }
+
+    /// Removes the trap installed at the address and restores the original bytes
+    pub fn unpatch(&mut self, address: u16) -> Result<(), String> {
+        let trap = self
+            .traps
+            .remove(&address)
+            .ok_or_else(|| format!("No trap is installed at {address:04X}"))?;
+        for (i, byte) in trap.original.into_iter().enumerate() {
+            self.set(address as int + i as int, byte as int);
+        }
+        Ok(())
+    }
+
+    /// The addresses of the traps installed
+    pub fn traps(&self) -> Vec<u16> {
+        self.traps.keys().copied().collect()
+    }
 
-        self.set(0xF1AF, 0x02);
-        self.set(0xF1B0, 0x39);
+    /// Saves the bytes under the trap and writes its code
+    fn apply_trap(&mut self, address: u16) {
+        let Some(code) = self.traps.get(&address).map(|trap| trap.code.clone()) else {
+            return;
+        };
+        let mut original = Vec::with_capacity(code.len());
+        for (i, byte) in code.into_iter().enumerate() {
+            let at = address as int + i as int;
+            original.push(self.get(at) as u8);
+            self.set(at, byte as int);
+        }
+        if let Some(trap) = self.traps.get_mut(&address) {
+            trap.original = original;
+        }
+    }
+}
 
-        self.set(0xF18B, 0x02);
-        self.set(0xF18C, 0x39);
+impl Bus for Memory {
+    // read with io
+    #[inline(always)]
+    fn read(&self, address: u16) -> u8 {
+        let value = self.byte(address);
+        if !self.watchpoints.is_empty() {
+            self.watch(address as int, value as int, false);
+        }
+        if self.logging_code_data
+            && let Some(log) = &self.code_data
+        {
+            log.read(address as int);
+        }
+        if address >> 12 == 0xA
+            && let Some((pia, register)) = self.pia_at(address)
+        {
+            // reading a port acknowledges its interrupts
+            pia.read(register);
+            self.update_pia_irq();
+        }
+        value
+    }
 
-        self.set(0xF168, 0x02);
+    // write with io
+    #[inline(always)]
+    fn write(&mut self, address: u16, value: u8) {
+        if !self.watchpoints.is_empty() {
+            self.watch(address as int, value as int, true);
+        }
+        if self.logging_code_data
+            && let Some(log) = &self.code_data
+        {
+            log.write(address as int);
+        }
+        if self.mapper[(address >> 12) as usize] >= 14 {
+            return; // Protection en écriture de la ROM
+        }
+        self.write_p(address, value);
+    }
 
-        // LDA immediate for return
-        self.set(0xF169, 0x86); //RTS
-        self.set(0xF16A, 0x00); // no opcode
+    /// Runs the handler of the trap installed at the opcode, nothing when there is none
+    fn trap(&mut self, registers: &mut Registers) {
+        let address = registers.pc.wrapping_sub(1);
+        let Some(mut handler) = self
+            .traps
+            .get_mut(&address)
+            .and_then(|trap| trap.handler.take())
+        else {
+            return;
+        };
+        handler(self, registers);
+        // unless the handler removed or replaced its trap
+        if let Some(trap) = self.traps.get_mut(&address)
+            && trap.handler.is_none()
+        {
+            trap.handler = Some(handler);
+        }
+    }
 
-        self.set(0xF16B, 0x39);
+    /// The gate array needs the clock to give the position of the beam
+    #[inline(always)]
+    fn tick(&mut self, clock: int) {
+        self.cpu_clock = clock;
     }
 
-    fn unpatch_k7(&mut self) {}
+    fn interrupts(&self) -> &InterruptLines {
+        &self.interrupts
+    }
 }
 
 #[cfg(test)]
@@ -467,4 +901,169 @@ mod tests {
         memory.set_key(3);
         memory.key[3] = true;
     }
+
+    // stores PC and A, and returns B to the CPU
+    fn store_registers(mem: &mut Memory, registers: &mut Registers) {
+        mem.write_16(0x7000, registers.pc);
+        mem.write(0x7002, registers.a);
+        registers.b = 0x42;
+    }
+
+    #[test]
+    fn test_patch() {
+        let mut memory = Memory::default();
+        memory.reset();
+        memory.write_16(0x6000, 0x1234);
+        memory
+            .patch(0x6000, &[0x02, 0x39], store_registers)
+            .unwrap();
+        assert_eq!(0x0239, memory.read_16(0x6000));
+        let mut registers = Registers {
+            a: 0x12,
+            pc: 0x6001,
+            ..Registers::default()
+        };
+        memory.trap(&mut registers);
+        assert_eq!(0x6001, memory.read_16(0x7000));
+        assert_eq!(0x12, memory.read(0x7002));
+        assert_eq!(0x42, registers.b);
+        assert!(
+            memory
+                .patch(0x5FFF, &[0x02, 0x39], store_registers)
+                .is_err()
+        );
+        assert!(memory.patch(0x7000, &[0x39], store_registers).is_err());
+        assert!(
+            memory
+                .patch(0xFFFF, &[0x02, 0x39], store_registers)
+                .is_err()
+        );
+        memory.unpatch(0x6000).unwrap();
+        assert_eq!(0x1234, memory.read_16(0x6000));
+        assert!(memory.unpatch(0x6000).is_err());
+    }
+
+    #[test]
+    fn test_patch_state() {
+        let mut memory = Memory::default();
+        memory.reset();
+        let mut calls = 0;
+        memory
+            .patch(0x6000, &[0x02, 0x39], move |_, registers| {
+                calls += 1;
+                registers.a = calls;
+            })
+            .unwrap();
+        let mut registers = Registers {
+            pc: 0x6001,
+            ..Registers::default()
+        };
+        memory.trap(&mut registers);
+        memory.trap(&mut registers);
+        assert_eq!(2, registers.a);
+    }
+
+    #[test]
+    fn test_unpatch_rom() {
+        let mut memory = Memory::default();
+        memory.reset();
+        assert_eq!(vec![0xF168, 0xF18B, 0xF1AF, 0xF548], memory.traps());
+        assert_eq!(0x02, memory.read(0xF548));
+        memory.unpatch(0xF548).unwrap();
+        assert_eq!(bios::BIOS[0x3548], memory.read(0xF548));
+        assert_eq!(bios::BIOS[0x3549], memory.read(0xF549));
+        // the traps are patched again in the reloaded ROM
+        memory.reset();
+        assert_eq!(bios::BIOS[0x3548], memory.read(0xF548));
+        assert_eq!(0x02, memory.read(0xF168));
+    }
+
+    #[test]
+    fn test_keyboard() {
+        let mut memory = Memory::default();
+        memory.reset();
+        memory.write(0xA7C3, 0x04);
+        // PB1 to PB6 select the key, PB7 is low when it is pressed
+        memory.write(0xA7C1, 0x06);
+        assert_eq!(0x86, memory.read(0xA7C1));
+        memory.set_key(6);
+        assert_eq!(0x06, memory.read(0xA7C1));
+        memory.rem_key(6);
+        assert_eq!(0x86, memory.read(0xA7C1));
+    }
+
+    #[test]
+    fn test_pia_interrupt() {
+        let mut memory = Memory::default();
+        memory.reset();
+        // disabled interrupt, on the rising edge of CB1 like the ROM
+        memory.write(0xA7C3, 0x06);
+        memory.frame_signal(true);
+        assert!(!memory.interrupts.is_asserted(Line::Irq));
+        assert_eq!(0x86, memory.peek(0xA7C3));
+        // the flag stays set after the signal, enabling it interrupts
+        memory.frame_signal(false);
+        memory.write(0xA7C3, 0x07);
+        assert!(memory.interrupts.is_asserted(Line::Irq));
+        memory.write(0xA7C3, 0x06);
+        assert!(!memory.interrupts.is_asserted(Line::Irq));
+        memory.write(0xA7C3, 0x07);
+        assert!(memory.interrupts.is_asserted(Line::Irq));
+        memory.read(0xA7C1);
+        assert!(!memory.interrupts.is_asserted(Line::Irq));
+        assert_eq!(0x07, memory.peek(0xA7C3));
+        // the extension PIA holds the line through the extension port
+        memory.write(0xA7CE, 0x04);
+        memory.extension_pia.set_c1(Port::A, true);
+        memory.extension_pia.set_c1(Port::A, false);
+        memory.write(0xA7CE, 0x05);
+        assert!(memory.interrupts.is_asserted(Line::Irq));
+        memory.read(0xA7CC);
+        assert!(!memory.interrupts.is_asserted(Line::Irq));
+    }
+
+    #[test]
+    fn test_gate_array() {
+        let mut memory = Memory {
+            beam_start: WINDOW_START + 2 * CYCLES_PER_LINE,
+            cpu_clock: 10,
+            GA3: 0x80,
+            ..Default::default()
+        };
+        let line = (WINDOW_START + 2 * CYCLES_PER_LINE + 10) / CYCLES_PER_LINE;
+        let column = (WINDOW_START + 10) % CYCLES_PER_LINE;
+        assert_eq!(line >> 1, memory.read(0xA7E4) as int);
+        assert_eq!(((line & 1) << 7) | column, memory.read(0xA7E5) as int);
+        assert_eq!(2, memory.read(0xA7E6));
+        assert_eq!(0xA0, memory.read(0xA7E7));
+        memory.beam_start = 0;
+        memory.GA3 = 0;
+        assert_eq!(0xFF, memory.read(0xA7E6));
+    }
+
+    #[test]
+    fn test_spaces() {
+        let mut memory = Memory::default();
+        memory.reset();
+        assert_eq!(0x10000, Space::Cpu.size());
+        assert_eq!(0x4000, Space::Bank(Bank::Rom).size());
+        // the CPU sees the end of the ROM at the end of the address space
+        assert_eq!(
+            memory.peek_space(Space::Cpu, 0xFFFE),
+            memory.peek_space(Space::Bank(Bank::Rom), 0x3FFE)
+        );
+        memory
+            .write_space(Space::Bank(Bank::Ram1), 0x10, &[1, 2, 3])
+            .unwrap();
+        assert_eq!(vec![1, 2, 3], memory.read_space(Space::Cpu, 0x2010, 3));
+        memory.poke_space(Space::Cpu, 0x0028, 0x55);
+        assert!(memory.is_dirty(1));
+        assert_eq!(0x55, memory.peek_space(Space::Bank(Bank::Point), 0x0028));
+        assert!(
+            memory
+                .write_space(Space::Bank(Bank::LineA), 0xFFF, &[1, 2])
+                .is_err()
+        );
+        assert_eq!(2, memory.read_space(Space::Cpu, 0xFFFE, 16).len());
+    }
 }
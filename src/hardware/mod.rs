@@ -1,7 +1,22 @@
 pub mod M6809;
+pub mod basic;
+pub mod binary;
+pub mod bus;
+#[cfg(test)]
+mod conformance;
+pub mod debugger;
+pub mod disassembler;
+pub mod interrupts;
 pub mod k7;
 pub(crate) mod keyboard;
 pub mod machine;
 pub mod memory;
+pub mod pia;
+pub mod profiler;
+pub mod scheduler;
 pub mod screen;
 pub mod sound;
+pub mod symbols;
+pub mod trace;
+pub mod traps;
+pub mod wav;
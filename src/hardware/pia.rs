@@ -0,0 +1,399 @@
+use std::cell::Cell;
+
+/// The control register bits
+const C1_IRQ: u8 = 0x01;
+const C1_RISING: u8 = 0x02;
+const PORT_SELECTED: u8 = 0x04;
+// C2 input: enables its interrupt. C2 output: the level in set/reset mode, the pulse restore
+// in handshake mode
+const C2_BIT3: u8 = 0x08;
+// C2 input: the rising edge is active. C2 output: set/reset mode
+const C2_BIT4: u8 = 0x10;
+const C2_OUTPUT: u8 = 0x20;
+const IRQ2_FLAG: u8 = 0x40;
+const IRQ1_FLAG: u8 = 0x80;
+
+/// The sides of the PIA, each one has a port of 8 lines and two control lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Port {
+    A,
+    B,
+}
+
+impl Port {
+    pub const ALL: [Self; 2] = [Self::A, Self::B];
+
+    /// The side of a register, selected by RS1
+    const fn of(register: u8) -> Self {
+        if register & 0x02 == 0 {
+            Self::A
+        } else {
+            Self::B
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Side {
+    output: u8,
+    ddr: u8,
+    // the flags of bits 6 and 7 change when the CPU reads the port
+    control: Cell<u8>,
+    // the levels driven by the devices on the lines of the port
+    input: u8,
+    c1: bool,
+    // the level of C2, driven by the PIA when it is an output
+    c2: Cell<bool>,
+}
+
+impl Side {
+    const fn c2_is_output(&self) -> bool {
+        self.control.get() & C2_OUTPUT != 0
+    }
+
+    /// C2 in handshake mode goes low on an access to the port and is restored by C1
+    const fn c2_handshake(&self) -> bool {
+        self.control.get() & (C2_OUTPUT | C2_BIT4) == C2_OUTPUT
+    }
+
+    const fn pins(&self) -> u8 {
+        (self.output & self.ddr) | (self.input & !self.ddr)
+    }
+
+    fn peek(&self, control: bool) -> u8 {
+        if control {
+            self.control.get()
+        } else if self.control.get() & PORT_SELECTED != 0 {
+            self.pins()
+        } else {
+            self.ddr
+        }
+    }
+
+    /// Reading the port clears the flags
+    fn acknowledge(&self) {
+        self.control
+            .set(self.control.get() & !(IRQ1_FLAG | IRQ2_FLAG));
+    }
+
+    /// Starts the handshake: C2 goes low until the next active edge of C1. In pulse mode it
+    /// only lasts one cycle, shorter than what the devices sample, so it stays high.
+    fn strobe(&self) {
+        if self.c2_handshake() && self.control.get() & C2_BIT3 == 0 {
+            self.c2.set(false);
+        }
+    }
+
+    fn write_control(&mut self, value: u8) {
+        let mut control = (self.control.get() & (IRQ1_FLAG | IRQ2_FLAG)) | (value & 0x3F);
+        if control & C2_OUTPUT != 0 {
+            // the flag of C2 is only set when it is an input
+            control &= !IRQ2_FLAG;
+            self.c2
+                .set(control & C2_BIT4 == 0 || control & C2_BIT3 != 0);
+        }
+        self.control.set(control);
+    }
+
+    fn set_c1(&mut self, level: bool) {
+        let rising = self.control.get() & C1_RISING != 0;
+        if level != self.c1 && level == rising {
+            self.control.set(self.control.get() | IRQ1_FLAG);
+            if self.c2_handshake() {
+                self.c2.set(true);
+            }
+        }
+        self.c1 = level;
+    }
+
+    fn set_c2(&mut self, level: bool) {
+        if self.c2_is_output() {
+            return;
+        }
+        let rising = self.control.get() & C2_BIT4 != 0;
+        if level != self.c2.get() && level == rising {
+            self.control.set(self.control.get() | IRQ2_FLAG);
+        }
+        self.c2.set(level);
+    }
+
+    /// The interrupt output is held while an enabled flag is set
+    const fn irq(&self) -> bool {
+        let control = self.control.get();
+        (control & IRQ1_FLAG != 0 && control & C1_IRQ != 0)
+            || (control & IRQ2_FLAG != 0 && control & C2_BIT3 != 0 && control & C2_OUTPUT == 0)
+    }
+}
+
+/// The Motorola 6821 Peripheral Interface Adapter. The registers are numbered by the RS1 and
+/// RS0 inputs: the port or the DDR of A, the control of A, then the same for B. The interrupt
+/// outputs must be read again after each access and each change of the control lines.
+#[derive(Debug, Default)]
+pub struct Pia6821 {
+    a: Side,
+    b: Side,
+}
+
+impl Pia6821 {
+    /// A PIA whose ports read the given levels on the lines not driven by the devices
+    pub fn with_inputs(a: u8, b: u8) -> Self {
+        let mut pia = Self::default();
+        pia.a.input = a;
+        pia.b.input = b;
+        pia
+    }
+
+    const fn side(&self, port: Port) -> &Side {
+        match port {
+            Port::A => &self.a,
+            Port::B => &self.b,
+        }
+    }
+
+    const fn side_mut(&mut self, port: Port) -> &mut Side {
+        match port {
+            Port::A => &mut self.a,
+            Port::B => &mut self.b,
+        }
+    }
+
+    /// The registers are cleared, the levels of the lines are kept
+    pub fn reset(&mut self) {
+        for port in Port::ALL {
+            let side = self.side_mut(port);
+            side.output = 0;
+            side.ddr = 0;
+            side.control.set(0);
+        }
+    }
+
+    /// Reads a register without side effect, for the debugger
+    pub fn peek(&self, register: u8) -> u8 {
+        self.side(Port::of(register)).peek(register & 0x01 != 0)
+    }
+
+    /// Reads a register like the CPU: reading a port clears its flags, and on the side A
+    /// starts the handshake of CA2
+    pub fn read(&self, register: u8) -> u8 {
+        let value = self.peek(register);
+        let port = Port::of(register);
+        let side = self.side(port);
+        if register & 0x01 == 0 && side.control.get() & PORT_SELECTED != 0 {
+            side.acknowledge();
+            if port == Port::A {
+                side.strobe();
+            }
+        }
+        value
+    }
+
+    /// Writes a register, on the side B writing the port starts the handshake of CB2
+    pub fn write(&mut self, register: u8, value: u8) {
+        let port = Port::of(register);
+        let side = self.side_mut(port);
+        if register & 0x01 != 0 {
+            side.write_control(value);
+        } else if side.control.get() & PORT_SELECTED != 0 {
+            side.output = value;
+            if port == Port::B {
+                side.strobe();
+            }
+        } else {
+            side.ddr = value;
+        }
+    }
+
+    /// The levels of the lines of a port: the output register on the lines of the DDR, the
+    /// devices on the others
+    pub const fn pins(&self, port: Port) -> u8 {
+        self.side(port).pins()
+    }
+
+    /// Sets the levels driven by the devices on a port
+    pub const fn set_input(&mut self, port: Port, value: u8) {
+        self.side_mut(port).input = value;
+    }
+
+    /// Changes the level of C1, its active edge sets the flag of the bit 7 of the control
+    pub fn set_c1(&mut self, port: Port, level: bool) {
+        self.side_mut(port).set_c1(level);
+    }
+
+    /// Changes the level of C2 when it is an input, its active edge sets the flag of the bit 6
+    pub fn set_c2(&mut self, port: Port, level: bool) {
+        self.side_mut(port).set_c2(level);
+    }
+
+    /// The level of C2, driven by the PIA when it is an output
+    pub const fn c2(&self, port: Port) -> bool {
+        self.side(port).c2.get()
+    }
+
+    /// The level of C2 when the PIA drives it, None when it is an input
+    pub const fn c2_output(&self, port: Port) -> Option<bool> {
+        let side = self.side(port);
+        if side.c2_is_output() {
+            Some(side.c2.get())
+        } else {
+            None
+        }
+    }
+
+    /// The interrupt output of a side, IRQA or IRQB
+    pub const fn irq(&self, port: Port) -> bool {
+        self.side(port).irq()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    const PRA: u8 = 0;
+    const CRA: u8 = 1;
+    const PRB: u8 = 2;
+    const CRB: u8 = 3;
+
+    #[test]
+    fn test_ddr_and_port() {
+        let mut pia = Pia6821::with_inputs(0xA5, 0xFF);
+        pia.write(PRA, 0x0F);
+        assert_eq!(0x0F, pia.peek(PRA));
+        pia.write(CRA, PORT_SELECTED);
+        pia.write(PRA, 0x33);
+        // the low lines are outputs
+        assert_eq!(0xA3, pia.read(PRA));
+        assert_eq!(0xA3, pia.pins(Port::A));
+        pia.write(CRA, 0);
+        assert_eq!(0x0F, pia.read(PRA));
+        assert_eq!(0xFF, pia.pins(Port::B));
+    }
+
+    #[rstest]
+    #[case::falling(0, false, true)]
+    #[case::rising(C1_RISING, true, false)]
+    fn test_c1_edge(#[case] edge: u8, #[case] active: bool, #[case] inactive: bool) {
+        let mut pia = Pia6821::default();
+        pia.set_c1(Port::B, inactive);
+        pia.write(CRB, PORT_SELECTED | edge);
+        pia.set_c1(Port::B, inactive);
+        assert_eq!(0, pia.peek(CRB) & IRQ1_FLAG);
+        pia.set_c1(Port::B, active);
+        assert_eq!(IRQ1_FLAG, pia.peek(CRB) & IRQ1_FLAG);
+        // the flag is set without interrupt until it is enabled
+        assert!(!pia.irq(Port::B));
+        pia.write(CRB, PORT_SELECTED | edge | C1_IRQ);
+        assert!(pia.irq(Port::B));
+        // the other edge does not clear it, reading the port does
+        pia.set_c1(Port::B, inactive);
+        assert!(pia.irq(Port::B));
+        pia.read(PRB);
+        assert!(!pia.irq(Port::B));
+        assert_eq!(0, pia.peek(CRB) & IRQ1_FLAG);
+    }
+
+    #[test]
+    fn test_reading_ddr_keeps_flags() {
+        let mut pia = Pia6821::default();
+        pia.write(CRA, C1_IRQ);
+        pia.set_c1(Port::A, true);
+        pia.set_c1(Port::A, false);
+        pia.read(PRA);
+        assert!(pia.irq(Port::A));
+        assert!(!pia.irq(Port::B));
+    }
+
+    #[rstest]
+    #[case::falling(0, false)]
+    #[case::rising(C2_BIT4, true)]
+    fn test_c2_input(#[case] edge: u8, #[case] active: bool) {
+        let mut pia = Pia6821::default();
+        pia.set_c2(Port::A, !active);
+        pia.write(CRA, PORT_SELECTED | edge);
+        pia.set_c2(Port::A, active);
+        assert_eq!(IRQ2_FLAG, pia.peek(CRA) & IRQ2_FLAG);
+        assert!(!pia.irq(Port::A));
+        pia.write(CRA, PORT_SELECTED | edge | C2_BIT3);
+        assert!(pia.irq(Port::A));
+        pia.read(PRA);
+        assert!(!pia.irq(Port::A));
+    }
+
+    #[test]
+    fn test_c2_set_reset() {
+        let mut pia = Pia6821::default();
+        pia.write(CRB, C2_BIT4);
+        pia.set_c2(Port::B, true);
+        assert_eq!(IRQ2_FLAG, pia.peek(CRB) & IRQ2_FLAG);
+        pia.write(CRB, C2_OUTPUT | C2_BIT4 | C2_BIT3);
+        assert!(pia.c2(Port::B));
+        // an output clears the flag and ignores the devices
+        assert_eq!(0, pia.peek(CRB) & IRQ2_FLAG);
+        pia.set_c2(Port::B, false);
+        assert!(pia.c2(Port::B));
+        pia.write(CRB, C2_OUTPUT | C2_BIT4);
+        assert!(!pia.c2(Port::B));
+    }
+
+    #[test]
+    fn test_ca2_read_handshake() {
+        let mut pia = Pia6821::default();
+        pia.write(CRA, PORT_SELECTED | C2_OUTPUT);
+        assert!(pia.c2(Port::A));
+        pia.peek(PRA);
+        assert!(pia.c2(Port::A));
+        pia.read(PRA);
+        assert!(!pia.c2(Port::A));
+        // restored by the active edge of CA1
+        pia.set_c1(Port::A, true);
+        assert!(!pia.c2(Port::A));
+        pia.set_c1(Port::A, false);
+        assert!(pia.c2(Port::A));
+        // writing the port does not start it
+        pia.write(PRA, 0);
+        assert!(pia.c2(Port::A));
+    }
+
+    #[test]
+    fn test_cb2_write_handshake() {
+        let mut pia = Pia6821::default();
+        pia.write(CRB, PORT_SELECTED | C2_OUTPUT | C1_RISING);
+        pia.read(PRB);
+        assert!(pia.c2(Port::B));
+        pia.write(PRB, 0x12);
+        assert!(!pia.c2(Port::B));
+        pia.set_c1(Port::B, true);
+        assert!(pia.c2(Port::B));
+    }
+
+    #[test]
+    fn test_c2_pulse() {
+        let mut pia = Pia6821::default();
+        pia.write(CRA, PORT_SELECTED | C2_OUTPUT | C2_BIT3);
+        pia.write(CRB, PORT_SELECTED | C2_OUTPUT | C2_BIT3);
+        pia.read(PRA);
+        pia.write(PRB, 0);
+        assert!(pia.c2(Port::A));
+        assert!(pia.c2(Port::B));
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut pia = Pia6821::with_inputs(0, 0xFF);
+        pia.write(CRB, C1_IRQ | PORT_SELECTED);
+        pia.write(PRB, 0x12);
+        pia.set_c1(Port::B, true);
+        pia.set_c1(Port::B, false);
+        assert!(pia.irq(Port::B));
+        pia.reset();
+        assert!(!pia.irq(Port::B));
+        assert_eq!(
+            [0; 4],
+            [PRA, CRA, PRB, CRB].map(|register| pia.peek(register))
+        );
+        // the devices still drive the lines
+        pia.write(CRB, PORT_SELECTED);
+        assert_eq!(0xFF, pia.peek(PRB));
+    }
+}
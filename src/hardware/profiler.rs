@@ -0,0 +1,285 @@
+use crate::hardware::debugger::call_length;
+use crate::hardware::memory::Memory;
+use crate::int;
+use std::cell::Cell;
+use std::collections::BTreeMap;
+
+const ADDRESSES: usize = 0x10000;
+// deeper calls are assumed to never return, the oldest ones are forgotten
+const MAX_CALLS: usize = 256;
+
+/// The statistics of a subroutine, entered by JSR, BSR or LBSR
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Routine {
+    pub calls: u64,
+    /// Cycles from the call to the return, with the subroutines it calls
+    pub cycles: u64,
+    /// Cycles of the instructions of the subroutine itself
+    pub own_cycles: u64,
+}
+
+#[derive(Debug)]
+struct Call {
+    entry: int,
+    return_address: int,
+    start: u64,
+}
+
+/// How an instruction changes the flow of the program
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Flow {
+    Call { return_address: int },
+    Return,
+    Other,
+}
+
+impl Flow {
+    pub(crate) fn of(pc: int, mem: &Memory) -> Self {
+        match mem.peek(pc) {
+            // RTS, RTI, PULS with PC
+            0x39 | 0x3B => Self::Return,
+            0x35 if mem.peek(pc + 1) & 0x80 != 0 => Self::Return,
+            _ => match call_length(pc, mem) {
+                Some(length) => Self::Call {
+                    return_address: (pc + length) & 0xFFFF,
+                },
+                None => Self::Other,
+            },
+        }
+    }
+}
+
+/// Counts the executions and the cycles of each address and each subroutine
+#[derive(Debug)]
+pub struct Profiler {
+    executions: Vec<u64>,
+    cycles: Vec<u64>,
+    routines: BTreeMap<int, Routine>,
+    calls: Vec<Call>,
+    total: u64,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self {
+            executions: vec![0; ADDRESSES],
+            cycles: vec![0; ADDRESSES],
+            routines: BTreeMap::new(),
+            calls: Vec::new(),
+            total: 0,
+        }
+    }
+}
+
+impl Profiler {
+    /// Records the instruction at `pc` which ran for `cycles`, the CPU is now at `next`
+    pub(crate) fn executed(&mut self, pc: int, flow: Flow, cycles: int, next: int) {
+        let cycles = cycles.max(0) as u64;
+        let next = next & 0xFFFF;
+        let address = (pc & 0xFFFF) as usize;
+        self.executions[address] += 1;
+        self.cycles[address] += cycles;
+        self.total += cycles;
+        if let Some(call) = self.calls.last() {
+            self.routines.entry(call.entry).or_default().own_cycles += cycles;
+        }
+        match flow {
+            Flow::Call { return_address } => {
+                if self.calls.len() == MAX_CALLS {
+                    self.calls.remove(0);
+                }
+                self.routines.entry(next).or_default().calls += 1;
+                self.calls.push(Call {
+                    entry: next,
+                    return_address,
+                    start: self.total,
+                });
+            }
+            Flow::Return => {
+                // an interrupt or a return address dropped from the stack may not match
+                if let Some(position) = self
+                    .calls
+                    .iter()
+                    .rposition(|call| call.return_address == next)
+                {
+                    for call in self.calls.drain(position..) {
+                        self.routines.entry(call.entry).or_default().cycles +=
+                            self.total - call.start;
+                    }
+                }
+            }
+            Flow::Other => {}
+        }
+    }
+
+    /// The number of cycles run since the start of the profiling
+    pub const fn total_cycles(&self) -> u64 {
+        self.total
+    }
+
+    pub fn executions(&self, address: int) -> u64 {
+        self.executions[(address & 0xFFFF) as usize]
+    }
+
+    pub fn cycles(&self, address: int) -> u64 {
+        self.cycles[(address & 0xFFFF) as usize]
+    }
+
+    /// The `count` subroutines which ran the most cycles, with their entry address
+    pub fn hottest_routines(&self, count: usize) -> Vec<(int, Routine)> {
+        let mut routines: Vec<(int, Routine)> =
+            self.routines.iter().map(|(a, r)| (*a, *r)).collect();
+        routines.sort_by(|a, b| b.1.cycles.cmp(&a.1.cycles).then(a.0.cmp(&b.0)));
+        routines.truncate(count);
+        routines
+    }
+
+    /// The `count` addresses which ran the most cycles
+    pub fn hottest_addresses(&self, count: usize) -> Vec<int> {
+        let mut addresses: Vec<int> = (0..ADDRESSES as int)
+            .filter(|address| self.executions[*address as usize] > 0)
+            .collect();
+        addresses.sort_by(|a, b| self.cycles[*b as usize].cmp(&self.cycles[*a as usize]));
+        addresses.truncate(count);
+        addresses
+    }
+}
+
+pub const EXECUTED: u8 = 0x01;
+pub const READ: u8 = 0x02;
+pub const WRITTEN: u8 = 0x04;
+
+/// Marks every byte of the memory seen by the CPU as executed, read as data or written. The
+/// bytes of the instructions are not marked as read by the CPU fetching them.
+///
+/// The log is saved as one byte of flags per address.
+#[derive(Debug)]
+pub struct CodeDataLog {
+    flags: Vec<Cell<u8>>,
+    // the addresses of the instruction running
+    instruction: Cell<(int, int)>,
+}
+
+impl Default for CodeDataLog {
+    fn default() -> Self {
+        Self {
+            flags: vec![Cell::new(0); ADDRESSES],
+            instruction: Cell::new((0, 0)),
+        }
+    }
+}
+
+impl CodeDataLog {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() != ADDRESSES {
+            return Err(format!(
+                "A code/data log has {ADDRESSES} bytes, not {}",
+                bytes.len()
+            ));
+        }
+        Ok(Self {
+            flags: bytes.iter().map(|flags| Cell::new(*flags)).collect(),
+            ..Self::default()
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.flags.iter().map(Cell::get).collect()
+    }
+
+    pub fn flags(&self, address: int) -> u8 {
+        self.flags[(address & 0xFFFF) as usize].get()
+    }
+
+    /// Returns true if the byte was read or written, but never executed
+    pub fn is_data(&self, address: int) -> bool {
+        let flags = self.flags(address);
+        flags & EXECUTED == 0 && flags & (READ | WRITTEN) != 0
+    }
+
+    /// Counts the bytes having any of the flags
+    pub fn count(&self, flags: u8) -> usize {
+        self.flags.iter().filter(|f| f.get() & flags != 0).count()
+    }
+
+    pub(crate) fn execute(&self, pc: int, length: int) {
+        self.instruction.set((pc, pc + length));
+        for address in pc..pc + length {
+            self.mark(address, EXECUTED);
+        }
+    }
+
+    pub(crate) fn read(&self, address: int) {
+        let (start, end) = self.instruction.get();
+        if !(start..end).contains(&address) {
+            self.mark(address, READ);
+        }
+    }
+
+    pub(crate) fn write(&self, address: int) {
+        self.mark(address, WRITTEN);
+    }
+
+    fn mark(&self, address: int, flag: u8) {
+        let flags = &self.flags[(address & 0xFFFF) as usize];
+        flags.set(flags.get() | flag);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_routines() {
+        let mut profiler = Profiler::default();
+        // 6000 JSR 7000 / 7000 NOP / 7001 RTS / 6003 NOP
+        profiler.executed(
+            0x6000,
+            Flow::Call {
+                return_address: 0x6003,
+            },
+            8,
+            0x7000,
+        );
+        profiler.executed(0x7000, Flow::Other, 2, 0x7001);
+        profiler.executed(0x7001, Flow::Return, 5, 0x6003);
+        profiler.executed(0x6003, Flow::Other, 2, 0x6004);
+        // an interrupt returning elsewhere does not end the subroutine
+        profiler.executed(0x6004, Flow::Return, 6, 0x6010);
+        assert_eq!(23, profiler.total_cycles());
+        assert_eq!(1, profiler.executions(0x7000));
+        let routines = profiler.hottest_routines(10);
+        assert_eq!(
+            vec![(
+                0x7000,
+                Routine {
+                    calls: 1,
+                    cycles: 7,
+                    own_cycles: 7
+                }
+            )],
+            routines
+        );
+        assert_eq!(vec![0x6000, 0x6004], profiler.hottest_addresses(2));
+    }
+
+    #[test]
+    fn test_code_data_log() {
+        let log = CodeDataLog::default();
+        // LDA $7000
+        log.execute(0x6000, 3);
+        log.read(0x6001);
+        log.read(0x7000);
+        log.write(0x7001);
+        assert_eq!(EXECUTED, log.flags(0x6001));
+        assert!(log.is_data(0x7000));
+        assert!(log.is_data(0x7001));
+        assert!(!log.is_data(0x6000));
+        assert!(!log.is_data(0x7002));
+        assert_eq!(3, log.count(EXECUTED));
+        let copy = CodeDataLog::from_bytes(&log.to_bytes()).unwrap();
+        assert_eq!(WRITTEN, copy.flags(0x7001));
+        assert!(CodeDataLog::from_bytes(&[0; 16]).is_err());
+    }
+}
@@ -0,0 +1,169 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// Run when the clock reaches the time of its event, with that time. It may schedule other
+/// events, a periodic device schedules its next one and moves its state into it.
+pub type Handler<T> = Box<dyn FnOnce(&mut T, u64)>;
+
+struct Event<T> {
+    time: u64,
+    // the events due at the same time run in the order they were scheduled
+    order: u64,
+    name: &'static str,
+    handler: Handler<T>,
+}
+
+impl<T> PartialEq for Event<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T> Eq for Event<T> {}
+
+impl<T> PartialOrd for Event<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Event<T> {
+    // reversed, the heap gives the earliest event first
+    fn cmp(&self, other: &Self) -> Ordering {
+        (other.time, other.order).cmp(&(self.time, self.order))
+    }
+}
+
+/// The events of the devices at absolute cycle times, the CPU runs until the next one
+pub struct Scheduler<T> {
+    events: BinaryHeap<Event<T>>,
+    scheduled: u64,
+}
+
+impl<T> Default for Scheduler<T> {
+    fn default() -> Self {
+        Self {
+            events: BinaryHeap::new(),
+            scheduled: 0,
+        }
+    }
+}
+
+impl<T> Scheduler<T> {
+    /// Runs the handler when the clock reaches the given cycle, the name is for the debugger
+    pub fn schedule(
+        &mut self,
+        time: u64,
+        name: &'static str,
+        handler: impl FnOnce(&mut T, u64) + 'static,
+    ) {
+        self.scheduled += 1;
+        self.events.push(Event {
+            time,
+            order: self.scheduled,
+            name,
+            handler: Box::new(handler),
+        });
+    }
+
+    /// Removes the events of the given name
+    pub fn cancel(&mut self, name: &str) {
+        self.events.retain(|event| event.name != name);
+    }
+
+    /// The time of the next event
+    pub fn next_time(&self) -> Option<u64> {
+        self.events.peek().map(|event| event.time)
+    }
+
+    /// Removes the next event if it is due at the given time, with its time
+    pub fn pop_due(&mut self, now: u64) -> Option<(u64, Handler<T>)> {
+        if self.next_time()? > now {
+            return None;
+        }
+        self.events.pop().map(|event| (event.time, event.handler))
+    }
+
+    /// The names and the times of the events to come, the earliest first
+    pub fn pending(&self) -> Vec<(u64, &'static str)> {
+        let mut pending: Vec<_> = self
+            .events
+            .iter()
+            .map(|event| (event.time, event.order, event.name))
+            .collect();
+        pending.sort_unstable();
+        pending
+            .into_iter()
+            .map(|(time, _, name)| (time, name))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Device {
+        scheduler: Scheduler<Device>,
+        fired: Vec<(u64, &'static str)>,
+    }
+
+    fn tick(device: &mut Device, time: u64) {
+        device.fired.push((time, "tick"));
+        device.scheduler.schedule(time + 10, "tick", tick);
+    }
+
+    fn once(device: &mut Device, time: u64) {
+        device.fired.push((time, "once"));
+    }
+
+    fn run_until(device: &mut Device, now: u64) {
+        while let Some((time, handler)) = device.scheduler.pop_due(now) {
+            handler(device, time);
+        }
+    }
+
+    #[test]
+    fn test_order() {
+        let mut device = Device::default();
+        device.scheduler.schedule(10, "tick", tick);
+        device.scheduler.schedule(20, "once", once);
+        device.scheduler.schedule(5, "once", once);
+        assert_eq!(Some(5), device.scheduler.next_time());
+        run_until(&mut device, 20);
+        // the events at the same time run in the order they were scheduled
+        assert_eq!(
+            vec![(5, "once"), (10, "tick"), (20, "once"), (20, "tick")],
+            device.fired
+        );
+        assert_eq!(vec![(30, "tick")], device.scheduler.pending());
+    }
+
+    #[test]
+    fn test_state() {
+        let mut device = Device::default();
+        let mut count = 0;
+        device.scheduler.schedule(10, "count", move |device, time| {
+            count += 1;
+            device
+                .scheduler
+                .schedule(time + 10, "count", move |device, time| {
+                    device.fired.push((time + count, "count"));
+                });
+        });
+        run_until(&mut device, 20);
+        assert_eq!(vec![(21, "count")], device.fired);
+    }
+
+    #[test]
+    fn test_cancel() {
+        let mut device = Device::default();
+        device.scheduler.schedule(10, "tick", tick);
+        device.scheduler.schedule(15, "once", once);
+        device.scheduler.cancel("tick");
+        run_until(&mut device, 100);
+        assert_eq!(vec![(15, "once")], device.fired);
+        assert_eq!(None, device.scheduler.next_time());
+    }
+}
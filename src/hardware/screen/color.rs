@@ -1,7 +1,9 @@
 pub(crate) const COLOR_DEPTH: usize = 3;
-pub(super) type Color = [u8; COLOR_DEPTH];
+pub type Color = [u8; COLOR_DEPTH];
+pub type Palette = [Color; 16];
 
-pub(crate) static PALETTE: [Color; 16] = [
+/// The colors used since the first versions of Maurice
+pub(crate) static PALETTE: Palette = [
     [0x00, 0x00, 0x00],
     [0xF0, 0x00, 0x00],
     [0x00, 0xF0, 0x00],
@@ -19,3 +21,193 @@ pub(crate) static PALETTE: [Color; 16] = [
     [0x63, 0xF0, 0xF0],
     [0xF0, 0x63, 0x00],
 ];
+
+/// The base colors at full intensity and light pastel ones, the gray being lighter than the
+/// default one
+static BRIGHT: Palette = [
+    [0x00, 0x00, 0x00],
+    [0xFF, 0x00, 0x00],
+    [0x00, 0xFF, 0x00],
+    [0xFF, 0xFF, 0x00],
+    [0x00, 0x00, 0xFF],
+    [0xFF, 0x00, 0xFF],
+    [0x00, 0xFF, 0xFF],
+    [0xFF, 0xFF, 0xFF],
+    [0xCB, 0xCB, 0xCB],
+    [0xE7, 0x8E, 0x8E],
+    [0x8E, 0xE7, 0x8E],
+    [0xE7, 0xE7, 0x8E],
+    [0x8E, 0x8E, 0xE7],
+    [0xE7, 0x8E, 0xE7],
+    [0xCB, 0xFB, 0xFB],
+    [0xEF, 0xCB, 0x00],
+];
+
+/// Saturated colors, the pastel ones are much lighter than the base ones
+static HIGH_CONTRAST: Palette = [
+    [0x00, 0x00, 0x00],
+    [0xC0, 0x00, 0x00],
+    [0x00, 0xA0, 0x00],
+    [0xC0, 0xC0, 0x00],
+    [0x00, 0x00, 0xC0],
+    [0xC0, 0x00, 0xC0],
+    [0x00, 0xA0, 0xA0],
+    [0xFF, 0xFF, 0xFF],
+    [0x60, 0x60, 0x60],
+    [0xFF, 0x80, 0x80],
+    [0x80, 0xFF, 0x80],
+    [0xFF, 0xFF, 0x80],
+    [0x80, 0x80, 0xFF],
+    [0xFF, 0x80, 0xFF],
+    [0x80, 0xFF, 0xFF],
+    [0xFF, 0x90, 0x00],
+];
+
+/// Based on the Okabe-Ito colors, which stay distinct with the common color blindness
+static COLOR_BLIND: Palette = [
+    [0x00, 0x00, 0x00],
+    [0xD5, 0x5E, 0x00],
+    [0x00, 0x9E, 0x73],
+    [0xF0, 0xE4, 0x42],
+    [0x00, 0x72, 0xB2],
+    [0xCC, 0x79, 0xA7],
+    [0x56, 0xB4, 0xE9],
+    [0xFF, 0xFF, 0xFF],
+    [0x80, 0x80, 0x80],
+    [0xF0, 0xA0, 0x70],
+    [0x70, 0xD0, 0xB0],
+    [0xF8, 0xF0, 0xA0],
+    [0x70, 0xA8, 0xD8],
+    [0xE8, 0xB8, 0xD0],
+    [0xB0, 0xDC, 0xF4],
+    [0xE6, 0x9F, 0x00],
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PalettePreset {
+    Default,
+    Bright,
+    Grayscale,
+    /// A green phosphor monochrome monitor
+    GreenMonitor,
+    HighContrast,
+    ColorBlind,
+}
+
+impl PalettePreset {
+    pub const ALL: [Self; 6] = [
+        Self::Default,
+        Self::Bright,
+        Self::Grayscale,
+        Self::GreenMonitor,
+        Self::HighContrast,
+        Self::ColorBlind,
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Default => "Default",
+            Self::Bright => "Bright",
+            Self::Grayscale => "Grayscale",
+            Self::GreenMonitor => "Green monitor",
+            Self::HighContrast => "High contrast",
+            Self::ColorBlind => "Color blind friendly",
+        }
+    }
+
+    pub fn palette(self) -> Palette {
+        match self {
+            Self::Default => PALETTE,
+            Self::Bright => BRIGHT,
+            Self::Grayscale => BRIGHT.map(|color| {
+                let luma = luminance(color);
+                [luma, luma, luma]
+            }),
+            Self::GreenMonitor => BRIGHT.map(|color| {
+                let luma = luminance(color) as u16;
+                [(luma / 5) as u8, luma as u8, (luma / 4) as u8]
+            }),
+            Self::HighContrast => HIGH_CONTRAST,
+            Self::ColorBlind => COLOR_BLIND,
+        }
+    }
+
+    /// Returns the preset having these colors, if any
+    pub fn matching(palette: &Palette) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|preset| preset.palette() == *palette)
+    }
+}
+
+fn luminance([r, g, b]: Color) -> u8 {
+    ((299 * r as u32 + 587 * g as u32 + 114 * b as u32) / 1000) as u8
+}
+
+/// Writes the palette as text, one `#RRGGBB` color per line
+pub fn palette_to_string(palette: &Palette) -> String {
+    palette
+        .iter()
+        .map(|[r, g, b]| format!("#{r:02X}{g:02X}{b:02X}\n"))
+        .collect()
+}
+
+/// Reads a palette written by [`palette_to_string`], empty lines and lines starting with `;`
+/// are ignored
+pub fn parse_palette(text: &str) -> Result<Palette, String> {
+    let mut palette = PALETTE;
+    let mut count = 0;
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+        if count == palette.len() {
+            return Err(format!("More than {} colors", palette.len()));
+        }
+        palette[count] = parse_color(line)?;
+        count += 1;
+    }
+    if count < palette.len() {
+        return Err(format!("Expected {} colors, found {count}", palette.len()));
+    }
+    Ok(palette)
+}
+
+fn parse_color(text: &str) -> Result<Color, String> {
+    let hex = text.strip_prefix('#').unwrap_or(text);
+    if hex.len() != 6 {
+        return Err(format!("Invalid color {text}"));
+    }
+    let value = u32::from_str_radix(hex, 16).map_err(|_| format!("Invalid color {text}"))?;
+    Ok([(value >> 16) as u8, (value >> 8) as u8, value as u8])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palette_text() {
+        let text = palette_to_string(&PALETTE);
+        assert!(text.starts_with("#000000\n#F00000\n"));
+        assert_eq!(Ok(PALETTE), parse_palette(&text));
+        assert_eq!(
+            Ok(BRIGHT),
+            parse_palette(&format!("; bright\n\n{}", palette_to_string(&BRIGHT)))
+        );
+    }
+
+    #[test]
+    fn test_parse_palette_errors() {
+        assert!(parse_palette("#000000\n").is_err());
+        assert!(parse_palette(&"#GG0000\n".repeat(16)).is_err());
+        assert!(parse_palette(&"#000000\n".repeat(17)).is_err());
+    }
+
+    #[test]
+    fn test_preset_matching() {
+        for preset in PalettePreset::ALL {
+            assert_eq!(Some(preset), PalettePreset::matching(&preset.palette()));
+        }
+    }
+}
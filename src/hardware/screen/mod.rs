@@ -1,15 +1,28 @@
-pub(crate) mod color;
+pub mod color;
 
 use crate::hardware::memory::Memory;
-use crate::hardware::screen::color::{COLOR_DEPTH, PALETTE};
+use crate::hardware::screen::color::{COLOR_DEPTH, PALETTE, Palette};
 use crate::int;
 use crate::raw_image::RawImage;
 
 pub const WIDTH: usize = 320;
 pub const HEIGHT: usize = 200;
 
+/// Initial zoom of the window
 pub const DEFAULT_PIXEL_SIZE: usize = 3;
 
+/// A line of the screen is scanned in 64µs
+pub(crate) const CYCLES_PER_LINE: int = 64;
+/// Cycle of the frame at which the window opens, after the top border
+pub(crate) const WINDOW_START: int = 4100;
+/// Cycle of the frame at which the window closes, the 200 lines of the picture are scanned first
+pub(crate) const WINDOW_END: int = 17200;
+/// The frame lasts 20ms
+pub(crate) const CYCLES_PER_FRAME: int = 20000;
+
+/// The overscan sizes that can be displayed around the picture, in MO5 pixels
+pub const BORDER_SIZES: [usize; 4] = [0, 8, 16, 32];
+
 #[derive(Debug)]
 pub struct Screen {
     pub(crate) mouse_clic: bool,
@@ -18,42 +31,130 @@ pub struct Screen {
     pixels: Vec<u8>,
     pub(crate) led: u8,
     pub(crate) show_led: u8,
-    ratio: usize,
+    // size of the overscan area around the picture
+    border: usize,
+    // the color of the border currently drawn, None if it must be redrawn
+    border_color: Option<usize>,
+    palette: Palette,
 }
 
-impl Screen {
-    pub fn new(ratio: usize) -> Self {
+/// The picture is rendered at the MO5 resolution, scaling it is left to the GPU
+impl Default for Screen {
+    fn default() -> Self {
         Screen {
             mouse_clic: false,
             mouse_x: -1,
             mouse_y: -1,
-            pixels: vec![0; WIDTH * ratio * HEIGHT * ratio * COLOR_DEPTH],
+            pixels: vec![0; WIDTH * HEIGHT * COLOR_DEPTH],
             led: 0,
             show_led: 0,
-            ratio,
+            border: 0,
+            border_color: None,
+            palette: PALETTE,
+        }
+    }
+}
+
+impl Screen {
+    pub const fn border(&self) -> usize {
+        self.border
+    }
+
+    /// Sets the size of the overscan area displayed around the picture
+    pub(crate) fn set_border(&mut self, border: usize, mem: &mut Memory) {
+        if border != self.border {
+            self.border = border;
+            self.allocate_pixels();
+            mem.set_all_dirty();
         }
     }
 
-    fn set_ratio(&mut self, mut ratio: usize) {
-        if ratio == 0 {
-            ratio = 1;
+    pub const fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    /// Changes the colors, the whole picture is redrawn with them
+    pub(crate) fn set_palette(&mut self, palette: Palette, mem: &mut Memory) {
+        self.palette = palette;
+        self.border_color = None;
+        mem.set_all_dirty();
+    }
+
+    fn allocate_pixels(&mut self) {
+        self.pixels = vec![0; self.width() * self.height() * COLOR_DEPTH];
+        self.border_color = None;
+    }
+
+    /// Width of the picture and its overscan
+    pub const fn width(&self) -> usize {
+        WIDTH + 2 * self.border
+    }
+
+    /// Height of the picture and its overscan
+    pub const fn height(&self) -> usize {
+        HEIGHT + 2 * self.border
+    }
+
+    /// Updates the light pen position from coordinates in the image, which includes the border.
+    /// The light pen can only point the picture, outside of it the coordinates are -1.
+    pub(crate) fn set_mouse(&mut self, position: Option<(usize, usize)>, clic: bool) {
+        let position =
+            position.map(|(x, y)| (x as int - self.border as int, y as int - self.border as int));
+        if let Some((x, y)) = position
+            && (0..WIDTH as int).contains(&x)
+            && (0..HEIGHT as int).contains(&y)
+        {
+            self.mouse_x = x;
+            self.mouse_y = y;
+            self.mouse_clic = clic;
+        } else {
+            self.mouse_x = -1;
+            self.mouse_y = -1;
+            self.mouse_clic = false;
         }
-        self.ratio = ratio;
-        self.pixels = vec![0; WIDTH * ratio * HEIGHT * ratio * COLOR_DEPTH];
     }
 
+    /// Finishes the frame, the lines of the picture have already been painted while the
+    /// beam scanned them
     pub(crate) fn paint(&mut self, mem: &mut Memory) {
-        self.dopaint(mem);
+        self.draw_border(mem.border_color());
         if self.show_led > 0 {
             self.show_led -= 1;
             self.draw_led();
         }
     }
 
+    fn draw_border(&mut self, color: usize) {
+        if self.border == 0 || self.border_color == Some(color) {
+            return;
+        }
+        self.border_color = Some(color);
+        let border_color = &self.palette[color];
+        let row_stride = self.width() * COLOR_DEPTH;
+        let border_height = self.border;
+        let border_width = self.border * COLOR_DEPTH;
+        let height = self.height();
+        for (y, row) in self.pixels.chunks_exact_mut(row_stride).enumerate() {
+            if y < border_height || y >= height - border_height {
+                for pixel in row.chunks_exact_mut(COLOR_DEPTH) {
+                    pixel.copy_from_slice(border_color);
+                }
+            } else {
+                let (left, rest) = row.split_at_mut(border_width);
+                let (_, right) = rest.split_at_mut(row_stride - 2 * border_width);
+                for pixel in left.chunks_exact_mut(COLOR_DEPTH) {
+                    pixel.copy_from_slice(border_color);
+                }
+                for pixel in right.chunks_exact_mut(COLOR_DEPTH) {
+                    pixel.copy_from_slice(border_color);
+                }
+            }
+        }
+    }
+
     pub fn draw_led(&mut self) {
-        let led_width_pixels = 16 * self.ratio;
-        let led_width_bytes = led_width_pixels * COLOR_DEPTH;
-        let row_stride = WIDTH * self.ratio * self.ratio * COLOR_DEPTH;
+        let led_width_bytes = 16 * COLOR_DEPTH;
+        let row_stride = self.width() * COLOR_DEPTH;
 
         let first_row_end = row_stride;
         let first_row_start = row_stride - led_width_bytes;
@@ -79,48 +180,42 @@ impl Screen {
     }
 
     pub fn get_pixels(&self) -> RawImage<'_> {
-        RawImage::new_with_data(WIDTH * self.ratio, HEIGHT * self.ratio, &self.pixels)
+        RawImage::new_with_data(self.width(), self.height(), &self.pixels)
     }
 
+    /// Paints all the modified lines of the picture
     pub fn dopaint(&mut self, mem: &mut Memory) {
-        let mut i = 0;
-
-        let pixels = &mut self.pixels;
         for y in 0..HEIGHT {
-            let offset = y * WIDTH * self.ratio * self.ratio * COLOR_DEPTH;
-            if !mem.is_dirty(y) {
-                i += 40;
-            } else {
-                let mut x = 0;
-                for _ in 0..40 {
-                    let col = mem.COLOR(i);
-                    let c2 = (col & 0x0F) as usize;
-                    let c1 = (col >> 4) as usize;
-                    let cc2 = &PALETTE[c1];
-                    let cc1 = &PALETTE[c2];
-
-                    let pt = mem.POINT(i);
-                    const PATTERN: [int; 8] = [0x80, 0x40, 0x20, 0x10, 0x08, 0x04, 0x02, 0x01];
-                    for v in PATTERN {
-                        for _ in 0..self.ratio {
-                            let range_start = x * COLOR_DEPTH + offset;
-                            let pixel_range = range_start..range_start + COLOR_DEPTH;
-                            if (v & pt) != 0 {
-                                pixels[pixel_range].copy_from_slice(cc2);
-                            } else {
-                                pixels[pixel_range].copy_from_slice(cc1);
-                            }
-                            x += 1;
-                        }
-                    }
-                    i += 1;
+            self.paint_line(mem, y);
+        }
+    }
+
+    /// Paints the line of the picture if it was modified since it was last painted
+    pub(crate) fn paint_line(&mut self, mem: &mut Memory, y: usize) {
+        if !mem.is_dirty(y) {
+            return;
+        }
+        let row_stride = self.width() * COLOR_DEPTH;
+        let offset = (self.border + y) * row_stride + self.border * COLOR_DEPTH;
+        let pixels = &mut self.pixels[offset..offset + WIDTH * COLOR_DEPTH];
+        let mut x = 0;
+        for i in (y * 40) as int..(y * 40 + 40) as int {
+            let col = mem.COLOR(i);
+            let c2 = (col & 0x0F) as usize;
+            let c1 = (col >> 4) as usize;
+            let cc2 = &self.palette[c1];
+            let cc1 = &self.palette[c2];
+
+            let pt = mem.POINT(i);
+            const PATTERN: [int; 8] = [0x80, 0x40, 0x20, 0x10, 0x08, 0x04, 0x02, 0x01];
+            for v in PATTERN {
+                let pixel_range = x * COLOR_DEPTH..(x + 1) * COLOR_DEPTH;
+                if (v & pt) != 0 {
+                    pixels[pixel_range].copy_from_slice(cc2);
+                } else {
+                    pixels[pixel_range].copy_from_slice(cc1);
                 }
-            }
-            for a in 1..self.ratio {
-                pixels.copy_within(
-                    offset..offset + WIDTH * COLOR_DEPTH * self.ratio,
-                    offset + WIDTH * self.ratio * COLOR_DEPTH * a,
-                );
+                x += 1;
             }
         }
     }
@@ -132,8 +227,10 @@ mod tests {
 
     #[test]
     fn test_draw_led_with_led_on() {
-        let mut screen = Screen::new(1);
-        screen.led = 1;
+        let mut screen = Screen {
+            led: 1,
+            ..Default::default()
+        };
         screen.draw_led();
 
         // Check that the LED area (top-right corner, lines 1-16, last 16 pixels) is red
@@ -167,8 +264,10 @@ mod tests {
 
     #[test]
     fn test_draw_led_with_led_off() {
-        let mut screen = Screen::new(1);
-        screen.led = 0;
+        let mut screen = Screen {
+            led: 0,
+            ..Default::default()
+        };
         screen.draw_led();
 
         // Check that the LED area is black
@@ -200,44 +299,9 @@ mod tests {
         }
     }
 
-    #[test]
-    fn test_draw_led_with_ratio_3() {
-        let mut screen = Screen::new(3);
-        screen.led = 1;
-        screen.draw_led();
-
-        // Check that the LED area is properly scaled (16*3 = 48 pixels wide)
-        for y in 1..17 {
-            let line_offset = y * WIDTH * 3 * 3 * COLOR_DEPTH;
-            let led_start = line_offset - 16 * 3 * COLOR_DEPTH;
-            for x in 0..(16 * 3) {
-                let pixel_offset = led_start + x * COLOR_DEPTH;
-                assert_eq!(
-                    screen.pixels[pixel_offset], 0xFF,
-                    "Red channel should be 0xFF at y={}, x={}",
-                    y, x
-                );
-                assert_eq!(
-                    screen.pixels[pixel_offset + 1],
-                    0x00,
-                    "Green channel should be 0x00 at y={}, x={}",
-                    y,
-                    x
-                );
-                assert_eq!(
-                    screen.pixels[pixel_offset + 2],
-                    0x00,
-                    "Blue channel should be 0x00 at y={}, x={}",
-                    y,
-                    x
-                );
-            }
-        }
-    }
-
     #[test]
     fn test_draw_led_does_not_affect_other_pixels() {
-        let mut screen = Screen::new(1);
+        let mut screen = Screen::default();
 
         // Set some pixels outside the LED area
         let test_offset = 20 * WIDTH * COLOR_DEPTH;
@@ -253,4 +317,59 @@ mod tests {
         assert_eq!(screen.pixels[test_offset + 1], 0xBB);
         assert_eq!(screen.pixels[test_offset + 2], 0xCC);
     }
+
+    #[test]
+    fn test_set_mouse_with_border() {
+        let mut screen = Screen::default();
+        screen.set_border(8, &mut Memory::default());
+        screen.set_mouse(Some((8, 10)), true);
+        assert_eq!(
+            (0, 2, true),
+            (screen.mouse_x, screen.mouse_y, screen.mouse_clic)
+        );
+        screen.set_mouse(Some((5, 10)), true);
+        assert_eq!(
+            (-1, -1, false),
+            (screen.mouse_x, screen.mouse_y, screen.mouse_clic)
+        );
+        screen.set_mouse(None, true);
+        assert_eq!(
+            (-1, -1, false),
+            (screen.mouse_x, screen.mouse_y, screen.mouse_clic)
+        );
+    }
+
+    #[test]
+    fn test_draw_border() {
+        let mut screen = Screen::default();
+        screen.set_border(8, &mut Memory::default());
+        screen.draw_border(1);
+        let row_stride = (WIDTH + 16) * COLOR_DEPTH;
+        // corner, left and right borders
+        assert_eq!(PALETTE[1], screen.pixels[0..COLOR_DEPTH]);
+        assert_eq!(
+            PALETTE[1],
+            screen.pixels[20 * row_stride..20 * row_stride + COLOR_DEPTH]
+        );
+        assert_eq!(
+            PALETTE[1],
+            screen.pixels[21 * row_stride - COLOR_DEPTH..21 * row_stride]
+        );
+        // the picture is left untouched
+        let picture = 20 * row_stride + 8 * COLOR_DEPTH;
+        assert_eq!([0, 0, 0], screen.pixels[picture..picture + COLOR_DEPTH]);
+    }
+
+    #[test]
+    fn test_set_palette_redraws() {
+        let mut screen = Screen::default();
+        let mut mem = Memory::default();
+        let mut palette = PALETTE;
+        palette[0] = [1, 2, 3];
+        screen.set_palette(palette, &mut mem);
+        screen.dopaint(&mut mem);
+        assert_eq!([1, 2, 3], screen.pixels[0..COLOR_DEPTH]);
+        let last = screen.pixels.len() - COLOR_DEPTH;
+        assert_eq!([1, 2, 3], screen.pixels[last..]);
+    }
 }
@@ -2,18 +2,27 @@ use std::sync::{Arc, Mutex};
 
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 
-use crate::hardware::M6809::M6809;
+use crate::hardware::wav::WavRecorder;
+use log::warn;
 
-const N_BYTES: usize = 1024; // Buffer size
+const N_BYTES: usize = 256; // Buffer size, in samples
 
 const DESIRED_CHANNELS: u16 = 2;
 const DESIRED_SAMPLE_FORMAT: cpal::SampleFormat = cpal::SampleFormat::F32;
 const DESIRED_SAMPLE_RATE: cpal::SampleRate = 44100;
 
+/// The rate the machine samples the sound output at
+pub(crate) const SAMPLE_RATE: u32 = DESIRED_SAMPLE_RATE;
+
 pub struct Sound {
     pub buffer: Arc<Mutex<Vec<u8>>>,
-    audio_stream: Option<cpal::Stream>,
-    audio: [u8; N_BYTES / 4],
+    // the stream is kept to continue playing
+    _audio_stream: Option<cpal::Stream>,
+    // the samples waiting for a complete block
+    levels: Vec<u8>,
+    recorder: Option<WavRecorder>,
+    // the soundtrack of the video being captured, apart from the user's recording
+    video_track: Option<WavRecorder>,
 }
 
 impl Default for Sound {
@@ -28,24 +37,81 @@ impl Default for Sound {
         };
         Sound {
             buffer,
-            audio_stream,
-            audio: [0; N_BYTES / 4],
+            _audio_stream: audio_stream,
+            levels: Vec::with_capacity(N_BYTES),
+            recorder: None,
+            video_track: None,
         }
     }
 }
 
 impl Sound {
-    // Copie du buffer de son provenant du 6809 vers le buffer de la carte son
-    // Cette fonction est lancée lorsque le buffer 6809 est plein
-    pub fn play_sound(&mut self, cpu: &M6809) {
-        for i in 0..N_BYTES {
-            self.audio[i / 4] = cpu.sound_buffer[i];
+    /// Adds a sample of the sound output, the samples are played by blocks of [`N_BYTES`]
+    pub(crate) fn sample(&mut self, level: u8) {
+        self.levels.push(level);
+        if self.levels.len() == N_BYTES {
+            let levels = std::mem::take(&mut self.levels);
+            self.play_sound(&levels);
+            self.levels = levels;
+            self.levels.clear();
         }
+    }
+
+    /// Copies the samples to the buffer of the sound card, with the same level on both channels
+    pub fn play_sound(&mut self, levels: &[u8]) {
         let mut buffer = self.buffer.lock().unwrap();
-        // buffer.copy_from_slice(&self.audio[..N_BYTES / 4]);
-        for i in 0..N_BYTES / 4 {
-            buffer.push(self.audio[i]);
+        for level in levels {
+            buffer.extend([*level; DESIRED_CHANNELS as usize]);
+        }
+        drop(buffer);
+        record(&mut self.recorder, levels);
+        record(&mut self.video_track, levels);
+    }
+
+    pub fn start_recording(&mut self, name: &str) -> Result<(), String> {
+        self.stop_recording()?;
+        self.recorder = Some(WavRecorder::new(name)?);
+        Ok(())
+    }
+
+    /// Stops the current recording if any and returns the name of the recorded file, the
+    /// samples waiting for a complete block are written first
+    pub fn stop_recording(&mut self) -> Result<Option<String>, String> {
+        finish(self.recorder.take(), &self.levels)
+    }
+
+    pub const fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    /// Records the soundtrack of a video, the user's recording goes on
+    pub(crate) fn start_video_track(&mut self, name: &str) -> Result<(), String> {
+        self.stop_video_track()?;
+        self.video_track = Some(WavRecorder::new(name)?);
+        Ok(())
+    }
+
+    pub(crate) fn stop_video_track(&mut self) -> Result<Option<String>, String> {
+        finish(self.video_track.take(), &self.levels)
+    }
+}
+
+fn record(recorder: &mut Option<WavRecorder>, levels: &[u8]) {
+    if let Some(wav) = recorder
+        && let Err(e) = wav.push(levels)
+    {
+        warn!("Unable to record audio: {e}");
+        *recorder = None;
+    }
+}
+
+fn finish(recorder: Option<WavRecorder>, levels: &[u8]) -> Result<Option<String>, String> {
+    match recorder {
+        Some(mut recorder) => {
+            recorder.push(levels)?;
+            recorder.finish().map(Some)
         }
+        None => Ok(None),
     }
 }
 
@@ -0,0 +1,160 @@
+use crate::bios;
+use crate::int;
+use std::collections::BTreeMap;
+
+/// The hardware registers and the entry points of the MO5 ROM
+static MO5_SYMBOLS: [(int, &str); 18] = [
+    (0xA7C0, "PIA_PRA"),
+    (0xA7C1, "PIA_PRB"),
+    (0xA7C2, "PIA_CRA"),
+    (0xA7C3, "PIA_CRB"),
+    (0xA7CC, "PIA2_PRA"),
+    (0xA7CD, "PIA2_PRB"),
+    (0xA7CE, "PIA2_CRA"),
+    (0xA7CF, "PIA2_CRB"),
+    (0xA7E4, "GA_LINE"),
+    (0xA7E5, "GA_COLUMN"),
+    (0xA7E6, "GA_PICTURE_LINE"),
+    (0xA7E7, "GA_STATUS"),
+    (0xC000, "BASIC"),
+    (0xF000, "MONITOR"),
+    // the routines patched by the emulator
+    (0xF168, "K7_READ_BIT"),
+    (0xF18B, "K7_MOTOR"),
+    (0xF1AF, "K7_WRITE_BYTE"),
+    (0xF548, "LIGHT_PEN"),
+];
+
+/// The interrupt vectors, the routines they point to are named after them
+static VECTORS: [(int, &str); 7] = [
+    (0xFFFE, "RESET"),
+    (0xFFF8, "IRQ"),
+    (0xFFF6, "FIRQ"),
+    (0xFFFA, "SWI"),
+    (0xFFFC, "NMI"),
+    (0xFFF4, "SWI2"),
+    (0xFFF2, "SWI3"),
+];
+
+/// Names of addresses, they replace the addresses in the disassembly
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Symbols {
+    names: BTreeMap<int, String>,
+}
+
+impl Symbols {
+    /// The symbols of the MO5 hardware and ROM
+    pub fn mo5() -> Self {
+        let mut symbols = Self::default();
+        for (address, name) in MO5_SYMBOLS {
+            symbols.insert(address, name);
+        }
+        for (vector, name) in VECTORS {
+            let offset = (vector - 0xC000) as usize;
+            let routine = ((bios::BIOS[offset] as int) << 8) | bios::BIOS[offset + 1] as int;
+            // several vectors may share a routine, it keeps the first name
+            symbols
+                .names
+                .entry(routine)
+                .or_insert_with(|| name.to_string());
+        }
+        symbols
+    }
+
+    /// Reads symbols, one per line, written as `NAME EQU $ADDR`, `NAME = $ADDR` or `ADDR NAME`.
+    /// The addresses are hexadecimal, the lines starting with `;`, `*` or `#` are comments.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut symbols = Self::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with([';', '*', '#']) {
+                continue;
+            }
+            let words: Vec<&str> = line
+                .split(|c: char| c.is_whitespace() || c == '=')
+                .filter(|word| !word.is_empty())
+                .collect();
+            let symbol = match words.as_slice() {
+                [name, equ, address, ..] if equ.eq_ignore_ascii_case("EQU") => {
+                    parse_address(address).map(|address| (address, *name))
+                }
+                [first, second, ..] => parse_address(second)
+                    .filter(|_| line.contains('='))
+                    .map(|address| (address, *first))
+                    .or_else(|| parse_address(first).map(|address| (address, *second))),
+                _ => None,
+            };
+            match symbol {
+                Some((address, name)) => symbols.insert(address, name),
+                None => return Err(format!("Invalid symbol line {}: {line}", number + 1)),
+            }
+        }
+        Ok(symbols)
+    }
+
+    pub fn insert(&mut self, address: int, name: &str) {
+        self.names.insert(address & 0xFFFF, name.to_string());
+    }
+
+    /// Adds the symbols, replacing the names of the addresses already known
+    pub fn extend(&mut self, other: Symbols) {
+        self.names.extend(other.names);
+    }
+
+    pub fn get(&self, address: int) -> Option<&str> {
+        self.names.get(&address).map(String::as_str)
+    }
+
+    pub fn address_of(&self, name: &str) -> Option<int> {
+        self.names
+            .iter()
+            .find(|(_, symbol)| symbol.eq_ignore_ascii_case(name))
+            .map(|(address, _)| *address)
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+fn parse_address(text: &str) -> Option<int> {
+    let digits = text
+        .strip_prefix('$')
+        .or_else(|| text.strip_prefix("0x"))
+        .or_else(|| text.strip_prefix("0X"))
+        .unwrap_or(text);
+    int::from_str_radix(digits, 16)
+        .ok()
+        .filter(|address| (0..=0xFFFF).contains(address))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let symbols =
+            Symbols::parse("; game\nSTART EQU $6000\nLOOP = $6010\n* data\n6100 SPRITES\n\n")
+                .unwrap();
+        assert_eq!(3, symbols.len());
+        assert_eq!(Some("START"), symbols.get(0x6000));
+        assert_eq!(Some("LOOP"), symbols.get(0x6010));
+        assert_eq!(Some("SPRITES"), symbols.get(0x6100));
+        assert_eq!(Some(0x6010), symbols.address_of("loop"));
+        assert!(Symbols::parse("START EQU $GGGG").is_err());
+        assert!(Symbols::parse("START").is_err());
+    }
+
+    #[test]
+    fn test_mo5_symbols() {
+        let symbols = Symbols::mo5();
+        assert_eq!(Some("PIA_CRB"), symbols.get(0xA7C3));
+        assert_eq!(Some("RESET"), symbols.get(0xF003));
+        assert_eq!(Some("IRQ"), symbols.get(0xF657));
+    }
+}
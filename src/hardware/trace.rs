@@ -0,0 +1,255 @@
+use crate::hardware::M6809::Registers;
+use crate::hardware::binary::parse_address;
+use crate::hardware::disassembler::decode;
+use crate::hardware::memory::Memory;
+use crate::int;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::ops::RangeInclusive;
+
+/// Selects the instructions written to the trace, all of them by default
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TraceFilter {
+    /// Only the instructions at these addresses
+    pub pc: Option<RangeInclusive<int>>,
+    /// Only during these frames, counted from the start of the trace
+    pub frames: Option<RangeInclusive<u64>>,
+    /// Nothing before the CPU reaches this address
+    pub after: Option<int>,
+}
+
+impl TraceFilter {
+    /// Reads a range of hexadecimal addresses like `F000-F0FF`, or a single address
+    pub fn parse_pc(text: &str) -> Result<RangeInclusive<int>, String> {
+        parse_range(text, |address| parse_address(address).ok())
+    }
+
+    /// Reads a range of frames like `100-200`, or a single frame
+    pub fn parse_frames(text: &str) -> Result<RangeInclusive<u64>, String> {
+        parse_range(text, |value| value.parse().ok())
+    }
+}
+
+fn parse_range<T: Copy + PartialOrd>(
+    text: &str,
+    parse: impl Fn(&str) -> Option<T>,
+) -> Result<RangeInclusive<T>, String> {
+    let (start, end) = text.split_once('-').unwrap_or((text, text));
+    match (parse(start.trim()), parse(end.trim())) {
+        (Some(start), Some(end)) if start <= end => Ok(start..=end),
+        _ => Err(format!("Invalid range {text}")),
+    }
+}
+
+/// Writes a line per instruction run by the CPU, with the registers before the instruction:
+///
+/// `F003 8E 20 00      LDX   #$2000           A=00 B=00 X=0000 Y=0000 U=0000 S=8000 DP=00 CC=.F.I.... CYC=12`
+///
+/// The cycles are counted from the start of the trace.
+pub struct Tracer {
+    out: Box<dyn Write>,
+    name: Option<String>,
+    filter: TraceFilter,
+    // the address of `filter.after` was reached
+    armed: bool,
+    frame: u64,
+    // cycles of the periods run by FetchUntil before the current one
+    cycles: u64,
+    error: Option<String>,
+}
+
+impl fmt::Debug for Tracer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tracer")
+            .field("name", &self.name)
+            .field("filter", &self.filter)
+            .field("frame", &self.frame)
+            .finish()
+    }
+}
+
+impl Tracer {
+    pub fn new(out: Box<dyn Write>, filter: TraceFilter) -> Self {
+        Self {
+            out,
+            name: None,
+            armed: filter.after.is_none(),
+            filter,
+            frame: 0,
+            cycles: 0,
+            error: None,
+        }
+    }
+
+    pub fn create(name: &str, filter: TraceFilter) -> Result<Self, String> {
+        let file = File::create(name).map_err(|e| format!("{name}: {e}"))?;
+        let mut tracer = Self::new(Box::new(BufWriter::new(file)), filter);
+        tracer.name = Some(name.to_string());
+        Ok(tracer)
+    }
+
+    /// Writes the end of the trace, returns the name of the file
+    pub fn finish(mut self) -> Result<Option<String>, String> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        self.out.flush().map_err(|e| e.to_string())?;
+        Ok(self.name)
+    }
+
+    pub(crate) const fn start_frame(&mut self) {
+        self.frame += 1;
+    }
+
+    /// Called by FetchUntil at the end of each period of `clock` cycles
+    pub(crate) const fn end_period(&mut self, clock: int) {
+        self.cycles += clock as u64;
+    }
+
+    pub(crate) fn trace(&mut self, registers: &Registers, clock: int, mem: &Memory) {
+        let pc = registers.pc as int;
+        if !self.armed {
+            if Some(pc) != self.filter.after {
+                return;
+            }
+            self.armed = true;
+        }
+        if self.error.is_some()
+            || self
+                .filter
+                .pc
+                .as_ref()
+                .is_some_and(|range| !range.contains(&pc))
+            || self
+                .filter
+                .frames
+                .as_ref()
+                .is_some_and(|range| !range.contains(&self.frame))
+        {
+            return;
+        }
+        let line = trace_line(registers, self.cycles as i64 + clock as i64, mem);
+        if let Err(e) = writeln!(self.out, "{line}") {
+            self.error = Some(e.to_string());
+        }
+    }
+}
+
+fn trace_line(registers: &Registers, cycles: i64, mem: &Memory) -> String {
+    let instruction = decode(registers.pc as int, mem);
+    let bytes: Vec<String> = instruction
+        .bytes
+        .iter()
+        .map(|b| format!("{b:02X}"))
+        .collect();
+    let flags: String = "EFHINZVC"
+        .chars()
+        .enumerate()
+        .map(|(i, flag)| {
+            if registers.cc & (0x80 >> i) != 0 {
+                flag
+            } else {
+                '.'
+            }
+        })
+        .collect();
+    format!(
+        "{:04X} {:<14} {:<5} {:<16} A={:02X} B={:02X} X={:04X} Y={:04X} U={:04X} S={:04X} DP={:02X} CC={flags} CYC={cycles}",
+        registers.pc,
+        bytes.join(" "),
+        instruction.mnemonic,
+        instruction.operands,
+        registers.a,
+        registers.b,
+        registers.x,
+        registers.y,
+        registers.u,
+        registers.s,
+        registers.dp,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hardware::bus::Bus;
+    use std::sync::{Arc, Mutex};
+
+    /// Keeps the trace in memory for the tests
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Output {
+        fn lines(&self) -> Vec<String> {
+            String::from_utf8(self.0.lock().unwrap().clone())
+                .unwrap()
+                .lines()
+                .map(str::to_string)
+                .collect()
+        }
+    }
+
+    fn registers(pc: u16) -> Registers {
+        Registers {
+            pc,
+            s: 0x8000,
+            cc: 0x50,
+            ..Registers::default()
+        }
+    }
+
+    #[test]
+    fn test_trace_line() {
+        let mut mem = Memory::default();
+        mem.reset();
+        mem.write(0x6000, 0x8E);
+        mem.write(0x6001, 0x20);
+        mem.write(0x6002, 0x00);
+        assert_eq!(
+            "6000 8E 20 00       LDX   #$2000           A=00 B=00 X=0000 Y=0000 U=0000 S=8000 DP=00 CC=.F.I.... CYC=12",
+            trace_line(&registers(0x6000), 12, &mem)
+        );
+    }
+
+    #[test]
+    fn test_filters() {
+        let mem = Memory::default();
+        let output = Output::default();
+        let filter = TraceFilter {
+            pc: Some(TraceFilter::parse_pc("6000-6FFF").unwrap()),
+            frames: Some(TraceFilter::parse_frames("1").unwrap()),
+            after: Some(0x6010),
+        };
+        let mut tracer = Tracer::new(Box::new(output.clone()), filter);
+        tracer.start_frame();
+        for pc in [0x6000, 0x6010, 0x7000, 0x6000] {
+            tracer.trace(&registers(pc), 0, &mem);
+        }
+        tracer.start_frame();
+        tracer.trace(&registers(0x6020), 0, &mem);
+        let lines = output.lines();
+        assert_eq!(2, lines.len());
+        assert!(lines[0].starts_with("6010"));
+        assert!(lines[1].starts_with("6000"));
+    }
+
+    #[test]
+    fn test_parse_ranges() {
+        assert_eq!(Ok(0xF000..=0xF0FF), TraceFilter::parse_pc("F000-F0FF"));
+        assert_eq!(Ok(0x6000..=0x6000), TraceFilter::parse_pc("$6000"));
+        assert!(TraceFilter::parse_pc("F0FF-F000").is_err());
+        assert_eq!(Ok(10..=20), TraceFilter::parse_frames("10-20"));
+        assert!(TraceFilter::parse_frames("x").is_err());
+    }
+}
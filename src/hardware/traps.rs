@@ -0,0 +1,48 @@
+use crate::hardware::M6809::Registers;
+use crate::hardware::memory::Memory;
+
+/// The opcode patched at the start of a trapped routine, it replaces the undocumented XNC opcode
+/// of the 6809 which no program of the MO5 uses
+pub const TRAP_OPCODE: u8 = 0x02;
+
+/// The high-level emulation of a routine. The registers are the ones after the trap opcode,
+/// PC points to the bytes patched after it, and the changes are kept by the CPU. The handler
+/// can keep its own state, like an open host file.
+pub type TrapHandler = Box<dyn FnMut(&mut Memory, &mut Registers)>;
+
+/// A routine patched with the trap opcode, the original bytes are restored when it is removed
+pub(crate) struct Trap {
+    pub(crate) code: Vec<u8>,
+    pub(crate) original: Vec<u8>,
+    // taken while it runs, as it gets the memory holding it
+    pub(crate) handler: Option<TrapHandler>,
+}
+
+impl std::fmt::Debug for Trap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Trap")
+            .field("code", &self.code)
+            .field("original", &self.original)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Trap {
+    pub(crate) fn new(code: &[u8], handler: TrapHandler) -> Result<Self, String> {
+        if code.first() != Some(&TRAP_OPCODE) {
+            return Err(format!(
+                "The patched code must start with the trap opcode {TRAP_OPCODE:02X}"
+            ));
+        }
+        Ok(Self {
+            code: code.to_vec(),
+            original: Vec::new(),
+            handler: Some(handler),
+        })
+    }
+
+    /// The addresses patched by the trap installed at the given address
+    pub(crate) const fn range(&self, address: u16) -> std::ops::Range<u32> {
+        address as u32..address as u32 + self.code.len() as u32
+    }
+}
@@ -0,0 +1,134 @@
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+
+use log::info;
+
+use crate::hardware::sound::SAMPLE_RATE;
+
+const HEADER_SIZE: u32 = 44;
+const CHANNELS: u16 = 1;
+const BITS_PER_SAMPLE: u16 = 8;
+
+/// Records the emulated sound output to an 8 bits unsigned mono WAV file.
+///
+/// The samples are the ones played, taken at [`SAMPLE_RATE`] on the emulated clock.
+#[derive(Debug)]
+pub struct WavRecorder {
+    name: String,
+    writer: BufWriter<File>,
+    samples: u32,
+}
+
+impl WavRecorder {
+    pub fn new(name: &str) -> Result<Self, String> {
+        let file = File::create(name).map_err(|e| e.to_string())?;
+        let mut writer = BufWriter::new(file);
+        // the header is rewritten with the right sizes once the recording is finished
+        write_header(&mut writer, 0, 0).map_err(|e| e.to_string())?;
+        info!("Recording audio to {name}");
+        Ok(Self {
+            name: name.to_string(),
+            writer,
+            samples: 0,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn push(&mut self, samples: &[u8]) -> Result<(), String> {
+        self.writer.write_all(samples).map_err(|e| e.to_string())?;
+        self.samples += samples.len() as u32;
+        Ok(())
+    }
+
+    /// Returns the duration of the recording in emulated seconds
+    pub const fn seconds(&self) -> u32 {
+        self.samples / SAMPLE_RATE
+    }
+
+    /// Writes the final header and closes the file
+    pub fn finish(mut self) -> Result<String, String> {
+        self.writer
+            .seek(SeekFrom::Start(0))
+            .and_then(|_| write_header(&mut self.writer, self.samples, SAMPLE_RATE))
+            .and_then(|_| self.writer.flush())
+            .map_err(|e| e.to_string())?;
+        info!(
+            "Recorded {} samples at {SAMPLE_RATE}Hz to {}",
+            self.samples, self.name
+        );
+        Ok(self.name)
+    }
+}
+
+fn write_header<W: Write>(writer: &mut W, samples: u32, sample_rate: u32) -> std::io::Result<()> {
+    let block_align = CHANNELS * BITS_PER_SAMPLE / 8;
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(HEADER_SIZE - 8 + samples).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    writer.write_all(&1u16.to_le_bytes())?; // PCM
+    writer.write_all(&CHANNELS.to_le_bytes())?;
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&(sample_rate * block_align as u32).to_le_bytes())?;
+    writer.write_all(&block_align.to_le_bytes())?;
+    writer.write_all(&BITS_PER_SAMPLE.to_le_bytes())?;
+    writer.write_all(b"data")?;
+    writer.write_all(&samples.to_le_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hardware::sound::Sound;
+
+    #[test]
+    fn test_header() {
+        let mut header = Vec::new();
+        write_header(&mut header, 100, 22050).unwrap();
+        assert_eq!(HEADER_SIZE as usize, header.len());
+        assert_eq!(b"RIFF", &header[0..4]);
+        assert_eq!(136, u32::from_le_bytes(header[4..8].try_into().unwrap()));
+        assert_eq!(
+            22050,
+            u32::from_le_bytes(header[24..28].try_into().unwrap())
+        );
+        assert_eq!(100, u32::from_le_bytes(header[40..44].try_into().unwrap()));
+    }
+
+    #[test]
+    fn test_record() {
+        let path =
+            std::env::temp_dir().join(format!("maurice_test_record_{}.wav", std::process::id()));
+        let name = path.to_str().unwrap();
+        let mut recorder = WavRecorder::new(name).unwrap();
+        for _ in 0..SAMPLE_RATE / 4 {
+            recorder.push(&[0, 32, 0, 32]).unwrap();
+        }
+        assert_eq!(1, recorder.seconds());
+        recorder.finish().unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(HEADER_SIZE as usize + SAMPLE_RATE as usize, bytes.len());
+        assert_eq!(
+            SAMPLE_RATE,
+            u32::from_le_bytes(bytes[24..28].try_into().unwrap())
+        );
+        assert_eq!(&[0, 32, 0, 32], &bytes[44..48]);
+
+        // the samples of the last partial block are written when the recording stops
+        let mut sound = Sound::default();
+        sound.start_recording(name).unwrap();
+        for level in 0..300 {
+            sound.sample(level as u8);
+        }
+        sound.stop_recording().unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(300, u32::from_le_bytes(bytes[40..44].try_into().unwrap()));
+        assert_eq!(HEADER_SIZE as usize + 300, bytes.len());
+        assert_eq!(43, bytes[HEADER_SIZE as usize + 299]);
+    }
+}
@@ -1,8 +1,13 @@
 #[cfg(not(target_family = "wasm"))]
 pub mod args;
 pub(crate) mod bios;
+#[cfg(not(target_family = "wasm"))]
+pub mod capture;
 pub mod gui;
 pub mod hardware;
 pub mod raw_image;
+#[cfg(not(target_family = "wasm"))]
+pub mod watch;
 
+#[allow(non_camel_case_types)]
 pub(crate) type int = i32;
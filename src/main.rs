@@ -2,34 +2,159 @@ use maurice_lib::gui::Gui;
 #[cfg(not(target_family = "wasm"))]
 use {
     clap::Parser,
-    log::warn,
-    maurice_lib::args::Args,
-    maurice_lib::hardware::k7::K7,
+    log::{info, warn},
+    maurice_lib::args::{Args, Command},
+    maurice_lib::capture::{self, VideoFormat},
+    maurice_lib::gui::display::window_size,
+    maurice_lib::hardware::basic,
+    maurice_lib::hardware::binary::Binary,
+    maurice_lib::hardware::k7::{self, K7},
+    maurice_lib::hardware::machine::{FRAMES_PER_SECOND, Machine},
     maurice_lib::hardware::screen::{DEFAULT_PIXEL_SIZE, HEIGHT, WIDTH},
+    std::path::Path,
 };
 
 #[cfg(not(target_family = "wasm"))]
 fn main() {
     env_logger::init();
+    let args = Args::parse();
+    if let Some(command) = &args.command {
+        run_command(command);
+        return;
+    }
+    if let Some(seconds) = args.headless {
+        run_headless(args, seconds);
+        return;
+    }
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_drag_and_drop(true)
             .with_icon(icon_data())
-            .with_inner_size([
-                (DEFAULT_PIXEL_SIZE * WIDTH) as f32,
-                (DEFAULT_PIXEL_SIZE * HEIGHT) as f32,
-            ]),
+            .with_inner_size(window_size(WIDTH, HEIGHT, DEFAULT_PIXEL_SIZE)),
         ..Default::default()
     };
-    let args = Args::parse();
     let mut gui = Gui::default();
+    if let Some(trace_file) = &args.trace
+        && let Err(e) = gui.start_trace(trace_file, args.trace_filter())
+    {
+        warn!("Unable to trace {e}");
+    }
+    if let Some(watcher) = args.watcher() {
+        gui.watch(watcher);
+    }
     if let Some(k7_file) = args.k7 {
         match K7::try_from(k7_file) {
             Ok(k7) => gui.set_k7(k7),
             Err(e) => warn!("Unable to open tape {e}"),
         }
     }
-    let _ = eframe::run_native("Maurice", native_options, Box::new(|_cc| Ok(Box::new(gui))));
+    if let Some(wav_file) = args.record_audio
+        && let Err(e) = gui.start_audio_recording(&wav_file)
+    {
+        warn!("Unable to record audio {e}");
+    }
+    if let Some(video_file) = args.record_video
+        && let Err(e) =
+            gui.start_video_capture(&video_file, VideoFormat::from_name(&video_file), args.scale)
+    {
+        warn!("Unable to capture video {e}");
+    }
+    let _ = eframe::run_native(
+        "Maurice",
+        native_options,
+        Box::new(|cc| {
+            gui.load_settings(cc.storage);
+            Ok(Box::new(gui))
+        }),
+    );
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn run_command(command: &Command) {
+    let result = match command {
+        Command::ListBasic { k7, name } => K7::try_from(k7.clone())
+            .and_then(|k7| basic::list_tape(&k7, name.as_deref()))
+            .map(|listing| print!("{listing}")),
+        Command::BasicToK7 { bas, k7, name } => {
+            let name = name
+                .clone()
+                .unwrap_or_else(|| k7::tape_name(Path::new(bas)));
+            std::fs::read_to_string(bas)
+                .map_err(|e| e.to_string())
+                .and_then(|listing| basic::tape(&name, &listing))
+                .and_then(|tape| tape.save(k7))
+        }
+        Command::BinToK7 {
+            bin,
+            k7,
+            address,
+            name,
+        } => {
+            let name = name
+                .clone()
+                .unwrap_or_else(|| k7::tape_name(Path::new(bin)));
+            std::fs::read(bin)
+                .map_err(|e| e.to_string())
+                .and_then(|data| match address {
+                    Some(address) => Ok(Binary::raw(data, *address as u16)),
+                    None => Binary::parse(&data),
+                })
+                .and_then(|binary| binary.tape(&name))
+                .and_then(|tape| tape.save(k7))
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn run_headless(args: Args, seconds: u32) {
+    let mut machine = Machine::default();
+    if let Some(trace_file) = &args.trace
+        && let Err(e) = machine.start_trace(trace_file, args.trace_filter())
+    {
+        warn!("Unable to trace {e}");
+    }
+    // the file is only loaded once, there is no time to change it
+    if let Some(mut watcher) = args.watcher()
+        && let Some(message) = watcher.poll(&mut machine)
+    {
+        info!("{message}");
+    }
+    if let Some(k7_file) = args.k7 {
+        match K7::try_from(k7_file) {
+            Ok(k7) => machine.set_k7(k7),
+            Err(e) => warn!("Unable to open tape {e}"),
+        }
+    }
+    if let Some(wav_file) = &args.record_audio
+        && let Err(e) = machine.start_audio_recording(wav_file)
+    {
+        warn!("Unable to record audio {e}");
+    }
+    if let Some(video_file) = &args.record_video
+        && let Err(e) =
+            machine.start_video_capture(video_file, VideoFormat::from_name(video_file), args.scale)
+    {
+        warn!("Unable to capture video {e}");
+    }
+    machine.run_frames(seconds * FRAMES_PER_SECOND);
+    if let Err(e) = machine.stop_trace() {
+        warn!("Unable to save trace {e}");
+    }
+    if let Err(e) = machine.stop_video_capture() {
+        warn!("Unable to save video {e}");
+    }
+    if let Err(e) = machine.stop_audio_recording() {
+        warn!("Unable to save audio {e}");
+    }
+    if let Some(png_file) = &args.screenshot
+        && let Err(e) = capture::save_png(&machine.screenshot(), args.scale, png_file)
+    {
+        warn!("Unable to save screenshot {e}");
+    }
 }
 
 #[cfg(not(target_family = "wasm"))]
@@ -66,7 +191,11 @@ fn main() {
             .start(
                 canvas,
                 web_options,
-                Box::new(|_cc| Ok(Box::<Gui>::default())),
+                Box::new(|cc| {
+                    let mut gui = Gui::default();
+                    gui.load_settings(cc.storage);
+                    Ok(Box::new(gui))
+                }),
             )
             .await;
 
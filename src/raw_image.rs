@@ -23,4 +23,52 @@ impl<'a> RawImage<'a> {
             height,
         }
     }
+
+    pub const fn width(&self) -> usize {
+        self.width
+    }
+
+    pub const fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The RGB pixels, line by line
+    pub fn data(&self) -> &[u8] {
+        self.data
+    }
+
+    /// Returns the RGB pixels resized to the given size using the nearest pixel
+    pub fn resize(&self, width: usize, height: usize) -> Vec<u8> {
+        if width == self.width && height == self.height {
+            return self.data.clone();
+        }
+        let mut pixels = Vec::with_capacity(width * height * COLOR_DEPTH);
+        for y in 0..height {
+            let line = (y * self.height / height) * self.width;
+            for x in 0..width {
+                let start = (line + x * self.width / width) * COLOR_DEPTH;
+                pixels.extend_from_slice(&self.data[start..start + COLOR_DEPTH]);
+            }
+        }
+        pixels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resize() {
+        let data = vec![1, 1, 1, 2, 2, 2, 3, 3, 3, 4, 4, 4];
+        let image = RawImage::new_with_data(2, 2, &data);
+        let bigger = image.resize(4, 4);
+        assert_eq!(4 * 4 * COLOR_DEPTH, bigger.len());
+        assert_eq!(&[1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2], &bigger[0..12]);
+        assert_eq!(&bigger[0..12], &bigger[12..24]);
+        assert_eq!(&[3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4], &bigger[24..36]);
+
+        let smaller = RawImage::new_with_data(4, 4, &bigger).resize(2, 2);
+        assert_eq!(data, smaller);
+    }
 }
@@ -0,0 +1,103 @@
+use crate::hardware::binary::Binary;
+use crate::hardware::k7::K7;
+use crate::hardware::machine::{Machine, Reset};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Delay between two checks of the watched file
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Watches a program being developed, and restarts the machine with it each time it changes
+pub struct Watcher {
+    path: PathBuf,
+    // the load address of a raw binary, None for a BIN file
+    address: Option<u16>,
+    reset: Reset,
+    // typed once BASIC started, like RUN or LOADM
+    keys: String,
+    modified: Option<SystemTime>,
+    polled: Option<Instant>,
+}
+
+impl Watcher {
+    /// A literal `\n` in the keys is Return
+    pub fn new(path: PathBuf, address: Option<u16>, reset: Reset, keys: &str) -> Self {
+        Self {
+            path,
+            address,
+            reset,
+            keys: keys.replace("\\n", "\n"),
+            modified: None,
+            polled: None,
+        }
+    }
+
+    /// Reloads the file if it changed since the last reload, returns the message to show
+    pub fn poll(&mut self, machine: &mut Machine) -> Option<String> {
+        if self
+            .polled
+            .is_some_and(|polled| polled.elapsed() < POLL_INTERVAL)
+        {
+            return None;
+        }
+        self.polled = Some(Instant::now());
+        // the file may be missing while it is being written
+        let modified = std::fs::metadata(&self.path)
+            .and_then(|m| m.modified())
+            .ok()?;
+        if self.modified == Some(modified) {
+            return None;
+        }
+        self.modified = Some(modified);
+        Some(match self.reload(machine) {
+            Ok(()) => format!("Reloaded {}", self.path.display()),
+            Err(e) => format!("Failed to reload {}: {e}", self.path.display()),
+        })
+    }
+
+    fn reload(&self, machine: &mut Machine) -> Result<(), String> {
+        let is_k7 = self
+            .path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("k7"));
+        if is_k7 {
+            let k7 = K7::try_from(self.path.clone())?;
+            machine.restart(self.reset, None, &self.keys)?;
+            machine.set_k7(k7);
+            return Ok(());
+        }
+        let data = std::fs::read(&self.path).map_err(|e| e.to_string())?;
+        let binary = match self.address {
+            Some(address) => Binary::raw(data, address),
+            None => Binary::parse(&data)?,
+        };
+        machine.restart(self.reset, Some(binary), &self.keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_poll() {
+        let path =
+            std::env::temp_dir().join(format!("maurice_test_watch_{}.bin", std::process::id()));
+        // LDA #$2A, STA $7000, BRA *
+        std::fs::write(&path, [0x86, 0x2A, 0xB7, 0x70, 0x00, 0x20, 0xFE]).unwrap();
+        let mut machine = Machine::default();
+        let mut watcher = Watcher::new(path.clone(), Some(0x6000), Reset::Hard, "");
+        assert_eq!(
+            Some(format!("Reloaded {}", path.display())),
+            watcher.poll(&mut machine)
+        );
+        assert_eq!(None, watcher.poll(&mut machine));
+        machine.run_frames(101);
+        assert_eq!(0x2A, machine.mem.peek(0x7000));
+
+        let mut watcher = Watcher::new(path.clone(), None, Reset::Hard, "");
+        let message = watcher.poll(&mut machine).unwrap();
+        assert!(message.starts_with("Failed to reload"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}